@@ -22,7 +22,11 @@ fn main() -> Result<(), Box<dyn Error>> {
             "#[derive(serde::Deserialize, serde::Serialize)]",
         )
         .compile(
-            &["protos/coordinator_service.proto", "protos/raft.proto"],
+            &[
+                "protos/coordinator_service.proto",
+                "protos/raft.proto",
+                "protos/data_plane_service.proto",
+            ],
             &["protos"],
         )
         .unwrap();
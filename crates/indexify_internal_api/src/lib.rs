@@ -90,6 +90,18 @@ pub struct Index {
     pub extractor_name: ExtractorName,
     pub graph_name: ExtractionGraphName,
     pub visibility: bool,
+    /// Vector store of the index, the default store of servers when empty.
+    #[serde(default)]
+    pub vector_store: String,
+    /// Vector store the index is being migrated to, if any.
+    #[serde(default)]
+    pub migrating_to: String,
+    /// Version of the extractor the outputs in the index are extracted with.
+    #[serde(default)]
+    pub extractor_version: String,
+    /// Options the index was created with in its vector store, as JSON.
+    #[serde(default)]
+    pub index_options: String,
 }
 
 impl Index {
@@ -137,6 +149,10 @@ impl From<Index> for indexify_coordinator::Index {
             extraction_policy: value.extraction_policy_name,
             namespace: value.namespace,
             graph_name: value.graph_name,
+            vector_store: value.vector_store,
+            migrating_to: value.migrating_to,
+            extractor_version: value.extractor_version,
+            index_options: value.index_options,
         }
     }
 }
@@ -153,21 +169,82 @@ impl From<indexify_coordinator::Index> for Index {
             namespace: value.namespace,
             graph_name: value.graph_name,
             visibility: false,
+            vector_store: value.vector_store,
+            migrating_to: value.migrating_to,
+            extractor_version: value.extractor_version,
+            index_options: value.index_options,
         };
         index.id = index.id();
         index
     }
 }
 
+/// Kind of the vectors of an embedding output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingKind {
+    /// A single vector of `dim` dimensions.
+    #[default]
+    Dense,
+    /// The few non zero dimensions of a vector of `dim` dimensions, such as
+    /// the term weights of SPLADE models.
+    Sparse,
+    /// A vector of `dim` dimensions per token of the content, scored with late
+    /// interaction like ColBERT models.
+    MultiVector,
+}
+
+impl EmbeddingKind {
+    pub fn is_dense(&self) -> bool {
+        *self == EmbeddingKind::Dense
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct EmbeddingSchema {
     pub dim: usize,
     pub distance: String,
+    #[serde(default, skip_serializing_if = "EmbeddingKind::is_dense")]
+    pub kind: EmbeddingKind,
+}
+
+/// Non zero dimensions of a sparse vector.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SparseVector {
+    pub indices: Vec<u32>,
+    pub values: Vec<f32>,
+}
+
+/// Values of an embedding. Dense vectors are lists of numbers, sparse vectors
+/// objects with the `indices` and `values` of their non zero dimensions, and
+/// multi-vectors lists of dense vectors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingVector {
+    Dense(Vec<f32>),
+    Sparse(SparseVector),
+    MultiVector(Vec<Vec<f32>>),
+}
+
+impl EmbeddingVector {
+    pub fn kind(&self) -> EmbeddingKind {
+        match self {
+            EmbeddingVector::Dense(_) => EmbeddingKind::Dense,
+            EmbeddingVector::Sparse(_) => EmbeddingKind::Sparse,
+            EmbeddingVector::MultiVector(_) => EmbeddingKind::MultiVector,
+        }
+    }
+}
+
+impl From<Vec<f32>> for EmbeddingVector {
+    fn from(values: Vec<f32>) -> Self {
+        EmbeddingVector::Dense(values)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Embedding {
-    pub values: Vec<f32>,
+    pub values: EmbeddingVector,
     pub distance: String,
 }
 
@@ -175,10 +252,39 @@ pub struct Embedding {
 pub enum OutputSchema {
     #[serde(rename = "embedding")]
     Embedding(EmbeddingSchema),
+    #[serde(rename = "sparse_embedding")]
+    SparseEmbedding(EmbeddingSchema),
+    #[serde(rename = "multi_vector_embedding")]
+    MultiVectorEmbedding(EmbeddingSchema),
     #[serde(rename = "attributes")]
     Attributes(HashMap<String, SchemaColumn>),
 }
 
+impl OutputSchema {
+    /// Schema of the index of embedding outputs, with the kind of their
+    /// vectors.
+    pub fn embedding_schema(&self) -> Option<EmbeddingSchema> {
+        let (schema, kind) = match self {
+            OutputSchema::Embedding(schema) => (schema, EmbeddingKind::Dense),
+            OutputSchema::SparseEmbedding(schema) => (schema, EmbeddingKind::Sparse),
+            OutputSchema::MultiVectorEmbedding(schema) => (schema, EmbeddingKind::MultiVector),
+            OutputSchema::Attributes(_) => return None,
+        };
+        Some(EmbeddingSchema {
+            kind,
+            ..schema.clone()
+        })
+    }
+
+    fn from_embedding_schema(schema: EmbeddingSchema) -> Self {
+        match schema.kind {
+            EmbeddingKind::Dense => OutputSchema::Embedding(schema),
+            EmbeddingKind::Sparse => OutputSchema::SparseEmbedding(schema),
+            EmbeddingKind::MultiVector => OutputSchema::MultiVectorEmbedding(schema),
+        }
+    }
+}
+
 pub type ExtractorName = String;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
@@ -188,6 +294,14 @@ pub struct ExtractorDescription {
     pub input_params: serde_json::Value,
     pub outputs: HashMap<String, OutputSchema>,
     pub input_mime_types: Vec<String>,
+    /// Version of the extractor, tasks and indexes record the version they
+    /// were created with.
+    #[serde(default)]
+    pub version: String,
+    /// Types of the content the extractor outputs, which extractors reading
+    /// content from it should accept. Not declared when empty.
+    #[serde(default)]
+    pub output_mime_types: Vec<String>,
 }
 
 impl From<ExtractorDescription> for indexify_coordinator::Extractor {
@@ -195,17 +309,14 @@ impl From<ExtractorDescription> for indexify_coordinator::Extractor {
         let mut embedding_schemas = HashMap::new();
         let mut metadata_schemas = HashMap::new();
         for (output_name, schema) in value.outputs {
-            match schema {
-                OutputSchema::Embedding(embedding_schema) => {
-                    embedding_schemas.insert(
-                        output_name,
-                        serde_json::to_string(&embedding_schema).unwrap(),
-                    );
-                }
-                OutputSchema::Attributes(attributes) => {
-                    metadata_schemas
-                        .insert(output_name, serde_json::to_string(&attributes).unwrap());
-                }
+            //  The kind of embedding outputs is kept in their schema
+            if let Some(embedding_schema) = schema.embedding_schema() {
+                embedding_schemas.insert(
+                    output_name,
+                    serde_json::to_string(&embedding_schema).unwrap(),
+                );
+            } else if let OutputSchema::Attributes(attributes) = schema {
+                metadata_schemas.insert(output_name, serde_json::to_string(&attributes).unwrap());
             }
         }
         Self {
@@ -215,6 +326,8 @@ impl From<ExtractorDescription> for indexify_coordinator::Extractor {
             embedding_schemas,
             input_mime_types: value.input_mime_types,
             metadata_schemas,
+            version: value.version,
+            output_mime_types: value.output_mime_types,
         }
     }
 }
@@ -267,7 +380,10 @@ impl From<indexify_coordinator::Extractor> for ExtractorDescription {
         for (output_name, embedding_schema) in value.embedding_schemas {
             let embedding_schema: EmbeddingSchema =
                 serde_json::from_str(&embedding_schema).unwrap();
-            output_schema.insert(output_name, OutputSchema::Embedding(embedding_schema));
+            output_schema.insert(
+                output_name,
+                OutputSchema::from_embedding_schema(embedding_schema),
+            );
         }
         for (output_name, metadata_schema) in value.metadata_schemas {
             let metadata_schema: HashMap<String, OutputColumn> =
@@ -298,6 +414,8 @@ impl From<indexify_coordinator::Extractor> for ExtractorDescription {
             input_params: serde_json::from_str(&value.input_params).unwrap(),
             outputs: output_schema,
             input_mime_types: value.input_mime_types,
+            version: value.version,
+            output_mime_types: value.output_mime_types,
         }
     }
 }
@@ -322,6 +440,21 @@ pub struct ExtractResponse {
     pub features: Vec<Feature>,
 }
 
+/// Extracts many contents with a single call to an executor.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExtractBatchRequest {
+    pub extractor_name: String,
+    pub content_list: Vec<Content>,
+    pub input_params: Option<serde_json::Value>,
+}
+
+/// Responses of a batch extraction, in the order of the content of the
+/// request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractBatchResponse {
+    pub outcomes: Vec<ExtractResponse>,
+}
+
 #[derive(
     Debug, PartialEq, Eq, Serialize, Clone, Deserialize, EnumString, Display, SmartDefault,
 )]
@@ -394,6 +527,7 @@ pub enum TaskOutcome {
     Unknown,
     Success,
     Failed,
+    Cancelled,
 }
 
 impl From<indexify_coordinator::TaskOutcome> for TaskOutcome {
@@ -402,6 +536,7 @@ impl From<indexify_coordinator::TaskOutcome> for TaskOutcome {
             indexify_coordinator::TaskOutcome::Unknown => TaskOutcome::Unknown,
             indexify_coordinator::TaskOutcome::Success => TaskOutcome::Success,
             indexify_coordinator::TaskOutcome::Failed => TaskOutcome::Failed,
+            indexify_coordinator::TaskOutcome::Cancelled => TaskOutcome::Cancelled,
         }
     }
 }
@@ -412,6 +547,7 @@ impl From<TaskOutcome> for indexify_coordinator::TaskOutcome {
             TaskOutcome::Unknown => indexify_coordinator::TaskOutcome::Unknown,
             TaskOutcome::Success => indexify_coordinator::TaskOutcome::Success,
             TaskOutcome::Failed => indexify_coordinator::TaskOutcome::Failed,
+            TaskOutcome::Cancelled => indexify_coordinator::TaskOutcome::Cancelled,
         }
     }
 }
@@ -426,6 +562,60 @@ impl From<bool> for TaskOutcome {
     }
 }
 
+#[derive(
+    Serialize, Debug, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema, Display, EnumString,
+)]
+#[schema(as = internal_api::TaskEventType)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TaskEventType {
+    Created,
+    Assigned,
+    /// The executor of the task was removed, the task is assigned again.
+    Unassigned,
+    /// The executor started running the task.
+    Started,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, ToSchema)]
+#[schema(as = internal_api::TaskEvent)]
+pub struct TaskEvent {
+    pub event_type: TaskEventType,
+    /// Time of the event, in seconds since the epoch.
+    pub timestamp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executor_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl From<TaskEvent> for indexify_coordinator::TaskEvent {
+    fn from(value: TaskEvent) -> Self {
+        Self {
+            event_type: value.event_type.to_string(),
+            timestamp: value.timestamp,
+            executor_id: value.executor_id.unwrap_or_default(),
+            message: value.message.unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<indexify_coordinator::TaskEvent> for TaskEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(value: indexify_coordinator::TaskEvent) -> Result<Self> {
+        Ok(Self {
+            event_type: TaskEventType::from_str(&value.event_type)?,
+            timestamp: value.timestamp,
+            executor_id: Some(value.executor_id).filter(|id| !id.is_empty()),
+            message: Some(value.message).filter(|message| !message.is_empty()),
+        })
+    }
+}
+
 #[derive(Serialize, Debug, Deserialize, Clone, PartialEq, ToSchema, Default)]
 #[schema(as = internal_api::Task)]
 pub struct Task {
@@ -440,6 +630,20 @@ pub struct Task {
     #[schema(value_type = internal_api::TaskOutcome)]
     pub outcome: TaskOutcome,
     pub index_tables: Vec<String>, // list of index tables that this content may be present in
+    /// Version of the extractor the task was created for.
+    #[serde(default)]
+    pub extractor_version: String,
+    /// Time the task was last assigned to an executor, in seconds since the
+    /// epoch, zero if it hasn't been assigned.
+    #[serde(default)]
+    pub assigned_at: u64,
+    /// Why the task failed, as reported by its executor or by the
+    /// coordinator.
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+    /// Events of the task, oldest first.
+    #[serde(default)]
+    pub history: Vec<TaskEvent>,
 }
 
 impl Task {
@@ -447,6 +651,74 @@ impl Task {
         self.outcome != TaskOutcome::Unknown
     }
 
+    pub fn record_event(
+        &mut self,
+        event_type: TaskEventType,
+        timestamp: u64,
+        executor_id: Option<String>,
+        message: Option<String>,
+    ) {
+        self.history.push(TaskEvent {
+            event_type,
+            timestamp,
+            executor_id,
+            message,
+        });
+    }
+
+    /// Records the outcome of a finished task, with its failure reason.
+    pub fn record_outcome(&mut self, timestamp: u64, executor_id: Option<String>) {
+        let event_type = match self.outcome {
+            TaskOutcome::Unknown => return,
+            TaskOutcome::Success => TaskEventType::Succeeded,
+            TaskOutcome::Failed => TaskEventType::Failed,
+            TaskOutcome::Cancelled => TaskEventType::Cancelled,
+        };
+        let message = self.failure_reason.clone();
+        self.record_event(event_type, timestamp, executor_id, message);
+    }
+
+    fn event_timestamp(&self, event_types: &[TaskEventType]) -> Option<u64> {
+        self.history
+            .iter()
+            .rev()
+            .find(|event| event_types.contains(&event.event_type))
+            .map(|event| event.timestamp)
+    }
+
+    pub fn created_at(&self) -> Option<u64> {
+        self.event_timestamp(&[TaskEventType::Created])
+    }
+
+    /// Time the executor last started running the task.
+    pub fn started_at(&self) -> Option<u64> {
+        self.event_timestamp(&[TaskEventType::Started])
+    }
+
+    pub fn finished_at(&self) -> Option<u64> {
+        self.event_timestamp(&[
+            TaskEventType::Succeeded,
+            TaskEventType::Failed,
+            TaskEventType::Cancelled,
+        ])
+    }
+
+    /// Number of times the task was assigned to an executor.
+    pub fn attempts(&self) -> usize {
+        self.history
+            .iter()
+            .filter(|event| event.event_type == TaskEventType::Assigned)
+            .count()
+    }
+
+    /// Whether an executor the task was assigned to has the id.
+    pub fn was_assigned_to(&self, executor_id: &str) -> bool {
+        self.history.iter().any(|event| {
+            event.event_type == TaskEventType::Assigned &&
+                event.executor_id.as_deref() == Some(executor_id)
+        })
+    }
+
     pub fn new(
         id: &str,
         content_metadata: &ContentMetadata,
@@ -463,6 +735,10 @@ impl Task {
             input_params: serde_json::Value::Null,
             outcome: TaskOutcome::Unknown,
             index_tables: Vec::new(),
+            extractor_version: "".to_string(),
+            assigned_at: 0,
+            failure_reason: None,
+            history: Vec::new(),
         }
     }
 }
@@ -480,6 +756,10 @@ impl Display for Task {
 impl From<Task> for indexify_coordinator::Task {
     fn from(value: Task) -> Self {
         let outcome: indexify_coordinator::TaskOutcome = value.outcome.into();
+        let created_at = value.created_at().unwrap_or_default();
+        let started_at = value.started_at().unwrap_or_default();
+        let finished_at = value.finished_at().unwrap_or_default();
+        let attempts = value.attempts() as u64;
         indexify_coordinator::Task {
             id: value.id,
             extractor: value.extractor,
@@ -491,6 +771,13 @@ impl From<Task> for indexify_coordinator::Task {
             output_index_mapping: value.output_index_table_mapping,
             outcome: outcome as i32,
             index_tables: value.index_tables,
+            extractor_version: value.extractor_version,
+            failure_reason: value.failure_reason.unwrap_or_default(),
+            history: value.history.into_iter().map(Into::into).collect(),
+            created_at,
+            started_at,
+            finished_at,
+            attempts,
         }
     }
 }
@@ -628,6 +915,161 @@ impl From<&str> for ExtractionPolicyContentSource {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionOperator {
+    Eq,
+    Neq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Regex,
+    Glob,
+}
+
+/// Condition content has to meet for an extraction policy to be applied to
+/// it. The field is `mime_type`, `labels.<name>` or `metadata.<path>`, the
+/// path being the dot separated keys of a value in the metadata extracted
+/// along with the content.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct RoutingCondition {
+    pub field: String,
+    pub operator: ConditionOperator,
+    pub value: serde_json::Value,
+}
+
+/// Resources of an executor, or resources an executor needs to run the
+/// extractor of an extraction policy. Zero means none were advertised, or
+/// none are needed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, ToSchema)]
+pub struct ExecutorResources {
+    #[serde(default)]
+    pub cpus: u32,
+    #[serde(default)]
+    pub memory_mb: u64,
+    #[serde(default)]
+    pub gpus: u32,
+}
+
+impl ExecutorResources {
+    pub fn satisfies(&self, required: &ExecutorResources) -> bool {
+        self.cpus >= required.cpus &&
+            self.memory_mb >= required.memory_mb &&
+            self.gpus >= required.gpus
+    }
+}
+
+impl From<indexify_coordinator::ExecutorResources> for ExecutorResources {
+    fn from(value: indexify_coordinator::ExecutorResources) -> Self {
+        Self {
+            cpus: value.cpus,
+            memory_mb: value.memory_mb,
+            gpus: value.gpus,
+        }
+    }
+}
+
+impl From<ExecutorResources> for indexify_coordinator::ExecutorResources {
+    fn from(value: ExecutorResources) -> Self {
+        Self {
+            cpus: value.cpus,
+            memory_mb: value.memory_mb,
+            gpus: value.gpus,
+        }
+    }
+}
+
+/// Labels of the executors tasks of an extraction policy are assigned to.
+/// Tasks are only assigned to executors with all the required labels, and
+/// preferably to the executors with the most preferred labels.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default, ToSchema)]
+pub struct ExecutorAffinity {
+    #[serde(default)]
+    pub required_labels: HashMap<String, String>,
+    #[serde(default)]
+    pub preferred_labels: HashMap<String, String>,
+}
+
+impl ExecutorAffinity {
+    pub fn is_empty(&self) -> bool {
+        self.required_labels.is_empty() && self.preferred_labels.is_empty()
+    }
+
+    pub fn allows(&self, executor_labels: &HashMap<String, String>) -> bool {
+        self.required_labels
+            .iter()
+            .all(|(key, value)| executor_labels.get(key) == Some(value))
+    }
+
+    /// Number of preferred labels the executor has.
+    pub fn score(&self, executor_labels: &HashMap<String, String>) -> usize {
+        self.preferred_labels
+            .iter()
+            .filter(|(key, value)| executor_labels.get(*key) == Some(value))
+            .count()
+    }
+}
+
+impl From<indexify_coordinator::ExecutorAffinity> for ExecutorAffinity {
+    fn from(value: indexify_coordinator::ExecutorAffinity) -> Self {
+        Self {
+            required_labels: value.required_labels,
+            preferred_labels: value.preferred_labels,
+        }
+    }
+}
+
+impl From<ExecutorAffinity> for indexify_coordinator::ExecutorAffinity {
+    fn from(value: ExecutorAffinity) -> Self {
+        Self {
+            required_labels: value.required_labels,
+            preferred_labels: value.preferred_labels,
+        }
+    }
+}
+
+/// Limits on how an extraction policy is applied to content.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default, ToSchema)]
+pub struct ExtractionPolicyLimits {
+    /// Content larger than this isn't extracted, its task fails right away.
+    #[serde(default)]
+    pub max_content_size_bytes: Option<u64>,
+    /// Tasks which haven't finished this long after they were assigned to an
+    /// executor fail.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Resources executors need to be assigned tasks of the policy.
+    #[serde(default)]
+    pub resources: ExecutorResources,
+    /// Outputs of tasks are refused, rather than written with a warning, when
+    /// their indexes hold the outputs of another version of the extractor.
+    #[serde(default)]
+    pub reject_mixed_extractor_versions: bool,
+}
+
+impl From<indexify_coordinator::ExtractionPolicyLimits> for ExtractionPolicyLimits {
+    fn from(value: indexify_coordinator::ExtractionPolicyLimits) -> Self {
+        Self {
+            max_content_size_bytes: value.max_content_size_bytes,
+            timeout_secs: value.timeout_secs,
+            resources: value.resources.map(|r| r.into()).unwrap_or_default(),
+            reject_mixed_extractor_versions: value.reject_mixed_extractor_versions,
+        }
+    }
+}
+
+impl From<ExtractionPolicyLimits> for indexify_coordinator::ExtractionPolicyLimits {
+    fn from(value: ExtractionPolicyLimits) -> Self {
+        Self {
+            max_content_size_bytes: value.max_content_size_bytes,
+            timeout_secs: value.timeout_secs,
+            resources: Some(value.resources.into()),
+            reject_mixed_extractor_versions: value.reject_mixed_extractor_versions,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq, Eq, Deserialize, Default, Builder)]
 #[builder(build_fn(skip))]
 pub struct ExtractionPolicy {
@@ -637,6 +1079,12 @@ pub struct ExtractionPolicy {
     pub namespace: String,
     pub extractor: String,
     pub filters: HashMap<String, String>,
+    #[serde(default)]
+    pub conditions: Vec<RoutingCondition>,
+    #[serde(default)]
+    pub limits: ExtractionPolicyLimits,
+    #[serde(default)]
+    pub affinity: ExecutorAffinity,
     pub input_params: serde_json::Value,
     // Extractor Output -> Table Name
     pub output_table_mapping: HashMap<String, String>,
@@ -656,6 +1104,9 @@ impl From<ExtractionPolicy> for indexify_coordinator::ExtractionPolicy {
             extractor: value.extractor,
             name: value.name,
             filters,
+            conditions: serde_json::to_string(&value.conditions).unwrap_or_default(),
+            limits: Some(value.limits.into()),
+            affinity: Some(value.affinity.into()),
             input_params: value.input_params.to_string(),
             content_source: value.content_source.into(),
             graph_name: value.graph_name,
@@ -700,7 +1151,9 @@ impl ExtractionPolicyBuilder {
         let mut output_table_mapping = HashMap::new();
         for (output_name, output_schema) in extractor_description.outputs {
             let index_table_name = match output_schema {
-                OutputSchema::Embedding(_) => {
+                OutputSchema::Embedding(_) |
+                OutputSchema::SparseEmbedding(_) |
+                OutputSchema::MultiVectorEmbedding(_) => {
                     format!("{}.{}.{}.{}", ns, graph_name, name, output_name)
                 }
                 OutputSchema::Attributes(_) => {
@@ -716,6 +1169,9 @@ impl ExtractionPolicyBuilder {
             namespace: ns,
             extractor,
             filters: self.filters.clone().unwrap_or_default(),
+            conditions: self.conditions.clone().unwrap_or_default(),
+            limits: self.limits.clone().unwrap_or_default(),
+            affinity: self.affinity.clone().unwrap_or_default(),
             input_params: self.input_params.clone().unwrap_or_default(),
             output_table_mapping,
             content_source,
@@ -898,6 +1354,13 @@ pub struct ContentMetadata {
     pub extraction_policy_ids: HashMap<ExtractionPolicyId, u64>, /*  map of completion time for
                                                                   * each extraction policy id */
     pub extraction_graph_names: Vec<ExtractionGraphName>,
+    // Metadata extracted along with the content by the policy which created it
+    #[serde(default)]
+    pub extracted_metadata: HashMap<String, serde_json::Value>,
+    // Whether the blob was written compressed or encrypted by the blob store
+    // and has to be decoded when read
+    #[serde(default)]
+    pub encoded: bool,
 }
 
 impl From<ContentMetadata> for indexify_coordinator::ContentMetadata {
@@ -919,6 +1382,12 @@ impl From<ContentMetadata> for indexify_coordinator::ContentMetadata {
             hash: value.hash,
             extraction_policy_ids: value.extraction_policy_ids,
             extraction_graph_names: value.extraction_graph_names,
+            extracted_metadata: if value.extracted_metadata.is_empty() {
+                String::new()
+            } else {
+                serde_json::to_string(&value.extracted_metadata).unwrap_or_default()
+            },
+            encoded: value.encoded,
         }
     }
 }
@@ -954,6 +1423,8 @@ impl From<indexify_coordinator::ContentMetadata> for ContentMetadata {
             hash: value.hash,
             extraction_policy_ids: value.extraction_policy_ids,
             extraction_graph_names: value.extraction_graph_names,
+            extracted_metadata: serde_json::from_str(&value.extracted_metadata).unwrap_or_default(),
+            encoded: value.encoded,
         }
     }
 }
@@ -981,6 +1452,8 @@ impl Default for ContentMetadata {
             tombstoned: false,
             hash: "test_hash".to_string(),
             extraction_graph_names: vec![],
+            extracted_metadata: HashMap::new(),
+            encoded: false,
         }
     }
 }
@@ -991,6 +1464,10 @@ pub struct ExecutorMetadata {
     pub last_seen: u64,
     pub addr: String,
     pub extractors: Vec<ExtractorDescription>,
+    #[serde(default)]
+    pub resources: ExecutorResources,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -1062,6 +1539,7 @@ pub enum ChangeType {
     ExecutorRemoved,
     NewGargabeCollectionTask,
     TaskCompleted { content_id: ContentMetadataId },
+    ReextractOutdatedContent,
 }
 
 impl fmt::Display for ChangeType {
@@ -1077,6 +1555,7 @@ impl fmt::Display for ChangeType {
             ChangeType::TaskCompleted { content_id } => {
                 write!(f, "TaskCompleted(content_id: {})", content_id)
             }
+            ChangeType::ReextractOutdatedContent => write!(f, "ReextractOutdatedContent"),
         }
     }
 }
@@ -1123,6 +1602,7 @@ impl TryFrom<indexify_coordinator::StateChange> for StateChange {
             "NewBinding" => ChangeType::NewExtractionPolicy,
             "ExecutorAdded" => ChangeType::ExecutorAdded,
             "ExecutorRemoved" => ChangeType::ExecutorRemoved,
+            "ReextractOutdatedContent" => ChangeType::ReextractOutdatedContent,
             _ => return Err(anyhow!("Invalid ChangeType")),
         };
         Ok(Self {
@@ -1150,7 +1630,7 @@ impl From<StateChange> for indexify_coordinator::StateChange {
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct ExtractedEmbeddings {
     pub content_id: String,
-    pub embedding: Vec<f32>,
+    pub embedding: EmbeddingVector,
     pub metadata: HashMap<String, serde_json::Value>,
     pub root_content_metadata: Option<ContentMetadata>,
     pub content_metadata: ContentMetadata,
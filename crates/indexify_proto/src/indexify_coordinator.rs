@@ -25,6 +25,90 @@ pub struct GetContentTreeMetadataResponse {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContentStorageUpdate {
+    #[prost(string, tag = "1")]
+    pub content_id: ::prost::alloc::string::String,
+    /// The update is only applied while the content is stored at this url
+    #[prost(string, tag = "2")]
+    pub from_storage_url: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub to_storage_url: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateContentStorageRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub updates: ::prost::alloc::vec::Vec<ContentStorageUpdate>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateContentStorageResponse {
+    /// Content which was moved to its new storage url
+    #[prost(string, repeated, tag = "1")]
+    pub content_list: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MergeExtractedMetadataRequest {
+    #[prost(string, tag = "1")]
+    pub content_id: ::prost::alloc::string::String,
+    /// JSON encoded metadata merged into the metadata extracted along with the
+    /// latest version of the content
+    #[prost(string, tag = "2")]
+    pub metadata: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MergeExtractedMetadataResponse {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetContentTreeStatusRequest {
+    #[prost(string, tag = "1")]
+    pub content_id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetContentTreeStatusResponse {
+    /// Every task of the content tree has finished
+    #[prost(bool, tag = "1")]
+    pub indexed: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateIndexStoreRequest {
+    #[prost(string, tag = "1")]
+    pub namespace: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    /// Stores the index is expected to be in and migrating to
+    #[prost(string, tag = "3")]
+    pub from_vector_store: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub from_migrating_to: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub vector_store: ::prost::alloc::string::String,
+    #[prost(string, tag = "6")]
+    pub migrating_to: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateIndexStoreResponse {
+    #[prost(message, optional, tag = "1")]
+    pub index: ::core::option::Option<Index>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListIndexStoresRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListIndexStoresResponse {
+    /// Indexes which are not in the default vector store, or are being
+    /// migrated
+    #[prost(message, repeated, tag = "1")]
+    pub indexes: ::prost::alloc::vec::Vec<Index>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct UpdateTaskRequest {
     #[prost(string, tag = "1")]
     pub executor_id: ::prost::alloc::string::String,
@@ -34,6 +118,12 @@ pub struct UpdateTaskRequest {
     pub outcome: i32,
     #[prost(message, repeated, tag = "4")]
     pub content_list: ::prost::alloc::vec::Vec<ContentMetadata>,
+    /// when the executor started running the task, in seconds since the epoch
+    #[prost(uint64, tag = "5")]
+    pub started_at: u64,
+    /// why the task failed, reported by the executor
+    #[prost(string, tag = "6")]
+    pub failure_reason: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -65,6 +155,18 @@ pub struct ListTasksRequest {
     pub namespace: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub extraction_policy: ::prost::alloc::string::String,
+    #[prost(enumeration = "TaskOutcome", optional, tag = "3")]
+    pub outcome: ::core::option::Option<i32>,
+    /// only tasks which were assigned to the executor
+    #[prost(string, tag = "4")]
+    pub executor_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub content_id: ::prost::alloc::string::String,
+    /// bounds of the creation time of the tasks, in seconds since the epoch
+    #[prost(uint64, tag = "6")]
+    pub created_after: u64,
+    #[prost(uint64, tag = "7")]
+    pub created_before: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -86,6 +188,10 @@ pub struct GetExtractorCoordinatesRequest {
 pub struct GetExtractorCoordinatesResponse {
     #[prost(string, repeated, tag = "1")]
     pub addrs: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Changes whenever the extractor is registered with a different
+    /// description, empty when no executor runs the extractor.
+    #[prost(string, tag = "2")]
+    pub extractor_fingerprint: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -139,6 +245,19 @@ pub struct Index {
     pub extractor: ::prost::alloc::string::String,
     #[prost(string, tag = "7")]
     pub graph_name: ::prost::alloc::string::String,
+    /// Vector store of the index, the default store of servers when empty
+    #[prost(string, tag = "8")]
+    pub vector_store: ::prost::alloc::string::String,
+    /// Vector store the index is being migrated to, which is written to as
+    /// well until the migration completes
+    #[prost(string, tag = "9")]
+    pub migrating_to: ::prost::alloc::string::String,
+    /// Version of the extractor the outputs in the index are extracted with
+    #[prost(string, tag = "10")]
+    pub extractor_version: ::prost::alloc::string::String,
+    /// Options the index was created with in its vector store, as JSON
+    #[prost(string, tag = "11")]
+    pub index_options: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -181,6 +300,13 @@ pub struct RegisterExecutorRequest {
     pub addr: ::prost::alloc::string::String,
     #[prost(message, repeated, tag = "3")]
     pub extractors: ::prost::alloc::vec::Vec<Extractor>,
+    #[prost(message, optional, tag = "4")]
+    pub resources: ::core::option::Option<ExecutorResources>,
+    #[prost(map = "string, string", tag = "5")]
+    pub labels: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -260,6 +386,21 @@ pub struct HeartbeatResponse {
     pub executor_id: ::prost::alloc::string::String,
     #[prost(message, repeated, tag = "2")]
     pub tasks: ::prost::alloc::vec::Vec<Task>,
+    /// Tasks of the executor which were cancelled, repeated until it heartbeats again
+    #[prost(string, repeated, tag = "3")]
+    pub cancelled_task_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskEvent {
+    #[prost(string, tag = "1")]
+    pub event_type: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub timestamp: u64,
+    #[prost(string, tag = "3")]
+    pub executor_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub message: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -289,6 +430,25 @@ pub struct Task {
     /// list of all tables that the content may belong to
     #[prost(string, repeated, tag = "10")]
     pub index_tables: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// version of the extractor the task was created for
+    #[prost(string, tag = "11")]
+    pub extractor_version: ::prost::alloc::string::String,
+    /// why the task failed, reported by the executor or the coordinator
+    #[prost(string, tag = "12")]
+    pub failure_reason: ::prost::alloc::string::String,
+    /// events of the task, oldest first
+    #[prost(message, repeated, tag = "13")]
+    pub history: ::prost::alloc::vec::Vec<TaskEvent>,
+    /// times from the history, in seconds since the epoch, zero when unknown
+    #[prost(uint64, tag = "14")]
+    pub created_at: u64,
+    #[prost(uint64, tag = "15")]
+    pub started_at: u64,
+    #[prost(uint64, tag = "16")]
+    pub finished_at: u64,
+    /// number of times the task was assigned to an executor
+    #[prost(uint64, tag = "17")]
+    pub attempts: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -320,6 +480,11 @@ pub struct Extractor {
     >,
     #[prost(string, repeated, tag = "6")]
     pub input_mime_types: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, tag = "7")]
+    pub version: ::prost::alloc::string::String,
+    /// Types of the content the extractor outputs, not declared when empty
+    #[prost(string, repeated, tag = "8")]
+    pub output_mime_types: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -415,6 +580,42 @@ pub struct ExtractionGraph {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExecutorResources {
+    #[prost(uint32, tag = "1")]
+    pub cpus: u32,
+    #[prost(uint64, tag = "2")]
+    pub memory_mb: u64,
+    #[prost(uint32, tag = "3")]
+    pub gpus: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExecutorAffinity {
+    #[prost(map = "string, string", tag = "1")]
+    pub required_labels: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    #[prost(map = "string, string", tag = "2")]
+    pub preferred_labels: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExtractionPolicyLimits {
+    #[prost(uint64, optional, tag = "1")]
+    pub max_content_size_bytes: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "2")]
+    pub timeout_secs: ::core::option::Option<u64>,
+    #[prost(message, optional, tag = "3")]
+    pub resources: ::core::option::Option<ExecutorResources>,
+    #[prost(bool, tag = "4")]
+    pub reject_mixed_extractor_versions: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExtractionPolicy {
     #[prost(string, tag = "1")]
     pub id: ::prost::alloc::string::String,
@@ -438,6 +639,13 @@ pub struct ExtractionPolicy {
         ::prost::alloc::string::String,
         ::prost::alloc::string::String,
     >,
+    /// JSON encoded list of routing conditions
+    #[prost(string, tag = "9")]
+    pub conditions: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "10")]
+    pub limits: ::core::option::Option<ExtractionPolicyLimits>,
+    #[prost(message, optional, tag = "11")]
+    pub affinity: ::core::option::Option<ExecutorAffinity>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -459,6 +667,13 @@ pub struct ExtractionPolicyRequest {
     pub content_source: ::prost::alloc::string::String,
     #[prost(int64, tag = "7")]
     pub created_at: i64,
+    /// JSON encoded list of routing conditions
+    #[prost(string, tag = "8")]
+    pub conditions: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "9")]
+    pub limits: ::core::option::Option<ExtractionPolicyLimits>,
+    #[prost(message, optional, tag = "10")]
+    pub affinity: ::core::option::Option<ExecutorAffinity>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -546,6 +761,12 @@ pub struct ContentMetadata {
     pub root_content_id: ::prost::alloc::string::String,
     #[prost(string, repeated, tag = "14")]
     pub extraction_graph_names: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// JSON encoded metadata extracted along with the content
+    #[prost(string, tag = "15")]
+    pub extracted_metadata: ::prost::alloc::string::String,
+    /// whether the blob was written compressed or encrypted by the blob store
+    #[prost(bool, tag = "16")]
+    pub encoded: bool,
 }
 #[derive(serde::Deserialize, serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -562,6 +783,21 @@ pub struct CreateContentResponse {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateContentBatchRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub content_list: ::prost::alloc::vec::Vec<ContentMetadata>,
+    /// fail instead of updating content whose id exists already
+    #[prost(bool, tag = "2")]
+    pub create_only: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateContentBatchResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TombstoneContentRequest {
     #[prost(string, tag = "1")]
     pub namespace: ::prost::alloc::string::String,
@@ -738,12 +974,95 @@ pub struct GetIngestionInfoResponse {
     #[prost(message, optional, tag = "2")]
     pub root_content: ::core::option::Option<ContentMetadata>,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReextractOutdatedContentRequest {
+    #[prost(string, tag = "1")]
+    pub namespace: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub extraction_graph: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub extraction_policy: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReextractOutdatedContentResponse {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PlanExtractionGraphRequest {
+    #[prost(string, tag = "1")]
+    pub namespace: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub extraction_graph: ::prost::alloc::string::String,
+    /// Type and labels of the sample content the graph is planned for
+    #[prost(string, tag = "3")]
+    pub content_type: ::prost::alloc::string::String,
+    #[prost(map = "string, string", tag = "4")]
+    pub labels: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PlannedExtraction {
+    #[prost(string, tag = "1")]
+    pub extraction_policy: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub extractor: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub content_source: ::prost::alloc::string::String,
+    /// Number of policies the content goes through before this one
+    #[prost(uint32, tag = "4")]
+    pub depth: u32,
+    /// Types of the content the policy is applied to, empty when the parent
+    /// extractor doesn't declare the types it outputs
+    #[prost(string, repeated, tag = "5")]
+    pub content_types: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(map = "string, string", tag = "6")]
+    pub output_table_mapping: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PlanExtractionGraphResponse {
+    /// Policies applied to the content, in the order they are applied
+    #[prost(message, repeated, tag = "1")]
+    pub extractions: ::prost::alloc::vec::Vec<PlannedExtraction>,
+}
+/// Cancels the unfinished tasks of the namespace. At most one of task_ids,
+/// content_id and extraction_policy can be set, all the unfinished tasks of the
+/// namespace are cancelled when none is.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CancelTasksRequest {
+    #[prost(string, tag = "1")]
+    pub namespace: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "2")]
+    pub task_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Cancels the tasks of the content and of the content extracted from it
+    #[prost(string, tag = "3")]
+    pub content_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub extraction_graph: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub extraction_policy: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CancelTasksResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub cancelled_task_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum TaskOutcome {
     Unknown = 0,
     Failed = 1,
     Success = 2,
+    Cancelled = 3,
 }
 impl TaskOutcome {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -755,6 +1074,7 @@ impl TaskOutcome {
             TaskOutcome::Unknown => "UNKNOWN",
             TaskOutcome::Failed => "FAILED",
             TaskOutcome::Success => "SUCCESS",
+            TaskOutcome::Cancelled => "CANCELLED",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -763,6 +1083,7 @@ impl TaskOutcome {
             "UNKNOWN" => Some(Self::Unknown),
             "FAILED" => Some(Self::Failed),
             "SUCCESS" => Some(Self::Success),
+            "CANCELLED" => Some(Self::Cancelled),
             _ => None,
         }
     }
@@ -882,6 +1203,36 @@ pub mod coordinator_service_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        pub async fn create_content_batch(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateContentBatchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CreateContentBatchResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/indexify_coordinator.CoordinatorService/CreateContentBatch",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "indexify_coordinator.CoordinatorService",
+                        "CreateContentBatch",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
         pub async fn tombstone_content(
             &mut self,
             request: impl tonic::IntoRequest<super::TombstoneContentRequest>,
@@ -1002,11 +1353,11 @@ pub mod coordinator_service_client {
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn create_extraction_graph(
+        pub async fn update_content_storage(
             &mut self,
-            request: impl tonic::IntoRequest<super::CreateExtractionGraphRequest>,
+            request: impl tonic::IntoRequest<super::UpdateContentStorageRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::CreateExtractionGraphResponse>,
+            tonic::Response<super::UpdateContentStorageResponse>,
             tonic::Status,
         > {
             self.inner
@@ -1020,23 +1371,23 @@ pub mod coordinator_service_client {
                 })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/indexify_coordinator.CoordinatorService/CreateExtractionGraph",
+                "/indexify_coordinator.CoordinatorService/UpdateContentStorage",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
                     GrpcMethod::new(
                         "indexify_coordinator.CoordinatorService",
-                        "CreateExtractionGraph",
+                        "UpdateContentStorage",
                     ),
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn get_extraction_policy(
+        pub async fn merge_extracted_metadata(
             &mut self,
-            request: impl tonic::IntoRequest<super::GetExtractionPolicyRequest>,
+            request: impl tonic::IntoRequest<super::MergeExtractedMetadataRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::GetExtractionPolicyResponse>,
+            tonic::Response<super::MergeExtractedMetadataResponse>,
             tonic::Status,
         > {
             self.inner
@@ -1050,23 +1401,23 @@ pub mod coordinator_service_client {
                 })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/indexify_coordinator.CoordinatorService/GetExtractionPolicy",
+                "/indexify_coordinator.CoordinatorService/MergeExtractedMetadata",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
                     GrpcMethod::new(
                         "indexify_coordinator.CoordinatorService",
-                        "GetExtractionPolicy",
+                        "MergeExtractedMetadata",
                     ),
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn list_extraction_policies(
+        pub async fn get_content_tree_status(
             &mut self,
-            request: impl tonic::IntoRequest<super::ListExtractionPoliciesRequest>,
+            request: impl tonic::IntoRequest<super::GetContentTreeStatusRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::ListExtractionPoliciesResponse>,
+            tonic::Response<super::GetContentTreeStatusResponse>,
             tonic::Status,
         > {
             self.inner
@@ -1080,23 +1431,23 @@ pub mod coordinator_service_client {
                 })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/indexify_coordinator.CoordinatorService/ListExtractionPolicies",
+                "/indexify_coordinator.CoordinatorService/GetContentTreeStatus",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
                     GrpcMethod::new(
                         "indexify_coordinator.CoordinatorService",
-                        "ListExtractionPolicies",
+                        "GetContentTreeStatus",
                     ),
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn create_ns(
+        pub async fn update_index_store(
             &mut self,
-            request: impl tonic::IntoRequest<super::CreateNamespaceRequest>,
+            request: impl tonic::IntoRequest<super::UpdateIndexStoreRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::CreateNamespaceResponse>,
+            tonic::Response<super::UpdateIndexStoreResponse>,
             tonic::Status,
         > {
             self.inner
@@ -1110,23 +1461,23 @@ pub mod coordinator_service_client {
                 })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/indexify_coordinator.CoordinatorService/CreateNS",
+                "/indexify_coordinator.CoordinatorService/UpdateIndexStore",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
                     GrpcMethod::new(
                         "indexify_coordinator.CoordinatorService",
-                        "CreateNS",
+                        "UpdateIndexStore",
                     ),
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn list_ns(
+        pub async fn list_index_stores(
             &mut self,
-            request: impl tonic::IntoRequest<super::ListNamespaceRequest>,
+            request: impl tonic::IntoRequest<super::ListIndexStoresRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::ListNamespaceResponse>,
+            tonic::Response<super::ListIndexStoresResponse>,
             tonic::Status,
         > {
             self.inner
@@ -1140,20 +1491,23 @@ pub mod coordinator_service_client {
                 })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/indexify_coordinator.CoordinatorService/ListNS",
+                "/indexify_coordinator.CoordinatorService/ListIndexStores",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
-                    GrpcMethod::new("indexify_coordinator.CoordinatorService", "ListNS"),
+                    GrpcMethod::new(
+                        "indexify_coordinator.CoordinatorService",
+                        "ListIndexStores",
+                    ),
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn get_ns(
+        pub async fn create_extraction_graph(
             &mut self,
-            request: impl tonic::IntoRequest<super::GetNamespaceRequest>,
+            request: impl tonic::IntoRequest<super::CreateExtractionGraphRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::GetNamespaceResponse>,
+            tonic::Response<super::CreateExtractionGraphResponse>,
             tonic::Status,
         > {
             self.inner
@@ -1167,20 +1521,23 @@ pub mod coordinator_service_client {
                 })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/indexify_coordinator.CoordinatorService/GetNS",
+                "/indexify_coordinator.CoordinatorService/CreateExtractionGraph",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
-                    GrpcMethod::new("indexify_coordinator.CoordinatorService", "GetNS"),
+                    GrpcMethod::new(
+                        "indexify_coordinator.CoordinatorService",
+                        "CreateExtractionGraph",
+                    ),
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn list_extractors(
+        pub async fn get_extraction_policy(
             &mut self,
-            request: impl tonic::IntoRequest<super::ListExtractorsRequest>,
+            request: impl tonic::IntoRequest<super::GetExtractionPolicyRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::ListExtractorsResponse>,
+            tonic::Response<super::GetExtractionPolicyResponse>,
             tonic::Status,
         > {
             self.inner
@@ -1194,23 +1551,23 @@ pub mod coordinator_service_client {
                 })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/indexify_coordinator.CoordinatorService/ListExtractors",
+                "/indexify_coordinator.CoordinatorService/GetExtractionPolicy",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
                     GrpcMethod::new(
                         "indexify_coordinator.CoordinatorService",
-                        "ListExtractors",
+                        "GetExtractionPolicy",
                     ),
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn register_executor(
+        pub async fn list_extraction_policies(
             &mut self,
-            request: impl tonic::IntoRequest<super::RegisterExecutorRequest>,
+            request: impl tonic::IntoRequest<super::ListExtractionPoliciesRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::RegisterExecutorResponse>,
+            tonic::Response<super::ListExtractionPoliciesResponse>,
             tonic::Status,
         > {
             self.inner
@@ -1224,19 +1581,163 @@ pub mod coordinator_service_client {
                 })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/indexify_coordinator.CoordinatorService/RegisterExecutor",
+                "/indexify_coordinator.CoordinatorService/ListExtractionPolicies",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
                     GrpcMethod::new(
                         "indexify_coordinator.CoordinatorService",
-                        "RegisterExecutor",
+                        "ListExtractionPolicies",
                     ),
                 );
             self.inner.unary(req, path, codec).await
         }
-        pub async fn register_ingestion_server(
+        pub async fn create_ns(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateNamespaceRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CreateNamespaceResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/indexify_coordinator.CoordinatorService/CreateNS",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "indexify_coordinator.CoordinatorService",
+                        "CreateNS",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_ns(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListNamespaceRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListNamespaceResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/indexify_coordinator.CoordinatorService/ListNS",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("indexify_coordinator.CoordinatorService", "ListNS"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_ns(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetNamespaceRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetNamespaceResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/indexify_coordinator.CoordinatorService/GetNS",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("indexify_coordinator.CoordinatorService", "GetNS"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_extractors(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListExtractorsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListExtractorsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/indexify_coordinator.CoordinatorService/ListExtractors",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "indexify_coordinator.CoordinatorService",
+                        "ListExtractors",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn register_executor(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RegisterExecutorRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RegisterExecutorResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/indexify_coordinator.CoordinatorService/RegisterExecutor",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "indexify_coordinator.CoordinatorService",
+                        "RegisterExecutor",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn register_ingestion_server(
             &mut self,
             request: impl tonic::IntoRequest<super::RegisterIngestionServerRequest>,
         ) -> std::result::Result<
@@ -1775,6 +2276,96 @@ pub mod coordinator_service_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        pub async fn reextract_outdated_content(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ReextractOutdatedContentRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReextractOutdatedContentResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/indexify_coordinator.CoordinatorService/ReextractOutdatedContent",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "indexify_coordinator.CoordinatorService",
+                        "ReextractOutdatedContent",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn plan_extraction_graph(
+            &mut self,
+            request: impl tonic::IntoRequest<super::PlanExtractionGraphRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PlanExtractionGraphResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/indexify_coordinator.CoordinatorService/PlanExtractionGraph",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "indexify_coordinator.CoordinatorService",
+                        "PlanExtractionGraph",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn cancel_tasks(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CancelTasksRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CancelTasksResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/indexify_coordinator.CoordinatorService/CancelTasks",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "indexify_coordinator.CoordinatorService",
+                        "CancelTasks",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -1791,6 +2382,13 @@ pub mod coordinator_service_server {
             tonic::Response<super::CreateContentResponse>,
             tonic::Status,
         >;
+        async fn create_content_batch(
+            &self,
+            request: tonic::Request<super::CreateContentBatchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CreateContentBatchResponse>,
+            tonic::Status,
+        >;
         async fn tombstone_content(
             &self,
             request: tonic::Request<super::TombstoneContentRequest>,
@@ -1819,6 +2417,41 @@ pub mod coordinator_service_server {
             tonic::Response<super::ListContentResponse>,
             tonic::Status,
         >;
+        async fn update_content_storage(
+            &self,
+            request: tonic::Request<super::UpdateContentStorageRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UpdateContentStorageResponse>,
+            tonic::Status,
+        >;
+        async fn merge_extracted_metadata(
+            &self,
+            request: tonic::Request<super::MergeExtractedMetadataRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MergeExtractedMetadataResponse>,
+            tonic::Status,
+        >;
+        async fn get_content_tree_status(
+            &self,
+            request: tonic::Request<super::GetContentTreeStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetContentTreeStatusResponse>,
+            tonic::Status,
+        >;
+        async fn update_index_store(
+            &self,
+            request: tonic::Request<super::UpdateIndexStoreRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UpdateIndexStoreResponse>,
+            tonic::Status,
+        >;
+        async fn list_index_stores(
+            &self,
+            request: tonic::Request<super::ListIndexStoresRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListIndexStoresResponse>,
+            tonic::Status,
+        >;
         async fn create_extraction_graph(
             &self,
             request: tonic::Request<super::CreateExtractionGraphRequest>,
@@ -2004,6 +2637,27 @@ pub mod coordinator_service_server {
             tonic::Response<super::GetIngestionInfoResponse>,
             tonic::Status,
         >;
+        async fn reextract_outdated_content(
+            &self,
+            request: tonic::Request<super::ReextractOutdatedContentRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReextractOutdatedContentResponse>,
+            tonic::Status,
+        >;
+        async fn plan_extraction_graph(
+            &self,
+            request: tonic::Request<super::PlanExtractionGraphRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PlanExtractionGraphResponse>,
+            tonic::Status,
+        >;
+        async fn cancel_tasks(
+            &self,
+            request: tonic::Request<super::CancelTasksRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CancelTasksResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct CoordinatorServiceServer<T: CoordinatorService> {
@@ -2086,23 +2740,267 @@ pub mod coordinator_service_server {
             match req.uri().path() {
                 "/indexify_coordinator.CoordinatorService/CreateContent" => {
                     #[allow(non_camel_case_types)]
-                    struct CreateContentSvc<T: CoordinatorService>(pub Arc<T>);
+                    struct CreateContentSvc<T: CoordinatorService>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorService,
+                    > tonic::server::UnaryService<super::CreateContentRequest>
+                    for CreateContentSvc<T> {
+                        type Response = super::CreateContentResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CreateContentRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CoordinatorService>::create_content(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CreateContentSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/indexify_coordinator.CoordinatorService/CreateContentBatch" => {
+                    #[allow(non_camel_case_types)]
+                    struct CreateContentBatchSvc<T: CoordinatorService>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorService,
+                    > tonic::server::UnaryService<super::CreateContentBatchRequest>
+                    for CreateContentBatchSvc<T> {
+                        type Response = super::CreateContentBatchResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CreateContentBatchRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CoordinatorService>::create_content_batch(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CreateContentBatchSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/indexify_coordinator.CoordinatorService/TombstoneContent" => {
+                    #[allow(non_camel_case_types)]
+                    struct TombstoneContentSvc<T: CoordinatorService>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorService,
+                    > tonic::server::UnaryService<super::TombstoneContentRequest>
+                    for TombstoneContentSvc<T> {
+                        type Response = super::TombstoneContentResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TombstoneContentRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CoordinatorService>::tombstone_content(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = TombstoneContentSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/indexify_coordinator.CoordinatorService/GetContentMetadata" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetContentMetadataSvc<T: CoordinatorService>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorService,
+                    > tonic::server::UnaryService<super::GetContentMetadataRequest>
+                    for GetContentMetadataSvc<T> {
+                        type Response = super::GetContentMetadataResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetContentMetadataRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CoordinatorService>::get_content_metadata(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetContentMetadataSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/indexify_coordinator.CoordinatorService/GetContentTreeMetadata" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetContentTreeMetadataSvc<T: CoordinatorService>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorService,
+                    > tonic::server::UnaryService<super::GetContentTreeMetadataRequest>
+                    for GetContentTreeMetadataSvc<T> {
+                        type Response = super::GetContentTreeMetadataResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetContentTreeMetadataRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CoordinatorService>::get_content_tree_metadata(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetContentTreeMetadataSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/indexify_coordinator.CoordinatorService/ListContent" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListContentSvc<T: CoordinatorService>(pub Arc<T>);
                     impl<
                         T: CoordinatorService,
-                    > tonic::server::UnaryService<super::CreateContentRequest>
-                    for CreateContentSvc<T> {
-                        type Response = super::CreateContentResponse;
+                    > tonic::server::UnaryService<super::ListContentRequest>
+                    for ListContentSvc<T> {
+                        type Response = super::ListContentResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::CreateContentRequest>,
+                            request: tonic::Request<super::ListContentRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as CoordinatorService>::create_content(&inner, request)
+                                <T as CoordinatorService>::list_content(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -2115,7 +3013,7 @@ pub mod coordinator_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = CreateContentSvc(inner);
+                        let method = ListContentSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -2131,28 +3029,25 @@ pub mod coordinator_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/indexify_coordinator.CoordinatorService/TombstoneContent" => {
+                "/indexify_coordinator.CoordinatorService/UpdateContentStorage" => {
                     #[allow(non_camel_case_types)]
-                    struct TombstoneContentSvc<T: CoordinatorService>(pub Arc<T>);
+                    struct UpdateContentStorageSvc<T: CoordinatorService>(pub Arc<T>);
                     impl<
                         T: CoordinatorService,
-                    > tonic::server::UnaryService<super::TombstoneContentRequest>
-                    for TombstoneContentSvc<T> {
-                        type Response = super::TombstoneContentResponse;
+                    > tonic::server::UnaryService<super::UpdateContentStorageRequest>
+                    for UpdateContentStorageSvc<T> {
+                        type Response = super::UpdateContentStorageResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::TombstoneContentRequest>,
+                            request: tonic::Request<super::UpdateContentStorageRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as CoordinatorService>::tombstone_content(
-                                        &inner,
-                                        request,
-                                    )
+                                <T as CoordinatorService>::update_content_storage(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -2165,7 +3060,7 @@ pub mod coordinator_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = TombstoneContentSvc(inner);
+                        let method = UpdateContentStorageSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -2181,28 +3076,25 @@ pub mod coordinator_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/indexify_coordinator.CoordinatorService/GetContentMetadata" => {
+                "/indexify_coordinator.CoordinatorService/MergeExtractedMetadata" => {
                     #[allow(non_camel_case_types)]
-                    struct GetContentMetadataSvc<T: CoordinatorService>(pub Arc<T>);
+                    struct MergeExtractedMetadataSvc<T: CoordinatorService>(pub Arc<T>);
                     impl<
                         T: CoordinatorService,
-                    > tonic::server::UnaryService<super::GetContentMetadataRequest>
-                    for GetContentMetadataSvc<T> {
-                        type Response = super::GetContentMetadataResponse;
+                    > tonic::server::UnaryService<super::MergeExtractedMetadataRequest>
+                    for MergeExtractedMetadataSvc<T> {
+                        type Response = super::MergeExtractedMetadataResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::GetContentMetadataRequest>,
+                            request: tonic::Request<super::MergeExtractedMetadataRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as CoordinatorService>::get_content_metadata(
-                                        &inner,
-                                        request,
-                                    )
+                                <T as CoordinatorService>::merge_extracted_metadata(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -2215,7 +3107,7 @@ pub mod coordinator_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = GetContentMetadataSvc(inner);
+                        let method = MergeExtractedMetadataSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -2231,28 +3123,25 @@ pub mod coordinator_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/indexify_coordinator.CoordinatorService/GetContentTreeMetadata" => {
+                "/indexify_coordinator.CoordinatorService/GetContentTreeStatus" => {
                     #[allow(non_camel_case_types)]
-                    struct GetContentTreeMetadataSvc<T: CoordinatorService>(pub Arc<T>);
+                    struct GetContentTreeStatusSvc<T: CoordinatorService>(pub Arc<T>);
                     impl<
                         T: CoordinatorService,
-                    > tonic::server::UnaryService<super::GetContentTreeMetadataRequest>
-                    for GetContentTreeMetadataSvc<T> {
-                        type Response = super::GetContentTreeMetadataResponse;
+                    > tonic::server::UnaryService<super::GetContentTreeStatusRequest>
+                    for GetContentTreeStatusSvc<T> {
+                        type Response = super::GetContentTreeStatusResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::GetContentTreeMetadataRequest>,
+                            request: tonic::Request<super::GetContentTreeStatusRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as CoordinatorService>::get_content_tree_metadata(
-                                        &inner,
-                                        request,
-                                    )
+                                <T as CoordinatorService>::get_content_tree_status(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -2265,7 +3154,7 @@ pub mod coordinator_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = GetContentTreeMetadataSvc(inner);
+                        let method = GetContentTreeStatusSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -2281,25 +3170,25 @@ pub mod coordinator_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/indexify_coordinator.CoordinatorService/ListContent" => {
+                "/indexify_coordinator.CoordinatorService/UpdateIndexStore" => {
                     #[allow(non_camel_case_types)]
-                    struct ListContentSvc<T: CoordinatorService>(pub Arc<T>);
+                    struct UpdateIndexStoreSvc<T: CoordinatorService>(pub Arc<T>);
                     impl<
                         T: CoordinatorService,
-                    > tonic::server::UnaryService<super::ListContentRequest>
-                    for ListContentSvc<T> {
-                        type Response = super::ListContentResponse;
+                    > tonic::server::UnaryService<super::UpdateIndexStoreRequest>
+                    for UpdateIndexStoreSvc<T> {
+                        type Response = super::UpdateIndexStoreResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListContentRequest>,
+                            request: tonic::Request<super::UpdateIndexStoreRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as CoordinatorService>::list_content(&inner, request)
+                                <T as CoordinatorService>::update_index_store(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -2312,7 +3201,54 @@ pub mod coordinator_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = ListContentSvc(inner);
+                        let method = UpdateIndexStoreSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/indexify_coordinator.CoordinatorService/ListIndexStores" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListIndexStoresSvc<T: CoordinatorService>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorService,
+                    > tonic::server::UnaryService<super::ListIndexStoresRequest>
+                    for ListIndexStoresSvc<T> {
+                        type Response = super::ListIndexStoresResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListIndexStoresRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CoordinatorService>::list_index_stores(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListIndexStoresSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -3588,6 +4524,147 @@ pub mod coordinator_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/indexify_coordinator.CoordinatorService/ReextractOutdatedContent" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReextractOutdatedContentSvc<T: CoordinatorService>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorService,
+                    > tonic::server::UnaryService<super::ReextractOutdatedContentRequest>
+                    for ReextractOutdatedContentSvc<T> {
+                        type Response = super::ReextractOutdatedContentResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ReextractOutdatedContentRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CoordinatorService>::reextract_outdated_content(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ReextractOutdatedContentSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/indexify_coordinator.CoordinatorService/PlanExtractionGraph" => {
+                    #[allow(non_camel_case_types)]
+                    struct PlanExtractionGraphSvc<T: CoordinatorService>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorService,
+                    > tonic::server::UnaryService<super::PlanExtractionGraphRequest>
+                    for PlanExtractionGraphSvc<T> {
+                        type Response = super::PlanExtractionGraphResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PlanExtractionGraphRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CoordinatorService>::plan_extraction_graph(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = PlanExtractionGraphSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/indexify_coordinator.CoordinatorService/CancelTasks" => {
+                    #[allow(non_camel_case_types)]
+                    struct CancelTasksSvc<T: CoordinatorService>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorService,
+                    > tonic::server::UnaryService<super::CancelTasksRequest>
+                    for CancelTasksSvc<T> {
+                        type Response = super::CancelTasksResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CancelTasksRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CoordinatorService>::cancel_tasks(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CancelTasksSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -1,4 +1,6 @@
 #[rustfmt::skip]
 pub mod indexify_coordinator;
 #[rustfmt::skip]
+pub mod indexify_data_plane;
+#[rustfmt::skip]
 pub mod indexify_raft;
@@ -46,6 +46,12 @@ pub struct ExtractionPolicy {
     pub name: String,
     #[serde(default, deserialize_with = "api_utils::deserialize_labels_eq_filter")]
     pub filters_eq: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub conditions: Vec<internal_api::RoutingCondition>,
+    #[serde(default)]
+    pub limits: internal_api::ExtractionPolicyLimits,
+    #[serde(default)]
+    pub affinity: internal_api::ExecutorAffinity,
     pub input_params: Option<serde_json::Value>,
     pub content_source: Option<String>,
     pub graph_name: String,
@@ -58,6 +64,9 @@ impl From<indexify_coordinator::ExtractionPolicy> for ExtractionPolicy {
             extractor: value.extractor,
             name: value.name,
             filters_eq: Some(value.filters),
+            conditions: serde_json::from_str(&value.conditions).unwrap_or_default(),
+            limits: value.limits.map(|l| l.into()).unwrap_or_default(),
+            affinity: value.affinity.map(|a| a.into()).unwrap_or_default(),
             input_params: Some(serde_json::from_str(&value.input_params).unwrap()),
             content_source: Some(value.content_source),
             graph_name: value.graph_name,
@@ -148,8 +157,22 @@ pub struct ExtractionPolicyRequest {
     pub name: String,
     #[serde(default, deserialize_with = "api_utils::deserialize_labels_eq_filter")]
     pub filters_eq: Option<HashMap<String, String>>,
+    /// Conditions content has to meet, in addition to matching the labels of
+    /// filters_eq, for the policy to be applied to it.
+    #[serde(default)]
+    pub conditions: Vec<internal_api::RoutingCondition>,
+    /// Content size, run time and executor resource limits of the policy.
+    #[serde(default)]
+    pub limits: internal_api::ExtractionPolicyLimits,
+    /// Labels of the executors the tasks of the policy are assigned to.
+    #[serde(default)]
+    pub affinity: internal_api::ExecutorAffinity,
     pub input_params: Option<serde_json::Value>,
     pub content_source: Option<String>,
+    /// Options of the vector indexes created for the embedding outputs of the
+    /// extractor, by output name.
+    #[serde(default)]
+    pub index_options: HashMap<String, vectordbs::IndexOptions>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, ToSchema)]
@@ -194,12 +217,18 @@ pub struct RunExtractorsResponse {}
 pub struct EmbeddingSchema {
     pub dim: usize,
     pub distance: IndexDistance,
+    #[serde(default, skip_serializing_if = "internal_api::EmbeddingKind::is_dense")]
+    pub kind: internal_api::EmbeddingKind,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Display, ToSchema)]
 pub enum ExtractorOutputSchema {
     #[serde(rename = "embedding")]
     Embedding(EmbeddingSchema),
+    #[serde(rename = "sparse_embedding")]
+    SparseEmbedding(EmbeddingSchema),
+    #[serde(rename = "multi_vector_embedding")]
+    MultiVectorEmbedding(EmbeddingSchema),
     #[serde(rename = "metadata")]
     Metadata(serde_json::Value),
 }
@@ -211,6 +240,10 @@ pub struct ExtractorDescription {
     pub description: String,
     pub input_params: serde_json::Value,
     pub outputs: HashMap<String, ExtractorOutputSchema>,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub output_mime_types: Vec<String>,
 }
 
 impl TryFrom<indexify_coordinator::Extractor> for ExtractorDescription {
@@ -220,7 +253,16 @@ impl TryFrom<indexify_coordinator::Extractor> for ExtractorDescription {
         let mut outputs = HashMap::new();
         for (k, v) in value.embedding_schemas.iter() {
             let schema: EmbeddingSchema = serde_json::from_str(v)?;
-            outputs.insert(k.clone(), ExtractorOutputSchema::Embedding(schema));
+            let output = match schema.kind {
+                internal_api::EmbeddingKind::Dense => ExtractorOutputSchema::Embedding(schema),
+                internal_api::EmbeddingKind::Sparse => {
+                    ExtractorOutputSchema::SparseEmbedding(schema)
+                }
+                internal_api::EmbeddingKind::MultiVector => {
+                    ExtractorOutputSchema::MultiVectorEmbedding(schema)
+                }
+            };
+            outputs.insert(k.clone(), output);
         }
         for (k, v) in value.metadata_schemas.iter() {
             outputs.insert(
@@ -234,6 +276,8 @@ impl TryFrom<indexify_coordinator::Extractor> for ExtractorDescription {
             input_params: serde_json::from_str(&value.input_params)?,
             outputs,
             input_mime_types: value.input_mime_types,
+            version: value.version,
+            output_mime_types: value.output_mime_types,
         })
     }
 }
@@ -261,6 +305,12 @@ pub struct TextAdditionResponse {}
 pub struct Index {
     pub name: String,
     pub embedding_schema: EmbeddingSchema,
+    /// Vector store the index is in, not set for the default store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_store: Option<String>,
+    /// Version of the extractor the outputs in the index are extracted with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extractor_version: Option<String>,
 }
 
 impl TryFrom<indexify_coordinator::Index> for Index {
@@ -276,6 +326,8 @@ impl TryFrom<indexify_coordinator::Index> for Index {
                     e.to_string()
                 )
             })?,
+            vector_store: Some(value.vector_store).filter(|s| !s.is_empty()),
+            extractor_version: Some(value.extractor_version).filter(|v| !v.is_empty()),
         })
     }
 }
@@ -285,6 +337,80 @@ pub struct ListIndexesResponse {
     pub indexes: Vec<Index>,
 }
 
+/// Moves an index to another of the vector stores configured on the server,
+/// such as `qdrant` or `pg-vector`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MigrateIndexRequest {
+    pub vector_store: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexMigrationState {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Progress of the migration of an index to another vector store.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IndexMigrationStatus {
+    /// Vector store the index is migrated to.
+    pub vector_store: String,
+    pub state: IndexMigrationState,
+    /// Number of vectors the index has in the store, once migrated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_vectors: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, ToSchema)]
+pub struct ReextractOutdatedContentResponse {}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PlanExtractionGraphRequest {
+    /// Type of the sample content the graph is planned for.
+    pub content_type: String,
+    /// Labels of the sample content.
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PlannedExtraction {
+    pub extraction_policy: String,
+    pub extractor: String,
+    /// Policy the content comes from, empty for ingested content.
+    pub content_source: String,
+    /// Number of policies the content goes through before this one.
+    pub depth: u32,
+    /// Types of the content the policy is applied to, empty when the
+    /// extractor of its parent doesn't declare the types it outputs.
+    pub content_types: Vec<String>,
+    /// Index tables the outputs of the extractor are written to.
+    pub output_table_mapping: HashMap<String, String>,
+}
+
+impl From<indexify_coordinator::PlannedExtraction> for PlannedExtraction {
+    fn from(value: indexify_coordinator::PlannedExtraction) -> Self {
+        Self {
+            extraction_policy: value.extraction_policy,
+            extractor: value.extractor,
+            content_source: value.content_source,
+            depth: value.depth,
+            content_types: value.content_types,
+            output_table_mapping: value.output_table_mapping,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PlanExtractionGraphResponse {
+    /// Policies applied to the content, in the order they are applied.
+    pub extractions: Vec<PlannedExtraction>,
+}
+
 #[derive(Debug, Serialize, Deserialize, IntoParams, ToSchema)]
 pub struct SearchRequest {
     pub index: String,
@@ -461,6 +587,18 @@ pub struct Task {
     pub input_params: serde_json::Value,
     pub outcome: i32,
     pub index_tables: Vec<String>,
+    pub extractor_version: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub failure_reason: String,
+    /// Events of the task, oldest first.
+    #[serde(default)]
+    pub history: Vec<internal_api::TaskEvent>,
+    pub created_at: Option<u64>,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    /// Number of times the task was assigned to an executor.
+    #[serde(default)]
+    pub attempts: u64,
 }
 
 impl From<indexify_coordinator::Task> for Task {
@@ -477,6 +615,17 @@ impl From<indexify_coordinator::Task> for Task {
             input_params: serde_json::Value::String(value.input_params),
             outcome: value.outcome, //  EGTODO: Is it correct to just return i32 for value outcome?
             index_tables: value.index_tables,
+            extractor_version: value.extractor_version,
+            failure_reason: value.failure_reason,
+            history: value
+                .history
+                .into_iter()
+                .filter_map(|e| e.try_into().ok())
+                .collect(),
+            created_at: (value.created_at > 0).then_some(value.created_at),
+            started_at: (value.started_at > 0).then_some(value.started_at),
+            finished_at: (value.finished_at > 0).then_some(value.finished_at),
+            attempts: value.attempts,
         }
     }
 }
@@ -561,6 +710,20 @@ pub struct ExtractResponse {
     pub features: Vec<Feature>,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExtractBatchRequest {
+    pub name: String,
+    pub content_list: Vec<Content>,
+    pub input_params: Option<serde_json::Value>,
+}
+
+/// Responses of a batch extraction, in the order of the content of the
+/// request.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExtractBatchResponse {
+    pub outcomes: Vec<ExtractResponse>,
+}
+
 impl From<internal_api::ExtractResponse> for ExtractResponse {
     fn from(internal_resp: internal_api::ExtractResponse) -> Self {
         ExtractResponse {
@@ -616,6 +779,12 @@ pub struct BeginExtractedContentIngest {
     pub task_id: String,
     pub executor_id: String,
     pub task_outcome: internal_api::TaskOutcome,
+    /// When the executor started running the task, in seconds since the epoch.
+    #[serde(default)]
+    pub started_at: Option<u64>,
+    /// Why the task failed, when it did.
+    #[serde(default)]
+    pub failure_reason: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
@@ -647,6 +816,18 @@ pub struct GetExtractedMetadataResponse {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListTasks {
     pub extraction_policy: Option<String>,
+    pub outcome: Option<internal_api::TaskOutcome>,
+    pub executor_id: Option<String>,
+    pub content_id: Option<String>,
+    /// Only tasks created at or after this time, in seconds since the epoch.
+    pub created_after: Option<u64>,
+    /// Only tasks created at or before this time, in seconds since the epoch.
+    pub created_before: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GetTaskResponse {
+    pub task: Task,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -654,6 +835,25 @@ pub struct ListTasksResponse {
     pub tasks: Vec<Task>,
 }
 
+/// Unfinished tasks of the namespace to cancel. At most one of `task_ids`,
+/// `content_id` and `extraction_policy` can be set, all the unfinished tasks
+/// of the namespace are cancelled when none is.
+#[derive(Debug, Serialize, Deserialize, Default, ToSchema)]
+pub struct CancelTasksRequest {
+    #[serde(default)]
+    pub task_ids: Vec<String>,
+    /// Cancels the tasks of the content and of the content extracted from it.
+    pub content_id: Option<String>,
+    /// Graph of `extraction_policy`.
+    pub extraction_graph: Option<String>,
+    pub extraction_policy: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CancelTasksResponse {
+    pub cancelled_task_ids: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListStateChanges {
     pub start_at: Option<u64>,
@@ -704,6 +904,17 @@ pub struct SqlQueryResponse {
     pub rows: Vec<serde_json::Value>,
 }
 
+/// How a remote file is ingested.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteFileIngestMode {
+    /// The file is referenced where it is and only its size is looked up.
+    #[default]
+    Probe,
+    /// The file is copied into the blob store and hashed.
+    Pin,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IngestRemoteFile {
     pub id: Option<String>,
@@ -711,6 +922,10 @@ pub struct IngestRemoteFile {
     pub mime_type: String,
     pub labels: HashMap<String, String>,
     pub extraction_graph_names: Vec<String>,
+    #[serde(default)]
+    pub mode: RemoteFileIngestMode,
+    /// The expected sha256 of the file, checked when it is pinned.
+    pub sha256: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -718,6 +933,64 @@ pub struct IngestRemoteFileResponse {
     pub content_id: String,
 }
 
+/// Where the content of an item of a batch ingestion comes from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchIngestSource {
+    Text {
+        text: String,
+    },
+    RemoteFile {
+        url: String,
+        mime_type: String,
+        #[serde(default)]
+        mode: RemoteFileIngestMode,
+        sha256: Option<String>,
+    },
+    /// A file uploaded in the multipart field named `part`.
+    File {
+        part: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchIngestItem {
+    pub id: Option<String>,
+    /// Content is created only once for an idempotency key, later items with
+    /// the same key report the content created by the first one.
+    pub idempotency_key: Option<String>,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    #[serde(flatten)]
+    pub source: BatchIngestSource,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchIngestRequest {
+    pub items: Vec<BatchIngestItem>,
+    pub extraction_graph_names: Vec<internal_api::ExtractionGraphName>,
+}
+
+/// The outcome of an item of a batch ingestion. Exactly one of `content_id`
+/// and `error` is set.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchIngestItemResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_id: Option<String>,
+    /// Whether the content was created before, by an item with the same
+    /// idempotency key.
+    #[serde(default)]
+    pub duplicate: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchIngestResponse {
+    /// The outcome of every item, in the order of the request.
+    pub results: Vec<BatchIngestItemResult>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TaskAssignments {
     pub assignments: HashMap<String, String>,
@@ -728,6 +1001,72 @@ pub struct UploadFileResponse {
     pub content_id: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateUploadSessionRequest {
+    pub id: Option<String>,
+    pub file_name: String,
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    pub extraction_graph_names: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UploadedPart {
+    pub part_number: usize,
+    pub offset: u64,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UploadSessionResponse {
+    pub upload_id: String,
+    pub content_id: String,
+    pub expires_at: u64,
+    pub received_bytes: u64,
+    pub parts: Vec<UploadedPart>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PresignedUploadRequest {
+    pub file_name: String,
+    pub id: Option<String>,
+    pub expires_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PresignedUploadResponse {
+    pub key: String,
+    pub content_id: String,
+    pub upload_token: String,
+    pub upload_url: String,
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct FinalizeUploadRequest {
+    pub key: String,
+    pub upload_token: String,
+    /// Hex encoded sha256 of the uploaded file, required for S3 where the
+    /// bytes don't go through the server.
+    pub sha256: Option<String>,
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    pub extraction_graph_names: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+pub struct PresignedDownloadParams {
+    pub expires_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PresignedDownloadResponse {
+    pub download_url: String,
+    pub expires_at: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExtractionGraphRequest {
     pub name: String,
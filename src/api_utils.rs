@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Range};
 
 use anyhow::{anyhow, Result};
 use serde::Deserialize;
@@ -417,3 +417,130 @@ mod test_deserialize_labels_eq_filter {
         }
     }
 }
+
+/// Parses an HTTP `Range` header value for an object of `size` bytes into a
+/// half-open byte range.
+///
+/// Returns `Ok(None)` when the header should be ignored and the whole object
+/// served, e.g. for unknown units, multi-range requests or syntactically
+/// invalid ranges (RFC 9110 section 14.2), and an error when the range can't
+/// be satisfied.
+pub fn parse_range_header(value: &str, size: u64) -> Result<Option<Range<u64>>> {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return Ok(None);
+    };
+    let parse = |pos: &str| pos.trim().parse::<u64>().ok();
+    let range = match (start.trim().is_empty(), end.trim().is_empty()) {
+        // bytes=-N, the last N bytes
+        (true, false) => {
+            let Some(suffix) = parse(end) else {
+                return Ok(None);
+            };
+            if suffix == 0 {
+                return Err(anyhow!("range not satisfiable - {}", value));
+            }
+            size.saturating_sub(suffix)..size
+        }
+        // bytes=N-, from N to the end
+        (false, true) => {
+            let Some(start) = parse(start) else {
+                return Ok(None);
+            };
+            start..size
+        }
+        // bytes=N-M, both ends inclusive
+        (false, false) => {
+            let (Some(start), Some(end)) = (parse(start), parse(end)) else {
+                return Ok(None);
+            };
+            if end < start {
+                return Ok(None);
+            }
+            start..(end + 1).min(size)
+        }
+        (true, true) => return Ok(None),
+    };
+    if range.start >= size {
+        return Err(anyhow!("range not satisfiable - {}", value));
+    }
+    Ok(Some(range))
+}
+
+/// Checks whether an `If-None-Match` header value matches the given entity
+/// tag, using the weak comparison.
+pub fn etag_matches(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// Checks whether an `If-Range` header value matches the given entity tag.
+///
+/// `If-Range` requires the strong comparison, weak validators and dates never
+/// match and the whole object is served instead.
+pub fn if_range_matches(header: &str, etag: &str) -> bool {
+    let tag = header.trim();
+    !tag.starts_with("W/") && tag == etag
+}
+
+#[cfg(test)]
+mod test_range_header {
+    use super::*;
+
+    #[test]
+    fn test_parse_range_header() {
+        let valid = vec![
+            ("bytes=0-4", Some(0..5)),
+            ("bytes=5-", Some(5..10)),
+            ("bytes=-3", Some(7..10)),
+            ("bytes=-20", Some(0..10)),
+            ("bytes=8-100", Some(8..10)),
+            ("bytes=0-0", Some(0..1)),
+            ("bytes=0-1,4-5", None),
+            ("items=0-4", None),
+            // syntactically invalid ranges are ignored
+            ("bytes=4-2", None),
+            ("bytes=-", None),
+            ("bytes=a-b", None),
+            ("bytes=5", None),
+        ];
+        for (header, expected) in valid {
+            let result = parse_range_header(header, 10);
+            assert!(result.is_ok(), "should be valid: {}", header);
+            assert_eq!(result.unwrap(), expected, "header: {}", header);
+        }
+
+        let unsatisfiable = vec!["bytes=10-", "bytes=-0", "bytes=20-30"];
+        for header in unsatisfiable {
+            let result = parse_range_header(header, 10);
+            assert!(result.is_err(), "should be unsatisfiable: {}", header);
+        }
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        assert!(etag_matches("\"abc\"", "\"abc\""));
+        assert!(etag_matches("W/\"abc\"", "\"abc\""));
+        assert!(etag_matches("\"xyz\", \"abc\"", "\"abc\""));
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(!etag_matches("\"xyz\"", "\"abc\""));
+    }
+
+    #[test]
+    fn test_if_range_matches() {
+        assert!(if_range_matches("\"abc\"", "\"abc\""));
+        assert!(!if_range_matches("W/\"abc\"", "\"abc\""));
+        assert!(!if_range_matches("*", "\"abc\""));
+        assert!(!if_range_matches(
+            "Wed, 21 Oct 2015 07:28:00 GMT",
+            "\"abc\""
+        ));
+    }
+}
@@ -0,0 +1,487 @@
+//! Ingestion of a batch of texts, remote files and uploaded files. Every item
+//! is written to the blob store on its own, and the content of all the items
+//! which were written is then created in a single proposal to the
+//! coordinator.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use futures::Stream;
+use indexify_internal_api as internal_api;
+use indexify_proto::indexify_coordinator;
+use tracing::error;
+
+use crate::{
+    api::{
+        self,
+        BatchIngestItem,
+        BatchIngestItemResult,
+        BatchIngestRequest,
+        BatchIngestResponse,
+        BatchIngestSource,
+    },
+    data_manager::DataManager,
+};
+
+#[derive(Debug)]
+enum ItemState {
+    /// The content of the item is yet to be written.
+    Pending {
+        id: String,
+    },
+    /// The content of the item was written, but not created yet. `owns_blob`
+    /// is false for remote files which are referenced in place, whose blob
+    /// must not be deleted when creating the content fails.
+    Written {
+        content_metadata: indexify_coordinator::ContentMetadata,
+        owns_blob: bool,
+    },
+    /// The item has the same id as an earlier item of the batch, created
+    /// with the same idempotency key, and gets its outcome.
+    Duplicate {
+        of: usize,
+    },
+    Done(BatchIngestItemResult),
+}
+
+impl ItemState {
+    fn error(e: impl std::fmt::Display) -> Self {
+        ItemState::Done(BatchIngestItemResult {
+            error: Some(e.to_string()),
+            ..Default::default()
+        })
+    }
+}
+
+/// The id given to an item, either the one provided by the client, one
+/// derived from its idempotency key, or a new one.
+fn item_id(namespace: &str, item: &BatchIngestItem) -> Result<String> {
+    match (&item.id, &item.idempotency_key) {
+        (Some(id), _) => {
+            DataManager::validate_content_id(id)?;
+            Ok(id.clone())
+        }
+        (None, Some(key)) => Ok(DataManager::idempotent_content_id(namespace, key)),
+        (None, None) => Ok(DataManager::make_id()),
+    }
+}
+
+/// Settles the items whose id is invalid, or is the id of content which
+/// exists already or of an earlier item of the batch. Such an item is a
+/// duplicate when it carries an idempotency key, and a failure otherwise.
+fn initial_states(
+    items: &[BatchIngestItem],
+    ids: Vec<Result<String>>,
+    existing: &HashSet<String>,
+) -> Vec<ItemState> {
+    let mut first_with_id: HashMap<String, usize> = HashMap::new();
+    let mut states = Vec::with_capacity(items.len());
+    for (index, (item, id)) in items.iter().zip(ids).enumerate() {
+        let id = match id {
+            Ok(id) => id,
+            Err(e) => {
+                states.push(ItemState::error(e));
+                continue;
+            }
+        };
+        let earlier = first_with_id.get(&id).copied();
+        let state = match (item.idempotency_key.is_some(), earlier) {
+            (true, Some(of)) => ItemState::Duplicate { of },
+            (true, None) if existing.contains(&id) => ItemState::Done(BatchIngestItemResult {
+                content_id: Some(id.clone()),
+                duplicate: true,
+                error: None,
+            }),
+            (false, Some(_)) => ItemState::error(format!(
+                "content with the provided id {} appears twice in the batch",
+                id
+            )),
+            (false, None) if existing.contains(&id) => ItemState::error(format!(
+                "content with the provided id {} already exists",
+                id
+            )),
+            _ => ItemState::Pending { id: id.clone() },
+        };
+        first_with_id.entry(id).or_insert(index);
+        states.push(state);
+    }
+    states
+}
+
+pub struct BatchIngestState {
+    data_manager: Arc<DataManager>,
+    namespace: String,
+    extraction_graph_names: Vec<internal_api::ExtractionGraphName>,
+    items: Vec<BatchIngestItem>,
+    states: Vec<ItemState>,
+}
+
+impl BatchIngestState {
+    pub async fn new(
+        data_manager: Arc<DataManager>,
+        namespace: &str,
+        request: BatchIngestRequest,
+    ) -> Result<Self> {
+        let ids: Vec<Result<String>> = request
+            .items
+            .iter()
+            .map(|item| item_id(namespace, item))
+            .collect();
+        // Only ids chosen by the client can belong to existing content
+        let candidates = request
+            .items
+            .iter()
+            .zip(&ids)
+            .filter(|(item, _)| item.id.is_some() || item.idempotency_key.is_some())
+            .filter_map(|(_, id)| id.as_ref().ok().cloned())
+            .collect();
+        let existing = data_manager
+            .existing_content_ids(namespace, candidates)
+            .await?;
+        let states = initial_states(&request.items, ids, &existing);
+        Ok(Self {
+            data_manager,
+            namespace: namespace.to_string(),
+            extraction_graph_names: request.extraction_graph_names,
+            items: request.items,
+            states,
+        })
+    }
+
+    /// Returns the index of the pending file item uploaded in the multipart
+    /// field named `part`.
+    pub fn file_item(&self, part: &str) -> Option<usize> {
+        self.items
+            .iter()
+            .zip(&self.states)
+            .position(|(item, state)| match (&item.source, state) {
+                (BatchIngestSource::File { part: p }, ItemState::Pending { .. }) => p == part,
+                _ => false,
+            })
+    }
+
+    /// Writes the file of the item at `index` to the blob store.
+    pub async fn write_file(
+        &mut self,
+        index: usize,
+        file_name: Option<&str>,
+        data: impl Stream<Item = Result<Bytes>> + Send + Unpin,
+    ) {
+        let ItemState::Pending { id } = &self.states[index] else {
+            return;
+        };
+        let (name, content_mime) = DataManager::upload_file_name(file_name.unwrap_or_default());
+        let res = self
+            .data_manager
+            .upload_file(
+                &self.namespace,
+                data,
+                &name,
+                content_mime,
+                self.items[index].labels.clone(),
+                Some(id.as_str()),
+                self.extraction_graph_names.clone(),
+            )
+            .await;
+        self.states[index] = match res {
+            Ok(content_metadata) => ItemState::Written {
+                content_metadata,
+                owns_blob: true,
+            },
+            Err(e) => ItemState::error(e),
+        };
+    }
+
+    /// Writes the texts and remote files of the batch. File items whose file
+    /// was not uploaded fail.
+    pub async fn write_items(&mut self) {
+        for index in 0..self.items.len() {
+            let ItemState::Pending { id } = &self.states[index] else {
+                continue;
+            };
+            let item = &self.items[index];
+            let res = match &item.source {
+                BatchIngestSource::Text { text } => {
+                    let document = api::Text {
+                        id: Some(id.clone()),
+                        text: text.clone(),
+                        labels: item.labels.clone(),
+                    };
+                    self.data_manager
+                        .write_text(
+                            &self.namespace,
+                            DataManager::text_content(document, &self.extraction_graph_names),
+                            &self.extraction_graph_names,
+                        )
+                        .await
+                        .map(|content_metadata| (content_metadata, true))
+                }
+                BatchIngestSource::RemoteFile {
+                    url,
+                    mime_type,
+                    mode,
+                    sha256,
+                } => self
+                    .data_manager
+                    .read_remote_file(
+                        &self.namespace,
+                        id,
+                        url,
+                        mime_type,
+                        item.labels.clone(),
+                        &self.extraction_graph_names,
+                        *mode,
+                        sha256.clone(),
+                    )
+                    .await
+                    .map(|content_metadata| {
+                        (content_metadata, *mode == api::RemoteFileIngestMode::Pin)
+                    }),
+                BatchIngestSource::File { part } => {
+                    Err(anyhow!("no file was uploaded in the part {}", part))
+                }
+            };
+            self.states[index] = match res {
+                Ok((content_metadata, owns_blob)) => ItemState::Written {
+                    content_metadata,
+                    owns_blob,
+                },
+                Err(e) => ItemState::error(e),
+            };
+        }
+    }
+
+    fn written_content(&self) -> Vec<indexify_coordinator::ContentMetadata> {
+        self.states
+            .iter()
+            .filter_map(|state| match state {
+                ItemState::Written {
+                    content_metadata, ..
+                } => Some(content_metadata.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    async fn delete_blob(&self, content_metadata: &indexify_coordinator::ContentMetadata) {
+        if let Err(e) = self
+            .data_manager
+            .delete_file(&content_metadata.storage_url)
+            .await
+        {
+            error!(
+                "unable to delete blob {}: {}",
+                content_metadata.storage_url, e
+            );
+        }
+    }
+
+    /// Settles the written items whose content was created by another request
+    /// in the meantime, like the items whose content existed already.
+    async fn settle_existing(&mut self, existing: &HashSet<String>) {
+        for index in 0..self.states.len() {
+            let ItemState::Written {
+                content_metadata,
+                owns_blob,
+            } = &self.states[index]
+            else {
+                continue;
+            };
+            if !existing.contains(&content_metadata.id) {
+                continue;
+            }
+            if *owns_blob {
+                self.delete_blob(content_metadata).await;
+            }
+            let id = content_metadata.id.clone();
+            self.states[index] = if self.items[index].idempotency_key.is_some() {
+                ItemState::Done(BatchIngestItemResult {
+                    content_id: Some(id),
+                    duplicate: true,
+                    error: None,
+                })
+            } else {
+                ItemState::error(format!(
+                    "content with the provided id {} already exists",
+                    id
+                ))
+            };
+        }
+    }
+
+    /// Creates the content of all the written items, and returns the outcome
+    /// of every item. The coordinator rejects the batch when content with the
+    /// id of an item was created since the batch was checked; such items are
+    /// settled like the items whose content existed already, and the rest of
+    /// the batch is created again. When creating the content fails otherwise,
+    /// every written item fails and the blobs written for them are deleted.
+    pub async fn finish(mut self) -> BatchIngestResponse {
+        let res = loop {
+            let content_list = self.written_content();
+            let ids = content_list.iter().map(|c| c.id.clone()).collect();
+            let Err(e) = self.data_manager.create_content_batch(content_list).await else {
+                break Ok(());
+            };
+            match self
+                .data_manager
+                .existing_content_ids(&self.namespace, ids)
+                .await
+            {
+                Ok(existing) if !existing.is_empty() => self.settle_existing(&existing).await,
+                _ => break Err(e),
+            }
+        };
+        let mut results: Vec<BatchIngestItemResult> = Vec::with_capacity(self.states.len());
+        for state in std::mem::take(&mut self.states) {
+            let result = match state {
+                ItemState::Written {
+                    content_metadata,
+                    owns_blob,
+                } => match &res {
+                    Ok(()) => BatchIngestItemResult {
+                        content_id: Some(content_metadata.id),
+                        ..Default::default()
+                    },
+                    Err(e) => {
+                        if owns_blob {
+                            self.delete_blob(&content_metadata).await;
+                        }
+                        BatchIngestItemResult {
+                            error: Some(e.to_string()),
+                            ..Default::default()
+                        }
+                    }
+                },
+                ItemState::Duplicate { of } => {
+                    let mut result = results[of].clone();
+                    result.duplicate = result.content_id.is_some();
+                    result
+                }
+                ItemState::Done(result) => result,
+                ItemState::Pending { .. } => BatchIngestItemResult {
+                    error: Some("the item was not ingested".to_string()),
+                    ..Default::default()
+                },
+            };
+            results.push(result);
+        }
+        BatchIngestResponse { results }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_item(id: Option<&str>, idempotency_key: Option<&str>) -> BatchIngestItem {
+        BatchIngestItem {
+            id: id.map(str::to_string),
+            idempotency_key: idempotency_key.map(str::to_string),
+            labels: HashMap::new(),
+            source: BatchIngestSource::Text {
+                text: "text".to_string(),
+            },
+        }
+    }
+
+    fn pending_ids(states: &[ItemState]) -> Vec<Option<&str>> {
+        states
+            .iter()
+            .map(|state| match state {
+                ItemState::Pending { id } => Some(id.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_item_id() {
+        let item = text_item(Some("abc"), Some("key"));
+        assert_eq!(item_id("ns", &item).unwrap(), "abc");
+
+        let item = text_item(Some("xyz"), None);
+        assert!(item_id("ns", &item).is_err());
+
+        // keys give the same id in a namespace, and different ones across
+        // namespaces
+        let item = text_item(None, Some("key"));
+        let id = item_id("ns", &item).unwrap();
+        assert_eq!(id, item_id("ns", &item).unwrap());
+        assert_ne!(id, item_id("other", &item).unwrap());
+        assert!(DataManager::is_hex_string(&id));
+
+        let item = text_item(None, None);
+        assert_ne!(item_id("ns", &item).unwrap(), item_id("ns", &item).unwrap());
+    }
+
+    #[test]
+    fn test_initial_states() {
+        let items = vec![
+            text_item(Some("a1"), None),
+            text_item(Some("a2"), None),
+            text_item(Some("a1"), None),
+            text_item(Some("b1"), Some("k1")),
+            text_item(Some("b1"), Some("k1")),
+            text_item(Some("c1"), Some("k2")),
+            text_item(Some("zz"), None),
+        ];
+        let ids: Vec<Result<String>> = items.iter().map(|item| item_id("ns", item)).collect();
+        let existing = HashSet::from(["a2".to_string(), "c1".to_string()]);
+        let states = initial_states(&items, ids, &existing);
+
+        assert_eq!(
+            pending_ids(&states),
+            vec![Some("a1"), None, None, Some("b1"), None, None, None]
+        );
+        let ItemState::Done(result) = &states[1] else {
+            panic!("existing content without a key should fail");
+        };
+        assert!(result.error.as_ref().unwrap().contains("already exists"));
+        let ItemState::Done(result) = &states[2] else {
+            panic!("a repeated id without a key should fail");
+        };
+        assert!(result.error.as_ref().unwrap().contains("twice"));
+        assert!(matches!(states[4], ItemState::Duplicate { of: 3 }));
+        assert!(matches!(
+            &states[5],
+            ItemState::Done(BatchIngestItemResult {
+                content_id: Some(id),
+                duplicate: true,
+                error: None,
+            }) if id == "c1"
+        ));
+        assert!(matches!(&states[6], ItemState::Done(result) if result.error.is_some()));
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let request: BatchIngestRequest = serde_json::from_str(
+            r#"{
+                "extraction_graph_names": ["graph"],
+                "items": [
+                    {"type": "text", "text": "hello", "idempotency_key": "k1"},
+                    {"type": "remote_file", "url": "s3://bucket/a.pdf", "mime_type": "application/pdf"},
+                    {"type": "file", "part": "file1", "labels": {"k": "v"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(request.items.len(), 3);
+        assert!(matches!(
+            &request.items[1].source,
+            BatchIngestSource::RemoteFile {
+                mode: api::RemoteFileIngestMode::Probe,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &request.items[2].source,
+            BatchIngestSource::File { part } if part == "file1"
+        ));
+        assert_eq!(request.items[2].labels.get("k").unwrap(), "v");
+    }
+}
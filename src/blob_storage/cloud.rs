@@ -0,0 +1,360 @@
+use std::{ops::Range, sync::Arc};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream::BoxStream, Stream, StreamExt};
+use nanoid::nanoid;
+use object_store::{
+    azure::MicrosoftAzureBuilder,
+    gcp::GoogleCloudStorageBuilder,
+    path::Path,
+    GetOptions,
+    GetRange,
+    ObjectStore,
+};
+use tokio::io::AsyncWriteExt;
+
+use super::{
+    read_error,
+    AzureConfig,
+    BlobStoragePartWriter,
+    BlobStorageReader,
+    BlobStorageWriter,
+    GcsConfig,
+    PutResult,
+    StoragePartWriter,
+    STAGING_PREFIX,
+};
+
+/// Blob storage on a bucket of any `object_store` backend, addressed with
+/// `{scheme}://{bucket}/{key}` urls. Google Cloud Storage (`gs://`) and Azure
+/// Blob Storage (`az://`) are stored through it.
+pub struct ObjectStoreStorage {
+    scheme: String,
+    bucket: String,
+    client: Arc<dyn ObjectStore>,
+}
+
+impl ObjectStoreStorage {
+    pub fn new(scheme: &str, bucket: &str, client: Arc<dyn ObjectStore>) -> Self {
+        Self {
+            scheme: scheme.to_string(),
+            bucket: bucket.to_string(),
+            client,
+        }
+    }
+
+    pub fn gcs(config: &GcsConfig) -> Result<Self> {
+        let mut builder = GoogleCloudStorageBuilder::from_env().with_bucket_name(&config.bucket);
+        if let Some(path) = &config.service_account_path {
+            builder = builder.with_service_account_path(path);
+        }
+        let client = builder.build().context("unable to build GCS client")?;
+        Ok(Self::new("gs", &config.bucket, Arc::new(client)))
+    }
+
+    pub fn azure(config: &AzureConfig) -> Result<Self> {
+        let mut builder = MicrosoftAzureBuilder::from_env().with_container_name(&config.container);
+        if let Some(account) = &config.account {
+            builder = builder.with_account(account);
+        }
+        if let Some(access_key) = &config.access_key {
+            builder = builder.with_access_key(access_key);
+        }
+        let client = builder.build().context("unable to build Azure client")?;
+        Ok(Self::new("az", &config.container, Arc::new(client)))
+    }
+
+    pub fn url(&self, key: &str) -> String {
+        format!("{}://{}/{}", self.scheme, self.bucket, key)
+    }
+
+    pub fn client(&self) -> Arc<dyn ObjectStore> {
+        self.client.clone()
+    }
+
+    fn staging_prefix(upload_id: &str) -> Result<Path> {
+        if upload_id.is_empty() ||
+            !upload_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(anyhow!("invalid upload id: {}", upload_id));
+        }
+        Ok(Path::from(format!("{}/{}", STAGING_PREFIX, upload_id)))
+    }
+
+    // Strips the url prefix off of keys given as urls of this bucket
+    fn path(&self, key: &str) -> Path {
+        let prefix = format!("{}://{}/", self.scheme, self.bucket);
+        Path::from(key.strip_prefix(&prefix).unwrap_or(key))
+    }
+}
+
+#[async_trait]
+impl BlobStorageWriter for ObjectStoreStorage {
+    async fn put(
+        &self,
+        key: &str,
+        mut data: impl Stream<Item = Result<Bytes>> + Send + Unpin,
+    ) -> Result<PutResult> {
+        let (_, mut writer) = self.client.put_multipart(&self.path(key)).await?;
+        let mut size_bytes: u64 = 0;
+        while let Some(chunk) = data.next().await {
+            let chunk = chunk?;
+            size_bytes += chunk.len() as u64;
+            writer.write_all(&chunk).await?;
+        }
+        writer.shutdown().await?;
+        Ok(PutResult {
+            url: self.url(key),
+            size_bytes,
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.client
+            .delete(&self.path(key))
+            .await
+            .map_err(|e| anyhow!("Failed to delete key: {}, error: {}", key, e))
+    }
+}
+
+// Resumable uploads stage every part as an object of its own, which works the
+// same on every backend, and concatenate them into the final object when the
+// upload is completed.
+#[async_trait]
+impl BlobStoragePartWriter for ObjectStoreStorage {
+    async fn writer(&self, key: &str) -> Result<StoragePartWriter> {
+        let (_, writer) = self.client.put_multipart(&self.path(key)).await?;
+        Ok(StoragePartWriter {
+            writer,
+            url: self.url(key),
+        })
+    }
+
+    async fn create_multipart(&self, _key: &str) -> Result<String> {
+        Ok(nanoid!(16))
+    }
+
+    async fn put_part(
+        &self,
+        _key: &str,
+        upload_id: &str,
+        part_index: usize,
+        data: Bytes,
+    ) -> Result<String> {
+        let part_path = Self::staging_prefix(upload_id)?.child(format!("{:05}", part_index));
+        self.client.put(&part_path, data).await?;
+        Ok(part_path.to_string())
+    }
+
+    async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<String>,
+    ) -> Result<PutResult> {
+        let prefix = Self::staging_prefix(upload_id)?.to_string();
+        let (_, mut writer) = self.client.put_multipart(&self.path(key)).await?;
+        let mut size_bytes: u64 = 0;
+        for part in &parts {
+            if !part.starts_with(&prefix) {
+                return Err(anyhow!("part {} is not part of upload {}", part, upload_id));
+            }
+            let mut stream = self
+                .client
+                .get(&Path::from(part.as_str()))
+                .await?
+                .into_stream();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                size_bytes += chunk.len() as u64;
+                writer.write_all(&chunk).await?;
+            }
+        }
+        writer.shutdown().await?;
+        self.abort_multipart(key, upload_id).await?;
+        Ok(PutResult {
+            url: self.url(key),
+            size_bytes,
+        })
+    }
+
+    async fn abort_multipart(&self, _key: &str, upload_id: &str) -> Result<()> {
+        let prefix = Self::staging_prefix(upload_id)?;
+        let parts: Vec<Path> = self
+            .client
+            .list(Some(&prefix))
+            .map(|meta| meta.map(|meta| meta.location))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+        for part in parts {
+            self.client.delete(&part).await?;
+        }
+        Ok(())
+    }
+}
+
+pub struct ObjectStoreReader {
+    client: Result<Arc<dyn ObjectStore>, String>,
+    key: String,
+}
+
+impl ObjectStoreReader {
+    /// Reader of `key` in the bucket of `client`. A client which could not be
+    /// built fails every read with its error.
+    pub fn new(client: Result<Arc<dyn ObjectStore>>, key: &str) -> Self {
+        Self {
+            client: client.map_err(|e| format!("{:#}", e)),
+            key: key.to_string(),
+        }
+    }
+
+    fn client(&self) -> Result<Arc<dyn ObjectStore>> {
+        self.client.clone().map_err(|e| anyhow!(e))
+    }
+}
+
+#[async_trait]
+impl BlobStorageReader for ObjectStoreReader {
+    fn get(&self, _key: &str) -> BoxStream<Result<Bytes>> {
+        let client = self.client();
+        let path = Path::from(self.key.as_str());
+        let stream = async_stream::stream! {
+            let mut stream = client?.get(&path).await?.into_stream();
+            while let Some(chunk) = stream.next().await {
+                yield chunk.map_err(|e| read_error("Error reading object", e));
+            }
+        };
+        Box::pin(stream)
+    }
+
+    fn get_range(&self, _key: &str, range: Range<u64>) -> BoxStream<Result<Bytes>> {
+        let client = self.client();
+        let path = Path::from(self.key.as_str());
+        let stream = async_stream::stream! {
+            let options = GetOptions {
+                range: Some(GetRange::Bounded(range.start as usize..range.end as usize)),
+                ..Default::default()
+            };
+            let mut stream = client?.get_opts(&path, options).await?.into_stream();
+            while let Some(chunk) = stream.next().await {
+                yield chunk.map_err(|e| read_error("Error reading object", e));
+            }
+        };
+        Box::pin(stream)
+    }
+
+    async fn size(&self, _key: &str) -> Result<u64> {
+        let meta = self
+            .client()?
+            .head(&Path::from(self.key.as_str()))
+            .await
+            .map_err(|e| read_error("Error reading object metadata", e))?;
+        Ok(meta.size as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+    use object_store::memory::InMemory;
+
+    use super::*;
+
+    fn test_storage() -> ObjectStoreStorage {
+        ObjectStoreStorage::new("gs", "bucket", Arc::new(InMemory::new()))
+    }
+
+    async fn read_all(stream: BoxStream<'_, Result<Bytes>>) -> Result<Vec<u8>> {
+        let chunks: Vec<Result<Bytes>> = stream.collect().await;
+        let mut contents = Vec::new();
+        for chunk in chunks {
+            contents.extend_from_slice(&chunk?);
+        }
+        Ok(contents)
+    }
+
+    #[tokio::test]
+    async fn test_put_get_delete() -> Result<()> {
+        let storage = test_storage();
+        let data = stream::iter(vec![
+            Ok(Bytes::from_static(b"01234")),
+            Ok(Bytes::from_static(b"56789")),
+        ]);
+        let res = storage.put("dir/file.txt", Box::pin(data)).await?;
+        assert_eq!(res.url, "gs://bucket/dir/file.txt");
+        assert_eq!(res.size_bytes, 10);
+
+        let reader = ObjectStoreReader::new(Ok(storage.client()), "dir/file.txt");
+        assert_eq!(read_all(reader.get(&res.url)).await?, b"0123456789");
+        assert_eq!(read_all(reader.get_range(&res.url, 2..6)).await?, b"2345");
+        assert_eq!(reader.size(&res.url).await?, 10);
+
+        storage.delete(&res.url).await?;
+        assert!(reader.size(&res.url).await.is_err());
+        assert!(read_all(reader.get(&res.url)).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multipart() -> Result<()> {
+        let storage = test_storage();
+        let upload_id = storage.create_multipart("upload.txt").await?;
+        let second = storage
+            .put_part("upload.txt", &upload_id, 1, Bytes::from_static(b"world"))
+            .await?;
+        let first = storage
+            .put_part("upload.txt", &upload_id, 0, Bytes::from_static(b"hello "))
+            .await?;
+
+        let res = storage
+            .complete_multipart("upload.txt", &upload_id, vec![first, second])
+            .await?;
+        assert_eq!(res.size_bytes, 11);
+        let reader = ObjectStoreReader::new(Ok(storage.client()), "upload.txt");
+        assert_eq!(read_all(reader.get(&res.url)).await?, b"hello world");
+
+        // the staged parts are removed once the upload is completed
+        let staged: Vec<_> = storage
+            .client
+            .list(Some(&Path::from(STAGING_PREFIX)))
+            .collect()
+            .await;
+        assert!(staged.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_abort_multipart() -> Result<()> {
+        let storage = test_storage();
+        let upload_id = storage.create_multipart("upload.txt").await?;
+        let part = storage
+            .put_part("upload.txt", &upload_id, 0, Bytes::from_static(b"hello"))
+            .await?;
+        storage.abort_multipart("upload.txt", &upload_id).await?;
+        assert!(storage
+            .complete_multipart("upload.txt", &upload_id, vec![part])
+            .await
+            .is_err());
+        assert!(storage
+            .put_part("upload.txt", "../escape", 0, Bytes::from_static(b"x"))
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reader_with_failed_client() {
+        let reader = ObjectStoreReader::new(Err(anyhow!("no credentials")), "file.txt");
+        assert!(reader.size("gs://bucket/file.txt").await.is_err());
+        assert!(read_all(reader.get("gs://bucket/file.txt")).await.is_err());
+    }
+}
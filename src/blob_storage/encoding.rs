@@ -0,0 +1,769 @@
+//! Compression and envelope encryption of blobs at rest.
+//!
+//! An encoded blob starts with a header naming the id of the key its data key
+//! is wrapped with, followed by frames of at most `FRAME_SIZE` bytes of
+//! plaintext, each compressed with zstd and sealed with AES-256-GCM under a
+//! data key generated for the blob. Frame nonces carry the frame index and
+//! whether it is the last frame, so that frames can't be reordered and a
+//! truncated blob fails to decode. Concatenated encoded blobs decode to the
+//! concatenation of their plaintexts.
+//!
+//! Whether a blob was written encoded is recorded in the metadata of its
+//! content rather than read from its first bytes, so that raw blobs which
+//! happen to start like the header are served as they are.
+//!
+//! The offsets of the plaintext in an encoded blob aren't known without
+//! decoding the frames before them, so ranged reads of encoded blobs decode
+//! the blob from its start. Deployments serving ranges of large media should
+//! leave blob encoding disabled.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    io,
+    ops::Range,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm,
+    Key,
+    Nonce,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::{stream::BoxStream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWrite;
+
+use super::{BlobStorageReader, BlobStorageReaderTS};
+
+const MAGIC: &[u8; 4] = b"\x89IXB";
+const VERSION: u8 = 1;
+
+const FLAG_COMPRESSED: u8 = 1;
+const FLAG_ENCRYPTED: u8 = 1 << 1;
+
+/// Bytes of plaintext in every frame but the last.
+const FRAME_SIZE: usize = 1024 * 1024;
+
+const NONCE_PREFIX_LEN: usize = 7;
+const NONCE_LEN: usize = 12;
+const WRAPPED_KEY_LEN: usize = NONCE_LEN + 32 + 16;
+const FRAME_HEADER_LEN: usize = 5;
+
+/// Compression and encryption applied to blobs when they are written.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlobEncodingConfig {
+    /// zstd level blobs are compressed with, compression is disabled when
+    /// unset.
+    #[serde(default)]
+    pub compression_level: Option<i32>,
+    #[serde(default)]
+    pub encryption: Option<EncryptionConfig>,
+}
+
+impl BlobEncodingConfig {
+    /// Whether blobs are written encoded.
+    pub fn is_enabled(&self) -> bool {
+        self.compression_level.is_some() || self.encryption.is_some()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// Id of the key the data keys of new blobs are wrapped with.
+    pub active_key_id: String,
+    /// Base64 encoded 256 bit keys by id. Keys which were rotated out have to
+    /// be kept for as long as blobs encrypted with them are read.
+    pub keys: HashMap<String, String>,
+}
+
+impl fmt::Debug for EncryptionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionConfig")
+            .field("active_key_id", &self.active_key_id)
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Encodes and decodes blobs according to a `BlobEncodingConfig`.
+#[derive(Default)]
+pub struct BlobCodec {
+    compression_level: Option<i32>,
+    active_key_id: Option<String>,
+    keys: HashMap<String, Aes256Gcm>,
+}
+
+impl fmt::Debug for BlobCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlobCodec")
+            .field("compression_level", &self.compression_level)
+            .field("active_key_id", &self.active_key_id)
+            .finish()
+    }
+}
+
+impl BlobCodec {
+    pub fn new(config: &BlobEncodingConfig) -> Result<Self> {
+        let mut keys = HashMap::new();
+        let mut active_key_id = None;
+        if let Some(encryption) = &config.encryption {
+            for (id, key) in &encryption.keys {
+                if id.is_empty() || id.len() > u8::MAX as usize {
+                    return Err(anyhow!("invalid encryption key id: {}", id));
+                }
+                let key = STANDARD
+                    .decode(key)
+                    .map_err(|e| anyhow!("encryption key {} is not valid base64: {}", id, e))?;
+                if key.len() != 32 {
+                    return Err(anyhow!("encryption key {} must be 256 bits long", id));
+                }
+                keys.insert(
+                    id.clone(),
+                    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+                );
+            }
+            if !keys.contains_key(&encryption.active_key_id) {
+                return Err(anyhow!(
+                    "active encryption key {} is not configured",
+                    encryption.active_key_id
+                ));
+            }
+            active_key_id = Some(encryption.active_key_id.clone());
+        }
+        Ok(Self {
+            compression_level: config.compression_level,
+            active_key_id,
+            keys,
+        })
+    }
+
+    /// Whether blobs are written encoded.
+    pub fn is_enabled(&self) -> bool {
+        self.compression_level.is_some() || self.active_key_id.is_some()
+    }
+
+    /// Encoder of a new blob. Compression can be turned off for blobs which
+    /// must not shrink, such as the parts of a multipart upload.
+    pub fn encoder(&self, compress: bool) -> Result<FrameEncoder> {
+        let compression_level = self.compression_level.filter(|_| compress);
+        let mut header = BytesMut::new();
+        header.put_slice(MAGIC);
+        header.put_u8(VERSION);
+        let mut flags = 0;
+        if compression_level.is_some() {
+            flags |= FLAG_COMPRESSED;
+        }
+        let mut cipher = None;
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        if let Some(key_id) = &self.active_key_id {
+            flags |= FLAG_ENCRYPTED;
+            header.put_u8(flags);
+            header.put_u8(key_id.len() as u8);
+            header.put_slice(key_id.as_bytes());
+
+            let data_key = Aes256Gcm::generate_key(OsRng);
+            let nonce = Aes256Gcm::generate_nonce(OsRng);
+            let wrapped_key = self.keys[key_id]
+                .encrypt(
+                    &nonce,
+                    Payload {
+                        msg: data_key.as_slice(),
+                        aad: key_id.as_bytes(),
+                    },
+                )
+                .map_err(|_| anyhow!("unable to wrap data key"))?;
+            header.put_slice(&nonce);
+            header.put_slice(&wrapped_key);
+            OsRng.fill_bytes(&mut nonce_prefix);
+            header.put_slice(&nonce_prefix);
+            cipher = Some(Aes256Gcm::new(&data_key));
+        } else {
+            header.put_u8(flags);
+            header.put_u8(0);
+        }
+        Ok(FrameEncoder {
+            out: header,
+            buf: BytesMut::new(),
+            compression_level,
+            cipher,
+            nonce_prefix,
+            index: 0,
+            finished: false,
+        })
+    }
+
+    /// Encodes a blob held in memory.
+    pub fn encode_bytes(&self, data: &[u8], compress: bool) -> Result<Bytes> {
+        let mut encoder = self.encoder(compress)?;
+        let mut out = BytesMut::from(&encoder.push(data)?[..]);
+        out.extend_from_slice(&encoder.finish()?);
+        Ok(out.freeze())
+    }
+
+    /// Encodes a stream of plaintext.
+    pub fn encode_stream<'a>(
+        &self,
+        mut data: impl Stream<Item = Result<Bytes>> + Send + Unpin + 'a,
+        compress: bool,
+    ) -> BoxStream<'a, Result<Bytes>> {
+        let encoder = self.encoder(compress);
+        Box::pin(async_stream::stream! {
+            let mut encoder = encoder?;
+            while let Some(chunk) = data.next().await {
+                let out = encoder.push(&chunk?)?;
+                if !out.is_empty() {
+                    yield Ok(out);
+                }
+            }
+            yield encoder.finish();
+        })
+    }
+
+    /// Decodes a stream of an encoded blob.
+    pub fn decode_stream<'a>(
+        self: Arc<Self>,
+        mut data: impl Stream<Item = Result<Bytes>> + Send + Unpin + 'a,
+    ) -> BoxStream<'a, Result<Bytes>> {
+        Box::pin(async_stream::stream! {
+            let mut decoder = FrameDecoder::new(self);
+            while let Some(chunk) = data.next().await {
+                let out = decoder.push(&chunk?)?;
+                if !out.is_empty() {
+                    yield Ok(out);
+                }
+            }
+            decoder.finish()?;
+        })
+    }
+
+    fn frame_nonce(prefix: &[u8; NONCE_PREFIX_LEN], index: u32, last: bool) -> Nonce<Aes256Gcm> {
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+        nonce[NONCE_PREFIX_LEN..NONCE_LEN - 1].copy_from_slice(&index.to_be_bytes());
+        nonce[NONCE_LEN - 1] = last as u8;
+        Nonce::<Aes256Gcm>::clone_from_slice(&nonce)
+    }
+}
+
+/// Encodes the plaintext of a blob pushed into it, one frame at a time.
+pub struct FrameEncoder {
+    out: BytesMut,
+    buf: BytesMut,
+    compression_level: Option<i32>,
+    cipher: Option<Aes256Gcm>,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    index: u32,
+    finished: bool,
+}
+
+impl FrameEncoder {
+    /// Adds plaintext and returns the encoded bytes which are ready.
+    pub fn push(&mut self, data: &[u8]) -> Result<Bytes> {
+        if self.finished {
+            return Err(anyhow!("blob encoder is already finished"));
+        }
+        self.buf.extend_from_slice(data);
+        // A full frame is only written once more data follows it, so that the
+        // last frame is the one written by `finish`
+        while self.buf.len() > FRAME_SIZE {
+            let frame = self.buf.split_to(FRAME_SIZE);
+            self.write_frame(&frame, false)?;
+        }
+        Ok(self.out.split().freeze())
+    }
+
+    /// Writes the last frame and returns the remaining encoded bytes.
+    pub fn finish(&mut self) -> Result<Bytes> {
+        if !self.finished {
+            let frame = self.buf.split();
+            self.write_frame(&frame, true)?;
+            self.finished = true;
+        }
+        Ok(self.out.split().freeze())
+    }
+
+    fn write_frame(&mut self, frame: &[u8], last: bool) -> Result<()> {
+        let compressed;
+        let mut payload = frame;
+        if let Some(level) = self.compression_level {
+            compressed = zstd::bulk::compress(frame, level)?;
+            payload = &compressed;
+        }
+        let sealed;
+        if let Some(cipher) = &self.cipher {
+            let nonce = BlobCodec::frame_nonce(&self.nonce_prefix, self.index, last);
+            sealed = cipher
+                .encrypt(&nonce, payload)
+                .map_err(|_| anyhow!("unable to encrypt blob"))?;
+            payload = &sealed;
+        }
+        self.out.put_u8(last as u8);
+        self.out.put_u32(payload.len() as u32);
+        self.out.put_slice(payload);
+        self.index = self
+            .index
+            .checked_add(1)
+            .ok_or_else(|| anyhow!("blob is too large to encode"))?;
+        Ok(())
+    }
+}
+
+enum DecoderState {
+    Header,
+    Frames {
+        compressed: bool,
+        cipher: Option<Aes256Gcm>,
+        nonce_prefix: [u8; NONCE_PREFIX_LEN],
+        index: u32,
+    },
+}
+
+/// Decodes the encoded bytes of a blob pushed into it.
+struct FrameDecoder {
+    codec: Arc<BlobCodec>,
+    buf: BytesMut,
+    state: DecoderState,
+    decoded_header: bool,
+}
+
+impl FrameDecoder {
+    fn new(codec: Arc<BlobCodec>) -> Self {
+        Self {
+            codec,
+            buf: BytesMut::new(),
+            state: DecoderState::Header,
+            decoded_header: false,
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) -> Result<Bytes> {
+        self.buf.extend_from_slice(data);
+        let mut out = BytesMut::new();
+        loop {
+            let progressed = match self.state {
+                DecoderState::Header => self.read_header()?,
+                DecoderState::Frames { .. } => self.read_frame(&mut out)?,
+            };
+            if !progressed {
+                return Ok(out.freeze());
+            }
+        }
+    }
+
+    fn finish(&self) -> Result<()> {
+        if !self.buf.is_empty() || !matches!(self.state, DecoderState::Header) {
+            return Err(anyhow!("encoded blob is truncated"));
+        }
+        if !self.decoded_header {
+            return Err(anyhow!("blob is not encoded"));
+        }
+        Ok(())
+    }
+
+    fn read_header(&mut self) -> Result<bool> {
+        let fixed_len = MAGIC.len() + 3;
+        if self.buf.len() < fixed_len {
+            return Ok(false);
+        }
+        if !self.buf.starts_with(MAGIC) {
+            return Err(anyhow!("invalid encoded blob header"));
+        }
+        if self.buf[MAGIC.len()] != VERSION {
+            return Err(anyhow!(
+                "unsupported encoded blob version {}",
+                self.buf[MAGIC.len()]
+            ));
+        }
+        let flags = self.buf[MAGIC.len() + 1];
+        let key_id_len = self.buf[MAGIC.len() + 2] as usize;
+        let encrypted = flags & FLAG_ENCRYPTED != 0;
+        let header_len = fixed_len +
+            key_id_len +
+            if encrypted {
+                WRAPPED_KEY_LEN + NONCE_PREFIX_LEN
+            } else {
+                0
+            };
+        if self.buf.len() < header_len {
+            return Ok(false);
+        }
+        let mut header = self.buf.split_to(header_len);
+        header.advance(fixed_len);
+        let key_id = String::from_utf8(header.split_to(key_id_len).to_vec())
+            .map_err(|_| anyhow!("invalid encryption key id in blob header"))?;
+
+        let mut cipher = None;
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        if encrypted {
+            let key = self
+                .codec
+                .keys
+                .get(&key_id)
+                .ok_or_else(|| anyhow!("encryption key {} is not configured", key_id))?;
+            let nonce = header.split_to(NONCE_LEN);
+            let wrapped_key = header.split_to(WRAPPED_KEY_LEN - NONCE_LEN);
+            let data_key = key
+                .decrypt(
+                    Nonce::<Aes256Gcm>::from_slice(&nonce),
+                    Payload {
+                        msg: &wrapped_key,
+                        aad: key_id.as_bytes(),
+                    },
+                )
+                .map_err(|_| anyhow!("unable to unwrap data key with key {}", key_id))?;
+            cipher = Some(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key)));
+            nonce_prefix.copy_from_slice(&header);
+        }
+        self.state = DecoderState::Frames {
+            compressed: flags & FLAG_COMPRESSED != 0,
+            cipher,
+            nonce_prefix,
+            index: 0,
+        };
+        self.decoded_header = true;
+        Ok(true)
+    }
+
+    fn read_frame(&mut self, out: &mut BytesMut) -> Result<bool> {
+        let DecoderState::Frames {
+            compressed,
+            cipher,
+            nonce_prefix,
+            index,
+        } = &mut self.state
+        else {
+            return Ok(false);
+        };
+        if self.buf.len() < FRAME_HEADER_LEN {
+            return Ok(false);
+        }
+        let last = match self.buf[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(anyhow!("invalid encoded blob frame")),
+        };
+        let len = u32::from_be_bytes(self.buf[1..FRAME_HEADER_LEN].try_into()?) as usize;
+        if self.buf.len() < FRAME_HEADER_LEN + len {
+            return Ok(false);
+        }
+        let mut frame = self.buf.split_to(FRAME_HEADER_LEN + len);
+        frame.advance(FRAME_HEADER_LEN);
+
+        let mut payload = frame.to_vec();
+        if let Some(cipher) = cipher {
+            let nonce = BlobCodec::frame_nonce(nonce_prefix, *index, last);
+            payload = cipher
+                .decrypt(&nonce, payload.as_slice())
+                .map_err(|_| anyhow!("unable to decrypt blob, it is corrupted or truncated"))?;
+        }
+        if *compressed {
+            payload = zstd::bulk::decompress(&payload, FRAME_SIZE)?;
+        }
+        out.extend_from_slice(&payload);
+        *index += 1;
+        if last {
+            // Another encoded blob may follow
+            self.state = DecoderState::Header;
+        }
+        Ok(true)
+    }
+}
+
+/// Reader which decodes the encoded blobs read through `inner`.
+pub struct DecodingReader {
+    inner: BlobStorageReaderTS,
+    codec: Arc<BlobCodec>,
+}
+
+impl DecodingReader {
+    pub fn new(inner: BlobStorageReaderTS, codec: Arc<BlobCodec>) -> Self {
+        Self { inner, codec }
+    }
+}
+
+#[async_trait]
+impl BlobStorageReader for DecodingReader {
+    fn get(&self, key: &str) -> BoxStream<Result<Bytes>> {
+        self.codec.clone().decode_stream(self.inner.get(key))
+    }
+
+    // Offsets of the plaintext aren't known up front, so the blob is decoded
+    // from the start until the end of the range
+    fn get_range(&self, key: &str, range: Range<u64>) -> BoxStream<Result<Bytes>> {
+        let key = key.to_string();
+        Box::pin(async_stream::stream! {
+            let mut stream = self.get(&key);
+            let mut offset = 0;
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                let start = offset;
+                offset += chunk.len() as u64;
+                if offset <= range.start {
+                    continue;
+                }
+                let from = range.start.saturating_sub(start) as usize;
+                let to = (range.end.min(offset) - start) as usize;
+                if from < to {
+                    yield Ok(chunk.slice(from..to));
+                }
+                if offset >= range.end {
+                    break;
+                }
+            }
+        })
+    }
+
+    // The size of the plaintext is only known by decoding the whole blob, the
+    // size recorded in the content metadata should be used instead
+    async fn size(&self, key: &str) -> Result<u64> {
+        let mut stream = self.get(key);
+        let mut size = 0;
+        while let Some(chunk) = stream.next().await {
+            size += chunk?.len() as u64;
+        }
+        Ok(size)
+    }
+}
+
+/// Writer which encodes everything written to it into `inner`.
+pub struct EncodingWriter {
+    inner: Box<dyn AsyncWrite + Send + Unpin>,
+    encoder: FrameEncoder,
+    pending: Bytes,
+}
+
+impl EncodingWriter {
+    pub fn new(inner: Box<dyn AsyncWrite + Send + Unpin>, encoder: FrameEncoder) -> Self {
+        Self {
+            inner,
+            encoder,
+            pending: Bytes::new(),
+        }
+    }
+
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.pending.is_empty() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.advance(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn to_io_error(e: anyhow::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+impl AsyncWrite for EncodingWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        this.pending = this.encoder.push(buf).map_err(to_io_error)?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        if !this.encoder.finished {
+            this.pending = this.encoder.finish().map_err(to_io_error)?;
+            ready!(this.poll_drain(cx))?;
+        }
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    fn test_config(active_key_id: &str) -> BlobEncodingConfig {
+        BlobEncodingConfig {
+            compression_level: Some(3),
+            encryption: Some(EncryptionConfig {
+                active_key_id: active_key_id.to_string(),
+                keys: HashMap::from([
+                    ("k1".to_string(), STANDARD.encode([1u8; 32])),
+                    ("k2".to_string(), STANDARD.encode([2u8; 32])),
+                ]),
+            }),
+        }
+    }
+
+    async fn collect(stream: BoxStream<'_, Result<Bytes>>) -> Result<Vec<u8>> {
+        let chunks: Vec<Result<Bytes>> = stream.collect().await;
+        let mut out = Vec::new();
+        for chunk in chunks {
+            out.extend_from_slice(&chunk?);
+        }
+        Ok(out)
+    }
+
+    async fn encode(codec: &BlobCodec, data: &[u8], compress: bool) -> Result<Vec<u8>> {
+        // Chunks which don't line up with frames
+        let chunks: Vec<Result<Bytes>> = data
+            .chunks(300_001)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        collect(codec.encode_stream(stream::iter(chunks), compress)).await
+    }
+
+    async fn decode(codec: Arc<BlobCodec>, data: Vec<u8>) -> Result<Vec<u8>> {
+        let chunks: Vec<Result<Bytes>> = data
+            .chunks(7_777)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        collect(codec.decode_stream(stream::iter(chunks))).await
+    }
+
+    fn test_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() -> Result<()> {
+        let codec = Arc::new(BlobCodec::new(&test_config("k1"))?);
+        for len in [0, 10, FRAME_SIZE, FRAME_SIZE + 1, 3 * FRAME_SIZE + 17] {
+            let data = test_data(len);
+            let encoded = encode(&codec, &data, true).await?;
+            assert!(encoded.starts_with(MAGIC));
+            assert_eq!(decode(codec.clone(), encoded).await?, data);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_key_rotation() -> Result<()> {
+        let data = test_data(1000);
+        let old = BlobCodec::new(&test_config("k1"))?;
+        let encoded = encode(&old, &data, true).await?;
+
+        // Blobs written with a key which was rotated out can still be read
+        let rotated = Arc::new(BlobCodec::new(&test_config("k2"))?);
+        assert_eq!(decode(rotated, encoded.clone()).await?, data);
+
+        let mut config = test_config("k2");
+        config.encryption.as_mut().unwrap().keys.remove("k1");
+        let removed = Arc::new(BlobCodec::new(&config)?);
+        assert!(decode(removed, encoded).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tampering_and_truncation() -> Result<()> {
+        let codec = Arc::new(BlobCodec::new(&test_config("k1"))?);
+        let encoded = encode(&codec, &test_data(2 * FRAME_SIZE + 5), false).await?;
+
+        let mut tampered = encoded.clone();
+        let i = tampered.len() - 20;
+        tampered[i] ^= 1;
+        assert!(decode(codec.clone(), tampered).await.is_err());
+
+        // Dropping the last frame leaves a blob which still parses frame by
+        // frame, it must be detected as truncated
+        let last_frame_len = FRAME_HEADER_LEN + 5 + 16;
+        let truncated = encoded[..encoded.len() - last_frame_len].to_vec();
+        assert!(decode(codec.clone(), truncated).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_raw_and_concatenation() -> Result<()> {
+        let codec = Arc::new(BlobCodec::new(&test_config("k1"))?);
+        assert!(decode(codec.clone(), b"raw".to_vec()).await.is_err());
+        assert!(decode(codec.clone(), vec![]).await.is_err());
+
+        let mut encoded = encode(&codec, b"hello ", false).await?;
+        encoded.extend(encode(&codec, b"world", false).await?);
+        assert_eq!(decode(codec, encoded).await?, b"hello world");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encoding_writer() -> Result<()> {
+        let codec = Arc::new(BlobCodec::new(&BlobEncodingConfig {
+            compression_level: Some(3),
+            encryption: None,
+        })?);
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("blob");
+        let data = vec![b'a'; FRAME_SIZE * 2];
+        let file = tokio::fs::File::create(&path).await?;
+        let mut writer = EncodingWriter::new(Box::new(file), codec.encoder(true)?);
+        writer.write_all(&data).await?;
+        writer.shutdown().await?;
+
+        let encoded = tokio::fs::read(&path).await?;
+        assert!(encoded.len() < data.len());
+        assert_eq!(decode(codec, encoded).await?, data);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_decoding_reader() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let codec = Arc::new(BlobCodec::new(&test_config("k1"))?);
+        let data = test_data(FRAME_SIZE + 100);
+        let encoded_path = dir.path().join("encoded");
+        tokio::fs::write(&encoded_path, encode(&codec, &data, true).await?).await?;
+        let raw_path = dir.path().join("raw");
+        tokio::fs::write(&raw_path, &data).await?;
+
+        let reader = DecodingReader::new(Arc::new(super::super::DiskFileReader::new()), codec);
+        let url = format!("file://{}", encoded_path.to_str().unwrap());
+        assert_eq!(collect(reader.get(&url)).await?, data);
+        let range = FRAME_SIZE as u64 - 10..FRAME_SIZE as u64 + 10;
+        assert_eq!(
+            collect(reader.get_range(&url, range.clone())).await?,
+            &data[range.start as usize..range.end as usize]
+        );
+        assert_eq!(reader.size(&url).await?, data.len() as u64);
+
+        // Blobs are decoded according to their metadata, not their first bytes
+        let url = format!("file://{}", raw_path.to_str().unwrap());
+        assert!(collect(reader.get(&url)).await.is_err());
+        let mut raw = MAGIC.to_vec();
+        raw.extend_from_slice(b"raw bytes");
+        tokio::fs::write(&raw_path, &raw).await?;
+        let content_reader = super::super::ContentReader::default();
+        assert_eq!(content_reader.bytes(&url, false).await?, raw);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(BlobCodec::new(&test_config("missing")).is_err());
+        let mut config = test_config("k1");
+        config
+            .encryption
+            .as_mut()
+            .unwrap()
+            .keys
+            .insert("short".to_string(), STANDARD.encode([0u8; 16]));
+        assert!(BlobCodec::new(&config).is_err());
+    }
+}
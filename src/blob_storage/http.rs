@@ -1,22 +1,85 @@
+use std::ops::Range;
+
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use bytes::Bytes;
 use futures::{stream::BoxStream, StreamExt};
 
-use super::BlobStorageReader;
+use super::{read_error, BlobStorageReader};
 
 pub struct HttpReader {}
 
+#[async_trait]
 impl BlobStorageReader for HttpReader {
     fn get(&self, key: &str) -> BoxStream<Result<Bytes>> {
         let client = reqwest::Client::new();
         let key = key.to_string();
         let stream = async_stream::stream! {
-            let response = client.get(key).send().await?;
+            let response = client.get(key).send().await?.error_for_status()?;
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                yield chunk.map_err(|e| read_error("Failed to read chunk", e));
+            }
+        };
+        Box::pin(stream)
+    }
+
+    fn get_range(&self, key: &str, range: Range<u64>) -> BoxStream<Result<Bytes>> {
+        let client = reqwest::Client::new();
+        let key = key.to_string();
+        let stream = async_stream::stream! {
+            let response = client
+                .get(key)
+                .header(
+                    reqwest::header::RANGE,
+                    format!("bytes={}-{}", range.start, range.end.saturating_sub(1)),
+                )
+                .send()
+                .await?
+                .error_for_status()?;
+
+            // Servers which don't support ranges reply with the whole body, so
+            // the requested window is cut out of it here.
+            let mut skip = if response.status() == reqwest::StatusCode::PARTIAL_CONTENT {
+                0
+            } else {
+                range.start
+            };
+            let mut remaining = range.end - range.start;
             let mut stream = response.bytes_stream();
             while let Some(chunk) = stream.next().await {
-                yield chunk.map_err(|e| anyhow!("Failed to read chunk: {}", e));
+                let mut chunk = chunk.map_err(|e| read_error("Failed to read chunk", e))?;
+                if skip > 0 {
+                    let n = skip.min(chunk.len() as u64);
+                    skip -= n;
+                    chunk = chunk.slice(n as usize..);
+                }
+                let n = remaining.min(chunk.len() as u64);
+                remaining -= n;
+                if n > 0 {
+                    yield Ok(chunk.slice(..n as usize));
+                }
+                if remaining == 0 {
+                    break;
+                }
             }
         };
         Box::pin(stream)
     }
+
+    async fn size(&self, key: &str) -> Result<u64> {
+        let response = reqwest::Client::new()
+            .head(key)
+            .send()
+            .await?
+            .error_for_status()?;
+        // The header is read directly since the body of a HEAD response is
+        // always empty
+        response
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| anyhow!("no content length returned for {}", key))
+    }
 }
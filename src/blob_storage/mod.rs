@@ -1,22 +1,39 @@
 use std::{
     fmt::{self, Debug, Formatter},
+    ops::Range,
     sync::Arc,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use bytes::{Bytes, BytesMut};
 use futures::{stream::BoxStream, StreamExt};
-use object_store::aws::AmazonS3Builder;
+use object_store::{aws::AmazonS3Builder, local::LocalFileSystem, path::Path, ObjectStore};
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncWrite;
 
-use self::{disk::DiskFileReader, s3::S3FileReader};
+use self::{
+    cloud::{ObjectStoreReader, ObjectStoreStorage},
+    disk::DiskFileReader,
+    encoding::{BlobCodec, BlobEncodingConfig, DecodingReader, EncodingWriter},
+    s3::S3FileReader,
+};
 
+pub mod cloud;
 pub mod disk;
+pub mod encoding;
 pub mod http;
+pub mod presign;
 pub mod s3;
 
+/// Prefix, relative to the root of the storage, under which the parts of
+/// resumable uploads are staged until the upload is completed, on backends
+/// without multipart uploads of their own.
+pub const STAGING_PREFIX: &str = "uploads";
+
+/// Size S3 requires of every part of a multipart upload but the last one.
+const S3_MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct S3Config {
     pub bucket: String,
@@ -28,10 +45,43 @@ pub struct DiskStorageConfig {
     pub path: String,
 }
 
+/// Google Cloud Storage bucket. Credentials not configured here are read from
+/// the `GOOGLE_*` environment variables.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcsConfig {
+    pub bucket: String,
+    /// Path to the json key of the service account to authenticate as.
+    #[serde(default)]
+    pub service_account_path: Option<String>,
+}
+
+/// Azure Blob Storage container. Credentials not configured here are read
+/// from the `AZURE_*` environment variables.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AzureConfig {
+    #[serde(default)]
+    pub account: Option<String>,
+    pub container: String,
+    #[serde(default)]
+    pub access_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BlobStorageConfig {
     pub s3: Option<S3Config>,
     pub disk: Option<DiskStorageConfig>,
+    #[serde(default)]
+    pub gcs: Option<GcsConfig>,
+    #[serde(default)]
+    pub azure: Option<AzureConfig>,
+    /// Compression and encryption of the blobs written.
+    #[serde(default)]
+    pub encoding: Option<BlobEncodingConfig>,
+    /// Cold tier which lifecycle policies move old content to. Blobs are
+    /// moved as they are stored, so they are read back with the encoding of
+    /// this storage and the `encoding` of the cold tier is ignored.
+    #[serde(default)]
+    pub cold: Option<Box<BlobStorageConfig>>,
 }
 
 #[derive(Debug)]
@@ -53,17 +103,49 @@ pub trait BlobStorageWriter {
 #[async_trait]
 pub trait BlobStoragePartWriter {
     async fn writer(&self, key: &str) -> Result<StoragePartWriter>;
+
+    /// Starts a resumable multipart upload of `key` and returns its upload id.
+    async fn create_multipart(&self, key: &str) -> Result<String>;
+
+    /// Stores part number `part_index` of an upload, returning the id of the
+    /// part which has to be passed back in `complete_multipart`.
+    async fn put_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_index: usize,
+        data: Bytes,
+    ) -> Result<String>;
+
+    /// Assembles the parts, in order, into the object at `key`.
+    async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<String>,
+    ) -> Result<PutResult>;
+
+    /// Discards an upload and every part stored for it.
+    async fn abort_multipart(&self, key: &str, upload_id: &str) -> Result<()>;
 }
 
 type BlobStorageReaderTS = Arc<dyn BlobStorageReader + Sync + Send>;
 
+#[async_trait]
 pub trait BlobStorageReader {
     fn get(&self, key: &str) -> BoxStream<Result<Bytes>>;
+
+    /// Streams the bytes of `key` within the half-open byte `range`.
+    fn get_range(&self, key: &str, range: Range<u64>) -> BoxStream<Result<Bytes>>;
+
+    /// Size in bytes of `key`, found without reading its contents.
+    async fn size(&self, key: &str) -> Result<u64>;
 }
 
 #[derive(Clone)]
 pub struct BlobStorage {
     config: BlobStorageConfig,
+    codec: Arc<BlobCodec>,
 }
 
 impl Debug for BlobStorage {
@@ -89,8 +171,21 @@ impl std::fmt::Debug for StoragePartWriter {
 }
 
 impl BlobStorage {
-    pub fn new_with_config(config: BlobStorageConfig) -> Self {
-        Self { config }
+    pub fn new_with_config(config: BlobStorageConfig) -> Result<Self> {
+        if let Some(cold) = config.cold.as_deref() {
+            if cold.cold.is_some() {
+                return Err(anyhow!("the cold tier can not have a cold tier of its own"));
+            }
+            if cold.storage_url("") == config.storage_url("") {
+                return Err(anyhow!(
+                    "the cold tier must be stored apart from the blob storage"
+                ));
+            }
+        }
+        let codec = Arc::new(BlobCodec::new(
+            &config.encoding.clone().unwrap_or_default(),
+        )?);
+        Ok(Self { config, codec })
     }
 
     fn s3_storage(&self, s3: &S3Config) -> Result<s3::S3Storage> {
@@ -105,9 +200,162 @@ impl BlobStorage {
         ))
     }
 
-    pub async fn writer(&self, _namespace: &str, key: &str) -> Result<StoragePartWriter> {
+    /// Storage of the configured GCS bucket or Azure container, if any.
+    fn cloud_storage(&self) -> Result<Option<ObjectStoreStorage>> {
+        if let Some(gcs) = self.config.gcs.as_ref() {
+            return Ok(Some(ObjectStoreStorage::gcs(gcs)?));
+        }
+        if let Some(azure) = self.config.azure.as_ref() {
+            return Ok(Some(ObjectStoreStorage::azure(azure)?));
+        }
+        Ok(None)
+    }
+
+    fn disk_storage(&self) -> Result<disk::DiskStorage> {
+        disk::DiskStorage::new(self.disk_config())
+    }
+
+    fn disk_config(&self) -> DiskStorageConfig {
+        self.config
+            .disk
+            .clone()
+            .unwrap_or_else(|| DiskStorageConfig {
+                path: "blobs".to_string(),
+            })
+    }
+
+    /// Client of the storage blobs are written to, for the records the server
+    /// keeps next to them, such as the state of resumable uploads.
+    pub fn object_store(&self) -> Result<Arc<dyn ObjectStore>> {
+        if let Some(s3) = self.config.s3.as_ref() {
+            return Ok(Arc::new(
+                AmazonS3Builder::from_env()
+                    .with_region(s3.region.as_str())
+                    .with_allow_http(true)
+                    .with_bucket_name(s3.bucket.clone())
+                    .build()
+                    .context("unable to build S3 builder")?,
+            ));
+        }
+        if let Some(storage) = self.cloud_storage()? {
+            return Ok(storage.client());
+        }
+        // The directory is created along with the disk storage
+        let config = self.disk_config();
+        disk::DiskStorage::new(config.clone())?;
+        Ok(Arc::new(LocalFileSystem::new_with_prefix(config.path)?))
+    }
+
+    /// Minimum size of the parts of a resumable upload but the last one.
+    pub fn min_part_size(&self) -> u64 {
+        if self.config.s3.is_some() {
+            S3_MIN_PART_SIZE
+        } else {
+            0
+        }
+    }
+
+    /// Upload ids of the resumable uploads which have parts staged under
+    /// `STAGING_PREFIX`. S3 keeps the parts of its multipart uploads itself.
+    pub async fn staged_uploads(&self) -> Result<Vec<String>> {
+        if self.config.s3.is_some() {
+            return Ok(Vec::new());
+        }
+        let listing = self
+            .object_store()?
+            .list_with_delimiter(Some(&Path::from(STAGING_PREFIX)))
+            .await?;
+        Ok(listing
+            .common_prefixes
+            .iter()
+            .filter_map(|prefix| prefix.filename().map(|id| id.to_string()))
+            .collect())
+    }
+
+    /// Reader of blobs which authenticates with the credentials of this
+    /// storage.
+    pub fn content_reader(&self) -> ContentReader {
+        ContentReader {
+            config: Some(self.config.clone()),
+            codec: self.codec.clone(),
+        }
+    }
+
+    /// The storage url a blob written under `key` is stored at.
+    pub fn storage_url(&self, key: &str) -> String {
+        self.config.storage_url(key)
+    }
+
+    /// Whether blobs are written compressed or encrypted, which is recorded
+    /// in the metadata of the content they are written for.
+    pub fn is_encoding(&self) -> bool {
+        self.codec.is_enabled()
+    }
+
+    pub fn has_cold_tier(&self) -> bool {
+        self.config.cold.is_some()
+    }
+
+    /// Whether the blob at `url` is stored in this storage or its cold tier.
+    pub fn is_stored(&self, url: &str) -> bool {
+        url.starts_with(&self.config.storage_url("")) || self.is_cold(url)
+    }
+
+    /// Whether the blob at `url` has been moved to the cold tier.
+    pub fn is_cold(&self, url: &str) -> bool {
+        self.config
+            .cold
+            .as_deref()
+            .map_or(false, |cold| url.starts_with(&cold.storage_url("")))
+    }
+
+    /// Copies the blob at `url`, as it is stored, to the cold tier under the
+    /// same key and returns its url there.
+    pub async fn copy_to_cold(&self, url: &str) -> Result<String> {
+        let cold = self
+            .cold_storage()
+            .ok_or_else(|| anyhow!("no cold tier is configured"))?;
+        let key = url
+            .strip_prefix(&self.config.storage_url(""))
+            .ok_or_else(|| anyhow!("{} is not stored in the blob storage", url))?;
+        let reader = self.content_reader().raw_reader(url);
+        let res = cold.put_raw(key, reader.get(url)).await?;
+        Ok(res.url)
+    }
+
+    // The cold tier shares the codec of this storage, blobs being moved
+    // without being decoded
+    fn cold_storage(&self) -> Option<BlobStorage> {
+        let cold = self.config.cold.as_deref()?;
+        Some(BlobStorage {
+            config: BlobStorageConfig {
+                encoding: self.config.encoding.clone(),
+                cold: None,
+                ..cold.clone()
+            },
+            codec: self.codec.clone(),
+        })
+    }
+
+    pub async fn writer(&self, namespace: &str, key: &str) -> Result<StoragePartWriter> {
+        let writer = self.raw_writer(namespace, key).await?;
+        if !self.codec.is_enabled() {
+            return Ok(writer);
+        }
+        Ok(StoragePartWriter {
+            writer: Box::new(EncodingWriter::new(
+                writer.writer,
+                self.codec.encoder(true)?,
+            )),
+            url: writer.url,
+        })
+    }
+
+    async fn raw_writer(&self, _namespace: &str, key: &str) -> Result<StoragePartWriter> {
         if let Some(s3) = self.config.s3.as_ref() {
             self.s3_storage(s3)?.writer(key).await
+        } else if let Some(storage) = self.cloud_storage()? {
+            storage.writer(key).await
         } else {
             // If it's not S3, assume it's a file
 
@@ -120,6 +368,73 @@ impl BlobStorage {
             storage.writer(key).await
         }
     }
+
+    pub async fn create_multipart(&self, key: &str) -> Result<String> {
+        if let Some(s3) = self.config.s3.as_ref() {
+            self.s3_storage(s3)?.create_multipart(key).await
+        } else if let Some(storage) = self.cloud_storage()? {
+            storage.create_multipart(key).await
+        } else {
+            self.disk_storage()?.create_multipart(key).await
+        }
+    }
+
+    pub async fn put_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_index: usize,
+        data: Bytes,
+    ) -> Result<String> {
+        // Parts are encoded as blobs of their own, which decode to the
+        // assembled upload once concatenated. They are not compressed since
+        // object stores have a minimum part size.
+        let data = if self.codec.is_enabled() {
+            self.codec.encode_bytes(&data, false)?
+        } else {
+            data
+        };
+        if let Some(s3) = self.config.s3.as_ref() {
+            self.s3_storage(s3)?
+                .put_part(key, upload_id, part_index, data)
+                .await
+        } else if let Some(storage) = self.cloud_storage()? {
+            storage.put_part(key, upload_id, part_index, data).await
+        } else {
+            self.disk_storage()?
+                .put_part(key, upload_id, part_index, data)
+                .await
+        }
+    }
+
+    pub async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<String>,
+    ) -> Result<PutResult> {
+        if let Some(s3) = self.config.s3.as_ref() {
+            self.s3_storage(s3)?
+                .complete_multipart(key, upload_id, parts)
+                .await
+        } else if let Some(storage) = self.cloud_storage()? {
+            storage.complete_multipart(key, upload_id, parts).await
+        } else {
+            self.disk_storage()?
+                .complete_multipart(key, upload_id, parts)
+                .await
+        }
+    }
+
+    pub async fn abort_multipart(&self, key: &str, upload_id: &str) -> Result<()> {
+        if let Some(s3) = self.config.s3.as_ref() {
+            self.s3_storage(s3)?.abort_multipart(key, upload_id).await
+        } else if let Some(storage) = self.cloud_storage()? {
+            storage.abort_multipart(key, upload_id).await
+        } else {
+            self.disk_storage()?.abort_multipart(key, upload_id).await
+        }
+    }
 }
 
 #[async_trait]
@@ -129,8 +444,40 @@ impl BlobStorageWriter for BlobStorage {
         key: &str,
         data: impl futures::Stream<Item = Result<Bytes>> + Send + Unpin,
     ) -> Result<PutResult, anyhow::Error> {
+        if !self.codec.is_enabled() {
+            return self.put_raw(key, data).await;
+        }
+        let mut size_bytes = 0;
+        let counted = data.map(|chunk| {
+            if let Ok(chunk) = &chunk {
+                size_bytes += chunk.len() as u64;
+            }
+            chunk
+        });
+        let res = self
+            .put_raw(key, self.codec.encode_stream(counted, true))
+            .await?;
+        Ok(PutResult {
+            url: res.url,
+            size_bytes,
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.delete_blob(key).await
+    }
+}
+
+impl BlobStorage {
+    async fn put_raw(
+        &self,
+        key: &str,
+        data: impl futures::Stream<Item = Result<Bytes>> + Send + Unpin,
+    ) -> Result<PutResult> {
         if let Some(s3) = self.config.s3.as_ref() {
             self.s3_storage(s3)?.put(key, data).await
+        } else if let Some(storage) = self.cloud_storage()? {
+            storage.put(key, data).await
         } else {
             // If it's not S3, assume it's a file
 
@@ -148,16 +495,16 @@ impl BlobStorageWriter for BlobStorage {
         }
     }
 
-    async fn delete(&self, key: &str) -> Result<()> {
+    async fn delete_blob(&self, key: &str) -> Result<()> {
         if key.starts_with("s3://") {
+            let tier = self.config.tier(key);
             let (bucket, key) = parse_s3_url(key)
                 .map_err(|err| anyhow::anyhow!("unable to parse s3 url: {}", err))?;
             return s3::S3Storage::new(
                 key,
                 AmazonS3Builder::from_env()
                     .with_region(
-                        self.config
-                            .s3
+                        tier.s3
                             .as_ref()
                             .map(|config| config.region.as_str())
                             .unwrap_or("us-east-1"),
@@ -170,6 +517,13 @@ impl BlobStorageWriter for BlobStorage {
             .await;
         }
 
+        if key.starts_with("gs://") || key.starts_with("az://") {
+            let (scheme, bucket, path) = parse_object_url(key)?;
+            return ObjectStoreStorage::new(scheme, bucket, self.config.object_store(key)?)
+                .delete(path)
+                .await;
+        }
+
         // If it's not S3, assume it's a file
 
         // We need a default implementation for `DiskStorageConfig`
@@ -186,6 +540,16 @@ impl BlobStorageWriter for BlobStorage {
     }
 }
 
+/// Wraps a failed read of a remote object, keeping the source error
+/// downcastable so retries can tell transient failures apart.
+fn read_error<E>(msg: &str, e: E) -> anyhow::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let msg = format!("{}: {}", msg, e);
+    anyhow::Error::new(e).context(msg)
+}
+
 fn parse_s3_url(s3_url: &str) -> Result<(&str, &str), &str> {
     let Some(("s3", url)) = s3_url.split_once("://") else {
         return Err("Invalid S3 URL format");
@@ -198,21 +562,101 @@ fn parse_s3_url(s3_url: &str) -> Result<(&str, &str), &str> {
     Ok((bucket, key))
 }
 
-#[derive(Debug)]
-pub struct ContentReader {}
+/// Splits a `{scheme}://{bucket}/{key}` url of an object store.
+fn parse_object_url(url: &str) -> Result<(&str, &str, &str)> {
+    let (scheme, url) = url
+        .split_once("://")
+        .ok_or_else(|| anyhow!("invalid object url: {}", url))?;
+    let (bucket, key) = url
+        .split_once('/')
+        .ok_or_else(|| anyhow!("invalid object url: {}", url))?;
+    if bucket.is_empty() || key.is_empty() {
+        return Err(anyhow!("invalid object url: {}", url));
+    }
+    Ok((scheme, bucket, key))
+}
 
-impl Default for ContentReader {
-    fn default() -> Self {
-        Self::new()
+impl BlobStorageConfig {
+    /// The storage url a blob written under `key` is stored at.
+    pub fn storage_url(&self, key: &str) -> String {
+        if let Some(s3) = self.s3.as_ref() {
+            format!("s3://{}/{}", s3.bucket, key)
+        } else if let Some(gcs) = self.gcs.as_ref() {
+            format!("gs://{}/{}", gcs.bucket, key)
+        } else if let Some(azure) = self.azure.as_ref() {
+            format!("az://{}/{}", azure.container, key)
+        } else if let Some(disk) = self.disk.as_ref() {
+            format!("file://{}/{}", disk.path, key)
+        } else {
+            format!("file://blobs/{}", key)
+        }
+    }
+
+    /// Config of the tier `url` is stored in, which holds the credentials
+    /// and region to access it with.
+    fn tier(&self, url: &str) -> &BlobStorageConfig {
+        match self.cold.as_deref() {
+            Some(cold) if url.starts_with(&cold.storage_url("")) => cold,
+            _ => self,
+        }
+    }
+
+    /// Client of the GCS bucket or Azure container of `url`, authenticated
+    /// with the configured credentials.
+    fn object_store(&self, url: &str) -> Result<Arc<dyn ObjectStore>> {
+        let (scheme, bucket, _) = parse_object_url(url)?;
+        let config = self.tier(url);
+        let storage = match scheme {
+            "gs" => ObjectStoreStorage::gcs(&GcsConfig {
+                bucket: bucket.to_string(),
+                service_account_path: config
+                    .gcs
+                    .as_ref()
+                    .and_then(|gcs| gcs.service_account_path.clone()),
+            })?,
+            "az" => ObjectStoreStorage::azure(&AzureConfig {
+                account: config
+                    .azure
+                    .as_ref()
+                    .and_then(|azure| azure.account.clone()),
+                container: bucket.to_string(),
+                access_key: config
+                    .azure
+                    .as_ref()
+                    .and_then(|azure| azure.access_key.clone()),
+            })?,
+            _ => return Err(anyhow!("unsupported object store url: {}", url)),
+        };
+        Ok(storage.client())
     }
 }
 
+/// Reader of blobs by their storage url. Blobs which were written compressed
+/// or encrypted, as recorded in the metadata of their content, are decoded.
+#[derive(Debug, Default)]
+pub struct ContentReader {
+    config: Option<BlobStorageConfig>,
+    codec: Arc<BlobCodec>,
+}
+
 impl ContentReader {
-    pub fn new() -> Self {
-        Self {}
+    /// Reader of the blob at `key`, which decodes it when it was written
+    /// `encoded` by the blob store.
+    pub fn get(&self, key: &str, encoded: bool) -> BlobStorageReaderTS {
+        // Remote files are never written by the blob store
+        if key.starts_with("http") {
+            return Arc::new(http::HttpReader {});
+        }
+        if !encoded {
+            return self.raw_reader(key);
+        }
+        Arc::new(DecodingReader::new(
+            self.raw_reader(key),
+            self.codec.clone(),
+        ))
     }
 
-    pub fn get(&self, key: &str) -> BlobStorageReaderTS {
+    fn raw_reader(&self, key: &str) -> BlobStorageReaderTS {
         if key.starts_with("s3://") {
             let (bucket, key) = parse_s3_url(key)
                 .map_err(|err| anyhow::anyhow!("unable to parse s3 url: {}", err))
@@ -220,16 +664,20 @@ impl ContentReader {
             return Arc::new(S3FileReader::new(bucket, key));
         }
 
-        if key.starts_with("http") {
-            return Arc::new(http::HttpReader {});
+        if key.starts_with("gs://") || key.starts_with("az://") {
+            let config = self.config.clone().unwrap_or_default();
+            let path = parse_object_url(key)
+                .map(|(_, _, path)| path)
+                .unwrap_or(key);
+            return Arc::new(ObjectStoreReader::new(config.object_store(key), path));
         }
 
         // If it's not S3, assume it's a file
         Arc::new(DiskFileReader::new())
     }
 
-    pub async fn bytes(&self, key: &str) -> Result<Bytes> {
-        let reader = self.get(key);
+    pub async fn bytes(&self, key: &str, encoded: bool) -> Result<Bytes> {
+        let reader = self.get(key, encoded);
         let mut stream = reader.get(key);
         let mut bytes = BytesMut::new();
         while let Some(chunk) = stream.next().await {
@@ -293,7 +741,12 @@ mod tests {
                 region: "us-east-1".to_string(),
             }),
             disk: None,
-        });
+            gcs: None,
+            azure: None,
+            encoding: None,
+            cold: None,
+        })
+        .unwrap();
         let result = storage.put("test-key-2", pin!(stream)).await;
 
         assert!(result.is_ok());
@@ -329,7 +782,12 @@ mod tests {
                 region: "us-east-1".to_string(),
             }),
             disk: None,
-        });
+            gcs: None,
+            azure: None,
+            encoding: None,
+            cold: None,
+        })
+        .unwrap();
         let mut writer = storage
             .writer("test-namespace", "test-key-3")
             .await
@@ -346,4 +804,80 @@ mod tests {
 
         storage.delete("s3://test-bucket/test-key-3").await.unwrap();
     }
+
+    #[test]
+    fn test_object_urls() {
+        assert_eq!(
+            parse_object_url("gs://bucket/dir/file.txt").unwrap(),
+            ("gs", "bucket", "dir/file.txt")
+        );
+        assert!(parse_object_url("gs://bucket").is_err());
+        assert!(parse_object_url("gs:///file.txt").is_err());
+
+        let storage = BlobStorage::new_with_config(BlobStorageConfig {
+            azure: Some(AzureConfig {
+                account: Some("account".to_string()),
+                container: "container".to_string(),
+                access_key: None,
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(storage.storage_url("file.txt"), "az://container/file.txt");
+    }
+
+    #[tokio::test]
+    async fn test_copy_to_cold() -> Result<()> {
+        let hot_dir = tempfile::tempdir()?;
+        let cold_dir = tempfile::tempdir()?;
+        let disk = |dir: &tempfile::TempDir| DiskStorageConfig {
+            path: dir.path().to_str().unwrap().to_string(),
+        };
+        let storage = BlobStorage::new_with_config(BlobStorageConfig {
+            disk: Some(disk(&hot_dir)),
+            encoding: Some(BlobEncodingConfig {
+                compression_level: Some(3),
+                encryption: None,
+            }),
+            cold: Some(Box::new(BlobStorageConfig {
+                disk: Some(disk(&cold_dir)),
+                ..Default::default()
+            })),
+            ..Default::default()
+        })?;
+
+        let data = stream::iter(vec![Ok(Bytes::from_static(b"hello world"))]);
+        let res = storage.put("file.txt", data).await?;
+        assert!(!storage.is_cold(&res.url));
+
+        let cold_url = storage.copy_to_cold(&res.url).await?;
+        assert!(storage.is_cold(&cold_url));
+        assert_eq!(
+            cold_url,
+            format!("file://{}/file.txt", cold_dir.path().to_str().unwrap())
+        );
+        // the copy is stored encoded, and decoded on reads like the original
+        let raw = tokio::fs::read(cold_dir.path().join("file.txt")).await?;
+        assert_ne!(raw, b"hello world");
+        let reader = storage.content_reader();
+        assert!(storage.is_encoding());
+        assert_eq!(reader.bytes(&cold_url, true).await?, "hello world");
+
+        storage.delete(&cold_url).await?;
+        assert!(reader.bytes(&cold_url, true).await.is_err());
+        assert!(storage.copy_to_cold("s3://bucket/file.txt").await.is_err());
+
+        // the cold tier must be apart from the hot one
+        assert!(BlobStorage::new_with_config(BlobStorageConfig {
+            disk: Some(disk(&hot_dir)),
+            cold: Some(Box::new(BlobStorageConfig {
+                disk: Some(disk(&hot_dir)),
+                ..Default::default()
+            })),
+            ..Default::default()
+        })
+        .is_err());
+
+        Ok(())
+    }
 }
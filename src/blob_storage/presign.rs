@@ -0,0 +1,344 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use object_store::{aws::AmazonS3Builder, path::Path, signer::Signer};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::{parse_s3_url, BlobStorageConfig};
+
+/// Lifetime of a presigned url when the client doesn't ask for one.
+pub const DEFAULT_EXPIRY: Duration = Duration::from_secs(15 * 60);
+
+/// Longest lifetime of a presigned url, which is the limit S3 imposes.
+pub const MAX_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Prefix, in the blob store, of the records of issued presigned uploads.
+const UPLOAD_RECORD_PREFIX: &str = "presigned_uploads";
+
+/// Configuration of the URLs handed out for blobs stored on disk, which are
+/// served by the server itself. Presigned S3 URLs don't need any.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresignedUrlConfig {
+    /// signing_key is the secret used to sign URLs with HMAC-SHA256.
+    pub signing_key: String,
+    /// public_url is the address clients reach this server on, i.e.
+    /// "https://indexify.example.com".
+    pub public_url: String,
+}
+
+/// Query parameters of an HMAC signed URL for a blob on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedBlobParams {
+    pub key: String,
+    pub expires: u64,
+    pub signature: String,
+}
+
+/// What a presigned upload was issued for. Finalizing the upload consumes
+/// its record, so an uploaded key is registered as content at most once and
+/// only in the namespace it was issued for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresignedUpload {
+    pub namespace: String,
+    pub content_id: String,
+    /// token is handed to the client and has to be presented to finalize.
+    pub token: String,
+    pub expires_at: u64,
+    /// sha256 of the bytes written through the server, set for uploads to
+    /// disk storage.
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// Location of the record of the presigned upload of `key`.
+pub fn upload_record_path(key: &str) -> Result<Path> {
+    if !is_valid_upload_key(key) {
+        return Err(anyhow!("invalid upload key"));
+    }
+    Ok(Path::from(format!("{}/{}.json", UPLOAD_RECORD_PREFIX, key)))
+}
+
+/// Whether `hash` looks like a hex encoded sha256 digest.
+pub fn is_valid_sha256(hash: &str) -> bool {
+    hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Issues time-limited URLs through which clients read and write blobs
+/// directly, without streaming the bytes through the API handlers.
+#[derive(Clone)]
+pub struct PresignedUrls {
+    blob_config: BlobStorageConfig,
+    config: Option<PresignedUrlConfig>,
+}
+
+impl std::fmt::Debug for PresignedUrls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PresignedUrls")
+            .field("config", &"<hidden>")
+            .finish()
+    }
+}
+
+/// The lifetime of a url requested by a client, capped to `MAX_EXPIRY`.
+pub fn expiry(expires_in_secs: Option<u64>) -> Duration {
+    expires_in_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_EXPIRY)
+        .min(MAX_EXPIRY)
+}
+
+/// Unix timestamp, in seconds, at which a url issued now for `expires_in`
+/// stops being valid.
+pub fn expires_at(expires_in: Duration) -> Result<u64> {
+    Ok((SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)? + expires_in).as_secs())
+}
+
+/// Keys of presigned uploads are generated by the server as flat file names;
+/// anything else could be used to write outside of the blob store.
+pub fn is_valid_upload_key(key: &str) -> bool {
+    !key.is_empty() &&
+        !key.starts_with('.') &&
+        !key.contains("..") &&
+        key.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+impl PresignedUrls {
+    pub fn new(blob_config: BlobStorageConfig, config: Option<PresignedUrlConfig>) -> Self {
+        Self {
+            blob_config,
+            config,
+        }
+    }
+
+    /// URL to PUT the bytes of a new blob stored under `key`.
+    pub async fn upload_url(&self, key: &str, expires_in: Duration) -> Result<String> {
+        self.check_not_encoded()?;
+        if let Some(s3) = self.blob_config.s3.as_ref() {
+            return self
+                .s3_signed_url(
+                    &s3.bucket,
+                    &s3.region,
+                    key,
+                    reqwest::Method::PUT,
+                    expires_in,
+                )
+                .await;
+        }
+        if self.blob_config.gcs.is_some() || self.blob_config.azure.is_some() {
+            return Err(anyhow!(
+                "presigned urls are not supported for gcs and azure blob storage"
+            ));
+        }
+        self.disk_signed_url("PUT", key, expires_in)
+    }
+
+    /// URL to GET the bytes of the blob at `storage_url`.
+    pub async fn download_url(&self, storage_url: &str, expires_in: Duration) -> Result<String> {
+        self.check_not_encoded()?;
+        if storage_url.starts_with("s3://") {
+            let (bucket, key) = parse_s3_url(storage_url)
+                .map_err(|err| anyhow!("unable to parse s3 url: {}", err))?;
+            let region = self
+                .blob_config
+                .tier(storage_url)
+                .s3
+                .as_ref()
+                .map(|config| config.region.as_str())
+                .unwrap_or("us-east-1");
+            return self
+                .s3_signed_url(bucket, region, key, reqwest::Method::GET, expires_in)
+                .await;
+        }
+        if storage_url.starts_with("file://") {
+            return self.disk_signed_url("GET", storage_url, expires_in);
+        }
+        Err(anyhow!(
+            "presigned urls are not supported for {}",
+            storage_url
+        ))
+    }
+
+    /// Checks the signature and expiry of a disk blob URL issued by this
+    /// server for `method`.
+    pub fn verify(&self, method: &str, params: &SignedBlobParams) -> Result<()> {
+        let config = self.disk_config()?;
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        if params.expires < now {
+            return Err(anyhow!("url has expired"));
+        }
+        let signature = URL_SAFE_NO_PAD
+            .decode(&params.signature)
+            .map_err(|_| anyhow!("invalid signature"))?;
+        Self::mac(config, method, &params.key, params.expires)?
+            .verify_slice(&signature)
+            .map_err(|_| anyhow!("invalid signature"))
+    }
+
+    async fn s3_signed_url(
+        &self,
+        bucket: &str,
+        region: &str,
+        key: &str,
+        method: reqwest::Method,
+        expires_in: Duration,
+    ) -> Result<String> {
+        let client = AmazonS3Builder::from_env()
+            .with_region(region)
+            .with_bucket_name(bucket)
+            .build()
+            .context("unable to build S3 builder")?;
+        let url = client
+            .signed_url(method, &key.into(), expires_in)
+            .await
+            .map_err(|e| anyhow!("unable to sign url for {}: {}", key, e))?;
+        Ok(url.to_string())
+    }
+
+    // Bytes transferred through presigned urls bypass the compression and
+    // encryption of the blob store
+    fn check_not_encoded(&self) -> Result<()> {
+        if self
+            .blob_config
+            .encoding
+            .as_ref()
+            .map_or(false, |encoding| encoding.is_enabled())
+        {
+            return Err(anyhow!(
+                "presigned urls are not supported when blobs are compressed or encrypted"
+            ));
+        }
+        Ok(())
+    }
+
+    fn disk_config(&self) -> Result<&PresignedUrlConfig> {
+        self.config
+            .as_ref()
+            .ok_or_else(|| anyhow!("presigned urls for disk storage are not configured"))
+    }
+
+    fn mac(
+        config: &PresignedUrlConfig,
+        method: &str,
+        key: &str,
+        expires: u64,
+    ) -> Result<Hmac<Sha256>> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(config.signing_key.as_bytes())
+            .map_err(|e| anyhow!("invalid signing key: {}", e))?;
+        mac.update(format!("{}\n{}\n{}", method, key, expires).as_bytes());
+        Ok(mac)
+    }
+
+    fn disk_signed_url(&self, method: &str, key: &str, expires_in: Duration) -> Result<String> {
+        let config = self.disk_config()?;
+        let expires = expires_at(expires_in)?;
+        let signature = URL_SAFE_NO_PAD.encode(
+            Self::mac(config, method, key, expires)?
+                .finalize()
+                .into_bytes(),
+        );
+        let mut url = url::Url::parse(&config.public_url)?.join("/blobs")?;
+        url.query_pairs_mut()
+            .append_pair("key", key)
+            .append_pair("expires", &expires.to_string())
+            .append_pair("signature", &signature);
+        Ok(url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blob_storage::DiskStorageConfig;
+
+    fn test_presigned_urls() -> PresignedUrls {
+        PresignedUrls::new(
+            BlobStorageConfig {
+                s3: None,
+                disk: Some(DiskStorageConfig {
+                    path: "/tmp/indexify-test".to_string(),
+                }),
+                gcs: None,
+                azure: None,
+                encoding: None,
+                cold: None,
+            },
+            Some(PresignedUrlConfig {
+                signing_key: "secret".to_string(),
+                public_url: "http://localhost:8900".to_string(),
+            }),
+        )
+    }
+
+    fn params(url: &str) -> SignedBlobParams {
+        let url = url::Url::parse(url).unwrap();
+        let query: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+        SignedBlobParams {
+            key: query["key"].clone(),
+            expires: query["expires"].parse().unwrap(),
+            signature: query["signature"].clone(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disk_signed_urls() {
+        let urls = test_presigned_urls();
+
+        let url = urls
+            .upload_url("abc.pdf", Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(url.starts_with("http://localhost:8900/blobs?key=abc.pdf"));
+        let signed = params(&url);
+        assert!(urls.verify("PUT", &signed).is_ok());
+        // a signature is only valid for the method it was issued for
+        assert!(urls.verify("GET", &signed).is_err());
+
+        let mut tampered = signed.clone();
+        tampered.key = "other.pdf".to_string();
+        assert!(urls.verify("PUT", &tampered).is_err());
+
+        let mut expired = signed.clone();
+        expired.expires = 1;
+        assert!(urls.verify("PUT", &expired).is_err());
+
+        let url = urls
+            .download_url("file:///tmp/indexify-test/abc.pdf", Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(urls.verify("GET", &params(&url)).is_ok());
+    }
+
+    #[test]
+    fn test_upload_keys_and_expiry() {
+        assert!(is_valid_upload_key("V1StGXR8_Z5jdHi6.pdf"));
+        assert!(!is_valid_upload_key("../secrets"));
+        assert!(!is_valid_upload_key("a/b.pdf"));
+        assert!(!is_valid_upload_key(".hidden"));
+        assert!(!is_valid_upload_key(""));
+
+        assert!(upload_record_path("abc.pdf").is_ok());
+        assert!(upload_record_path("../abc.pdf").is_err());
+        assert!(is_valid_sha256(&"a".repeat(64)));
+        assert!(!is_valid_sha256(&"g".repeat(64)));
+        assert!(!is_valid_sha256("abc"));
+
+        assert_eq!(expiry(None), DEFAULT_EXPIRY);
+        assert_eq!(expiry(Some(60)), Duration::from_secs(60));
+        assert_eq!(expiry(Some(u64::MAX / 2)), MAX_EXPIRY);
+    }
+
+    #[tokio::test]
+    async fn test_disk_signed_urls_not_configured() {
+        let urls = PresignedUrls::new(test_presigned_urls().blob_config, None);
+        assert!(urls
+            .upload_url("abc.pdf", Duration::from_secs(60))
+            .await
+            .is_err());
+    }
+}
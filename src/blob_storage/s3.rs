@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{ops::Range, sync::Arc};
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
@@ -6,12 +6,21 @@ use bytes::Bytes;
 use futures::{stream::BoxStream, Stream, StreamExt};
 use object_store::{
     aws::{AmazonS3, AmazonS3Builder},
+    multipart::{MultiPartStore, PartId},
+    GetOptions,
+    GetRange,
     ObjectStore,
 };
 use tokio::{io::AsyncWriteExt, sync::mpsc};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
-use super::{BlobStoragePartWriter, BlobStorageReader, BlobStorageWriter, StoragePartWriter};
+use super::{
+    read_error,
+    BlobStoragePartWriter,
+    BlobStorageReader,
+    BlobStorageWriter,
+    StoragePartWriter,
+};
 use crate::blob_storage::PutResult;
 
 pub struct S3Storage {
@@ -68,6 +77,50 @@ impl BlobStoragePartWriter for S3Storage {
             url: format!("s3://{}/{}", self.bucket, key),
         })
     }
+
+    async fn create_multipart(&self, key: &str) -> Result<String> {
+        Ok(self.client.create_multipart(&key.into()).await?)
+    }
+
+    async fn put_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_index: usize,
+        data: Bytes,
+    ) -> Result<String> {
+        let part = self
+            .client
+            .put_part(&key.into(), &upload_id.to_string(), part_index, data)
+            .await?;
+        Ok(part.content_id)
+    }
+
+    async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<String>,
+    ) -> Result<PutResult> {
+        let parts = parts
+            .into_iter()
+            .map(|content_id| PartId { content_id })
+            .collect();
+        self.client
+            .complete_multipart(&key.into(), &upload_id.to_string(), parts)
+            .await?;
+        let meta = self.client.head(&key.into()).await?;
+        Ok(PutResult {
+            url: format!("s3://{}/{}", self.bucket, key),
+            size_bytes: meta.size as u64,
+        })
+    }
+
+    async fn abort_multipart(&self, key: &str, upload_id: &str) -> Result<()> {
+        MultiPartStore::abort_multipart(&self.client, &key.into(), &upload_id.to_string())
+            .await
+            .map_err(|e| anyhow!("Failed to abort upload: {}, error: {}", upload_id, e))
+    }
 }
 
 pub struct S3FileReader {
@@ -95,11 +148,49 @@ impl BlobStorageReader for S3FileReader {
         let (tx, rx) = mpsc::unbounded_channel();
         let key = self.key.clone();
         tokio::spawn(async move {
-            let mut stream = client_clone.get(&key.into()).await.unwrap().into_stream();
+            let result = match client_clone.get(&key.into()).await {
+                Ok(result) => result,
+                Err(e) => {
+                    let _ = tx.send(Err(read_error("Error reading s3 object", e)));
+                    return;
+                }
+            };
+            let mut stream = result.into_stream();
             while let Some(chunk) = stream.next().await {
-                if let Ok(chunk) = chunk {
-                    let _ = tx.send(Ok(chunk));
+                let _ = tx.send(chunk.map_err(|e| read_error("Error reading s3 object", e)));
+            }
+        });
+        Box::pin(UnboundedReceiverStream::new(rx))
+    }
+
+    async fn size(&self, _key: &str) -> Result<u64> {
+        let meta = self
+            .client
+            .head(&self.key.as_str().into())
+            .await
+            .map_err(|e| read_error("Error reading s3 object metadata", e))?;
+        Ok(meta.size as u64)
+    }
+
+    fn get_range(&self, _key: &str, range: Range<u64>) -> BoxStream<Result<Bytes>> {
+        let client_clone = self.client.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let key = self.key.clone();
+        tokio::spawn(async move {
+            let options = GetOptions {
+                range: Some(GetRange::Bounded(range.start as usize..range.end as usize)),
+                ..Default::default()
+            };
+            let result = match client_clone.get_opts(&key.into(), options).await {
+                Ok(result) => result,
+                Err(e) => {
+                    let _ = tx.send(Err(read_error("Error reading s3 object", e)));
+                    return;
                 }
+            };
+            let mut stream = result.into_stream();
+            while let Some(chunk) = stream.next().await {
+                let _ = tx.send(chunk.map_err(|e| read_error("Error reading s3 object", e)));
             }
         });
         Box::pin(UnboundedReceiverStream::new(rx))
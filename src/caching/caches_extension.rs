@@ -1,33 +1,111 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::Result;
-use indexify_internal_api::{Content, ExtractResponse};
+use indexify_internal_api::ExtractResponse;
+use opentelemetry::KeyValue;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{info, warn};
 
 use super::prelude::*;
 use crate::{
+    api,
     caching::{MokaAsyncCache, NoOpCache, RedisCache},
+    metrics,
     server_config::{ServerCacheBackend, ServerCacheConfig},
 };
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// Key of the result of an extractor call. The fingerprint of the extractor's
+/// description is part of the key, so results are no longer served once the
+/// extractor is registered with a different description.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct ExtractContentCacheKey {
-    content: Arc<Content>,
-    input_params: Arc<Option<serde_json::Value>>,
+    extractor: String,
+    extractor_fingerprint: String,
+    input_params: String,
+    content_hash: String,
 }
+
+impl ExtractContentCacheKey {
+    pub fn new(
+        extractor: &str,
+        extractor_fingerprint: &str,
+        content: &api::Content,
+        input_params: &Option<serde_json::Value>,
+    ) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(content.content_type.as_bytes());
+        hasher.update([0]);
+        hasher.update(&content.bytes);
+        Self {
+            extractor: extractor.to_string(),
+            extractor_fingerprint: extractor_fingerprint.to_string(),
+            input_params: input_params
+                .as_ref()
+                .map(|params| params.to_string())
+                .unwrap_or_default(),
+            content_hash: format!("{:x}", hasher.finalize()),
+        }
+    }
+}
+
 pub type CacheTS<K, V> = Arc<RwLock<Box<dyn Cache<K, V>>>>;
 
 #[derive(Clone)]
 pub struct Caches {
-    pub cache_extract_content: Arc<RwLock<Box<dyn Cache<ExtractContentCacheKey, ExtractResponse>>>>,
+    pub cache_extract_content: CacheTS<ExtractContentCacheKey, ExtractResponse>,
+    metrics: Arc<metrics::cache::Metrics>,
 }
 
 impl Caches {
     pub fn new(cache_config: ServerCacheConfig) -> Self {
         Self {
             cache_extract_content: Self::create_cache(cache_config).unwrap(),
+            metrics: Arc::new(metrics::cache::Metrics::new()),
+        }
+    }
+
+    /// Cached result of an extractor call. Failing to read from the cache is
+    /// counted as a miss, so that extraction falls back to the extractor.
+    pub async fn get_extract_content(
+        &self,
+        key: &ExtractContentCacheKey,
+    ) -> Option<ExtractResponse> {
+        let attrs = [
+            KeyValue::new("cache", "extract_content"),
+            KeyValue::new("extractor", key.extractor.clone()),
+        ];
+        let response = self
+            .cache_extract_content
+            .read()
+            .await
+            .get(key)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("unable to read extracted content from cache: {}", e);
+                None
+            });
+        match response {
+            Some(_) => self.metrics.hits.add(1, &attrs),
+            None => self.metrics.misses.add(1, &attrs),
+        }
+        response
+    }
+
+    pub async fn insert_extract_content(
+        &self,
+        key: ExtractContentCacheKey,
+        response: ExtractResponse,
+    ) {
+        if let Err(e) = self
+            .cache_extract_content
+            .write()
+            .await
+            .insert(key, response)
+            .await
+        {
+            warn!("unable to write extracted content to cache: {}", e);
         }
     }
 
@@ -36,6 +114,7 @@ impl Caches {
         K: CacheKey,
         V: CacheValue,
     {
+        let ttl = (cache_config.ttl_secs > 0).then_some(Duration::from_secs(cache_config.ttl_secs));
         match cache_config.backend {
             ServerCacheBackend::None => {
                 info!("no cache backend configured. Using NoOpCache");
@@ -43,20 +122,26 @@ impl Caches {
             }
             ServerCacheBackend::Memory => {
                 info!("memory cache config provided. Using MoKaAsyncCache");
+                let mut builder = moka::future::CacheBuilder::default();
                 if let Some(memory_config) = cache_config.memory {
-                    let cache = MokaAsyncCache::new(memory_config.max_size as u64);
-                    Ok(Arc::new(RwLock::new(Box::new(cache))))
+                    builder = builder.max_capacity(memory_config.max_size as u64);
                 } else {
                     tracing::warn!("memory config not provided. Using default config");
-                    let cache = MokaAsyncCache::default();
-                    Ok(Arc::new(RwLock::new(Box::new(cache))))
                 }
+                if let Some(ttl) = ttl {
+                    builder = builder.time_to_live(ttl);
+                }
+                let cache = MokaAsyncCache::new_from_builder(builder);
+                Ok(Arc::new(RwLock::new(Box::new(cache))))
             }
             ServerCacheBackend::Redis => {
                 info!("redis cache config provided. Using RedisCache");
                 if let Some(redis_config) = cache_config.redis {
                     let client = redis::Client::open(redis_config.addr)?;
-                    let cache = RedisCache::new(client);
+                    let mut cache = RedisCache::new(client);
+                    if let Some(ttl) = ttl {
+                        cache = cache.with_ttl(ttl);
+                    }
                     Ok(Arc::new(RwLock::new(Box::new(cache))))
                 } else {
                     // TODO: Is a panic the right thing to do here? We could log an error, but it
@@ -67,3 +152,72 @@ impl Caches {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use indexify_internal_api as internal_api;
+
+    use super::*;
+    use crate::server_config::MemoryConfig;
+
+    fn text(text: &str) -> api::Content {
+        api::Content {
+            content_type: "text/plain".to_string(),
+            bytes: text.as_bytes().to_vec(),
+            features: vec![],
+            labels: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_extract_content_cache_key() {
+        let params = Some(serde_json::json!({"chunk_size": 100}));
+        let key = ExtractContentCacheKey::new("chunker", "v1", &text("hello"), &params);
+        assert_eq!(
+            key,
+            ExtractContentCacheKey::new("chunker", "v1", &text("hello"), &params)
+        );
+        assert_ne!(
+            key,
+            ExtractContentCacheKey::new("chunker", "v2", &text("hello"), &params)
+        );
+        assert_ne!(
+            key,
+            ExtractContentCacheKey::new("chunker", "v1", &text("world"), &params)
+        );
+        assert_ne!(
+            key,
+            ExtractContentCacheKey::new("chunker", "v1", &text("hello"), &None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extract_content_cache() {
+        let caches = Caches::new(ServerCacheConfig {
+            backend: ServerCacheBackend::Memory,
+            memory: Some(MemoryConfig { max_size: 100 }),
+            ..Default::default()
+        });
+        let key = ExtractContentCacheKey::new("embedder", "v1", &text("query"), &None);
+        assert!(caches.get_extract_content(&key).await.is_none());
+
+        let response = ExtractResponse {
+            content: vec![],
+            features: vec![internal_api::Feature {
+                feature_type: internal_api::FeatureType::Embedding,
+                name: "embedding".to_string(),
+                data: serde_json::json!({"values": [0.1, 0.2]}),
+            }],
+        };
+        caches
+            .insert_extract_content(key.clone(), response.clone())
+            .await;
+        let cached = caches.get_extract_content(&key).await.unwrap();
+        assert_eq!(cached.features[0].data, response.features[0].data);
+
+        let new_fingerprint = ExtractContentCacheKey::new("embedder", "v2", &text("query"), &None);
+        assert!(caches.get_extract_content(&new_fingerprint).await.is_none());
+    }
+}
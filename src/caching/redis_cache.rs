@@ -1,7 +1,10 @@
+use std::time::Duration;
+
 use super::prelude::*;
 
 pub struct RedisCache<K, V> {
     client: redis::Client,
+    ttl: Option<Duration>,
     _k: std::marker::PhantomData<K>,
     _v: std::marker::PhantomData<V>,
 }
@@ -14,10 +17,17 @@ where
     pub fn new(client: redis::Client) -> Self {
         Self {
             client,
+            ttl: None,
             _k: std::marker::PhantomData,
             _v: std::marker::PhantomData,
         }
     }
+
+    /// Expire entries after `ttl`, redis only keeps whole seconds.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
 }
 
 impl From<redis::Client> for RedisCache<String, String> {
@@ -48,11 +58,12 @@ where
         let mut conn = self.client.get_async_connection().await?;
         let key: Vec<u8> = key.serialize_to_flexbuffer()?;
         let value: Vec<u8> = value.serialize_to_flexbuffer()?;
-        redis::cmd("SET")
-            .arg(key)
-            .arg(value)
-            .query_async(&mut conn)
-            .await?;
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(value);
+        if let Some(ttl) = self.ttl {
+            cmd.arg("EX").arg(ttl.as_secs().max(1));
+        }
+        cmd.query_async(&mut conn).await?;
         Ok(())
     }
 
@@ -70,7 +81,7 @@ mod tests {
     use tokio;
 
     use super::*;
-    use crate::caching::traits::Cache;
+    use crate::caching::traits::{Cache, FlexBufferable};
 
     #[derive(Serialize, Deserialize, Clone)]
     struct TestCacheKey {
@@ -116,4 +127,29 @@ mod tests {
         let cached_value = cache.get(&key).await.unwrap();
         assert!(cached_value.is_none());
     }
+
+    #[tokio::test]
+    async fn test_redis_cache_ttl() {
+        let key = TestCacheKey {
+            key: "test_ttl".to_string(),
+        };
+        let value = TestCacheValue {
+            value: vec![1, 2, 3],
+        };
+        let client = redis::Client::open("redis://localhost:6379").unwrap();
+        let mut cache = RedisCache::<TestCacheKey, TestCacheValue>::new(client.clone())
+            .with_ttl(Duration::from_secs(60));
+        cache.insert(key.clone(), value.clone()).await.unwrap();
+
+        let mut conn = client.get_async_connection().await.unwrap();
+        let ttl: i64 = redis::cmd("TTL")
+            .arg(key.serialize_to_flexbuffer().unwrap())
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        assert!(ttl > 0 && ttl <= 60);
+        assert_eq!(cache.get(&key).await.unwrap(), Some(value));
+
+        cache.invalidate(&key).await.unwrap();
+    }
 }
@@ -7,7 +7,7 @@ use crate::{
     coordinator_service::CoordinatorServer,
     prelude::*,
     server,
-    server_config::ServerConfig,
+    server_config::{IndexStoreKind, ServerConfig},
 };
 
 #[derive(Debug, ClapArgs)]
@@ -35,7 +35,12 @@ impl Args {
                 .unwrap_or_else(|e| panic!("failed to load config file `{}`: {}", config_path, e))
         } else {
             info!("No config file provided. Using defaults");
-            ServerConfig::default()
+            let mut config = ServerConfig::default();
+            // Dev servers run without any external service
+            if dev_mode {
+                config.index_config.index_store = IndexStoreKind::Embedded;
+            }
+            config
         };
 
         debug!("Server config is: {:?}", config);
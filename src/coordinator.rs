@@ -5,7 +5,7 @@ use std::{
     vec,
 };
 
-use anyhow::{Ok, Result};
+use anyhow::{anyhow, Ok, Result};
 use indexify_internal_api as internal_api;
 use indexify_proto::indexify_coordinator;
 use internal_api::{
@@ -18,21 +18,101 @@ use internal_api::{
     StructuredDataSchema,
 };
 use itertools::Itertools;
+use sha2::{Digest, Sha256};
 use tokio::sync::{broadcast, watch::Receiver};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::{
     coordinator_client::CoordinatorClient,
     coordinator_filters::*,
+    extraction_graph_planner::{
+        plan_extraction_graph,
+        validate_extraction_graph,
+        PlannedExtraction,
+    },
     forwardable_coordinator::ForwardableCoordinator,
     garbage_collector::GarbageCollector,
     metrics::Timer,
     scheduler::Scheduler,
-    state::{store::requests::StateChangeProcessed, RaftMetrics, SharedState},
+    state::{
+        store::{
+            requests::{ContentStorageUpdate, IndexStoreUpdate, StateChangeProcessed},
+            TaskId,
+        },
+        RaftMetrics,
+        SharedState,
+    },
     task_allocator::TaskAllocator,
     utils,
 };
 
+/// Fingerprint of the description an extractor is registered with. Results of
+/// extractor calls are cached under it, so re-registering an extractor with a
+/// different description invalidates them.
+fn extractor_fingerprint(extractor: &internal_api::ExtractorDescription) -> Result<String> {
+    // Objects of json values keep their keys sorted, unlike the hash maps of the
+    // description, so the fingerprint doesn't depend on iteration order.
+    let description = serde_json::to_value(extractor)?;
+    Ok(format!("{:x}", Sha256::digest(description.to_string())))
+}
+
+/// Which of the unfinished tasks of a namespace to cancel.
+#[derive(Debug, Clone)]
+pub enum TaskSelector {
+    Tasks(Vec<TaskId>),
+    /// The tasks of a content and of the content extracted from it.
+    ContentTree(String),
+    ExtractionPolicy {
+        graph: String,
+        policy: String,
+    },
+    Namespace,
+}
+
+/// Filters of listed tasks, on top of their namespace and extraction policy.
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    pub outcome: Option<internal_api::TaskOutcome>,
+    /// Only tasks which were assigned to the executor.
+    pub executor_id: Option<String>,
+    pub content_id: Option<String>,
+    pub created_after: Option<u64>,
+    pub created_before: Option<u64>,
+}
+
+impl TaskFilter {
+    pub fn matches(&self, task: &internal_api::Task) -> bool {
+        if self
+            .outcome
+            .map_or(false, |outcome| outcome != task.outcome)
+        {
+            return false;
+        }
+        if let Some(executor_id) = &self.executor_id {
+            if !task.was_assigned_to(executor_id) {
+                return false;
+            }
+        }
+        if let Some(content_id) = &self.content_id {
+            if &task.content_metadata.id.id != content_id {
+                return false;
+            }
+        }
+        if self.created_after.is_some() || self.created_before.is_some() {
+            let Some(created_at) = task.created_at() else {
+                return false;
+            };
+            if self.created_after.map_or(false, |after| created_at < after) ||
+                self.created_before
+                    .map_or(false, |before| created_at > before)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
 pub struct Coordinator {
     pub shared_state: SharedState,
     scheduler: Scheduler,
@@ -115,14 +195,44 @@ impl Coordinator {
         executor_id: &str,
         outcome: internal_api::TaskOutcome,
         content_list: Vec<indexify_coordinator::ContentMetadata>,
+        started_at: Option<u64>,
+        failure_reason: Option<String>,
     ) -> Result<()> {
         info!(
             "updating task: {}, executor_id: {}, outcome: {:?}",
             task_id, executor_id, outcome
         );
         let mut task = self.shared_state.task_with_id(task_id).await?;
+        //  The state machine rejects outcomes of cancelled tasks as well, this only
+        // saves proposing them
+        if task.outcome == internal_api::TaskOutcome::Cancelled {
+            return Err(anyhow!("task {} was cancelled", task_id));
+        }
+        // Tasks failed by the coordinator, for example when they timed out,
+        // keep their outcome when their executor reports a late one
+        if task.failure_reason.is_some() {
+            warn!(
+                "ignoring outcome {:?} of task {} which already failed: {}",
+                outcome,
+                task_id,
+                task.failure_reason.unwrap_or_default()
+            );
+            return Ok(());
+        }
         let content_meta_list = self.external_content_metadata_to_internal(content_list);
+        if let Some(started_at) = started_at {
+            task.record_event(
+                internal_api::TaskEventType::Started,
+                started_at,
+                Some(executor_id.to_string()),
+                None,
+            );
+        }
         task.outcome = outcome;
+        if outcome == internal_api::TaskOutcome::Failed {
+            task.failure_reason = failure_reason;
+        }
+        task.record_outcome(utils::timestamp_secs(), Some(executor_id.to_string()));
         self.shared_state
             .update_task(task, Some(executor_id.to_string()), content_meta_list)
             .await?;
@@ -164,7 +274,13 @@ impl Coordinator {
         self.shared_state.list_extractors().await
     }
 
-    pub async fn heartbeat(&self, executor_id: &str) -> Result<Vec<indexify_coordinator::Task>> {
+    /// Tasks assigned to the executor, and the ids of its tasks which were
+    /// cancelled. Cancelled tasks are listed until `release_cancelled_tasks`
+    /// is called for them, once the executor was told about them.
+    pub async fn heartbeat(
+        &self,
+        executor_id: &str,
+    ) -> Result<(Vec<indexify_coordinator::Task>, Vec<TaskId>)> {
         let tasks = self
             .shared_state
             .tasks_for_executor(executor_id, Some(10))
@@ -173,9 +289,112 @@ impl Coordinator {
             .into_iter()
             .map(|task| -> Result<indexify_coordinator::Task> { Ok(task.into()) })
             .collect::<Result<Vec<_>>>()?;
+        let cancelled_task_ids = self
+            .shared_state
+            .cancelled_tasks_for_executor(executor_id)
+            .await?;
+        Ok((tasks, cancelled_task_ids))
+    }
+
+    pub async fn release_cancelled_tasks(
+        &self,
+        executor_id: &str,
+        task_ids: Vec<TaskId>,
+    ) -> Result<()> {
+        if task_ids.is_empty() {
+            return Ok(());
+        }
+        self.shared_state
+            .release_cancelled_tasks(executor_id, task_ids)
+            .await
+    }
+
+    /// Cancels the unfinished tasks of the namespace the selector matches and
+    /// returns their ids.
+    pub async fn cancel_tasks(
+        &self,
+        namespace: &str,
+        selector: TaskSelector,
+    ) -> Result<Vec<TaskId>> {
+        let tasks = match selector {
+            TaskSelector::Tasks(task_ids) => {
+                let task_ids: HashSet<TaskId> = task_ids.into_iter().collect();
+                let mut tasks = Vec::new();
+                for task_id in task_ids {
+                    let task = self.shared_state.task_with_id(&task_id).await?;
+                    if task.namespace == namespace && !task.terminal_state() {
+                        tasks.push(task);
+                    }
+                }
+                tasks
+            }
+            TaskSelector::ContentTree(content_id) => {
+                let content_ids: Vec<ContentMetadataId> = self
+                    .shared_state
+                    .get_content_tree_metadata(&content_id)?
+                    .into_iter()
+                    .filter(|content| content.namespace == namespace)
+                    .map(|content| content.id)
+                    .collect();
+                self.pending_tasks_for_contents(&content_ids).await?
+            }
+            TaskSelector::ExtractionPolicy { graph, policy } => {
+                let policy = self
+                    .get_extraction_graph(namespace, &graph)?
+                    .extraction_policies
+                    .into_iter()
+                    .find(|extraction_policy| extraction_policy.name == policy)
+                    .ok_or_else(|| anyhow!("extraction policy {} not found", policy))?;
+                self.shared_state
+                    .list_tasks(namespace, Some(policy.id))
+                    .await?
+                    .into_iter()
+                    .filter(|task| !task.terminal_state())
+                    .collect()
+            }
+            TaskSelector::Namespace => self
+                .shared_state
+                .list_tasks(namespace, None)
+                .await?
+                .into_iter()
+                .filter(|task| !task.terminal_state())
+                .collect(),
+        };
+        self.cancel_unfinished_tasks(tasks).await
+    }
+
+    /// Unfinished tasks of the contents, looked up through the index of
+    /// their pending tasks.
+    async fn pending_tasks_for_contents(
+        &self,
+        content_ids: &[ContentMetadataId],
+    ) -> Result<Vec<internal_api::Task>> {
+        let mut tasks = Vec::new();
+        for content_id in content_ids {
+            for task_id in self
+                .shared_state
+                .pending_tasks_for_content(content_id)
+                .await
+            {
+                let task = self.shared_state.task_with_id(&task_id).await?;
+                if !task.terminal_state() {
+                    tasks.push(task);
+                }
+            }
+        }
         Ok(tasks)
     }
 
+    async fn cancel_unfinished_tasks(&self, tasks: Vec<internal_api::Task>) -> Result<Vec<TaskId>> {
+        let task_ids: Vec<TaskId> = tasks.into_iter().map(|task| task.id).collect();
+        if task_ids.is_empty() {
+            return Ok(task_ids);
+        }
+        info!("cancelling tasks: {:?}", task_ids);
+        self.shared_state.cancel_tasks(task_ids.clone()).await?;
+        Ok(task_ids)
+    }
+
     pub async fn all_task_assignments(&self) -> Result<HashMap<String, String>> {
         self.shared_state.task_assignments().await
     }
@@ -188,6 +407,7 @@ impl Coordinator {
         &self,
         namespace: &str,
         extraction_policy: Option<String>,
+        filter: &TaskFilter,
     ) -> Result<Vec<indexify_coordinator::Task>> {
         let tasks = self
             .shared_state
@@ -195,6 +415,7 @@ impl Coordinator {
             .await?;
         let tasks = tasks
             .into_iter()
+            .filter(|task| filter.matches(task))
             .map(|task| -> Result<indexify_coordinator::Task> { Ok(task.into()) })
             .collect::<Result<Vec<_>>>()?;
         Ok(tasks)
@@ -210,19 +431,49 @@ impl Coordinator {
         self.shared_state.list_indexes(namespace).await
     }
 
-    pub async fn get_index(&self, namespace: &str, name: &str) -> Result<internal_api::Index> {
+    fn index_id(namespace: &str, name: &str) -> String {
         let mut s = DefaultHasher::new();
         namespace.hash(&mut s);
         name.hash(&mut s);
-        let id = format!("{:x}", s.finish());
-        self.shared_state.get_index(&id).await
+        format!("{:x}", s.finish())
+    }
+
+    pub async fn get_index(&self, namespace: &str, name: &str) -> Result<internal_api::Index> {
+        self.shared_state
+            .get_index(&Self::index_id(namespace, name))
+            .await
+    }
+
+    /// Moves an index to other vector stores if it is still in the stores the
+    /// request expects, and returns the index as stored afterwards.
+    pub async fn update_index_store(
+        &self,
+        req: indexify_coordinator::UpdateIndexStoreRequest,
+    ) -> Result<internal_api::Index> {
+        let update = IndexStoreUpdate {
+            index_id: Self::index_id(&req.namespace, &req.name),
+            from_vector_store: req.from_vector_store,
+            from_migrating_to: req.from_migrating_to,
+            vector_store: req.vector_store,
+            migrating_to: req.migrating_to,
+        };
+        self.shared_state.update_index_store(update).await
+    }
+
+    pub async fn list_index_stores(&self) -> Result<Vec<internal_api::Index>> {
+        self.shared_state.list_index_stores().await
     }
 
     pub async fn update_indexes_state(&self, indexes: Vec<internal_api::Index>) -> Result<()> {
         self.shared_state.set_indexes(indexes).await
     }
 
-    pub async fn get_extractor_coordinates(&self, extractor_name: &str) -> Result<Vec<String>> {
+    /// Addresses of the executors running an extractor, along with the
+    /// fingerprint of the description the extractor is registered with.
+    pub async fn get_extractor_coordinates(
+        &self,
+        extractor_name: &str,
+    ) -> Result<(Vec<String>, String)> {
         let executors = self
             .shared_state
             .get_executors_for_extractor(extractor_name)
@@ -231,7 +482,11 @@ impl Coordinator {
             .iter()
             .map(|e| e.addr.clone())
             .collect::<Vec<String>>();
-        Ok(addresses)
+        if addresses.is_empty() {
+            return Ok((addresses, String::new()));
+        }
+        let extractor = self.get_extractor(extractor_name)?;
+        Ok((addresses, extractor_fingerprint(&extractor)?))
     }
 
     // TODO: edwin
@@ -240,10 +495,28 @@ impl Coordinator {
         addr: &str,
         executor_id: &str,
         extractors: Vec<internal_api::ExtractorDescription>,
+    ) -> Result<()> {
+        self.register_executor_with_capabilities(
+            addr,
+            executor_id,
+            extractors,
+            internal_api::ExecutorResources::default(),
+            HashMap::new(),
+        )
+        .await
+    }
+
+    pub async fn register_executor_with_capabilities(
+        &self,
+        addr: &str,
+        executor_id: &str,
+        extractors: Vec<internal_api::ExtractorDescription>,
+        resources: internal_api::ExecutorResources,
+        labels: HashMap<String, String>,
     ) -> Result<()> {
         let _ = self
             .shared_state
-            .register_executor(addr, executor_id, extractors)
+            .register_executor_with_capabilities(addr, executor_id, extractors, resources, labels)
             .await;
         Ok(())
     }
@@ -325,6 +598,46 @@ impl Coordinator {
         Ok((task, root_content))
     }
 
+    /// Checks whether the outputs of a task are about to be written to indexes
+    /// holding the outputs of another version of its extractor, in which case
+    /// the indexes end up with vectors of both versions. The outputs are
+    /// refused when the policy of the task rejects mixed versions, and written
+    /// with a warning otherwise.
+    pub async fn check_extractor_version(&self, task: &internal_api::Task) -> Result<()> {
+        let policy = self
+            .shared_state
+            .get_extraction_policy(&task.extraction_policy_id)?;
+        // Indexes are named after their tables without the namespace, outputs
+        // which aren't embeddings have no index.
+        let prefix = format!("{}.", task.namespace);
+        for table_name in task.output_index_table_mapping.values() {
+            let Some(index_name) = table_name.strip_prefix(&prefix) else {
+                continue;
+            };
+            let index_id = Self::index_id(&task.namespace, index_name);
+            let Some(index) = self.shared_state.find_index(&index_id)? else {
+                continue;
+            };
+            if index.extractor_version == task.extractor_version {
+                continue;
+            }
+            let mismatch = format!(
+                "task {} writes outputs of extractor {} version '{}' to index {} holding outputs of version '{}', re-extract the outdated content of policy {} to avoid mixing versions",
+                task.id,
+                task.extractor,
+                task.extractor_version,
+                index.name,
+                index.extractor_version,
+                index.extraction_policy_name
+            );
+            if policy.limits.reject_mixed_extractor_versions {
+                return Err(anyhow!(mismatch));
+            }
+            warn!("{}", mismatch);
+        }
+        Ok(())
+    }
+
     pub async fn get_content_tree_metadata(
         &self,
         content_id: &str,
@@ -334,6 +647,71 @@ impl Coordinator {
         Ok(content_tree)
     }
 
+    /// Merges metadata extracted from content after it was created into the
+    /// metadata of its latest version, so that policies can route it on it.
+    pub async fn merge_extracted_metadata(
+        &self,
+        content_id: &str,
+        metadata: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        let content = self
+            .shared_state
+            .get_content_metadata_batch(vec![content_id.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("content with id: {} not found", content_id))?;
+        self.shared_state
+            .merge_extracted_metadata(content.id, metadata)
+            .await
+    }
+
+    /// Moves the blobs of the latest version of content to new storage urls,
+    /// returning the ids of the content which was moved.
+    pub async fn update_content_storage(
+        &self,
+        updates: Vec<indexify_coordinator::ContentStorageUpdate>,
+    ) -> Result<Vec<String>> {
+        let content = self
+            .shared_state
+            .get_content_metadata_batch(updates.iter().map(|u| u.content_id.clone()).collect())
+            .await?;
+        let content_ids: HashMap<String, ContentMetadataId> = content
+            .into_iter()
+            .map(|c| (c.id.id.clone(), c.id))
+            .collect();
+        let storage_updates = updates
+            .iter()
+            .filter_map(|update| {
+                content_ids
+                    .get(&update.content_id)
+                    .map(|content_id| ContentStorageUpdate {
+                        content_id: content_id.clone(),
+                        from_storage_url: update.from_storage_url.clone(),
+                        to_storage_url: update.to_storage_url.clone(),
+                    })
+            })
+            .collect_vec();
+        self.shared_state
+            .update_content_storage(storage_updates.clone())
+            .await?;
+
+        let mut updated = Vec::new();
+        for update in storage_updates {
+            let content = self
+                .shared_state
+                .get_content_metadata_with_version(&update.content_id)
+                .await?;
+            if content.storage_url == update.to_storage_url {
+                updated.push(update.content_id.id);
+            }
+        }
+        Ok(updated)
+    }
+
+    pub async fn is_content_tree_indexed(&self, content_id: &str) -> Result<bool> {
+        self.shared_state.is_content_tree_indexed(content_id).await
+    }
+
     pub fn get_extractor(
         &self,
         extractor_name: &str,
@@ -345,33 +723,38 @@ impl Coordinator {
         &self,
         extraction_graph: ExtractionGraph,
     ) -> Result<Vec<internal_api::Index>> {
+        let extractors = self.graph_extractors(&extraction_graph)?;
+        validate_extraction_graph(
+            &extraction_graph.name,
+            &extraction_graph.extraction_policies,
+            &extractors,
+        )?;
         let mut structured_data_schema =
             StructuredDataSchema::new(&extraction_graph.name, &extraction_graph.namespace);
         let mut indexes_to_create = Vec::new();
         for extraction_policy in &extraction_graph.extraction_policies {
-            let extractor = self.get_extractor(&extraction_policy.extractor)?;
+            let extractor = extractors[&extraction_policy.extractor].clone();
             for (output_name, output_schema) in extractor.outputs {
-                match output_schema {
-                    OutputSchema::Embedding(embeddings) => {
-                        let mut index_to_create = internal_api::Index {
-                            id: "".to_string(),
-                            namespace: extraction_policy.namespace.clone(),
-                            name: "".to_string(),
-                            table_name: "".to_string(),
-                            schema: serde_json::to_value(embeddings).unwrap().to_string(),
-                            extraction_policy_name: extraction_policy.name.clone(),
-                            extractor_name: extractor.name.clone(),
-                            graph_name: extraction_graph.name.clone(),
-                            visibility: false,
-                        };
-                        index_to_create.name = index_to_create.build_name(&output_name);
-                        index_to_create.table_name = index_to_create.build_table_name(&output_name);
-                        index_to_create.id = index_to_create.id();
-                        indexes_to_create.push(index_to_create);
-                    }
-                    OutputSchema::Attributes(columns) => {
-                        structured_data_schema.merge(columns);
-                    }
+                if let Some(embeddings) = output_schema.embedding_schema() {
+                    let mut index_to_create = internal_api::Index {
+                        id: "".to_string(),
+                        namespace: extraction_policy.namespace.clone(),
+                        name: "".to_string(),
+                        table_name: "".to_string(),
+                        schema: serde_json::to_value(embeddings).unwrap().to_string(),
+                        extraction_policy_name: extraction_policy.name.clone(),
+                        extractor_name: extractor.name.clone(),
+                        graph_name: extraction_graph.name.clone(),
+                        visibility: false,
+                        extractor_version: extractor.version.clone(),
+                        ..Default::default()
+                    };
+                    index_to_create.name = index_to_create.build_name(&output_name);
+                    index_to_create.table_name = index_to_create.build_table_name(&output_name);
+                    index_to_create.id = index_to_create.id();
+                    indexes_to_create.push(index_to_create);
+                } else if let OutputSchema::Attributes(columns) = output_schema {
+                    structured_data_schema.merge(columns);
                 }
             }
         }
@@ -385,6 +768,82 @@ impl Coordinator {
         Ok(indexes_to_create)
     }
 
+    /// Extractors of the policies of an extraction graph, by name.
+    fn graph_extractors(
+        &self,
+        extraction_graph: &ExtractionGraph,
+    ) -> Result<HashMap<String, internal_api::ExtractorDescription>> {
+        let mut extractors = HashMap::new();
+        for extraction_policy in &extraction_graph.extraction_policies {
+            if !extractors.contains_key(&extraction_policy.extractor) {
+                let extractor = self.get_extractor(&extraction_policy.extractor)?;
+                extractors.insert(extractor.name.clone(), extractor);
+            }
+        }
+        Ok(extractors)
+    }
+
+    /// Policies of an extraction graph which would be applied to content of a
+    /// type with labels, in the order they would be applied.
+    pub async fn plan_extraction_graph(
+        &self,
+        namespace: &str,
+        extraction_graph: &str,
+        content_type: &str,
+        labels: &HashMap<String, String>,
+    ) -> Result<Vec<PlannedExtraction>> {
+        let graph = self.get_extraction_graph(namespace, extraction_graph)?;
+        let extractors = self.graph_extractors(&graph)?;
+        plan_extraction_graph(
+            &graph.extraction_policies,
+            &extractors,
+            content_type,
+            labels,
+        )
+    }
+
+    fn get_extraction_graph(&self, namespace: &str, name: &str) -> Result<ExtractionGraph> {
+        self.shared_state
+            .get_extraction_graphs_by_name(namespace, &[name.to_string()])?
+            .into_iter()
+            .flatten()
+            .next()
+            .ok_or_else(|| anyhow!("extraction graph {} not found", name))
+    }
+
+    /// Extracts the content an extraction policy was applied to with older
+    /// versions of its extractor again, and records the current version of the
+    /// extractor on the indexes of the policy.
+    pub async fn reextract_outdated_content(
+        &self,
+        namespace: &str,
+        extraction_graph: &str,
+        extraction_policy: &str,
+    ) -> Result<()> {
+        let graph = self.get_extraction_graph(namespace, extraction_graph)?;
+        let policy = graph
+            .extraction_policies
+            .into_iter()
+            .find(|policy| policy.name == extraction_policy)
+            .ok_or_else(|| anyhow!("extraction policy {} not found", extraction_policy))?;
+        let extractor = self.get_extractor(&policy.extractor)?;
+        let indexes = self
+            .list_indexes(namespace)
+            .await?
+            .into_iter()
+            .filter(|index| {
+                index.graph_name == graph.name && index.extraction_policy_name == policy.name
+            })
+            .map(|index| internal_api::Index {
+                extractor_version: extractor.version.clone(),
+                ..index
+            })
+            .collect();
+        self.shared_state
+            .reextract_outdated_content(&policy.id, indexes)
+            .await
+    }
+
     pub async fn create_gc_tasks(
         &self,
         state_change: &StateChange,
@@ -393,6 +852,16 @@ impl Coordinator {
         let content_tree_metadata = self
             .shared_state
             .get_content_tree_metadata_with_version(&content_id)?;
+
+        //  Tasks of tombstoned content are cancelled, so that executors stop extracting
+        // it
+        let content_ids: Vec<ContentMetadataId> = content_tree_metadata
+            .iter()
+            .map(|content| content.id.clone())
+            .collect();
+        let tasks = self.pending_tasks_for_contents(&content_ids).await?;
+        self.cancel_unfinished_tasks(tasks).await?;
+
         let mut output_tables = HashMap::new();
 
         for content_metadata in &content_tree_metadata {
@@ -528,6 +997,54 @@ impl Coordinator {
         Ok(())
     }
 
+    /// Fails the tasks which haven't finished within the timeout of their
+    /// extraction policy since they were assigned to an executor.
+    pub async fn fail_timed_out_tasks(&self) -> Result<()> {
+        let now = utils::timestamp_secs();
+        let mut timeouts: HashMap<ExtractionPolicyId, Option<(u64, String)>> = HashMap::new();
+        for (task_id, executor_id) in self.shared_state.task_assignments().await? {
+            let mut task = self.shared_state.task_with_id(&task_id).await?;
+            if task.terminal_state() || task.assigned_at == 0 {
+                continue;
+            }
+            let timeout = match timeouts.get(&task.extraction_policy_id) {
+                Some(timeout) => timeout.clone(),
+                None => {
+                    let policy = self
+                        .shared_state
+                        .get_extraction_policy(&task.extraction_policy_id)?;
+                    let timeout = policy
+                        .limits
+                        .timeout_secs
+                        .map(|timeout_secs| (timeout_secs, policy.name));
+                    timeouts.insert(task.extraction_policy_id.clone(), timeout.clone());
+                    timeout
+                }
+            };
+            let Some((timeout_secs, policy_name)) = timeout else {
+                continue;
+            };
+            if now.saturating_sub(task.assigned_at) < timeout_secs {
+                continue;
+            }
+            let reason = format!(
+                "task did not finish within the timeout of {} seconds of extraction policy {}",
+                timeout_secs, policy_name
+            );
+            warn!(
+                "failing task {} on executor {}: {}",
+                task.id, executor_id, reason
+            );
+            task.outcome = internal_api::TaskOutcome::Failed;
+            task.failure_reason = Some(reason);
+            task.record_outcome(now, Some(executor_id.clone()));
+            self.shared_state
+                .update_task(task, Some(executor_id), vec![])
+                .await?;
+        }
+        Ok(())
+    }
+
     pub async fn subscribe_to_gc_events(&self) -> broadcast::Receiver<GarbageCollectionTask> {
         self.shared_state.subscribe_to_gc_task_events().await
     }
@@ -544,6 +1061,16 @@ impl Coordinator {
         Ok(())
     }
 
+    pub async fn create_new_content_metadata(
+        &self,
+        content_list: Vec<indexify_internal_api::ContentMetadata>,
+    ) -> Result<()> {
+        self.shared_state
+            .create_new_content_batch(content_list)
+            .await?;
+        Ok(())
+    }
+
     pub async fn tombstone_content_metadatas(&self, content_ids: &[String]) -> Result<()> {
         self.shared_state
             .tombstone_content_batch(content_ids)
@@ -581,7 +1108,7 @@ mod tests {
     use indexify_internal_api as internal_api;
     use internal_api::ContentSource;
 
-    use super::Coordinator;
+    use super::{Coordinator, TaskFilter, TaskSelector};
     use crate::{
         coordinator_client::CoordinatorClient,
         garbage_collector::GarbageCollector,
@@ -1206,6 +1733,8 @@ mod tests {
                     "test_executor_id",
                     internal_api::TaskOutcome::Success,
                     vec![],
+                    None,
+                    None,
                 )
                 .await?;
         }
@@ -1287,4 +1816,478 @@ mod tests {
         assert_eq!(tasks.len() + unassigned_tasks.len(), 1);
         Ok(())
     }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_reextract_outdated_content() -> Result<(), anyhow::Error> {
+        let (coordinator, shared_state) = setup_coordinator().await;
+        coordinator.create_namespace(DEFAULT_TEST_NAMESPACE).await?;
+
+        //  Register the first version of the extractor and extract content with it
+        let executor_id = "test_executor_id";
+        let mut extractor = mock_extractor();
+        extractor.version = "v1".to_string();
+        coordinator
+            .register_executor("localhost:8950", executor_id, vec![extractor.clone()])
+            .await?;
+        coordinator.run_scheduler().await?;
+        let eg = create_test_extraction_graph("extraction_graph_1", vec!["extraction_policy_1"]);
+        coordinator.create_extraction_graph(eg.clone()).await?;
+        coordinator.run_scheduler().await?;
+        let policy = &eg.extraction_policies[0];
+        let index = coordinator.list_indexes(DEFAULT_TEST_NAMESPACE).await?;
+        assert_eq!(index[0].extractor_version, "v1");
+
+        let content_metadata = test_mock_content_metadata("test", "test", &eg.name);
+        coordinator
+            .create_content_metadata(vec![content_metadata])
+            .await?;
+        coordinator.run_scheduler().await?;
+        let mut task = shared_state.tasks_for_executor(executor_id, None).await?[0].clone();
+        assert_eq!(task.extractor_version, "v1");
+        task.outcome = internal_api::TaskOutcome::Success;
+        shared_state
+            .update_task(task.clone(), Some(executor_id.to_string()), vec![])
+            .await?;
+
+        //  Upgrade the extractor, only the content extracted with the first version
+        // is extracted again
+        extractor.version = "v2".to_string();
+        coordinator
+            .register_executor("localhost:8950", executor_id, vec![extractor])
+            .await?;
+        coordinator.run_scheduler().await?;
+        coordinator
+            .reextract_outdated_content(DEFAULT_TEST_NAMESPACE, &eg.name, &policy.name)
+            .await?;
+        coordinator.run_scheduler().await?;
+        let tasks = shared_state.tasks_for_executor(executor_id, None).await?;
+        assert_eq!(tasks.len(), 1);
+        assert_ne!(tasks[0].id, task.id);
+        assert_eq!(tasks[0].extractor_version, "v2");
+        assert_eq!(tasks[0].content_metadata.id, task.content_metadata.id);
+        let index = coordinator.list_indexes(DEFAULT_TEST_NAMESPACE).await?;
+        assert_eq!(index[0].extractor_version, "v2");
+
+        //  Content with a task for the current version isn't extracted again
+        coordinator
+            .reextract_outdated_content(DEFAULT_TEST_NAMESPACE, &eg.name, &policy.name)
+            .await?;
+        coordinator.run_scheduler().await?;
+        let tasks = shared_state
+            .list_tasks(DEFAULT_TEST_NAMESPACE, Some(policy.id.clone()))
+            .await?;
+        assert_eq!(tasks.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_extractor_version() -> Result<(), anyhow::Error> {
+        let (coordinator, shared_state) = setup_coordinator().await;
+        coordinator.create_namespace(DEFAULT_TEST_NAMESPACE).await?;
+
+        let executor_id = "test_executor_id";
+        let mut extractor = mock_extractor();
+        extractor.version = "v1".to_string();
+        coordinator
+            .register_executor("localhost:8950", executor_id, vec![extractor])
+            .await?;
+        coordinator.run_scheduler().await?;
+
+        //  The policy of the second graph rejects mixed versions
+        let mut tasks = Vec::new();
+        for (graph_name, reject) in [("extraction_graph_1", false), ("extraction_graph_2", true)] {
+            let mut eg = create_test_extraction_graph(graph_name, vec!["extraction_policy_1"]);
+            let policy = &mut eg.extraction_policies[0];
+            policy.output_table_mapping = HashMap::from([(
+                "test_output".to_string(),
+                format!(
+                    "{}.{}.{}.test_output",
+                    DEFAULT_TEST_NAMESPACE, graph_name, policy.name
+                ),
+            )]);
+            policy.limits.reject_mixed_extractor_versions = reject;
+            let policy_id = policy.id.clone();
+            coordinator.create_extraction_graph(eg.clone()).await?;
+            let content_metadata = test_mock_content_metadata(graph_name, graph_name, &eg.name);
+            coordinator
+                .create_content_metadata(vec![content_metadata])
+                .await?;
+            coordinator.run_scheduler().await?;
+            let task = shared_state
+                .list_tasks(DEFAULT_TEST_NAMESPACE, Some(policy_id))
+                .await?
+                .pop()
+                .unwrap();
+            coordinator.check_extractor_version(&task).await?;
+            tasks.push(task);
+        }
+
+        //  Outputs of another version are written with a warning, or refused
+        for task in &mut tasks {
+            task.extractor_version = "v2".to_string();
+        }
+        coordinator.check_extractor_version(&tasks[0]).await?;
+        let err = coordinator
+            .check_extractor_version(&tasks[1])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("holding outputs of version 'v1'"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_policy_limits() -> Result<(), anyhow::Error> {
+        let (coordinator, shared_state) = setup_coordinator().await;
+        coordinator.create_namespace(DEFAULT_TEST_NAMESPACE).await?;
+
+        //  Only one of the executors has the resources the policy needs
+        let extractor = mock_extractor();
+        coordinator
+            .register_executor("localhost:8950", "cpu_executor", vec![extractor.clone()])
+            .await?;
+        coordinator
+            .register_executor_with_capabilities(
+                "localhost:8951",
+                "gpu_executor",
+                vec![extractor.clone()],
+                internal_api::ExecutorResources {
+                    cpus: 8,
+                    memory_mb: 16384,
+                    gpus: 1,
+                },
+                HashMap::new(),
+            )
+            .await?;
+        coordinator.run_scheduler().await?;
+        let mut eg =
+            create_test_extraction_graph("extraction_graph_1", vec!["extraction_policy_1"]);
+        eg.extraction_policies[0].limits = internal_api::ExtractionPolicyLimits {
+            max_content_size_bytes: Some(100),
+            timeout_secs: Some(60),
+            resources: internal_api::ExecutorResources {
+                gpus: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        coordinator.create_extraction_graph(eg.clone()).await?;
+        coordinator.run_scheduler().await?;
+        let policy = &eg.extraction_policies[0];
+
+        let mut small_content = test_mock_content_metadata("small", "small", &eg.name);
+        small_content.size_bytes = 10;
+        let mut large_content = test_mock_content_metadata("large", "large", &eg.name);
+        large_content.size_bytes = 1000;
+        coordinator
+            .create_content_metadata(vec![small_content, large_content])
+            .await?;
+        coordinator.run_scheduler().await?;
+
+        //  Content over the size limit isn't extracted, its task fails right away
+        let tasks = shared_state
+            .list_tasks(DEFAULT_TEST_NAMESPACE, Some(policy.id.clone()))
+            .await?;
+        assert_eq!(tasks.len(), 2);
+        let large_task = tasks
+            .iter()
+            .find(|task| task.content_metadata.id.id == "large")
+            .unwrap();
+        assert_eq!(large_task.outcome, internal_api::TaskOutcome::Failed);
+        assert_eq!(
+            large_task.failure_reason.as_deref(),
+            Some("content size of 1000 bytes exceeds the limit of 100 bytes of extraction policy extraction_policy_1")
+        );
+
+        //  The other task is assigned to the executor with a gpu
+        assert!(shared_state
+            .tasks_for_executor("cpu_executor", None)
+            .await?
+            .is_empty());
+        let tasks = shared_state
+            .tasks_for_executor("gpu_executor", None)
+            .await?;
+        assert_eq!(tasks.len(), 1);
+        let mut task = tasks[0].clone();
+        assert_eq!(task.content_metadata.id.id, "small");
+        assert!(task.assigned_at > 0);
+
+        //  The task fails once it runs past the timeout, and keeps failing when
+        // the executor reports its outcome late
+        coordinator.fail_timed_out_tasks().await?;
+        assert_eq!(
+            shared_state.task_with_id(&task.id).await?.outcome,
+            internal_api::TaskOutcome::Unknown
+        );
+        task.assigned_at -= 61;
+        shared_state
+            .update_task(task.clone(), Some("gpu_executor".to_string()), vec![])
+            .await?;
+        coordinator.fail_timed_out_tasks().await?;
+        let timed_out_task = shared_state.task_with_id(&task.id).await?;
+        assert_eq!(timed_out_task.outcome, internal_api::TaskOutcome::Failed);
+        assert!(timed_out_task.failure_reason.is_some());
+        assert!(shared_state
+            .tasks_for_executor("gpu_executor", None)
+            .await?
+            .is_empty());
+        coordinator
+            .update_task(
+                &task.id,
+                "gpu_executor",
+                internal_api::TaskOutcome::Success,
+                vec![],
+                None,
+                None,
+            )
+            .await?;
+        assert_eq!(
+            shared_state.task_with_id(&task.id).await?.outcome,
+            internal_api::TaskOutcome::Failed
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_cancel_tasks() -> Result<(), anyhow::Error> {
+        let (coordinator, shared_state) = setup_coordinator().await;
+        coordinator.create_namespace(DEFAULT_TEST_NAMESPACE).await?;
+        coordinator
+            .register_executor("localhost:8950", "executor_id_1", vec![mock_extractor()])
+            .await?;
+        coordinator.run_scheduler().await?;
+        let eg = create_test_extraction_graph("extraction_graph_1", vec!["extraction_policy_1"]);
+        coordinator.create_extraction_graph(eg.clone()).await?;
+        coordinator.run_scheduler().await?;
+        coordinator
+            .create_content_metadata(vec![
+                test_mock_content_metadata("content_1", "content_1", &eg.name),
+                test_mock_content_metadata("content_2", "content_2", &eg.name),
+            ])
+            .await?;
+        coordinator.run_scheduler().await?;
+        let tasks = shared_state
+            .tasks_for_executor("executor_id_1", None)
+            .await?;
+        assert_eq!(tasks.len(), 2);
+
+        //  The executor is told about the cancelled task until it is released
+        let cancelled_task_ids = coordinator
+            .cancel_tasks(
+                DEFAULT_TEST_NAMESPACE,
+                TaskSelector::Tasks(vec![tasks[0].id.clone()]),
+            )
+            .await?;
+        assert_eq!(cancelled_task_ids, vec![tasks[0].id.clone()]);
+        let (assigned_tasks, cancelled_task_ids) = coordinator.heartbeat("executor_id_1").await?;
+        assert_eq!(assigned_tasks.len(), 1);
+        assert_eq!(assigned_tasks[0].id, tasks[1].id);
+        assert_eq!(cancelled_task_ids, vec![tasks[0].id.clone()]);
+        assert_eq!(
+            shared_state
+                .get_executor_running_task_count()
+                .await
+                .get("executor_id_1")
+                .cloned(),
+            Some(2)
+        );
+        let (_, cancelled_task_ids) = coordinator.heartbeat("executor_id_1").await?;
+        coordinator
+            .release_cancelled_tasks("executor_id_1", cancelled_task_ids.clone())
+            .await?;
+        coordinator
+            .release_cancelled_tasks("executor_id_1", cancelled_task_ids)
+            .await?;
+        let (_, cancelled_task_ids) = coordinator.heartbeat("executor_id_1").await?;
+        assert!(cancelled_task_ids.is_empty());
+        assert_eq!(
+            shared_state
+                .get_executor_running_task_count()
+                .await
+                .get("executor_id_1")
+                .cloned(),
+            Some(1)
+        );
+
+        //  Outcomes of cancelled tasks are rejected
+        assert!(coordinator
+            .update_task(
+                &tasks[0].id,
+                "executor_id_1",
+                internal_api::TaskOutcome::Success,
+                vec![],
+                None,
+                None,
+            )
+            .await
+            .is_err());
+        assert_eq!(
+            shared_state.task_with_id(&tasks[0].id).await?.outcome,
+            internal_api::TaskOutcome::Cancelled
+        );
+
+        //  Cancelling by policy only cancels the tasks which are unfinished
+        let cancelled_task_ids = coordinator
+            .cancel_tasks(
+                DEFAULT_TEST_NAMESPACE,
+                TaskSelector::ExtractionPolicy {
+                    graph: eg.name.clone(),
+                    policy: "extraction_policy_1".to_string(),
+                },
+            )
+            .await?;
+        assert_eq!(cancelled_task_ids, vec![tasks[1].id.clone()]);
+        assert!(coordinator
+            .cancel_tasks(DEFAULT_TEST_NAMESPACE, TaskSelector::Namespace)
+            .await?
+            .is_empty());
+
+        //  Late outcomes of cancelled tasks are rejected when they are applied, without
+        // changing the load of the executor, and cancelling them again changes nothing
+        let mut task = tasks[1].clone();
+        task.outcome = internal_api::TaskOutcome::Success;
+        assert!(shared_state
+            .update_task(task, Some("executor_id_1".to_string()), vec![])
+            .await
+            .is_err());
+        shared_state.cancel_tasks(vec![tasks[1].id.clone()]).await?;
+        assert_eq!(
+            shared_state.task_with_id(&tasks[1].id).await?.outcome,
+            internal_api::TaskOutcome::Cancelled
+        );
+        assert_eq!(
+            shared_state
+                .get_executor_running_task_count()
+                .await
+                .get("executor_id_1")
+                .cloned(),
+            Some(1)
+        );
+        let (assigned_tasks, cancelled_task_ids) = coordinator.heartbeat("executor_id_1").await?;
+        assert!(assigned_tasks.is_empty());
+        assert_eq!(cancelled_task_ids, vec![tasks[1].id.clone()]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_task_history() -> Result<(), anyhow::Error> {
+        let (coordinator, shared_state) = setup_coordinator().await;
+        coordinator.create_namespace(DEFAULT_TEST_NAMESPACE).await?;
+        coordinator
+            .register_executor("localhost:8950", "executor_id_1", vec![mock_extractor()])
+            .await?;
+        coordinator.run_scheduler().await?;
+        let eg = create_test_extraction_graph("extraction_graph_1", vec!["extraction_policy_1"]);
+        coordinator.create_extraction_graph(eg.clone()).await?;
+        coordinator.run_scheduler().await?;
+        coordinator
+            .create_content_metadata(vec![
+                test_mock_content_metadata("content_1", "content_1", &eg.name),
+                test_mock_content_metadata("content_2", "content_2", &eg.name),
+            ])
+            .await?;
+        coordinator.run_scheduler().await?;
+        let tasks = shared_state
+            .tasks_for_executor("executor_id_1", None)
+            .await?;
+        assert_eq!(tasks.len(), 2);
+        let task = &tasks[0];
+        let event_types: Vec<_> = task.history.iter().map(|e| e.event_type).collect();
+        assert_eq!(
+            event_types,
+            vec![
+                internal_api::TaskEventType::Created,
+                internal_api::TaskEventType::Assigned
+            ]
+        );
+        assert_eq!(
+            task.history[1].executor_id.as_deref(),
+            Some("executor_id_1")
+        );
+        assert_eq!(task.attempts(), 1);
+        assert!(task.started_at().is_none());
+
+        let started_at = task.created_at().unwrap();
+        coordinator
+            .update_task(
+                &task.id,
+                "executor_id_1",
+                internal_api::TaskOutcome::Failed,
+                vec![],
+                Some(started_at),
+                Some("extractor crashed".to_string()),
+            )
+            .await?;
+        let task = shared_state.task_with_id(&task.id).await?;
+        let event_types: Vec<_> = task.history.iter().map(|e| e.event_type).collect();
+        assert_eq!(
+            event_types,
+            vec![
+                internal_api::TaskEventType::Created,
+                internal_api::TaskEventType::Assigned,
+                internal_api::TaskEventType::Started,
+                internal_api::TaskEventType::Failed
+            ]
+        );
+        assert_eq!(task.started_at(), Some(started_at));
+        assert!(task.finished_at().is_some());
+        assert_eq!(task.failure_reason.as_deref(), Some("extractor crashed"));
+        assert_eq!(
+            task.history[3].message.as_deref(),
+            Some("extractor crashed")
+        );
+
+        //  Listed tasks are filtered by outcome, executor and content
+        let failed = coordinator
+            .list_tasks(
+                DEFAULT_TEST_NAMESPACE,
+                None,
+                &TaskFilter {
+                    outcome: Some(internal_api::TaskOutcome::Failed),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].id, task.id);
+        assert_eq!(failed[0].attempts, 1);
+        let assigned = coordinator
+            .list_tasks(
+                DEFAULT_TEST_NAMESPACE,
+                None,
+                &TaskFilter {
+                    executor_id: Some("executor_id_1".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(assigned.len(), 2);
+        let by_content = coordinator
+            .list_tasks(
+                DEFAULT_TEST_NAMESPACE,
+                None,
+                &TaskFilter {
+                    content_id: Some(tasks[1].content_metadata.id.id.clone()),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(by_content.len(), 1);
+        assert_eq!(by_content[0].id, tasks[1].id);
+        assert!(coordinator
+            .list_tasks(
+                DEFAULT_TEST_NAMESPACE,
+                None,
+                &TaskFilter {
+                    created_after: Some(started_at + 3600),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .is_empty());
+        Ok(())
+    }
 }
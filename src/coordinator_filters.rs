@@ -1,6 +1,17 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+};
 
+use anyhow::{anyhow, Result};
+use indexify_internal_api::{ConditionOperator, ContentMetadata, RoutingCondition};
 use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
 
 /// filter for content metadata
 pub fn list_content_filter<'a>(
@@ -208,3 +219,366 @@ mod test_extractor_mimetype_filter {
         assert!(!res);
     }
 }
+
+/// Field of content a routing condition is evaluated on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionField<'a> {
+    MimeType,
+    Label(&'a str),
+    Metadata(&'a str),
+}
+
+pub fn parse_condition_field(field: &str) -> Result<ConditionField> {
+    if field == "mime_type" {
+        return Ok(ConditionField::MimeType);
+    }
+    if let Some(name) = field
+        .strip_prefix("labels.")
+        .filter(|name| !name.is_empty())
+    {
+        return Ok(ConditionField::Label(name));
+    }
+    if let Some(path) = field
+        .strip_prefix("metadata.")
+        .filter(|path| !path.is_empty())
+    {
+        return Ok(ConditionField::Metadata(path));
+    }
+    Err(anyhow!(
+        "condition field {} is not mime_type, labels.<name> or metadata.<path>",
+        field
+    ))
+}
+
+/// Labels are strings, they are compared as the JSON values they parse to
+/// the same way they are when written to the metadata of extracted features.
+pub fn label_value(label: &str) -> Value {
+    serde_json::from_str(label).unwrap_or(Value::String(label.to_string()))
+}
+
+fn metadata_value<'a>(metadata: &'a HashMap<String, Value>, path: &str) -> Option<&'a Value> {
+    let mut keys = path.split('.');
+    let mut value = metadata.get(keys.next()?)?;
+    for key in keys {
+        value = match key.parse::<usize>() {
+            Ok(index) if value.is_array() => value.get(index)?,
+            _ => value.get(key)?,
+        };
+    }
+    Some(value)
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (as_f64(a), as_f64(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => as_string(a) == as_string(b),
+    }
+}
+
+/// Most patterns of regex and glob conditions kept compiled at once.
+const MAX_CONDITION_REGEXES: usize = 1024;
+
+/// Regexes of the regex and glob conditions, by the pattern they were compiled
+/// from, so that patterns aren't compiled every time content is matched.
+static CONDITION_REGEXES: Lazy<RegexCache> = Lazy::new(|| RegexCache::new(MAX_CONDITION_REGEXES));
+
+/// Compiled regexes, evicting the least recently used one once there are
+/// `capacity` of them.
+struct RegexCache {
+    capacity: usize,
+    clock: AtomicU64,
+    regexes: RwLock<HashMap<String, (Regex, AtomicU64)>>,
+}
+
+impl RegexCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            clock: AtomicU64::new(0),
+            regexes: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn get_or_compile(&self, pattern: &str) -> Result<Regex> {
+        let now = self.clock.fetch_add(1, Ordering::Relaxed);
+        if let Some((regex, used_at)) = self.regexes.read().unwrap().get(pattern) {
+            used_at.store(now, Ordering::Relaxed);
+            return Ok(regex.clone());
+        }
+        let regex = Regex::new(pattern)?;
+        let mut regexes = self.regexes.write().unwrap();
+        if regexes.len() >= self.capacity && !regexes.contains_key(pattern) {
+            let least_recently_used = regexes
+                .iter()
+                .min_by_key(|(_, (_, used_at))| used_at.load(Ordering::Relaxed))
+                .map(|(pattern, _)| pattern.clone());
+            if let Some(pattern) = least_recently_used {
+                regexes.remove(&pattern);
+            }
+        }
+        regexes.insert(pattern.to_string(), (regex.clone(), AtomicU64::new(now)));
+        Ok(regex)
+    }
+}
+
+fn glob_pattern(glob: &str) -> String {
+    let pattern = regex::escape(glob).replace(r"\*", ".*").replace(r"\?", ".");
+    format!("^{}$", pattern)
+}
+
+/// Returns the compiled regex of a regex or glob condition.
+fn condition_regex(operator: ConditionOperator, pattern: &str) -> Result<Regex> {
+    let pattern = match operator {
+        ConditionOperator::Glob => glob_pattern(pattern),
+        _ => pattern.to_string(),
+    };
+    CONDITION_REGEXES.get_or_compile(&pattern)
+}
+
+/// Checks that the field of a routing condition is one conditions can be
+/// evaluated on, and that its value is a valid operand of its operator.
+pub fn validate_routing_condition(condition: &RoutingCondition) -> Result<()> {
+    parse_condition_field(&condition.field)?;
+    match condition.operator {
+        ConditionOperator::Eq | ConditionOperator::Neq => {}
+        ConditionOperator::Gt |
+        ConditionOperator::Gte |
+        ConditionOperator::Lt |
+        ConditionOperator::Lte => {
+            if as_f64(&condition.value).is_none() {
+                return Err(anyhow!(
+                    "condition on {} compares with {}, which is not a number",
+                    condition.field,
+                    condition.value
+                ));
+            }
+        }
+        ConditionOperator::Regex | ConditionOperator::Glob => {
+            let pattern = condition.value.as_str().ok_or_else(|| {
+                anyhow!(
+                    "condition on {} matches {}, which is not a string",
+                    condition.field,
+                    condition.value
+                )
+            })?;
+            condition_regex(condition.operator, pattern).map_err(|e| {
+                anyhow!("invalid pattern of condition on {}: {}", condition.field, e)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Evaluates a routing condition on the value of its field, None when the
+/// content doesn't have the field. Only `neq` conditions match content
+/// without the field.
+pub fn matches_routing_condition(condition: &RoutingCondition, value: Option<&Value>) -> bool {
+    let Some(value) = value else {
+        return condition.operator == ConditionOperator::Neq;
+    };
+    match condition.operator {
+        ConditionOperator::Eq => values_equal(value, &condition.value),
+        ConditionOperator::Neq => !values_equal(value, &condition.value),
+        ConditionOperator::Gt |
+        ConditionOperator::Gte |
+        ConditionOperator::Lt |
+        ConditionOperator::Lte => match (as_f64(value), as_f64(&condition.value)) {
+            (Some(a), Some(b)) => match condition.operator {
+                ConditionOperator::Gt => a > b,
+                ConditionOperator::Gte => a >= b,
+                ConditionOperator::Lt => a < b,
+                _ => a <= b,
+            },
+            _ => false,
+        },
+        ConditionOperator::Regex | ConditionOperator::Glob => condition
+            .value
+            .as_str()
+            .and_then(|pattern| condition_regex(condition.operator, pattern).ok())
+            .map_or(false, |re| re.is_match(&as_string(value))),
+    }
+}
+
+/// Returns true if the content meets all the routing conditions of an
+/// extraction policy
+pub fn matches_routing_conditions(
+    conditions: &[RoutingCondition],
+    content: &ContentMetadata,
+) -> bool {
+    conditions.iter().all(|condition| {
+        let value = match parse_condition_field(&condition.field) {
+            Ok(ConditionField::MimeType) => Some(Value::String(content.content_type.clone())),
+            Ok(ConditionField::Label(name)) => content.labels.get(name).map(|v| label_value(v)),
+            Ok(ConditionField::Metadata(path)) => {
+                metadata_value(&content.extracted_metadata, path).cloned()
+            }
+            Err(_) => return false,
+        };
+        matches_routing_condition(condition, value.as_ref())
+    })
+}
+
+#[cfg(test)]
+mod test_routing_conditions {
+    use serde_json::json;
+
+    use super::*;
+
+    fn condition(field: &str, operator: ConditionOperator, value: Value) -> RoutingCondition {
+        RoutingCondition {
+            field: field.to_string(),
+            operator,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_validate_routing_condition() {
+        assert!(validate_routing_condition(&condition(
+            "labels.lang",
+            ConditionOperator::Eq,
+            json!("de")
+        ))
+        .is_ok());
+        assert!(validate_routing_condition(&condition(
+            "metadata.page.number",
+            ConditionOperator::Gte,
+            json!(2)
+        ))
+        .is_ok());
+        assert!(validate_routing_condition(&condition(
+            "mime_type",
+            ConditionOperator::Glob,
+            json!("image/*")
+        ))
+        .is_ok());
+
+        let err =
+            validate_routing_condition(&condition("lang", ConditionOperator::Eq, json!("de")))
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "condition field lang is not mime_type, labels.<name> or metadata.<path>"
+        );
+        assert!(validate_routing_condition(&condition(
+            "labels.size",
+            ConditionOperator::Gt,
+            json!("large")
+        ))
+        .is_err());
+        assert!(validate_routing_condition(&condition(
+            "labels.lang",
+            ConditionOperator::Regex,
+            json!("(de")
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_matches_routing_conditions() {
+        let content = ContentMetadata {
+            content_type: "image/png".to_string(),
+            labels: HashMap::from([
+                ("lang".to_string(), "de".to_string()),
+                ("pages".to_string(), "12".to_string()),
+            ]),
+            extracted_metadata: HashMap::from([
+                ("language".to_string(), json!("de")),
+                ("page".to_string(), json!({"number": 3, "words": [10, 20]})),
+            ]),
+            ..Default::default()
+        };
+        let matches = |field: &str, operator: ConditionOperator, value: Value| {
+            matches_routing_conditions(&[condition(field, operator, value)], &content)
+        };
+
+        assert!(matches("labels.lang", ConditionOperator::Eq, json!("de")));
+        assert!(!matches("labels.lang", ConditionOperator::Neq, json!("de")));
+        assert!(matches(
+            "labels.missing",
+            ConditionOperator::Neq,
+            json!("de")
+        ));
+        assert!(!matches(
+            "labels.missing",
+            ConditionOperator::Eq,
+            json!("de")
+        ));
+        assert!(matches("labels.pages", ConditionOperator::Eq, json!(12)));
+        assert!(matches("labels.pages", ConditionOperator::Gt, json!(10)));
+        assert!(!matches("labels.pages", ConditionOperator::Lte, json!(10)));
+        assert!(!matches("labels.lang", ConditionOperator::Gt, json!(10)));
+        assert!(matches(
+            "labels.lang",
+            ConditionOperator::Regex,
+            json!("^d")
+        ));
+        assert!(matches(
+            "mime_type",
+            ConditionOperator::Glob,
+            json!("image/*")
+        ));
+        assert!(!matches(
+            "mime_type",
+            ConditionOperator::Glob,
+            json!("text/*")
+        ));
+        assert!(matches(
+            "metadata.language",
+            ConditionOperator::Eq,
+            json!("de")
+        ));
+        assert!(matches(
+            "metadata.page.number",
+            ConditionOperator::Gte,
+            json!(3)
+        ));
+        assert!(matches(
+            "metadata.page.words.1",
+            ConditionOperator::Eq,
+            json!(20)
+        ));
+        assert!(!matches(
+            "metadata.page.lines",
+            ConditionOperator::Eq,
+            json!(1)
+        ));
+
+        let conditions = [
+            condition("metadata.language", ConditionOperator::Eq, json!("de")),
+            condition("labels.pages", ConditionOperator::Lt, json!(10)),
+        ];
+        assert!(!matches_routing_conditions(&conditions, &content));
+        assert!(matches_routing_conditions(&[], &content));
+    }
+
+    #[test]
+    fn test_regex_cache() {
+        let cache = RegexCache::new(2);
+        assert!(cache.get_or_compile("^a$").unwrap().is_match("a"));
+        cache.get_or_compile("^b$").unwrap();
+        cache.get_or_compile("^a$").unwrap();
+        assert!(cache.get_or_compile("(c").is_err());
+        // the least recently used pattern is evicted
+        cache.get_or_compile("^c$").unwrap();
+        let regexes = cache.regexes.read().unwrap();
+        assert_eq!(regexes.len(), 2);
+        assert!(regexes.contains_key("^a$"));
+        assert!(regexes.contains_key("^c$"));
+    }
+}
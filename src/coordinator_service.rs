@@ -7,6 +7,7 @@ use std::{
         Arc,
     },
     task::{Context, Poll},
+    time::Duration,
 };
 
 use anyhow::{anyhow, Result};
@@ -17,7 +18,11 @@ use indexify_internal_api as internal_api;
 use indexify_proto::indexify_coordinator::{
     self,
     coordinator_service_server::CoordinatorService,
+    CancelTasksRequest,
+    CancelTasksResponse,
     CoordinatorCommand,
+    CreateContentBatchRequest,
+    CreateContentBatchResponse,
     CreateContentRequest,
     CreateContentResponse,
     CreateExtractionGraphRequest,
@@ -31,6 +36,8 @@ use indexify_proto::indexify_coordinator::{
     GetAllTaskAssignmentRequest,
     GetContentMetadataRequest,
     GetContentTreeMetadataRequest,
+    GetContentTreeStatusRequest,
+    GetContentTreeStatusResponse,
     GetExtractionPolicyRequest,
     GetExtractionPolicyResponse,
     GetExtractorCoordinatesRequest,
@@ -51,12 +58,20 @@ use indexify_proto::indexify_coordinator::{
     ListExtractionPoliciesResponse,
     ListExtractorsRequest,
     ListExtractorsResponse,
+    ListIndexStoresRequest,
+    ListIndexStoresResponse,
     ListIndexesRequest,
     ListIndexesResponse,
     ListStateChangesRequest,
     ListTasksRequest,
     ListTasksResponse,
+    MergeExtractedMetadataRequest,
+    MergeExtractedMetadataResponse,
+    PlanExtractionGraphRequest,
+    PlanExtractionGraphResponse,
     RaftMetricsSnapshotResponse,
+    ReextractOutdatedContentRequest,
+    ReextractOutdatedContentResponse,
     RegisterExecutorRequest,
     RegisterExecutorResponse,
     RegisterIngestionServerRequest,
@@ -67,6 +82,10 @@ use indexify_proto::indexify_coordinator::{
     TombstoneContentRequest,
     TombstoneContentResponse,
     Uint64List,
+    UpdateContentStorageRequest,
+    UpdateContentStorageResponse,
+    UpdateIndexStoreRequest,
+    UpdateIndexStoreResponse,
     UpdateIndexesStateRequest,
     UpdateIndexesStateResponse,
     UpdateTaskRequest,
@@ -97,7 +116,7 @@ use tracing::{error, info, Instrument};
 
 use crate::{
     api::IndexifyAPIError,
-    coordinator::Coordinator,
+    coordinator::{Coordinator, TaskFilter, TaskSelector},
     coordinator_client::CoordinatorClient,
     garbage_collector::GarbageCollector,
     server_config::ServerConfig,
@@ -136,18 +155,18 @@ impl CoordinatorServiceServer {
         &self,
         extraction_graph: &CreateExtractionGraphRequest,
     ) -> Result<ExtractionPolicyCreationResult> {
-        let mut name_to_policy_mapping = HashMap::new();
-        for extraction_policy in &extraction_graph.policies {
-            name_to_policy_mapping
-                .insert(extraction_policy.name.clone(), extraction_policy.clone());
-        }
-
         let mut extraction_policies = Vec::new();
         let mut extractors = Vec::new();
 
-        for (_, policy_request) in name_to_policy_mapping.iter() {
+        for policy_request in &extraction_graph.policies {
             let input_params = serde_json::from_str(&policy_request.input_params)
                 .map_err(|e| anyhow!(format!("unable to parse input_params: {}", e)))?;
+            let conditions = if policy_request.conditions.is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&policy_request.conditions)
+                    .map_err(|e| anyhow!(format!("unable to parse conditions: {}", e)))?
+            };
             let extractor = self.coordinator.get_extractor(&policy_request.extractor)?;
             let content_source = if policy_request.content_source.eq("") {
                 internal_api::ExtractionPolicyContentSource::Ingestion
@@ -161,6 +180,21 @@ impl CoordinatorServiceServer {
                 .name(policy_request.name.clone())
                 .extractor(policy_request.extractor.clone())
                 .filters(policy_request.filters.clone())
+                .conditions(conditions)
+                .limits(
+                    policy_request
+                        .limits
+                        .clone()
+                        .map(|l| l.into())
+                        .unwrap_or_default(),
+                )
+                .affinity(
+                    policy_request
+                        .affinity
+                        .clone()
+                        .map(|a| a.into())
+                        .unwrap_or_default(),
+                )
                 .input_params(input_params)
                 .content_source(content_source)
                 .build(&extraction_graph.name, extractor.clone())
@@ -199,6 +233,25 @@ impl CoordinatorService for CoordinatorServiceServer {
         Ok(tonic::Response::new(CreateContentResponse { id: id.id }))
     }
 
+    async fn create_content_batch(
+        &self,
+        request: tonic::Request<CreateContentBatchRequest>,
+    ) -> Result<tonic::Response<CreateContentBatchResponse>, tonic::Status> {
+        let request = request.into_inner();
+        let content_list: Vec<indexify_internal_api::ContentMetadata> =
+            request.content_list.into_iter().map(Into::into).collect();
+        let ids = content_list.iter().map(|c| c.id.id.clone()).collect();
+        let res = if request.create_only {
+            self.coordinator
+                .create_new_content_metadata(content_list)
+                .await
+        } else {
+            self.coordinator.create_content_metadata(content_list).await
+        };
+        res.map_err(|e| tonic::Status::aborted(e.to_string()))?;
+        Ok(tonic::Response::new(CreateContentBatchResponse { ids }))
+    }
+
     async fn tombstone_content(
         &self,
         request: tonic::Request<TombstoneContentRequest>,
@@ -384,9 +437,16 @@ impl CoordinatorService for CoordinatorServiceServer {
             .map(|e| e.into())
             .collect::<Vec<internal_api::ExtractorDescription>>();
 
+        let resources = request.resources.map(|r| r.into()).unwrap_or_default();
         let _resp = self
             .coordinator
-            .register_executor(&request.addr, &request.executor_id, extractors)
+            .register_executor_with_capabilities(
+                &request.addr,
+                &request.executor_id,
+                extractors,
+                resources,
+                request.labels,
+            )
             .await
             .map_err(|e| tonic::Status::aborted(e.to_string()))?;
 
@@ -550,6 +610,9 @@ impl CoordinatorService for CoordinatorServiceServer {
         let mut shutdown_rx = self.shutdown_rx.clone();
         tokio::spawn(async move {
             let mut executor_id: Option<String> = None;
+            // Cancelled tasks sent with the last response, released once the executor
+            // heartbeats again
+            let mut sent_cancelled_task_ids = Vec::new();
             loop {
                 select! {
                     _ = shutdown_rx.changed() => {
@@ -573,6 +636,10 @@ impl CoordinatorService for CoordinatorServiceServer {
                             }
                         }
                         if let Some(executor_id) = executor_id.clone() {
+                            let released_task_ids = std::mem::take(&mut sent_cancelled_task_ids);
+                            if let Err(err) = coordinator.release_cancelled_tasks(&executor_id, released_task_ids).await {
+                                error!("error releasing cancelled tasks: {}", err);
+                            }
                             let tasks = coordinator.heartbeat(&executor_id).await;
                             match tasks {
                                 Err(err) => {
@@ -581,16 +648,18 @@ impl CoordinatorService for CoordinatorServiceServer {
                                         break;
                                     }
                                 }
-                                Ok(tasks) => {
+                                Ok((tasks, cancelled_task_ids)) => {
                                     // let tasks = tasks.into_iter().map(|t| t.into()).collect::<Vec<indexify_coordinator::Task>>();
                                     let resp = HeartbeatResponse {
                                         executor_id: executor_id.clone(),
                                         tasks,
+                                        cancelled_task_ids: cancelled_task_ids.clone(),
                                     };
                                     if let Err(err) = tx.send(Ok(resp)).await {
                                         error!("error sending heartbeat response: {:?}", err);
                                         break;
                                     }
+                                    sent_cancelled_task_ids = cancelled_task_ids;
                                 }
                             }
                         }
@@ -621,6 +690,8 @@ impl CoordinatorService for CoordinatorServiceServer {
                 &request.executor_id,
                 outcome,
                 request.content_list,
+                Some(request.started_at).filter(|started_at| *started_at > 0),
+                Some(request.failure_reason).filter(|reason| !reason.is_empty()),
             )
             .await
             .map_err(|e| tonic::Status::aborted(e.to_string()))?;
@@ -685,14 +756,15 @@ impl CoordinatorService for CoordinatorServiceServer {
         req: Request<GetExtractorCoordinatesRequest>,
     ) -> Result<Response<indexify_coordinator::GetExtractorCoordinatesResponse>, Status> {
         let req = req.into_inner();
-        let extractor_coordinates = self
+        let (addrs, extractor_fingerprint) = self
             .coordinator
             .get_extractor_coordinates(&req.extractor)
             .await
             .map_err(|e| tonic::Status::aborted(e.to_string()))?;
         Ok(Response::new(
             indexify_coordinator::GetExtractorCoordinatesResponse {
-                addrs: extractor_coordinates,
+                addrs,
+                extractor_fingerprint,
             },
         ))
     }
@@ -737,6 +809,10 @@ impl CoordinatorService for CoordinatorServiceServer {
             .get_task_and_root_content(&req.task_id)
             .await
             .map_err(|e| tonic::Status::aborted(e.to_string()))?;
+        self.coordinator
+            .check_extractor_version(&task)
+            .await
+            .map_err(|e| tonic::Status::aborted(e.to_string()))?;
 
         let root_content: Option<indexify_coordinator::ContentMetadata> =
             root_content.map(|c| c.into());
@@ -747,6 +823,77 @@ impl CoordinatorService for CoordinatorServiceServer {
         }))
     }
 
+    async fn plan_extraction_graph(
+        &self,
+        req: Request<PlanExtractionGraphRequest>,
+    ) -> Result<Response<PlanExtractionGraphResponse>, Status> {
+        let req = req.into_inner();
+        let extractions = self
+            .coordinator
+            .plan_extraction_graph(
+                &req.namespace,
+                &req.extraction_graph,
+                &req.content_type,
+                &req.labels,
+            )
+            .await
+            .map_err(|e| tonic::Status::aborted(e.to_string()))?;
+        Ok(Response::new(PlanExtractionGraphResponse {
+            extractions: extractions.into_iter().map(|e| e.into()).collect(),
+        }))
+    }
+
+    async fn reextract_outdated_content(
+        &self,
+        req: Request<ReextractOutdatedContentRequest>,
+    ) -> Result<Response<ReextractOutdatedContentResponse>, Status> {
+        let req = req.into_inner();
+        self.coordinator
+            .reextract_outdated_content(
+                &req.namespace,
+                &req.extraction_graph,
+                &req.extraction_policy,
+            )
+            .await
+            .map_err(|e| tonic::Status::aborted(e.to_string()))?;
+        Ok(Response::new(ReextractOutdatedContentResponse {}))
+    }
+
+    async fn cancel_tasks(
+        &self,
+        req: Request<CancelTasksRequest>,
+    ) -> Result<Response<CancelTasksResponse>, Status> {
+        let req = req.into_inner();
+        let selectors = [
+            !req.task_ids.is_empty(),
+            !req.content_id.is_empty(),
+            !req.extraction_policy.is_empty(),
+        ];
+        if selectors.into_iter().filter(|selected| *selected).count() > 1 {
+            return Err(tonic::Status::invalid_argument(
+                "only one of task_ids, content_id and extraction_policy can be set",
+            ));
+        }
+        let selector = if !req.task_ids.is_empty() {
+            TaskSelector::Tasks(req.task_ids)
+        } else if !req.content_id.is_empty() {
+            TaskSelector::ContentTree(req.content_id)
+        } else if !req.extraction_policy.is_empty() {
+            TaskSelector::ExtractionPolicy {
+                graph: req.extraction_graph,
+                policy: req.extraction_policy,
+            }
+        } else {
+            TaskSelector::Namespace
+        };
+        let cancelled_task_ids = self
+            .coordinator
+            .cancel_tasks(&req.namespace, selector)
+            .await
+            .map_err(|e| tonic::Status::aborted(e.to_string()))?;
+        Ok(Response::new(CancelTasksResponse { cancelled_task_ids }))
+    }
+
     async fn get_content_tree_metadata(
         &self,
         req: Request<GetContentTreeMetadataRequest>,
@@ -764,6 +911,76 @@ impl CoordinatorService for CoordinatorServiceServer {
         ))
     }
 
+    async fn update_content_storage(
+        &self,
+        req: Request<UpdateContentStorageRequest>,
+    ) -> Result<Response<UpdateContentStorageResponse>, Status> {
+        let req = req.into_inner();
+        let content_list = self
+            .coordinator
+            .update_content_storage(req.updates)
+            .await
+            .map_err(|e| tonic::Status::aborted(e.to_string()))?;
+        Ok(Response::new(UpdateContentStorageResponse { content_list }))
+    }
+
+    async fn merge_extracted_metadata(
+        &self,
+        req: Request<MergeExtractedMetadataRequest>,
+    ) -> Result<Response<MergeExtractedMetadataResponse>, Status> {
+        let req = req.into_inner();
+        let metadata = serde_json::from_str(&req.metadata)
+            .map_err(|e| tonic::Status::invalid_argument(format!("invalid metadata: {}", e)))?;
+        self.coordinator
+            .merge_extracted_metadata(&req.content_id, metadata)
+            .await
+            .map_err(|e| tonic::Status::aborted(e.to_string()))?;
+        Ok(Response::new(MergeExtractedMetadataResponse {}))
+    }
+
+    async fn get_content_tree_status(
+        &self,
+        req: Request<GetContentTreeStatusRequest>,
+    ) -> Result<Response<GetContentTreeStatusResponse>, Status> {
+        let req = req.into_inner();
+        let indexed = self
+            .coordinator
+            .is_content_tree_indexed(&req.content_id)
+            .await
+            .map_err(|e| tonic::Status::aborted(e.to_string()))?;
+        Ok(Response::new(GetContentTreeStatusResponse { indexed }))
+    }
+
+    async fn update_index_store(
+        &self,
+        req: Request<UpdateIndexStoreRequest>,
+    ) -> Result<Response<UpdateIndexStoreResponse>, Status> {
+        let req = req.into_inner();
+        let index = self
+            .coordinator
+            .update_index_store(req)
+            .await
+            .map_err(|e| tonic::Status::aborted(e.to_string()))?;
+        Ok(Response::new(UpdateIndexStoreResponse {
+            index: Some(index.into()),
+        }))
+    }
+
+    async fn list_index_stores(
+        &self,
+        _req: Request<ListIndexStoresRequest>,
+    ) -> Result<Response<ListIndexStoresResponse>, Status> {
+        let indexes = self
+            .coordinator
+            .list_index_stores()
+            .await
+            .map_err(|e| tonic::Status::aborted(e.to_string()))?
+            .into_iter()
+            .map(|i| i.into())
+            .collect();
+        Ok(Response::new(ListIndexStoresResponse { indexes }))
+    }
+
     async fn list_state_changes(
         &self,
         _req: Request<ListStateChangesRequest>,
@@ -791,11 +1008,18 @@ impl CoordinatorService for CoordinatorServiceServer {
         let extraction_policy = if req.extraction_policy.is_empty() {
             None
         } else {
-            Some(req.extraction_policy)
+            Some(req.extraction_policy.clone())
+        };
+        let filter = TaskFilter {
+            outcome: req.outcome.map(|_| req.outcome().into()),
+            executor_id: Some(req.executor_id).filter(|id| !id.is_empty()),
+            content_id: Some(req.content_id).filter(|id| !id.is_empty()),
+            created_after: Some(req.created_after).filter(|after| *after > 0),
+            created_before: Some(req.created_before).filter(|before| *before > 0),
         };
         let tasks = self
             .coordinator
-            .list_tasks(&req.namespace, extraction_policy)
+            .list_tasks(&req.namespace, extraction_policy, &filter)
             .await
             .map_err(|e| tonic::Status::aborted(e.to_string()))?;
         Ok(Response::new(indexify_coordinator::ListTasksResponse {
@@ -1223,6 +1447,9 @@ impl CoordinatorServer {
     }
 }
 
+// How often tasks are checked against the timeouts of their policies
+const TASK_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
 async fn run_scheduler(
     mut shutdown_rx: Receiver<()>,
     mut leader_changed: Receiver<bool>,
@@ -1230,9 +1457,17 @@ async fn run_scheduler(
     coordinator: Arc<Coordinator>,
 ) -> Result<()> {
     let is_leader = AtomicBool::new(false);
+    let mut task_timeout_interval = tokio::time::interval(TASK_TIMEOUT_CHECK_INTERVAL);
 
     loop {
         tokio::select! {
+            _ = task_timeout_interval.tick() => {
+                if is_leader.load(Ordering::Relaxed) {
+                    if let Err(err) = coordinator.fail_timed_out_tasks().await {
+                        error!("error failing timed out tasks: {:?}", err);
+                    }
+                }
+            },
             _ = state_watcher_rx.changed() => {
                 if is_leader.load(Ordering::Relaxed) {
                    let _state_change = state_watcher_rx.borrow_and_update().clone();
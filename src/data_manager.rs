@@ -1,26 +1,33 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt,
     hash::{DefaultHasher, Hash, Hasher},
     str::FromStr,
     sync::Arc,
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 
 use anyhow::{anyhow, Result};
 use bytes::Bytes;
-use futures::{Stream, StreamExt};
+use futures::{Future, Stream, StreamExt};
 use indexify_internal_api as internal_api;
 use indexify_proto::indexify_coordinator::{self};
 use itertools::Itertools;
 use mime::Mime;
 use nanoid::nanoid;
 use sha2::{Digest, Sha256};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::{
     api::{self, BeginExtractedContentIngest, ExtractionGraphRequest},
-    blob_storage::{BlobStorage, BlobStorageWriter, PutResult, StoragePartWriter},
+    blob_storage::{
+        presign::{self, PresignedUpload},
+        BlobStorage,
+        BlobStorageWriter,
+        ContentReader,
+        PutResult,
+        StoragePartWriter,
+    },
     coordinator_client::CoordinatorClient,
     grpc_helper::GrpcHelper,
     metadata_storage::{
@@ -30,8 +37,65 @@ use crate::{
         MetadataStorageTS,
     },
     vector_index::{ScoredText, VectorIndexManager},
+    vectordbs::IndexOptions,
 };
 
+/// Attempts made at reading a remote file before ingestion fails.
+const REMOTE_FILE_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry of a remote file read, doubled on every
+/// subsequent attempt.
+const REMOTE_FILE_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Metadata key of vector chunks holding the version of the extractor their
+/// embedding was extracted with.
+pub const EXTRACTOR_VERSION_METADATA_KEY: &str = "extractor_version";
+
+/// Whether a failed read of a remote file is worth retrying. Missing objects
+/// and client errors are not, while timeouts, connection failures and server
+/// errors are. Errors of unknown origin are not retried.
+fn is_transient(err: &anyhow::Error) -> bool {
+    if let Some(e) = err.downcast_ref::<reqwest::Error>() {
+        return match e.status() {
+            Some(status) => {
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            None => true,
+        };
+    }
+    if let Some(e) = err.downcast_ref::<std::io::Error>() {
+        return e.kind() != std::io::ErrorKind::NotFound;
+    }
+    if let Some(e) = err.downcast_ref::<object_store::Error>() {
+        // Generic errors wrap failed requests to the store, everything else
+        // is a missing object or a misconfiguration
+        return matches!(
+            e,
+            object_store::Error::Generic { .. } | object_store::Error::JoinError { .. }
+        );
+    }
+    false
+}
+
+async fn with_retries<T, F, Fut>(url: &str, mut f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Ok(res) => return Ok(res),
+            Err(e) if attempt < REMOTE_FILE_ATTEMPTS && is_transient(&e) => {
+                warn!("attempt {} at reading {} failed: {}", attempt, url, e);
+                tokio::time::sleep(REMOTE_FILE_RETRY_DELAY * 2u32.pow(attempt - 1)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 fn index_in_features(
     output_index_map: &HashMap<String, String>,
     features: &[api::Feature],
@@ -145,9 +209,28 @@ impl DataManager {
         req: ExtractionGraphRequest,
     ) -> Result<Vec<internal_api::IndexName>> {
         let mut extraction_policies = Vec::new();
+        let mut index_options = HashMap::new();
         for ep in req.extraction_policies {
+            for (output, options) in &ep.index_options {
+                self.vector_index_manager
+                    .validate_index_options(options)
+                    .await
+                    .map_err(|e| {
+                        anyhow!(
+                            "invalid index options of output {} of extraction policy {}: {}",
+                            output,
+                            ep.name,
+                            e
+                        )
+                    })?;
+            }
+            if !ep.index_options.is_empty() {
+                index_options.insert(ep.name.clone(), ep.index_options.clone());
+            }
             let input_params_serialized = serde_json::to_string(&ep.input_params)
                 .map_err(|e| anyhow!("unable to serialize input params to str {}", e))?;
+            let conditions_serialized = serde_json::to_string(&ep.conditions)
+                .map_err(|e| anyhow!("unable to serialize conditions to str {}", e))?;
             let req = indexify_coordinator::ExtractionPolicyRequest {
                 namespace: namespace.to_string(),
                 extractor: ep.extractor.clone(),
@@ -158,9 +241,14 @@ impl DataManager {
                 created_at: SystemTime::now()
                     .duration_since(SystemTime::UNIX_EPOCH)?
                     .as_secs() as i64,
+                conditions: conditions_serialized,
+                limits: Some(ep.limits.clone().into()),
+                affinity: Some(ep.affinity.clone().into()),
             };
             extraction_policies.push(req);
         }
+        self.validate_index_outputs(&extraction_policies, &index_options)
+            .await?;
         let req = indexify_coordinator::CreateExtractionGraphRequest {
             namespace: namespace.to_string(),
             name: req.name,
@@ -173,6 +261,7 @@ impl DataManager {
             .create_extraction_graph(req)
             .await?
             .into_inner();
+        let mut vector_stores = HashMap::new();
         for (_, policy) in response.policies {
             let extractor = response
                 .extractors
@@ -181,14 +270,18 @@ impl DataManager {
                     "extractor {} not found in response",
                     policy.extractor
                 ),))?;
+            let mut policy_index_options = index_options.remove(&policy.name).unwrap_or_default();
             for (name, output_schema) in &extractor.embedding_schemas {
                 let embedding_schema: internal_api::EmbeddingSchema =
                     serde_json::from_str(output_schema)?;
                 let table_name = policy.output_table_mapping.get(name).unwrap();
-                let _ = self
+                let options = policy_index_options.remove(name).unwrap_or_default();
+                let options_json = serde_json::to_string(&options)?;
+                let vector_store = self
                     .vector_index_manager
-                    .create_index(table_name, embedding_schema.clone())
+                    .create_index(table_name, embedding_schema.clone(), options)
                     .await?;
+                vector_stores.insert(table_name.clone(), (vector_store, options_json));
             }
 
             // Create metadata table for the namespace if it doesn't exist
@@ -197,9 +290,16 @@ impl DataManager {
                 .create_metadata_table(&namespace)
                 .await?;
         }
-        let req = indexify_coordinator::UpdateIndexesStateRequest {
-            indexes: response.indexes.clone(),
-        };
+        //  Indexes name the vector store they were created in, and the options
+        // they were created with
+        let mut indexes = response.indexes.clone();
+        for index in &mut indexes {
+            if let Some((vector_store, options)) = vector_stores.remove(&index.table_name) {
+                index.vector_store = vector_store;
+                index.index_options = options;
+            }
+        }
+        let req = indexify_coordinator::UpdateIndexesStateRequest { indexes };
         self.coordinator_client
             .get()
             .await?
@@ -214,6 +314,49 @@ impl DataManager {
         Ok(index_names)
     }
 
+    // Index options can only be set for the embedding outputs of extractors
+    async fn validate_index_outputs(
+        &self,
+        extraction_policies: &[indexify_coordinator::ExtractionPolicyRequest],
+        index_options: &HashMap<String, HashMap<String, IndexOptions>>,
+    ) -> Result<()> {
+        if index_options.is_empty() {
+            return Ok(());
+        }
+        let extractors: HashMap<String, indexify_coordinator::Extractor> = self
+            .coordinator_client
+            .get()
+            .await?
+            .list_extractors(indexify_coordinator::ListExtractorsRequest {})
+            .await?
+            .into_inner()
+            .extractors
+            .into_iter()
+            .map(|e| (e.name.clone(), e))
+            .collect();
+        for policy in extraction_policies {
+            let Some(options) = index_options.get(&policy.name) else {
+                continue;
+            };
+            let extractor = extractors
+                .get(&policy.extractor)
+                .ok_or_else(|| anyhow!("extractor {} not found", policy.extractor))?;
+            if let Some(output) = options
+                .keys()
+                .find(|output| !extractor.embedding_schemas.contains_key(*output))
+            {
+                return Err(anyhow!(
+                    "index options of extraction policy {} are set for {}, which is not an \
+                     embedding output of extractor {}",
+                    policy.name,
+                    output,
+                    policy.extractor
+                ));
+            }
+        }
+        Ok(())
+    }
+
     pub async fn list_content(
         &self,
         namespace: &str,
@@ -250,19 +393,8 @@ impl DataManager {
         extraction_graph_names: Vec<internal_api::ExtractionGraphName>,
     ) -> Result<()> {
         for content_with_id in content_list {
-            let text = content_with_id.content;
-            let stream = futures::stream::once(async { Ok(Bytes::from(text.bytes)) });
             let content_metadata = self
-                .write_content_bytes(
-                    namespace,
-                    Box::pin(stream),
-                    text.labels,
-                    text.content_type,
-                    None,
-                    "",
-                    Some(&content_with_id.id),
-                    &extraction_graph_names,
-                )
+                .write_text(namespace, content_with_id, &extraction_graph_names)
                 .await?;
 
             let req = indexify_coordinator::CreateContentRequest {
@@ -283,10 +415,36 @@ impl DataManager {
         Ok(())
     }
 
+    /// Writes a text document to the blob store and returns the metadata
+    /// of its content, which is yet to be created.
+    pub async fn write_text(
+        &self,
+        namespace: &str,
+        content_with_id: api::ContentWithId,
+        extraction_graph_names: &Vec<internal_api::ExtractionGraphName>,
+    ) -> Result<indexify_coordinator::ContentMetadata> {
+        let text = content_with_id.content;
+        let stream = futures::stream::once(async { Ok(Bytes::from(text.bytes)) });
+        self.write_content_bytes(
+            namespace,
+            Box::pin(stream),
+            text.labels,
+            text.content_type,
+            None,
+            "",
+            Some(&content_with_id.id),
+            extraction_graph_names,
+        )
+        .await
+    }
+
     #[tracing::instrument]
     pub async fn delete_content(&self, gc_task: &indexify_coordinator::GcTask) -> Result<()> {
-        //  Remove content from blob storage
-        self.blob_storage.delete(&gc_task.blob_store_path).await?;
+        //  Remove content from blob storage, unless a lifecycle policy already
+        // deleted its blob
+        if !gc_task.blob_store_path.is_empty() {
+            self.blob_storage.delete(&gc_task.blob_store_path).await?;
+        }
 
         //  Remove features and embeddings from vector stores
         for table in &gc_task.output_tables {
@@ -308,6 +466,11 @@ impl DataManager {
         self.blob_storage.delete(path).await
     }
 
+    /// Creates content for a file which lives outside of the blob store. In
+    /// `Probe` mode the file is referenced in place and only its size is
+    /// looked up, while in `Pin` mode it is copied into the blob store and
+    /// hashed, and checked against `expected_hash` when one is given.
+    #[allow(clippy::too_many_arguments)]
     pub async fn ingest_remote_file(
         &self,
         namespace: &str,
@@ -316,50 +479,126 @@ impl DataManager {
         mime: &str,
         labels: HashMap<String, String>,
         extraction_graph_names: &Vec<internal_api::ExtractionGraphName>,
+        mode: api::RemoteFileIngestMode,
+        expected_hash: Option<String>,
     ) -> Result<String> {
-        if !(["https://", "http://", "s3://", "file://"]
+        let id = id.unwrap_or(nanoid!(16));
+        let content_metadata = self
+            .read_remote_file(
+                namespace,
+                &id,
+                file,
+                mime,
+                labels,
+                extraction_graph_names,
+                mode,
+                expected_hash,
+            )
+            .await?;
+        self.create_content_metadata(content_metadata).await?;
+        Ok(id)
+    }
+
+    /// Returns the metadata of the content for a remote file, as ingested by
+    /// `ingest_remote_file`, without creating the content.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn read_remote_file(
+        &self,
+        namespace: &str,
+        id: &str,
+        file: &str,
+        mime: &str,
+        labels: HashMap<String, String>,
+        extraction_graph_names: &Vec<internal_api::ExtractionGraphName>,
+        mode: api::RemoteFileIngestMode,
+        expected_hash: Option<String>,
+    ) -> Result<indexify_coordinator::ContentMetadata> {
+        if !(["https://", "http://", "s3://", "gs://", "az://", "file://"]
             .iter()
             .any(|s| file.starts_with(*s)))
         {
-            return Err(anyhow!("invalid file path, must be a url, s3 or file path"));
+            return Err(anyhow!(
+                "invalid file path, must be a url, s3, gcs, azure or file path"
+            ));
         }
         let _ = mime::Mime::from_str(mime).map_err(|e| anyhow!("invalid mime type {}", e))?;
-        let current_ts_secs = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)?
-            .as_secs();
-        let id = id.unwrap_or(nanoid!(16));
-        let content_metadata = indexify_coordinator::ContentMetadata {
-            id: id.clone(),
-            file_name: file.to_string(),
-            storage_url: file.to_string(),
-            parent_id: "".to_string(),
-            created_at: current_ts_secs as i64,
-            mime: mime.to_string(),
-            namespace: namespace.to_string(),
-            labels,
-            source: "".to_string(),
-            size_bytes: 0,
-            hash: "".to_string(),
-            extraction_policy_ids: HashMap::new(),
-            root_content_id: "".to_string(),
-            extraction_graph_names: extraction_graph_names.clone(),
+        if expected_hash.is_some() && mode != api::RemoteFileIngestMode::Pin {
+            return Err(anyhow!(
+                "verifying the hash of a remote file requires the pin mode"
+            ));
+        }
+        // Remote files are read as they are
+        let reader = self.blob_storage.content_reader().get(file, false);
+        let content_metadata = match mode {
+            api::RemoteFileIngestMode::Probe => {
+                let size_bytes = with_retries(file, || reader.size(file)).await?;
+                let current_ts_secs = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)?
+                    .as_secs();
+                indexify_coordinator::ContentMetadata {
+                    id: id.to_string(),
+                    file_name: file.to_string(),
+                    storage_url: file.to_string(),
+                    parent_id: "".to_string(),
+                    created_at: current_ts_secs as i64,
+                    mime: mime.to_string(),
+                    namespace: namespace.to_string(),
+                    labels,
+                    source: "".to_string(),
+                    size_bytes,
+                    hash: "".to_string(),
+                    extraction_policy_ids: HashMap::new(),
+                    root_content_id: "".to_string(),
+                    extraction_graph_names: extraction_graph_names.clone(),
+                    extracted_metadata: String::new(),
+                    encoded: false,
+                }
+            }
+            api::RemoteFileIngestMode::Pin => {
+                // A missing local file would otherwise fail in the middle of
+                // writing the copy
+                if file.starts_with("file://") {
+                    reader
+                        .size(file)
+                        .await
+                        .map_err(|e| anyhow!("unable to read {}: {}", file, e))?;
+                }
+                // Every attempt writes to the same blob, so a partially copied
+                // file is overwritten by the retry
+                let file_name = DataManager::make_file_name(None);
+                let content_metadata = with_retries(file, || {
+                    self.write_content_bytes(
+                        namespace,
+                        reader.get(file),
+                        labels.clone(),
+                        mime.to_string(),
+                        Some(&file_name),
+                        "",
+                        Some(id),
+                        extraction_graph_names,
+                    )
+                })
+                .await?;
+                if let Some(expected_hash) = expected_hash {
+                    if !expected_hash.eq_ignore_ascii_case(&content_metadata.hash) {
+                        if let Err(e) = self.delete_file(&content_metadata.storage_url).await {
+                            error!(
+                                "unable to delete blob {}: {}",
+                                content_metadata.storage_url, e
+                            );
+                        }
+                        return Err(anyhow!(
+                            "hash mismatch for {}, expected {} but got {}",
+                            file,
+                            expected_hash,
+                            content_metadata.hash
+                        ));
+                    }
+                }
+                content_metadata
+            }
         };
-        let req: indexify_coordinator::CreateContentRequest =
-            indexify_coordinator::CreateContentRequest {
-                content: Some(content_metadata),
-            };
-        self.coordinator_client
-            .get()
-            .await?
-            .create_content(GrpcHelper::into_req(req))
-            .await
-            .map_err(|e| {
-                anyhow!(
-                    "unable to write content metadata to coordinator {}",
-                    e.to_string()
-                )
-            })?;
-        Ok(id)
+        Ok(content_metadata)
     }
 
     pub async fn get_content_metadata(
@@ -428,6 +667,156 @@ impl DataManager {
         Ok(content_metadata)
     }
 
+    /// Records a presigned upload issued for `key`, which is required to
+    /// write and finalize it.
+    pub async fn register_presigned_upload(
+        &self,
+        key: &str,
+        upload: &PresignedUpload,
+    ) -> Result<()> {
+        self.blob_storage
+            .object_store()?
+            .put(
+                &presign::upload_record_path(key)?,
+                Bytes::from(serde_json::to_vec(upload)?),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn presigned_upload(&self, key: &str) -> Result<PresignedUpload> {
+        let store = self.blob_storage.object_store()?;
+        let bytes = match store.get(&presign::upload_record_path(key)?).await {
+            Ok(result) => result.bytes().await?,
+            Err(object_store::Error::NotFound { .. }) => {
+                return Err(anyhow!(
+                    "upload {} was not issued or was already finalized",
+                    key
+                ))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Writes a blob under `key` in the blob store, as done for uploads
+    /// through presigned urls of disk storage. The bytes are hashed on the
+    /// way so that finalizing the upload doesn't have to read them back.
+    #[tracing::instrument(skip(self, data))]
+    pub async fn write_presigned_blob(
+        &self,
+        key: &str,
+        data: impl Stream<Item = Result<Bytes>> + Send + Unpin,
+    ) -> Result<PutResult> {
+        let mut upload = self.presigned_upload(key).await?;
+        let mut hasher = Sha256::new();
+        let data = data.inspect(|chunk| {
+            if let Ok(chunk) = chunk {
+                hasher.update(chunk);
+            }
+        });
+        let result = self.blob_storage.put(key, data).await?;
+        upload.sha256 = Some(format!("{:x}", hasher.finalize()));
+        self.register_presigned_upload(key, &upload).await?;
+        Ok(result)
+    }
+
+    /// Registers a blob which a client uploaded under `key` through a
+    /// presigned url as the content the upload was issued for. The size is
+    /// taken from the blob store and the hash is the one computed while
+    /// writing to disk storage, or the one declared by the client for S3.
+    #[tracing::instrument(skip(self))]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn finalize_upload(
+        &self,
+        namespace: &str,
+        key: &str,
+        token: &str,
+        sha256: Option<&str>,
+        mime_type: &str,
+        labels: HashMap<String, String>,
+        extraction_graph_names: Vec<internal_api::ExtractionGraphName>,
+    ) -> Result<indexify_coordinator::ContentMetadata> {
+        let upload = self.presigned_upload(key).await?;
+        if upload.namespace != namespace || upload.token != token {
+            return Err(anyhow!(
+                "upload {} was not issued for namespace {}",
+                key,
+                namespace
+            ));
+        }
+        let hash = match (upload.sha256.as_deref(), sha256) {
+            (Some(computed), Some(declared)) if !computed.eq_ignore_ascii_case(declared) => {
+                return Err(anyhow!(
+                    "sha256 of upload {} does not match the uploaded bytes",
+                    key
+                ))
+            }
+            (Some(computed), _) => computed.to_string(),
+            (None, Some(declared)) if presign::is_valid_sha256(declared) => {
+                declared.to_ascii_lowercase()
+            }
+            (None, Some(_)) => return Err(anyhow!("sha256 must be a hex encoded digest")),
+            (None, None) => {
+                return Err(anyhow!(
+                    "sha256 of the uploaded file is required to finalize upload {}",
+                    key
+                ))
+            }
+        };
+        let storage_url = self.blob_storage.storage_url(key);
+        // Uploads through signed urls are stored as they are sent
+        let size_bytes = self
+            .blob_storage
+            .content_reader()
+            .get(&storage_url, false)
+            .size(&storage_url)
+            .await
+            .map_err(|e| anyhow!("nothing was uploaded under {}: {}", key, e))?;
+        let existing = self
+            .get_content_metadata(namespace, vec![upload.content_id.clone()])
+            .await?;
+        if !existing.is_empty() {
+            return Err(anyhow!("content {} already exists", upload.content_id));
+        }
+
+        // The record is consumed before the content is created so that a
+        // second finalize of the same upload fails
+        let record_path = presign::upload_record_path(key)?;
+        let store = self.blob_storage.object_store()?;
+        store.delete(&record_path).await?;
+
+        let current_ts_secs = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        let content_metadata = indexify_coordinator::ContentMetadata {
+            id: upload.content_id.clone(),
+            file_name: key.to_string(),
+            storage_url,
+            parent_id: "".to_string(),
+            root_content_id: "".to_string(),
+            created_at: current_ts_secs as i64,
+            mime: mime_type.to_string(),
+            namespace: namespace.to_string(),
+            labels,
+            source: "".to_string(),
+            size_bytes,
+            hash,
+            extraction_policy_ids: HashMap::new(),
+            extraction_graph_names,
+            extracted_metadata: String::new(),
+            encoded: false,
+        };
+        if let Err(e) = self.create_content_metadata(content_metadata.clone()).await {
+            // Let the client retry
+            if let Err(e) = self.register_presigned_upload(key, &upload).await {
+                error!("unable to restore record of upload {}: {}", key, e);
+            }
+            return Err(e);
+        }
+        Ok(content_metadata)
+    }
+
     pub async fn create_content_metadata(
         &self,
         content_metadata: indexify_coordinator::ContentMetadata,
@@ -449,6 +838,29 @@ impl DataManager {
         Ok(())
     }
 
+    /// Creates the content of a batch in a single proposal to the
+    /// coordinator. It fails without creating any content when the id of any
+    /// of it exists already.
+    pub async fn create_content_batch(
+        &self,
+        content_list: Vec<indexify_coordinator::ContentMetadata>,
+    ) -> Result<()> {
+        if content_list.is_empty() {
+            return Ok(());
+        }
+        let req = indexify_coordinator::CreateContentBatchRequest {
+            content_list,
+            create_only: true,
+        };
+        self.coordinator_client
+            .get()
+            .await?
+            .create_content_batch(GrpcHelper::into_req(req))
+            .await
+            .map_err(|e| anyhow!("unable to write content batch to coordinator {}", e))?;
+        Ok(())
+    }
+
     pub fn make_file_name(file_name: Option<&str>) -> String {
         file_name.map(|f| f.to_string()).unwrap_or(nanoid!())
     }
@@ -465,6 +877,84 @@ impl DataManager {
         s.chars().all(|c| c.is_ascii_hexdigit())
     }
 
+    /// Checks an id given by a client for new content.
+    pub fn validate_content_id(id: &str) -> Result<()> {
+        if !DataManager::is_hex_string(id) {
+            return Err(anyhow!(
+                "Invalid ID format: {}, ID must be a hex string",
+                id
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether content with the given id was already ingested.
+    pub async fn content_exists(&self, namespace: &str, id: &str) -> Result<bool> {
+        let content_list = self
+            .get_content_metadata(namespace, vec![id.to_string()])
+            .await?;
+        Ok(!content_list.is_empty())
+    }
+
+    /// Returns the ids among `ids` of content which was already ingested.
+    pub async fn existing_content_ids(
+        &self,
+        namespace: &str,
+        ids: Vec<String>,
+    ) -> Result<HashSet<String>> {
+        if ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let content_list = self.get_content_metadata(namespace, ids).await?;
+        Ok(content_list.into_iter().map(|c| c.id).collect())
+    }
+
+    /// Derives the id of content from an idempotency key given by a client,
+    /// so that retrying an ingestion finds the content created by the first
+    /// attempt. The hash is stable across builds, unlike `make_id`.
+    pub fn idempotent_content_id(namespace: &str, idempotency_key: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(namespace.as_bytes());
+        hasher.update([0]);
+        hasher.update(idempotency_key.as_bytes());
+        format!("{:x}", hasher.finalize())[..16].to_string()
+    }
+
+    /// Makes the content of a text document added through the APIs.
+    pub fn text_content(
+        document: api::Text,
+        extraction_graph_names: &[internal_api::ExtractionGraphName],
+    ) -> api::ContentWithId {
+        api::ContentWithId {
+            id: document.id.unwrap_or_else(DataManager::make_id),
+            content: api::Content {
+                content_type: mime::TEXT_PLAIN.to_string(),
+                bytes: document.text.into_bytes(),
+                labels: document.labels,
+                features: vec![],
+            },
+            extraction_graph_names: extraction_graph_names.to_vec(),
+        }
+    }
+
+    /// Returns the name under which an uploaded file is stored, a random
+    /// name keeping the extension of the one given by the user, along with
+    /// the mime type guessed from that extension.
+    pub fn upload_file_name(user_file_name: &str) -> (String, Mime) {
+        let ext = std::path::Path::new(user_file_name)
+            .extension()
+            .unwrap_or_default()
+            .to_str()
+            .unwrap_or_default();
+        let name = nanoid!(16);
+        let name = if !ext.is_empty() {
+            format!("{}.{}", name, ext)
+        } else {
+            name
+        };
+        (name, mime_guess::from_ext(ext).first_or_octet_stream())
+    }
+
     async fn write_content_bytes(
         &self,
         namespace: &str,
@@ -517,6 +1007,8 @@ impl DataManager {
             hash: content_hash,
             extraction_policy_ids: HashMap::new(),
             extraction_graph_names: extraction_graph_names.to_vec(),
+            extracted_metadata: String::new(),
+            encoded: self.blob_storage.is_encoding(),
         })
     }
 
@@ -531,6 +1023,8 @@ impl DataManager {
             task_id: begin_ingest.task_id,
             outcome: outcome as i32,
             content_list: Vec::new(),
+            started_at: begin_ingest.started_at.unwrap_or_default(),
+            failure_reason: begin_ingest.failure_reason.unwrap_or_default(),
         };
         let res = self.coordinator_client.get().await?.update_task(req).await;
         if let Err(err) = res {
@@ -542,7 +1036,7 @@ impl DataManager {
     pub async fn write_extracted_embedding(
         &self,
         name: &str,
-        embedding: &[f32],
+        embedding: internal_api::EmbeddingVector,
         content_id: &str,
         output_index_map: &HashMap<String, String>,
         metadata: HashMap<String, serde_json::Value>,
@@ -551,7 +1045,7 @@ impl DataManager {
     ) -> Result<()> {
         let embeddings = internal_api::ExtractedEmbeddings {
             content_id: content_id.to_string(),
-            embedding: embedding.to_vec(),
+            embedding,
             metadata,
             root_content_metadata,
             content_metadata,
@@ -594,9 +1088,11 @@ impl DataManager {
         combined_metadata
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn write_existing_content_features(
         &self,
         extractor: &str,
+        extractor_version: &str,
         extraction_graph_name: &str,
         content_metadata: &indexify_coordinator::ContentMetadata,
         root_content_metadata: Option<indexify_internal_api::ContentMetadata>,
@@ -623,8 +1119,24 @@ impl DataManager {
             .collect();
         let new_metadata =
             Self::combine_metadata(existing_metadata, &features, content_metadata_labels);
+        // Metadata extracted from existing content is kept with it as well, so
+        // that extraction policies can route the content based on it
+        let extracted_metadata = Self::combine_metadata(Vec::new(), &features, HashMap::new());
+        if !extracted_metadata.is_empty() {
+            let req = indexify_coordinator::MergeExtractedMetadataRequest {
+                content_id: content_metadata.id.clone(),
+                metadata: serde_json::to_string(&extracted_metadata)?,
+            };
+            self.coordinator_client
+                .get()
+                .await?
+                .merge_extracted_metadata(GrpcHelper::into_req(req))
+                .await
+                .map_err(|e| anyhow!("unable to merge extracted metadata: {}", e))?;
+        }
         self.write_extracted_features(
             extractor,
+            extractor_version,
             extraction_graph_name,
             content_metadata.clone(),
             root_content_metadata,
@@ -651,17 +1163,26 @@ impl DataManager {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn write_extracted_features(
         &self,
         extractor: &str,
+        extractor_version: &str,
         extraction_graph_name: &str,
         content_metadata: indexify_coordinator::ContentMetadata,
         root_content_metadata: Option<indexify_internal_api::ContentMetadata>,
         features: Vec<api::Feature>,
-        metadata: HashMap<String, serde_json::Value>,
+        mut metadata: HashMap<String, serde_json::Value>,
         output_index_map: &HashMap<String, String>,
     ) -> Result<()> {
         let content_metadata: internal_api::ContentMetadata = content_metadata.try_into()?;
+        // Embeddings record the version of the extractor they were extracted with
+        if !extractor_version.is_empty() {
+            metadata.insert(
+                EXTRACTOR_VERSION_METADATA_KEY.to_string(),
+                serde_json::Value::String(extractor_version.to_string()),
+            );
+        }
         for feature in &features {
             match feature.feature_type {
                 api::FeatureType::Embedding => {
@@ -671,7 +1192,7 @@ impl DataManager {
                         })?;
                     self.write_extracted_embedding(
                         &feature.name,
-                        &embedding_payload.values,
+                        embedding_payload.values,
                         &content_metadata.id.id,
                         output_index_map,
                         metadata.clone(),
@@ -711,12 +1232,20 @@ impl DataManager {
         content_metadata: &indexify_coordinator::ContentMetadata,
         root_content_metadata: Option<indexify_internal_api::ContentMetadata>,
         extractor: &str,
+        extractor_version: &str,
         extraction_graph_name: &str,
         features: Vec<api::Feature>,
         output_index_map: &HashMap<String, String>,
     ) -> Result<()> {
+        // Metadata extracted along with the content is kept with it so that
+        // extraction policies can route the content based on it
+        let mut new_content_metadata = content_metadata.clone();
+        let extracted_metadata = Self::combine_metadata(Vec::new(), &features, HashMap::new());
+        if !extracted_metadata.is_empty() {
+            new_content_metadata.extracted_metadata = serde_json::to_string(&extracted_metadata)?;
+        }
         let req = indexify_coordinator::CreateContentRequest {
-            content: Some(content_metadata.clone()),
+            content: Some(new_content_metadata),
         };
         self.coordinator_client
             .get()
@@ -742,6 +1271,7 @@ impl DataManager {
         let metadata = Self::combine_metadata(Vec::new(), &features, content_metadata_labels);
         self.write_extracted_features(
             extractor,
+            extractor_version,
             extraction_graph_name,
             content_metadata.clone(),
             root_content_metadata,
@@ -819,6 +1349,119 @@ impl DataManager {
             .await
     }
 
+    async fn get_index(
+        &self,
+        namespace: &str,
+        index_name: &str,
+    ) -> Result<indexify_coordinator::Index> {
+        let req = indexify_coordinator::GetIndexRequest {
+            namespace: namespace.to_string(),
+            name: index_name.to_string(),
+        };
+        self.coordinator_client
+            .get()
+            .await?
+            .get_index(req)
+            .await?
+            .into_inner()
+            .index
+            .ok_or(anyhow!("Index not found"))
+    }
+
+    /// Starts migrating an index to another vector store in the background.
+    pub async fn migrate_index(
+        &self,
+        namespace: &str,
+        index_name: &str,
+        vector_store: &str,
+    ) -> Result<api::IndexMigrationStatus> {
+        let index = self.get_index(namespace, index_name).await?;
+        self.vector_index_manager
+            .start_migration(index, vector_store)
+            .await
+    }
+
+    /// Status of the migration of an index, if it is or was migrated.
+    pub async fn index_migration_status(
+        &self,
+        namespace: &str,
+        index_name: &str,
+    ) -> Result<Option<api::IndexMigrationStatus>> {
+        let index = self.get_index(namespace, index_name).await?;
+        Ok(self.vector_index_manager.migration_status(&index))
+    }
+
+    /// Policies of an extraction graph which would be applied to content of a
+    /// type with labels, in the order they would be applied.
+    pub async fn plan_extraction_graph(
+        &self,
+        namespace: &str,
+        extraction_graph: &str,
+        req: api::PlanExtractionGraphRequest,
+    ) -> Result<Vec<api::PlannedExtraction>> {
+        let req = indexify_coordinator::PlanExtractionGraphRequest {
+            namespace: namespace.to_string(),
+            extraction_graph: extraction_graph.to_string(),
+            content_type: req.content_type,
+            labels: req.labels,
+        };
+        let response = self
+            .coordinator_client
+            .get()
+            .await?
+            .plan_extraction_graph(req)
+            .await?
+            .into_inner();
+        Ok(response
+            .extractions
+            .into_iter()
+            .map(|extraction| extraction.into())
+            .collect())
+    }
+
+    /// Extracts the content an extraction policy was applied to with older
+    /// versions of its extractor again.
+    pub async fn reextract_outdated_content(
+        &self,
+        namespace: &str,
+        extraction_graph: &str,
+        extraction_policy: &str,
+    ) -> Result<()> {
+        let req = indexify_coordinator::ReextractOutdatedContentRequest {
+            namespace: namespace.to_string(),
+            extraction_graph: extraction_graph.to_string(),
+            extraction_policy: extraction_policy.to_string(),
+        };
+        self.coordinator_client
+            .get()
+            .await?
+            .reextract_outdated_content(req)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn cancel_tasks(
+        &self,
+        namespace: &str,
+        request: api::CancelTasksRequest,
+    ) -> Result<Vec<String>> {
+        let req = indexify_coordinator::CancelTasksRequest {
+            namespace: namespace.to_string(),
+            task_ids: request.task_ids,
+            content_id: request.content_id.unwrap_or_default(),
+            extraction_graph: request.extraction_graph.unwrap_or_default(),
+            extraction_policy: request.extraction_policy.unwrap_or_default(),
+        };
+        let resp = self
+            .coordinator_client
+            .get()
+            .await?
+            .cancel_tasks(req)
+            .await?
+            .into_inner();
+        Ok(resp.cancelled_task_ids)
+    }
+
     #[tracing::instrument]
     pub async fn metadata_lookup(
         &self,
@@ -859,6 +1502,11 @@ impl DataManager {
         self.blob_storage.put(name, file).await
     }
 
+    /// Whether the blobs written through `blob_store_writer` are encoded.
+    pub fn is_encoding_blobs(&self) -> bool {
+        self.blob_storage.is_encoding()
+    }
+
     pub async fn blob_store_writer(&self, namespace: &str, key: &str) -> Result<StoragePartWriter> {
         self.blob_storage.writer(namespace, key).await
     }
@@ -907,4 +1555,40 @@ mod tests {
 
         //assert_eq!(combined, expected);
     }
+
+    #[test]
+    fn test_upload_file_name() {
+        let (name, mime) = DataManager::upload_file_name("report.pdf");
+        assert!(name.ends_with(".pdf"));
+        assert_ne!(name, "report.pdf");
+        assert_eq!(mime, mime::APPLICATION_PDF);
+
+        let (name, mime) = DataManager::upload_file_name("README");
+        assert!(!name.contains('.'));
+        assert_eq!(mime, mime::APPLICATION_OCTET_STREAM);
+
+        assert!(DataManager::validate_content_id("8f0a").is_ok());
+        assert!(DataManager::validate_content_id("not-hex").is_err());
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let not_found = object_store::Error::NotFound {
+            path: "file.txt".to_string(),
+            source: anyhow!("missing").into(),
+        };
+        assert!(!is_transient(
+            &anyhow::Error::new(not_found).context("Error reading object")
+        ));
+        let generic = object_store::Error::Generic {
+            store: "S3",
+            source: anyhow!("connection reset").into(),
+        };
+        assert!(is_transient(
+            &anyhow::Error::new(generic).context("Error reading object")
+        ));
+        let timeout = std::io::Error::from(std::io::ErrorKind::TimedOut);
+        assert!(is_transient(&timeout.into()));
+        assert!(!is_transient(&anyhow!("invalid object url")));
+    }
 }
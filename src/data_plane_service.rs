@@ -0,0 +1,576 @@
+//! gRPC data plane of the ingestion server, defined in
+//! `protos/data_plane_service.proto`. It offers the operations of the HTTP
+//! ingestion and search endpoints, with streaming file uploads.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use indexify_proto::indexify_data_plane::{
+    data_plane_service_server::{self, DataPlaneService},
+    AddTextsRequest,
+    AddTextsResponse,
+    Content,
+    ListContentRequest,
+    ListContentResponse,
+    SearchRequest,
+    SearchResponse,
+    SearchResult,
+    SqlQueryRequest,
+    SqlQueryResponse,
+    SqlQueryRow,
+    UploadFileRequest,
+    UploadFileResponse,
+};
+use tokio::sync::watch;
+use tokio_stream::StreamExt;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::info;
+
+use crate::{
+    api,
+    data_manager::DataManager,
+    metrics,
+    server::DEFAULT_SEARCH_LIMIT,
+    server_config::TlsConfig,
+};
+
+impl From<api::ContentMetadata> for Content {
+    fn from(value: api::ContentMetadata) -> Self {
+        Self {
+            id: value.id,
+            parent_id: value.parent_id,
+            root_content_id: value.root_content_id,
+            namespace: value.namespace,
+            name: value.name,
+            mime_type: value.mime_type,
+            labels: value.labels,
+            extraction_graph_names: value.extraction_graph_names,
+            storage_url: value.storage_url,
+            created_at: value.created_at,
+            source: value.source,
+            size: value.size,
+            hash: value.hash,
+        }
+    }
+}
+
+fn internal_error(e: anyhow::Error) -> Status {
+    Status::internal(e.to_string())
+}
+
+pub struct DataPlaneServer {
+    data_manager: Arc<DataManager>,
+    metrics: Arc<metrics::server::Metrics>,
+}
+
+impl DataPlaneServer {
+    pub fn new(data_manager: Arc<DataManager>, metrics: Arc<metrics::server::Metrics>) -> Self {
+        Self {
+            data_manager,
+            metrics,
+        }
+    }
+
+    /// Fails with `InvalidArgument` or `AlreadyExists` unless `id` can be
+    /// used for new content.
+    async fn check_new_content_id(&self, namespace: &str, id: &str) -> Result<(), Status> {
+        DataManager::validate_content_id(id)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        if self
+            .data_manager
+            .content_exists(namespace, id)
+            .await
+            .map_err(internal_error)?
+        {
+            return Err(Status::already_exists(format!(
+                "content with the provided id {} already exists",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Serves the data plane on `addr` until `shutdown_rx` changes to true.
+    /// TLS is used when the `api` flag of the server TLS configuration is
+    /// set, with client certificates verified against its CA file if any.
+    pub async fn serve(
+        self,
+        addr: SocketAddr,
+        tls_config: Option<TlsConfig>,
+        mut shutdown_rx: watch::Receiver<bool>,
+    ) -> Result<()> {
+        let svc = data_plane_service_server::DataPlaneServiceServer::new(self);
+        let mut builder = tonic::transport::Server::builder();
+        match tls_config.filter(|tls_config| tls_config.api) {
+            Some(tls_config) => {
+                info!("starting data plane grpc server with TLS enabled");
+                let cert = tokio::fs::read(&tls_config.cert_file).await?;
+                let key = tokio::fs::read(&tls_config.key_file).await?;
+                let identity = tonic::transport::Identity::from_pem(cert, key);
+                let mut tonic_tls_config =
+                    tonic::transport::ServerTlsConfig::new().identity(identity);
+                if let Some(ca_file) = &tls_config.ca_file {
+                    let client_ca_cert = tokio::fs::read(ca_file).await?;
+                    let client_ca_cert = tonic::transport::Certificate::from_pem(client_ca_cert);
+                    tonic_tls_config = tonic_tls_config.client_ca_root(client_ca_cert);
+                }
+                builder = builder.tls_config(tonic_tls_config)?;
+            }
+            None => info!("starting data plane grpc server with TLS disabled"),
+        }
+        builder
+            .add_service(svc)
+            .serve_with_shutdown(addr, async move {
+                let _ = shutdown_rx.wait_for(|shutdown| *shutdown).await;
+            })
+            .await
+            .map_err(|e| anyhow!("unable to start grpc server: {} addr: {}", e, addr))
+    }
+}
+
+#[tonic::async_trait]
+impl DataPlaneService for DataPlaneServer {
+    async fn add_texts(
+        &self,
+        request: Request<AddTextsRequest>,
+    ) -> Result<Response<AddTextsResponse>, Status> {
+        let req = request.into_inner();
+        if req.extraction_graph_names.is_empty() {
+            return Err(Status::invalid_argument(
+                "extraction_graph_names must not be empty",
+            ));
+        }
+        let mut content_list = Vec::new();
+        for document in req.documents {
+            let id = (!document.id.is_empty()).then_some(document.id);
+            if let Some(id) = &id {
+                self.check_new_content_id(&req.namespace, id).await?;
+            }
+            let text = api::Text {
+                id,
+                text: document.text,
+                labels: document.labels,
+            };
+            content_list.push(DataManager::text_content(text, &req.extraction_graph_names));
+        }
+        let content_ids = content_list.iter().map(|c| c.id.clone()).collect();
+        self.data_manager
+            .add_texts(&req.namespace, content_list, req.extraction_graph_names)
+            .await
+            .map_err(|e| Status::invalid_argument(format!("failed to add text: {}", e)))?;
+        Ok(Response::new(AddTextsResponse { content_ids }))
+    }
+
+    async fn upload_file(
+        &self,
+        request: Request<Streaming<UploadFileRequest>>,
+    ) -> Result<Response<UploadFileResponse>, Status> {
+        let mut stream = request.into_inner();
+        let first = stream
+            .message()
+            .await?
+            .ok_or_else(|| Status::invalid_argument("no file provided"))?;
+        if first.extraction_graph_names.is_empty() {
+            return Err(Status::invalid_argument(
+                "extraction_graph_names must not be empty",
+            ));
+        }
+        let id = if first.id.is_empty() {
+            DataManager::make_id()
+        } else {
+            first.id.clone()
+        };
+        self.check_new_content_id(&first.namespace, &id).await?;
+
+        info!("user provided file name = {:?}", first.file_name);
+        let (name, content_mime) = DataManager::upload_file_name(&first.file_name);
+        info!("writing to blob store, file name = {:?}", name);
+
+        let head = tokio_stream::once(Ok(Bytes::from(first.data)));
+        let rest = stream.map(|res| res.map(|req| Bytes::from(req.data)).map_err(|e| anyhow!(e)));
+        let data = Box::pin(head.chain(rest));
+        let content_metadata = self
+            .data_manager
+            .upload_file(
+                &first.namespace,
+                data,
+                &name,
+                content_mime,
+                first.labels,
+                Some(&id),
+                first.extraction_graph_names,
+            )
+            .await
+            .map_err(|e| Status::invalid_argument(format!("failed to upload file: {}", e)))?;
+        let size_bytes = content_metadata.size_bytes;
+        self.data_manager
+            .create_content_metadata(content_metadata)
+            .await
+            .map_err(|e| {
+                Status::invalid_argument(format!("failed to create content for file: {}", e))
+            })?;
+        self.metrics.node_content_uploads.add(1, &[]);
+        self.metrics
+            .node_content_bytes_uploaded
+            .add(size_bytes, &[]);
+        Ok(Response::new(UploadFileResponse { content_id: id }))
+    }
+
+    async fn list_content(
+        &self,
+        request: Request<ListContentRequest>,
+    ) -> Result<Response<ListContentResponse>, Status> {
+        let req = request.into_inner();
+        let content_list = self
+            .data_manager
+            .list_content(
+                &req.namespace,
+                &req.source,
+                &req.parent_id,
+                Some(&req.labels_eq),
+            )
+            .await
+            .map_err(internal_error)?;
+        Ok(Response::new(ListContentResponse {
+            content_list: content_list.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<SearchResponse>, Status> {
+        let req = request.into_inner();
+        let results = self
+            .data_manager
+            .search(
+                &req.namespace,
+                &req.index,
+                &req.query,
+                req.k.unwrap_or(DEFAULT_SEARCH_LIMIT),
+                req.filters,
+                req.include_content.unwrap_or(true),
+            )
+            .await
+            .map_err(internal_error)?;
+        let results = results
+            .into_iter()
+            .map(|text| SearchResult {
+                content_id: text.content_id,
+                mime_type: text.mime_type,
+                text: text.text,
+                confidence_score: text.confidence_score,
+                labels: text.labels,
+                content_metadata: Some(api::ContentMetadata::from(text.content_metadata).into()),
+                root_content_metadata: text
+                    .root_content_metadata
+                    .map(|r| api::ContentMetadata::from(r).into()),
+            })
+            .collect();
+        Ok(Response::new(SearchResponse { results }))
+    }
+
+    async fn run_sql_query(
+        &self,
+        request: Request<SqlQueryRequest>,
+    ) -> Result<Response<SqlQueryResponse>, Status> {
+        let req = request.into_inner();
+        let results = self
+            .data_manager
+            .query_content_source(&req.namespace, &req.query)
+            .await
+            .map_err(internal_error)?;
+        let rows = results
+            .into_iter()
+            .map(|result| {
+                serde_json::to_string(&result)
+                    .map(|row| SqlQueryRow { row })
+                    .map_err(|e| {
+                        Status::internal(format!(
+                            "failed to serialize result {:?}: error: {}",
+                            result, e
+                        ))
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Response::new(SqlQueryResponse { rows }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use indexify_internal_api::StructuredDataSchema;
+    use indexify_proto::indexify_data_plane::{
+        data_plane_service_client::DataPlaneServiceClient,
+        data_plane_service_server::DataPlaneServiceServer,
+        Text,
+    };
+    use tokio::{net::TcpListener, task::JoinHandle};
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{transport::Channel, Code};
+
+    use super::*;
+    use crate::{
+        blob_storage::BlobStorage,
+        caching::caches_extension::Caches,
+        coordinator_client::CoordinatorClient,
+        extractor_router::ExtractorRouter,
+        metadata_storage,
+        test_util::{
+            db_utils::{create_test_extraction_graph, mock_extractor, DEFAULT_TEST_NAMESPACE},
+            server_utils::{make_test_config, TestCoordinator},
+        },
+        vector_index::VectorIndexManager,
+        vectordbs,
+    };
+
+    struct TestDataPlane {
+        client: DataPlaneServiceClient<Channel>,
+        coordinator: TestCoordinator,
+        server: JoinHandle<()>,
+        _dir: tempfile::TempDir,
+    }
+
+    impl TestDataPlane {
+        /// Starts a coordinator with the test extraction graph, and the data
+        /// plane on a free local port.
+        async fn new() -> TestDataPlane {
+            let dir = tempfile::tempdir().unwrap();
+            let config = Arc::new(make_test_config(dir.path()));
+            let coordinator = TestCoordinator::new(config.clone()).await;
+            let coordinator_client = Arc::new(CoordinatorClient::new(config.clone()));
+            coordinator
+                .coordinator
+                .shared_state
+                .register_executor("localhost:8950", "executor_id", vec![mock_extractor()])
+                .await
+                .unwrap();
+            coordinator
+                .coordinator
+                .shared_state
+                .create_extraction_graph(
+                    create_test_extraction_graph("graph", vec!["policy"]),
+                    StructuredDataSchema::default(),
+                    Vec::new(),
+                )
+                .await
+                .unwrap();
+
+            let vector_db = vectordbs::create_vectordb(config.index_config.clone())
+                .await
+                .unwrap();
+            let blob_storage =
+                Arc::new(BlobStorage::new_with_config(config.blob_storage.clone()).unwrap());
+            let extractor_router = ExtractorRouter::new(
+                coordinator_client.clone(),
+                Caches::new(config.cache.clone()),
+            )
+            .unwrap();
+            let vector_index_manager = Arc::new(
+                VectorIndexManager::new(
+                    coordinator_client.clone(),
+                    config.index_config.clone(),
+                    config.vector_stores.clone(),
+                    vector_db,
+                    Arc::new(blob_storage.content_reader()),
+                    Arc::new(extractor_router),
+                )
+                .unwrap(),
+            );
+            let data_manager = Arc::new(DataManager::new(
+                vector_index_manager,
+                metadata_storage::from_config(&config.metadata_storage).unwrap(),
+                metadata_storage::from_config_reader(&config.metadata_storage).unwrap(),
+                blob_storage,
+                coordinator_client,
+            ));
+            let data_plane =
+                DataPlaneServer::new(data_manager, Arc::new(metrics::server::Metrics::new()));
+
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server = tokio::spawn(async move {
+                tonic::transport::Server::builder()
+                    .add_service(DataPlaneServiceServer::new(data_plane))
+                    .serve_with_incoming(TcpListenerStream::new(listener))
+                    .await
+                    .unwrap();
+            });
+            let client = DataPlaneServiceClient::connect(format!("http://{}", addr))
+                .await
+                .unwrap();
+            TestDataPlane {
+                client,
+                coordinator,
+                server,
+                _dir: dir,
+            }
+        }
+
+        async fn stop(self) {
+            self.server.abort();
+            let _ = self.server.await;
+            self.coordinator.stop().await;
+        }
+    }
+
+    fn upload_request(id: &str, data: &[u8]) -> UploadFileRequest {
+        UploadFileRequest {
+            namespace: DEFAULT_TEST_NAMESPACE.to_string(),
+            id: id.to_string(),
+            file_name: "test.txt".to_string(),
+            labels: HashMap::from([("source".to_string(), "grpc".to_string())]),
+            extraction_graph_names: vec!["graph".to_string()],
+            data: data.to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_texts_and_list_content() {
+        let mut data_plane = TestDataPlane::new().await;
+
+        let response = data_plane
+            .client
+            .add_texts(AddTextsRequest {
+                namespace: DEFAULT_TEST_NAMESPACE.to_string(),
+                documents: vec![
+                    Text {
+                        id: "abc1".to_string(),
+                        text: "hello".to_string(),
+                        labels: HashMap::new(),
+                    },
+                    Text {
+                        id: "".to_string(),
+                        text: "world".to_string(),
+                        labels: HashMap::new(),
+                    },
+                ],
+                extraction_graph_names: vec!["graph".to_string()],
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.content_ids.len(), 2);
+        assert_eq!(response.content_ids[0], "abc1");
+
+        let content_list = data_plane
+            .client
+            .list_content(ListContentRequest {
+                namespace: DEFAULT_TEST_NAMESPACE.to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .content_list;
+        for id in &response.content_ids {
+            let content = content_list.iter().find(|c| &c.id == id).unwrap();
+            assert_eq!(content.mime_type, mime::TEXT_PLAIN.to_string());
+        }
+
+        // ids are validated and can't be reused
+        let err = data_plane
+            .client
+            .add_texts(AddTextsRequest {
+                namespace: DEFAULT_TEST_NAMESPACE.to_string(),
+                documents: vec![Text {
+                    id: "abc1".to_string(),
+                    text: "again".to_string(),
+                    labels: HashMap::new(),
+                }],
+                extraction_graph_names: vec!["graph".to_string()],
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::AlreadyExists);
+        let err = data_plane
+            .client
+            .add_texts(AddTextsRequest {
+                namespace: DEFAULT_TEST_NAMESPACE.to_string(),
+                documents: vec![Text {
+                    id: "not-hex".to_string(),
+                    text: "text".to_string(),
+                    labels: HashMap::new(),
+                }],
+                extraction_graph_names: vec!["graph".to_string()],
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        data_plane.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_streaming_upload() {
+        let mut data_plane = TestDataPlane::new().await;
+
+        // the file is split over several messages, metadata comes first
+        let mut rest = upload_request("", b" world");
+        rest.namespace.clear();
+        rest.file_name.clear();
+        let messages = vec![upload_request("abc2", b"hello"), rest];
+        let response = data_plane
+            .client
+            .upload_file(tokio_stream::iter(messages))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.content_id, "abc2");
+
+        let content_list = data_plane
+            .client
+            .list_content(ListContentRequest {
+                namespace: DEFAULT_TEST_NAMESPACE.to_string(),
+                labels_eq: HashMap::from([("source".to_string(), "grpc".to_string())]),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .content_list;
+        assert_eq!(content_list.len(), 1);
+        assert_eq!(content_list[0].id, "abc2");
+        assert_eq!(content_list[0].size, 11);
+        assert_eq!(content_list[0].mime_type, "text/plain");
+
+        let err = data_plane
+            .client
+            .upload_file(tokio_stream::iter(vec![upload_request("abc2", b"again")]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::AlreadyExists);
+        let err = data_plane
+            .client
+            .upload_file(tokio_stream::iter(Vec::<UploadFileRequest>::new()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        data_plane.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_search_unknown_index() {
+        let mut data_plane = TestDataPlane::new().await;
+
+        let err = data_plane
+            .client
+            .search(SearchRequest {
+                namespace: DEFAULT_TEST_NAMESPACE.to_string(),
+                index: "missing".to_string(),
+                query: "hello".to_string(),
+                k: Some(3),
+                filters: vec![],
+                include_content: None,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::Internal);
+
+        data_plane.stop().await;
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use indexify_internal_api::ExtractResponse;
+use tokio::sync::oneshot;
+
+use crate::{api, extractor_router::ExtractorRouter};
+
+/// How long the first query of a batch waits for other queries to join it.
+const EMBEDDING_BATCH_WINDOW: Duration = Duration::from_millis(5);
+
+/// Batches are sent as soon as they hold this many queries.
+const MAX_EMBEDDING_BATCH_SIZE: usize = 32;
+
+struct Query {
+    content: api::Content,
+    response_tx: oneshot::Sender<Result<ExtractResponse, String>>,
+}
+
+struct Batch {
+    id: u64,
+    queries: Vec<Query>,
+}
+
+/// Coalesces the embedding of search queries made concurrently with the same
+/// extractor into a single batch extraction.
+pub struct EmbeddingBatcher {
+    extractor_router: Arc<ExtractorRouter>,
+    batches: Mutex<HashMap<String, Batch>>,
+    next_batch_id: AtomicU64,
+}
+
+impl EmbeddingBatcher {
+    pub fn new(extractor_router: Arc<ExtractorRouter>) -> Self {
+        Self {
+            extractor_router,
+            batches: Mutex::new(HashMap::new()),
+            next_batch_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Extracts the embedding of a query along with the other queries made
+    /// with the same extractor within `EMBEDDING_BATCH_WINDOW`.
+    pub async fn extract(
+        self: &Arc<Self>,
+        extractor: &str,
+        content: api::Content,
+    ) -> Result<ExtractResponse> {
+        let (response_tx, response_rx) = oneshot::channel();
+        let (batch_id, full_batch) = {
+            let mut batches = self.batches.lock().unwrap();
+            let batch = batches
+                .entry(extractor.to_string())
+                .or_insert_with(|| Batch {
+                    id: self.next_batch_id.fetch_add(1, Ordering::Relaxed),
+                    queries: Vec::new(),
+                });
+            batch.queries.push(Query {
+                content,
+                response_tx,
+            });
+            let batch_id = (batch.queries.len() == 1).then_some(batch.id);
+            let full_batch = if batch.queries.len() >= MAX_EMBEDDING_BATCH_SIZE {
+                batches.remove(extractor).map(|batch| batch.queries)
+            } else {
+                None
+            };
+            (batch_id, full_batch)
+        };
+        // Batches are sent from their own task, so that dropping the query
+        // which started a batch doesn't drop the other queries of the batch.
+        if let Some(queries) = full_batch {
+            let batcher = self.clone();
+            let extractor = extractor.to_string();
+            tokio::spawn(async move {
+                batcher.send(&extractor, queries).await;
+            });
+        } else if let Some(batch_id) = batch_id {
+            let batcher = self.clone();
+            let extractor = extractor.to_string();
+            tokio::spawn(async move {
+                tokio::time::sleep(EMBEDDING_BATCH_WINDOW).await;
+                let queries = {
+                    let mut batches = batcher.batches.lock().unwrap();
+                    match batches.get(&extractor) {
+                        Some(batch) if batch.id == batch_id => batches.remove(&extractor),
+                        // The batch was sent as soon as it was full.
+                        _ => None,
+                    }
+                };
+                if let Some(batch) = queries {
+                    batcher.send(&extractor, batch.queries).await;
+                }
+            });
+        }
+        response_rx
+            .await
+            .map_err(|_| anyhow!("embedding batch was dropped"))?
+            .map_err(|e| anyhow!(e))
+    }
+
+    async fn send(&self, extractor: &str, queries: Vec<Query>) {
+        let (content_list, response_txs): (Vec<_>, Vec<_>) = queries
+            .into_iter()
+            .map(|query| (query.content, query.response_tx))
+            .unzip();
+        match self
+            .extractor_router
+            .extract_content_batch(extractor, content_list, None)
+            .await
+        {
+            Ok(responses) => {
+                for (response_tx, response) in response_txs.into_iter().zip(responses) {
+                    let _ = response_tx.send(Ok(response));
+                }
+            }
+            Err(e) => {
+                for response_tx in response_txs {
+                    let _ = response_tx.send(Err(e.to_string()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::atomic::AtomicUsize};
+
+    use axum::{extract::State, routing::post, Json, Router};
+    use futures::future::join_all;
+    use indexify_internal_api as internal_api;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        caching::caches_extension::Caches,
+        coordinator_client::CoordinatorClient,
+        test_util::{
+            db_utils::{mock_extractor, DEFAULT_TEST_EXTRACTOR},
+            server_utils::{make_test_config, TestCoordinator},
+        },
+    };
+
+    /// Executor which embeds text as its length, counting the batches it
+    /// receives.
+    async fn extract_batch(
+        State(batches): State<Arc<AtomicUsize>>,
+        Json(request): Json<internal_api::ExtractBatchRequest>,
+    ) -> Json<internal_api::ExtractBatchResponse> {
+        batches.fetch_add(1, Ordering::Relaxed);
+        let outcomes = request
+            .content_list
+            .iter()
+            .map(|content| internal_api::ExtractResponse {
+                content: vec![],
+                features: vec![internal_api::Feature {
+                    feature_type: internal_api::FeatureType::Embedding,
+                    name: "embedding".to_string(),
+                    data: json!({ "values": [content.bytes.len() as f32] }),
+                }],
+            })
+            .collect();
+        Json(internal_api::ExtractBatchResponse { outcomes })
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Arc::new(make_test_config(dir.path()));
+        let coordinator = TestCoordinator::new(config.clone()).await;
+        let coordinator_client = Arc::new(CoordinatorClient::new(config.clone()));
+
+        let batches = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route("/extract_batch", post(extract_batch))
+            .with_state(batches.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let executor_addr = listener.local_addr().unwrap().to_string();
+        let executor = tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        coordinator
+            .coordinator
+            .shared_state
+            .register_executor(&executor_addr, "executor_id", vec![mock_extractor()])
+            .await
+            .unwrap();
+
+        let extractor_router =
+            ExtractorRouter::new(coordinator_client, Caches::new(config.cache.clone())).unwrap();
+        let batcher = Arc::new(EmbeddingBatcher::new(Arc::new(extractor_router)));
+        let queries = ["a", "bb", "ccc"].map(|text| {
+            let batcher = batcher.clone();
+            async move {
+                let content = api::Content {
+                    content_type: "text/plain".to_string(),
+                    bytes: text.as_bytes().to_vec(),
+                    features: vec![],
+                    labels: HashMap::new(),
+                };
+                batcher.extract(DEFAULT_TEST_EXTRACTOR, content).await
+            }
+        });
+        let responses = join_all(queries).await;
+
+        assert_eq!(batches.load(Ordering::Relaxed), 1);
+        for (response, len) in responses.into_iter().zip([1.0, 2.0, 3.0]) {
+            assert_eq!(
+                response.unwrap().features[0].data,
+                json!({ "values": [len] })
+            );
+        }
+
+        executor.abort();
+        coordinator.stop().await;
+    }
+}
@@ -0,0 +1,460 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::{anyhow, Result};
+use indexify_internal_api as internal_api;
+use indexify_proto::indexify_coordinator;
+use internal_api::{ExtractionPolicy, ExtractionPolicyContentSource, ExtractorDescription};
+use serde_json::Value;
+
+use crate::coordinator_filters::{
+    label_value,
+    matches_mime_type,
+    matches_routing_condition,
+    parse_condition_field,
+    validate_routing_condition,
+    ConditionField,
+};
+
+/// Name of the policy an extraction policy reads its content from, if it
+/// doesn't read ingested content.
+fn parent_name(policy: &ExtractionPolicy) -> Option<&str> {
+    match &policy.content_source {
+        ExtractionPolicyContentSource::Ingestion => None,
+        ExtractionPolicyContentSource::ExtractionPolicyName(name) => Some(name),
+    }
+}
+
+fn extractor<'a>(
+    extractors: &'a HashMap<String, ExtractorDescription>,
+    policy: &ExtractionPolicy,
+) -> Result<&'a ExtractorDescription> {
+    extractors.get(&policy.extractor).ok_or_else(|| {
+        anyhow!(
+            "extractor {} of extraction policy {} not found",
+            policy.extractor,
+            policy.name
+        )
+    })
+}
+
+/// Checks that the policies of an extraction graph have unique names, valid
+/// routing conditions and non zero limits, read content from policies of the
+/// graph without forming cycles, and that their extractors accept content of
+/// the types the extractors of their parents output. Extractors which don't
+/// declare their output types are assumed to output content any extractor
+/// accepts.
+pub fn validate_extraction_graph(
+    graph_name: &str,
+    policies: &[ExtractionPolicy],
+    extractors: &HashMap<String, ExtractorDescription>,
+) -> Result<()> {
+    let mut by_name = HashMap::new();
+    for policy in policies {
+        if by_name.insert(policy.name.as_str(), policy).is_some() {
+            return Err(anyhow!(
+                "extraction graph {} has more than one extraction policy named {}",
+                graph_name,
+                policy.name
+            ));
+        }
+        for condition in &policy.conditions {
+            validate_routing_condition(condition).map_err(|e| {
+                anyhow!(
+                    "invalid condition of extraction policy {}: {}",
+                    policy.name,
+                    e
+                )
+            })?;
+        }
+        if policy.limits.timeout_secs == Some(0) || policy.limits.max_content_size_bytes == Some(0)
+        {
+            return Err(anyhow!(
+                "limits of extraction policy {} must be greater than zero",
+                policy.name
+            ));
+        }
+    }
+    for policy in policies {
+        let Some(parent_name) = parent_name(policy) else {
+            continue;
+        };
+        let parent = by_name.get(parent_name).ok_or_else(|| {
+            anyhow!(
+                "extraction policy {} reads content from extraction policy {}, which is not in extraction graph {}",
+                policy.name,
+                parent_name,
+                graph_name
+            )
+        })?;
+        let parent_extractor = extractor(extractors, parent)?;
+        let policy_extractor = extractor(extractors, policy)?;
+        if !parent_extractor.output_mime_types.is_empty() &&
+            !parent_extractor
+                .output_mime_types
+                .iter()
+                .any(|mime| matches_mime_type(&policy_extractor.input_mime_types, mime))
+        {
+            return Err(anyhow!(
+                "extractor {} of extraction policy {} accepts content of types [{}], but extractor {} of its parent extraction policy {} outputs content of types [{}]",
+                policy_extractor.name,
+                policy.name,
+                policy_extractor.input_mime_types.join(", "),
+                parent_extractor.name,
+                parent.name,
+                parent_extractor.output_mime_types.join(", ")
+            ));
+        }
+    }
+    // Policies have a single parent, so following the parents of each policy
+    // either ends at a policy reading ingested content or goes around a cycle.
+    for policy in policies {
+        let mut path = vec![policy.name.as_str()];
+        let mut current = policy;
+        while let Some(parent_name) = parent_name(current) {
+            if let Some(start) = path.iter().position(|name| *name == parent_name) {
+                // The path goes from children to parents, cycles are reported
+                // in the order content flows through them.
+                let mut cycle = path[start..].to_vec();
+                cycle.reverse();
+                cycle.rotate_right(1);
+                cycle.push(cycle[0]);
+                return Err(anyhow!(
+                    "extraction policies of extraction graph {} form a cycle: {}",
+                    graph_name,
+                    cycle.join(" -> ")
+                ));
+            }
+            path.push(parent_name);
+            current = by_name[parent_name];
+        }
+    }
+    Ok(())
+}
+
+/// Extraction policy which would be applied to content added to a graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedExtraction {
+    pub extraction_policy: String,
+    pub extractor: String,
+    pub content_source: String,
+    /// Number of policies the content goes through before this one.
+    pub depth: u32,
+    /// Types of the content the policy would be applied to, empty when the
+    /// extractor of its parent doesn't declare the types it outputs.
+    pub content_types: Vec<String>,
+    pub output_table_mapping: HashMap<String, String>,
+}
+
+impl From<PlannedExtraction> for indexify_coordinator::PlannedExtraction {
+    fn from(value: PlannedExtraction) -> Self {
+        Self {
+            extraction_policy: value.extraction_policy,
+            extractor: value.extractor,
+            content_source: value.content_source,
+            depth: value.depth,
+            content_types: value.content_types,
+            output_table_mapping: value.output_table_mapping,
+        }
+    }
+}
+
+fn matches_labels(policy: &ExtractionPolicy, labels: &HashMap<String, String>) -> bool {
+    policy
+        .filters
+        .iter()
+        .all(|(name, value)| labels.get(name).map_or(false, |v| v == value))
+}
+
+/// Evaluates the routing conditions of a policy on content of any of the
+/// types, any type when there are none. Metadata is only known for ingested
+/// content, which has none, conditions on the metadata of extracted content
+/// are assumed to be met.
+fn matches_conditions(
+    policy: &ExtractionPolicy,
+    content_types: &[String],
+    labels: &HashMap<String, String>,
+    ingested: bool,
+) -> bool {
+    policy
+        .conditions
+        .iter()
+        .all(|condition| match parse_condition_field(&condition.field) {
+            Ok(ConditionField::MimeType) => {
+                content_types.is_empty() ||
+                    content_types.iter().any(|content_type| {
+                        matches_routing_condition(
+                            condition,
+                            Some(&Value::String(content_type.clone())),
+                        )
+                    })
+            }
+            Ok(ConditionField::Label(name)) => matches_routing_condition(
+                condition,
+                labels.get(name).map(|v| label_value(v)).as_ref(),
+            ),
+            Ok(ConditionField::Metadata(_)) => {
+                !ingested || matches_routing_condition(condition, None)
+            }
+            Err(_) => false,
+        })
+}
+
+/// Policies of a graph which would be applied to content of a type with
+/// labels, in the order they would be applied. Policies are matched the way
+/// the scheduler matches them, content extracted by a policy keeps the labels
+/// of the content it was extracted from.
+pub fn plan_extraction_graph(
+    policies: &[ExtractionPolicy],
+    extractors: &HashMap<String, ExtractorDescription>,
+    content_type: &str,
+    labels: &HashMap<String, String>,
+) -> Result<Vec<PlannedExtraction>> {
+    let mut children: HashMap<&str, Vec<&ExtractionPolicy>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    let mut sorted_policies: Vec<&ExtractionPolicy> = policies.iter().collect();
+    sorted_policies.sort_by(|a, b| a.name.cmp(&b.name));
+    for policy in sorted_policies {
+        match parent_name(policy) {
+            Some(parent_name) => children.entry(parent_name).or_default().push(policy),
+            None => {
+                let policy_extractor = extractor(extractors, policy)?;
+                let content_types = vec![content_type.to_string()];
+                if matches_labels(policy, labels) &&
+                    matches_conditions(policy, &content_types, labels, true) &&
+                    matches_mime_type(
+                        &policy_extractor.input_mime_types,
+                        &content_type.to_string(),
+                    )
+                {
+                    queue.push_back((policy, 0, content_types));
+                }
+            }
+        }
+    }
+    let mut planned = Vec::new();
+    let mut visited = HashSet::new();
+    while let Some((policy, depth, content_types)) = queue.pop_front() {
+        if !visited.insert(policy.name.as_str()) {
+            continue;
+        }
+        let output_mime_types = &extractor(extractors, policy)?.output_mime_types;
+        for child in children.get(policy.name.as_str()).into_iter().flatten() {
+            let child_extractor = extractor(extractors, child)?;
+            let child_content_types: Vec<String> = output_mime_types
+                .iter()
+                .filter(|mime| matches_mime_type(&child_extractor.input_mime_types, mime))
+                .cloned()
+                .collect();
+            if !matches_labels(child, labels) ||
+                !matches_conditions(child, &child_content_types, labels, false) ||
+                (!output_mime_types.is_empty() && child_content_types.is_empty())
+            {
+                continue;
+            }
+            queue.push_back((*child, depth + 1, child_content_types));
+        }
+        planned.push(PlannedExtraction {
+            extraction_policy: policy.name.clone(),
+            extractor: policy.extractor.clone(),
+            content_source: policy.content_source.to_string(),
+            depth,
+            content_types,
+            output_table_mapping: policy.output_table_mapping.clone(),
+        });
+    }
+    Ok(planned)
+}
+
+#[cfg(test)]
+mod tests {
+    use internal_api::{ConditionOperator, RoutingCondition};
+    use serde_json::json;
+
+    use super::*;
+
+    fn policy(name: &str, extractor: &str, parent: Option<&str>) -> ExtractionPolicy {
+        ExtractionPolicy {
+            name: name.to_string(),
+            extractor: extractor.to_string(),
+            content_source: parent.map_or(ExtractionPolicyContentSource::Ingestion, |parent| {
+                ExtractionPolicyContentSource::ExtractionPolicyName(parent.to_string())
+            }),
+            output_table_mapping: HashMap::from([(
+                "embedding".to_string(),
+                format!("graph.{}.embedding", name),
+            )]),
+            ..Default::default()
+        }
+    }
+
+    fn extractors() -> HashMap<String, ExtractorDescription> {
+        let extractor = |name: &str, input: &[&str], output: &[&str]| ExtractorDescription {
+            name: name.to_string(),
+            input_mime_types: input.iter().map(|m| m.to_string()).collect(),
+            output_mime_types: output.iter().map(|m| m.to_string()).collect(),
+            ..Default::default()
+        };
+        [
+            extractor("pdf", &["application/pdf"], &["text/plain", "image/png"]),
+            extractor("text", &["text/plain"], &[]),
+            extractor("image", &["image/png"], &[]),
+            extractor("audio", &["audio/mpeg"], &[]),
+            extractor("any", &["*/*"], &[]),
+        ]
+        .into_iter()
+        .map(|extractor| (extractor.name.clone(), extractor))
+        .collect()
+    }
+
+    #[test]
+    fn test_validate_extraction_graph() {
+        let extractors = extractors();
+        let valid = [
+            policy("pdf", "pdf", None),
+            policy("text", "text", Some("pdf")),
+            policy("image", "image", Some("pdf")),
+            policy("chunks", "any", Some("text")),
+        ];
+        validate_extraction_graph("graph", &valid, &extractors).unwrap();
+
+        let duplicate = [policy("pdf", "pdf", None), policy("pdf", "text", None)];
+        let err = validate_extraction_graph("graph", &duplicate, &extractors).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "extraction graph graph has more than one extraction policy named pdf"
+        );
+
+        let dangling = [policy("text", "text", Some("pdf"))];
+        let err = validate_extraction_graph("graph", &dangling, &extractors).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "extraction policy text reads content from extraction policy pdf, which is not in extraction graph graph"
+        );
+
+        let mut conditional = policy("text", "text", Some("pdf"));
+        conditional.conditions = vec![RoutingCondition {
+            field: "lang".to_string(),
+            operator: ConditionOperator::Eq,
+            value: json!("de"),
+        }];
+        let invalid_condition = [policy("pdf", "pdf", None), conditional];
+        let err = validate_extraction_graph("graph", &invalid_condition, &extractors).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid condition of extraction policy text: condition field lang is not mime_type, labels.<name> or metadata.<path>"
+        );
+
+        let cycle = [
+            policy("pdf", "pdf", None),
+            policy("a", "any", Some("c")),
+            policy("b", "any", Some("a")),
+            policy("c", "any", Some("b")),
+        ];
+        let err = validate_extraction_graph("graph", &cycle, &extractors).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "extraction policies of extraction graph graph form a cycle: a -> b -> c -> a"
+        );
+
+        let incompatible = [
+            policy("pdf", "pdf", None),
+            policy("audio", "audio", Some("pdf")),
+        ];
+        let err = validate_extraction_graph("graph", &incompatible, &extractors).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "extractor audio of extraction policy audio accepts content of types [audio/mpeg], but extractor pdf of its parent extraction policy pdf outputs content of types [text/plain, image/png]"
+        );
+    }
+
+    #[test]
+    fn test_plan_extraction_graph() {
+        let extractors = extractors();
+        let mut image = policy("image", "image", Some("pdf"));
+        image.filters = HashMap::from([("images".to_string(), "true".to_string())]);
+        let policies = [
+            policy("pdf", "pdf", None),
+            policy("text", "text", Some("pdf")),
+            image,
+            policy("chunks", "any", Some("text")),
+            policy("raw_text", "text", None),
+        ];
+
+        let planned =
+            plan_extraction_graph(&policies, &extractors, "application/pdf", &HashMap::new())
+                .unwrap();
+        let steps: Vec<_> = planned
+            .iter()
+            .map(|p| {
+                (
+                    p.extraction_policy.as_str(),
+                    p.depth,
+                    p.content_types.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("pdf", 0, vec!["application/pdf".to_string()]),
+                ("text", 1, vec!["text/plain".to_string()]),
+                ("chunks", 2, vec![]),
+            ]
+        );
+        assert_eq!(
+            planned[1].output_table_mapping["embedding"],
+            "graph.text.embedding"
+        );
+
+        let labels = HashMap::from([("images".to_string(), "true".to_string())]);
+        let planned =
+            plan_extraction_graph(&policies, &extractors, "application/pdf", &labels).unwrap();
+        let names: Vec<_> = planned
+            .iter()
+            .map(|p| p.extraction_policy.as_str())
+            .collect();
+        assert_eq!(names, vec!["pdf", "image", "text", "chunks"]);
+
+        let planned = plan_extraction_graph(&policies, &extractors, "text/plain", &labels).unwrap();
+        let names: Vec<_> = planned
+            .iter()
+            .map(|p| p.extraction_policy.as_str())
+            .collect();
+        assert_eq!(names, vec!["raw_text"]);
+
+        let mut german = policy("german", "any", Some("text"));
+        german.conditions = vec![RoutingCondition {
+            field: "labels.lang".to_string(),
+            operator: ConditionOperator::Eq,
+            value: json!("de"),
+        }];
+        let mut pages = policy("pages", "any", Some("pdf"));
+        pages.conditions = vec![RoutingCondition {
+            field: "metadata.page".to_string(),
+            operator: ConditionOperator::Gt,
+            value: json!(1),
+        }];
+        let policies = [
+            policy("pdf", "pdf", None),
+            policy("text", "text", Some("pdf")),
+            german,
+            pages,
+        ];
+        let plan = |labels: &[(&str, &str)]| {
+            let labels = labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            plan_extraction_graph(&policies, &extractors, "application/pdf", &labels)
+                .unwrap()
+                .into_iter()
+                .map(|p| p.extraction_policy)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(plan(&[("lang", "en")]), vec!["pdf", "pages", "text"]);
+        assert_eq!(
+            plan(&[("lang", "de")]),
+            vec!["pdf", "pages", "text", "german"]
+        );
+    }
+}
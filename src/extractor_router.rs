@@ -1,21 +1,166 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use anyhow::{anyhow, Result};
+use futures::future::join_all;
 use indexify_internal_api as internal_api;
 use indexify_proto::indexify_coordinator::GetExtractorCoordinatesRequest;
 use internal_api::ExtractResponse;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::warn;
 
-use crate::{api::Content, coordinator_client::CoordinatorClient};
+use crate::{
+    api::Content,
+    caching::caches_extension::{Caches, ExtractContentCacheKey},
+    coordinator_client::CoordinatorClient,
+};
 
-const CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
 
+/// How long a call to an executor may take before it is attempted on another
+/// executor.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long the executors of an extractor are routed to before asking the
+/// coordinator for them again.
+const COORDINATES_TTL: Duration = Duration::from_secs(10);
+
+/// Number of executors a call is attempted on when connecting to them fails,
+/// they time out or respond with a server error.
+const MAX_ATTEMPTS: usize = 3;
+
+/// Consecutive failed calls after which an executor stops receiving calls.
+const CIRCUIT_FAILURE_THRESHOLD: u32 = 3;
+
+/// How long an executor is skipped once its circuit opens, before a single
+/// call is attempted on it again.
+const CIRCUIT_OPEN_DURATION: Duration = Duration::from_secs(30);
+
+struct Coordinates {
+    addrs: Vec<String>,
+    extractor_fingerprint: String,
+    fetched_at: Instant,
+}
+
+#[derive(Default)]
+struct ExecutorState {
+    outstanding: usize,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl ExecutorState {
+    fn is_available(&self, now: Instant) -> bool {
+        self.open_until.map_or(true, |open_until| now >= open_until)
+    }
+}
+
+/// Outstanding calls and circuit breakers of the executors calls are routed
+/// to.
+#[derive(Default)]
+struct Executors {
+    states: Mutex<HashMap<String, ExecutorState>>,
+    next: AtomicUsize,
+}
+
+impl Executors {
+    /// Picks the executor with the fewest outstanding calls among the ones not
+    /// tried yet and whose circuit is closed. Executors with as many
+    /// outstanding calls are picked in turn.
+    fn select<'a>(
+        &'a self,
+        addrs: &[String],
+        tried: &HashSet<String>,
+        now: Instant,
+    ) -> Option<SelectedExecutor<'a>> {
+        if addrs.is_empty() {
+            return None;
+        }
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut states = self.states.lock().unwrap();
+        let addr = (0..addrs.len())
+            .map(|i| &addrs[(start + i) % addrs.len()])
+            .filter(|addr| !tried.contains(*addr))
+            .filter(|addr| states.get(*addr).map_or(true, |s| s.is_available(now)))
+            .min_by_key(|addr| states.get(*addr).map_or(0, |s| s.outstanding))?
+            .clone();
+        let state = states.entry(addr.clone()).or_default();
+        state.outstanding += 1;
+        // A single call goes to an executor whose circuit is half open, the
+        // circuit closes again once it succeeds.
+        if state.open_until.is_some() {
+            state.open_until = Some(now + CIRCUIT_OPEN_DURATION);
+        }
+        Some(SelectedExecutor {
+            executors: self,
+            addr,
+        })
+    }
+}
+
+/// Executor a call is routed to, counted as outstanding until dropped.
+struct SelectedExecutor<'a> {
+    executors: &'a Executors,
+    addr: String,
+}
+
+impl SelectedExecutor<'_> {
+    fn succeeded(&self) {
+        let mut states = self.executors.states.lock().unwrap();
+        if let Some(state) = states.get_mut(&self.addr) {
+            state.consecutive_failures = 0;
+            state.open_until = None;
+        }
+    }
+
+    fn failed(&self, now: Instant) {
+        let mut states = self.executors.states.lock().unwrap();
+        if let Some(state) = states.get_mut(&self.addr) {
+            state.consecutive_failures += 1;
+            if state.consecutive_failures >= CIRCUIT_FAILURE_THRESHOLD {
+                state.open_until = Some(now + CIRCUIT_OPEN_DURATION);
+            }
+        }
+    }
+}
+
+impl Drop for SelectedExecutor<'_> {
+    fn drop(&mut self) {
+        let mut states = self.executors.states.lock().unwrap();
+        if let Some(state) = states.get_mut(&self.addr) {
+            state.outstanding = state.outstanding.saturating_sub(1);
+        }
+    }
+}
+
+/// Routes extractor calls made by the server to the executors running the
+/// extractor. It is shared by the server, so that outstanding calls and
+/// circuit breakers are tracked across requests.
 pub struct ExtractorRouter {
     coordinator_client: Arc<CoordinatorClient>,
     client: reqwest::Client,
+    caches: Caches,
+    coordinates: Mutex<HashMap<String, Coordinates>>,
+    executors: Executors,
+    request_timeout: Duration,
+}
+
+impl fmt::Debug for ExtractorRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractorRouter").finish()
+    }
 }
 
 impl ExtractorRouter {
-    pub fn new(coordinator_client: Arc<CoordinatorClient>) -> Result<Self> {
+    pub fn new(coordinator_client: Arc<CoordinatorClient>, caches: Caches) -> Result<Self> {
         let request_client = reqwest::Client::builder()
             .connect_timeout(CONNECT_TIMEOUT)
             .build()
@@ -23,26 +168,32 @@ impl ExtractorRouter {
         Ok(Self {
             coordinator_client,
             client: request_client,
+            caches,
+            coordinates: Mutex::new(HashMap::new()),
+            executors: Executors::default(),
+            request_timeout: REQUEST_TIMEOUT,
         })
     }
 
-    pub async fn extract_content(
-        &self,
-        extractor_name: &str,
-        content: Content,
-        input_params: Option<serde_json::Value>,
-    ) -> Result<ExtractResponse, anyhow::Error> {
-        let request = internal_api::ExtractRequest {
-            content: internal_api::Content {
-                content_type: content.content_type,
-                bytes: content.bytes,
-                features: vec![],
-                labels: HashMap::new(),
-            },
-            extractor_name: extractor_name.to_string(),
-            input_params,
-        };
-
+    /// Addresses of the executors of an extractor and the fingerprint of its
+    /// description, fetched from the coordinator once they are older than
+    /// `COORDINATES_TTL`.
+    async fn coordinates(&self, extractor_name: &str) -> Result<(Vec<String>, String)> {
+        let cached = self
+            .coordinates
+            .lock()
+            .unwrap()
+            .get(extractor_name)
+            .filter(|coordinates| coordinates.fetched_at.elapsed() < COORDINATES_TTL)
+            .map(|coordinates| {
+                (
+                    coordinates.addrs.clone(),
+                    coordinates.extractor_fingerprint.clone(),
+                )
+            });
+        if let Some(coordinates) = cached {
+            return Ok(coordinates);
+        }
         let req = GetExtractorCoordinatesRequest {
             extractor: extractor_name.to_string(),
         };
@@ -53,19 +204,211 @@ impl ExtractorRouter {
             .get_extractor_coordinates(req)
             .await?
             .into_inner();
-        let addresses = resp.addrs;
+        if !resp.addrs.is_empty() {
+            self.coordinates.lock().unwrap().insert(
+                extractor_name.to_string(),
+                Coordinates {
+                    addrs: resp.addrs.clone(),
+                    extractor_fingerprint: resp.extractor_fingerprint.clone(),
+                    fetched_at: Instant::now(),
+                },
+            );
+        }
+        Ok((resp.addrs, resp.extractor_fingerprint))
+    }
+
+    pub async fn extract_content(
+        &self,
+        extractor_name: &str,
+        content: Content,
+        input_params: Option<serde_json::Value>,
+    ) -> Result<ExtractResponse, anyhow::Error> {
+        self.extract_content_batch(extractor_name, vec![content], input_params)
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("no response was extracted"))
+    }
+
+    /// Extracts many contents with the same input params, returning their
+    /// responses in order. Contents which are not cached are sent to a single
+    /// executor in one call.
+    pub async fn extract_content_batch(
+        &self,
+        extractor_name: &str,
+        content_list: Vec<Content>,
+        input_params: Option<serde_json::Value>,
+    ) -> Result<Vec<ExtractResponse>> {
+        let (addresses, extractor_fingerprint) = self.coordinates(extractor_name).await?;
         if addresses.is_empty() {
             return Err(anyhow!("no extractor found"));
         }
-        let extractor_addr = addresses[0].clone();
+        let mut cache_keys = Vec::with_capacity(content_list.len());
+        let mut cached = Vec::with_capacity(content_list.len());
+        let mut missing = Vec::new();
+        for content in content_list {
+            let cache_key = ExtractContentCacheKey::new(
+                extractor_name,
+                &extractor_fingerprint,
+                &content,
+                &input_params,
+            );
+            let response = self.caches.get_extract_content(&cache_key).await;
+            if response.is_none() {
+                missing.push(internal_api::Content {
+                    content_type: content.content_type,
+                    bytes: content.bytes,
+                    features: vec![],
+                    labels: HashMap::new(),
+                });
+            }
+            cache_keys.push(cache_key);
+            cached.push(response);
+        }
+
+        let mut extracted = match missing.len() {
+            0 => vec![],
+            1 => vec![
+                self.extract_one(extractor_name, &addresses, missing.remove(0), &input_params)
+                    .await?,
+            ],
+            _ => {
+                self.extract_many(extractor_name, &addresses, missing, &input_params)
+                    .await?
+            }
+        }
+        .into_iter();
+        let mut responses = Vec::with_capacity(cached.len());
+        for (cache_key, response) in cache_keys.into_iter().zip(cached) {
+            let response = match response {
+                Some(response) => response,
+                None => {
+                    let response = extracted
+                        .next()
+                        .ok_or_else(|| anyhow!("executor returned fewer responses than content"))?;
+                    self.caches
+                        .insert_extract_content(cache_key, response.clone())
+                        .await;
+                    response
+                }
+            };
+            responses.push(response);
+        }
+        Ok(responses)
+    }
+
+    async fn extract_one(
+        &self,
+        extractor_name: &str,
+        addresses: &[String],
+        content: internal_api::Content,
+        input_params: &Option<serde_json::Value>,
+    ) -> Result<ExtractResponse> {
+        let request = internal_api::ExtractRequest {
+            content,
+            extractor_name: extractor_name.to_string(),
+            input_params: input_params.clone(),
+        };
         let resp = self
-            .client
-            .post(&format!("http://{}/extract", extractor_addr))
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| anyhow::anyhow!("unable to embed query: {}", e))?;
+            .send(extractor_name, addresses, "extract", &request)
+            .await?;
+        Self::parse_response(resp).await
+    }
 
+    async fn extract_many(
+        &self,
+        extractor_name: &str,
+        addresses: &[String],
+        content_list: Vec<internal_api::Content>,
+        input_params: &Option<serde_json::Value>,
+    ) -> Result<Vec<ExtractResponse>> {
+        let request = internal_api::ExtractBatchRequest {
+            extractor_name: extractor_name.to_string(),
+            content_list,
+            input_params: input_params.clone(),
+        };
+        let resp = self
+            .send(extractor_name, addresses, "extract_batch", &request)
+            .await?;
+        // Executors which don't support batches yet get a call per content.
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            let responses = request
+                .content_list
+                .into_iter()
+                .map(|content| self.extract_one(extractor_name, addresses, content, input_params));
+            return join_all(responses).await.into_iter().collect();
+        }
+        let response: internal_api::ExtractBatchResponse = Self::parse_response(resp).await?;
+        if response.outcomes.len() != request.content_list.len() {
+            return Err(anyhow!(
+                "executor returned {} responses for {} contents",
+                response.outcomes.len(),
+                request.content_list.len()
+            ));
+        }
+        Ok(response.outcomes)
+    }
+
+    /// Sends a call to the executors of an extractor, moving on to another
+    /// executor when connecting to one fails, it times out or it responds with
+    /// a server error.
+    async fn send<T: Serialize>(
+        &self,
+        extractor_name: &str,
+        addresses: &[String],
+        path: &str,
+        request: &T,
+    ) -> Result<reqwest::Response> {
+        let mut tried = HashSet::new();
+        let mut last_error = None;
+        for _ in 0..MAX_ATTEMPTS {
+            let executor = match self.executors.select(addresses, &tried, Instant::now()) {
+                Some(executor) => executor,
+                None => break,
+            };
+            tried.insert(executor.addr.clone());
+            let resp = self
+                .client
+                .post(&format!("http://{}/{}", executor.addr, path))
+                .timeout(self.request_timeout)
+                .json(request)
+                .send()
+                .await;
+            let error = match resp {
+                Ok(resp) if resp.status().is_server_error() => {
+                    let status = resp.status();
+                    anyhow!(
+                        "status: {}, error: {}",
+                        status,
+                        resp.text().await.unwrap_or_default()
+                    )
+                }
+                Ok(resp) => {
+                    executor.succeeded();
+                    return Ok(resp);
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => anyhow!(e),
+                Err(e) => return Err(anyhow!("unable to embed query: {}", e)),
+            };
+            warn!(
+                "call to executor {} of extractor {} failed: {}",
+                executor.addr, extractor_name, error
+            );
+            executor.failed(Instant::now());
+            last_error = Some(error);
+        }
+        // Executors might have gone away, ask the coordinator for them on the
+        // next call.
+        self.coordinates.lock().unwrap().remove(extractor_name);
+        match last_error {
+            Some(e) => Err(anyhow!("unable to embed query: {}", e)),
+            None => Err(anyhow!(
+                "no executor of extractor {} is available",
+                extractor_name
+            )),
+        }
+    }
+
+    async fn parse_response<T: DeserializeOwned>(resp: reqwest::Response) -> Result<T> {
         if !&resp.status().is_success() {
             return Err(anyhow!(
                 "unable to extract query: status: {}, error: {}",
@@ -78,10 +421,145 @@ impl ExtractorRouter {
             .await
             .map_err(|e| anyhow!("unable to get response body: {}", e))?;
 
-        let extractor_response: internal_api::ExtractResponse =
-            serde_json::from_str(&response_body)
-                .map_err(|e| anyhow!("unable to extract response from json: {}", e))?;
+        let extractor_response: T = serde_json::from_str(&response_body)
+            .map_err(|e| anyhow!("unable to extract response from json: {}", e))?;
 
         Ok(extractor_response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, routing::post, Router};
+
+    use super::*;
+    use crate::server_config::ServerConfig;
+
+    async fn serve(router: Router) -> (String, tokio::task::JoinHandle<()>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        (addr, handle)
+    }
+
+    fn addrs(addrs: &[&str]) -> Vec<String> {
+        addrs.iter().map(|addr| addr.to_string()).collect()
+    }
+
+    #[test]
+    fn test_select_least_outstanding() {
+        let executors = Executors::default();
+        let addrs = addrs(&["a:1", "b:1", "c:1"]);
+        let now = Instant::now();
+
+        // Executors without outstanding calls are picked in turn.
+        let first = executors.select(&addrs, &HashSet::new(), now).unwrap();
+        let second = executors.select(&addrs, &HashSet::new(), now).unwrap();
+        let third = executors.select(&addrs, &HashSet::new(), now).unwrap();
+        let selected: HashSet<String> = [&first, &second, &third]
+            .iter()
+            .map(|executor| executor.addr.clone())
+            .collect();
+        assert_eq!(selected.len(), 3);
+
+        // Once a call completes, its executor has the fewest outstanding calls.
+        let addr = second.addr.clone();
+        drop(second);
+        for _ in 0..3 {
+            let executor = executors.select(&addrs, &HashSet::new(), now).unwrap();
+            assert_eq!(executor.addr, addr);
+        }
+
+        // Executors already tried are skipped.
+        let tried = HashSet::from([addr.clone()]);
+        let executor = executors.select(&addrs, &tried, now).unwrap();
+        assert_ne!(executor.addr, addr);
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let executors = Executors::default();
+        let addrs = addrs(&["a:1", "b:1"]);
+        let now = Instant::now();
+
+        for _ in 0..CIRCUIT_FAILURE_THRESHOLD {
+            let tried = HashSet::from(["b:1".to_string()]);
+            let executor = executors.select(&addrs, &tried, now).unwrap();
+            assert_eq!(executor.addr, "a:1");
+            executor.failed(now);
+        }
+
+        // The circuit of a:1 is open, calls go to b:1 only.
+        for _ in 0..3 {
+            let executor = executors.select(&addrs, &HashSet::new(), now).unwrap();
+            assert_eq!(executor.addr, "b:1");
+        }
+        let tried = HashSet::from(["b:1".to_string()]);
+        assert!(executors.select(&addrs, &tried, now).is_none());
+
+        // Once the circuit is half open, a single call goes to a:1.
+        let later = now + CIRCUIT_OPEN_DURATION;
+        let executor = executors.select(&addrs, &tried, later).unwrap();
+        assert_eq!(executor.addr, "a:1");
+        assert!(executors.select(&addrs, &tried, later).is_none());
+
+        // The circuit closes when the call succeeds.
+        executor.succeeded();
+        drop(executor);
+        assert_eq!(executors.select(&addrs, &tried, later).unwrap().addr, "a:1");
+    }
+
+    #[tokio::test]
+    async fn test_send_fails_over() {
+        let (failing, failing_handle) = serve(Router::new().route(
+            "/extract",
+            post(|| async { (StatusCode::INTERNAL_SERVER_ERROR, "extractor crashed") }),
+        ))
+        .await;
+        let (hanging, hanging_handle) = serve(Router::new().route(
+            "/extract",
+            post(|| async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                "late"
+            }),
+        ))
+        .await;
+        let (healthy, healthy_handle) =
+            serve(Router::new().route("/extract", post(|| async { "ok" }))).await;
+
+        let config = Arc::new(ServerConfig::default());
+        let mut router = ExtractorRouter::new(
+            Arc::new(CoordinatorClient::new(config.clone())),
+            Caches::new(config.cache.clone()),
+        )
+        .unwrap();
+        router.request_timeout = Duration::from_millis(200);
+
+        // The executors are tried in order, the server error and the timeout
+        // count as failures of their executors.
+        let addrs = vec![failing.clone(), hanging.clone(), healthy.clone()];
+        let resp = router
+            .send("extractor", &addrs, "extract", &serde_json::json!({}))
+            .await
+            .unwrap();
+        assert_eq!(resp.text().await.unwrap(), "ok");
+        {
+            let states = router.executors.states.lock().unwrap();
+            assert_eq!(states[&failing].consecutive_failures, 1);
+            assert_eq!(states[&hanging].consecutive_failures, 1);
+            assert_eq!(states[&healthy].consecutive_failures, 0);
+        }
+
+        // The last error is returned once no executor succeeds.
+        let addrs = vec![failing.clone()];
+        let err = router
+            .send("extractor", &addrs, "extract", &serde_json::json!({}))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("extractor crashed"));
+
+        for handle in [failing_handle, hanging_handle, healthy_handle] {
+            handle.abort();
+        }
+    }
+}
@@ -16,7 +16,7 @@ use sha2::{
     Sha256VarCore,
 };
 use tokio::io::AsyncWriteExt;
-use tracing::info;
+use tracing::{error, info};
 
 use crate::{
     api::*,
@@ -60,6 +60,13 @@ enum FrameState {
     Writing(Writing),
 }
 
+fn ensure_not_cancelled(task: &indexify_coordinator::Task) -> Result<()> {
+    if task.outcome() == indexify_coordinator::TaskOutcome::Cancelled {
+        return Err(anyhow!("task {} was cancelled", task.id));
+    }
+    Ok(())
+}
+
 struct ContentStateWriting {
     ingest_metadata: BeginExtractedContentIngest,
     task: indexify_coordinator::Task,
@@ -76,6 +83,7 @@ impl ContentStateWriting {
         if task.content_metadata.is_none() {
             return Err(anyhow!("task does not have content metadata"));
         }
+        ensure_not_cancelled(&task)?;
         let root_content = root_content.map(|c| c.into());
         Ok(Self {
             ingest_metadata,
@@ -89,7 +97,21 @@ impl ContentStateWriting {
         self.task.content_metadata.as_ref().unwrap()
     }
 
+    /// Fails once the task was cancelled, checked before each write so that
+    /// no results of cancelled tasks are written.
+    async fn ensure_task_not_cancelled(&self, state: &NamespaceEndpointState) -> Result<()> {
+        let (task, _) = state
+            .coordinator_client
+            .get_metadata_for_ingestion(&self.task.id)
+            .await?;
+        match task {
+            Some(task) => ensure_not_cancelled(&task),
+            None => Ok(()),
+        }
+    }
+
     async fn start_content(&mut self, state: &NamespaceEndpointState) -> Result<()> {
+        self.ensure_task_not_cancelled(state).await?;
         let ts = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
@@ -148,6 +170,21 @@ impl ContentStateWriting {
         state: &NamespaceEndpointState,
         payload: FinishContent,
     ) -> Result<String> {
+        if let Err(e) = self.ensure_task_not_cancelled(state).await {
+            if let FrameState::Writing(mut frame_state) =
+                std::mem::replace(&mut self.frame_state, FrameState::New)
+            {
+                let _ = frame_state.writer.writer.shutdown().await;
+                if let Err(e) = state
+                    .data_manager
+                    .delete_file(&frame_state.writer.url)
+                    .await
+                {
+                    error!("unable to delete blob {}: {}", frame_state.writer.url, e);
+                }
+            }
+            return Err(e);
+        }
         let mut labels = self.content_metadata().labels.clone();
         let parent_id = self.content_metadata().id.clone();
         match &mut self.frame_state {
@@ -183,6 +220,8 @@ impl ContentStateWriting {
                     hash: content_hash,
                     extraction_policy_ids: HashMap::new(),
                     extraction_graph_names: vec![extraction_policy.graph_name],
+                    extracted_metadata: String::new(),
+                    encoded: state.data_manager.is_encoding_blobs(),
                 };
                 state
                     .data_manager
@@ -190,6 +229,7 @@ impl ContentStateWriting {
                         &content_metadata,
                         Some(root_content_metadata.clone()),
                         &self.task.extractor,
+                        &self.task.extractor_version,
                         &self.task.extraction_graph_name,
                         payload.features,
                         &self.task.output_index_mapping,
@@ -211,10 +251,12 @@ impl ContentStateWriting {
         state: &NamespaceEndpointState,
         payload: ExtractedFeatures,
     ) -> Result<()> {
+        self.ensure_task_not_cancelled(state).await?;
         state
             .data_manager
             .write_existing_content_features(
                 &self.task.extractor,
+                &self.task.extractor_version,
                 &self.task.extraction_graph_name,
                 self.content_metadata(),
                 self.root_content_metadata.clone(),
@@ -291,6 +333,7 @@ impl IngestExtractedContentState {
     async fn finish(&mut self) -> Result<()> {
         match &mut self.content_state {
             ContentState::Writing(s) => {
+                s.ensure_task_not_cancelled(&self.state).await?;
                 self.state
                     .data_manager
                     .finish_extracted_content_write(s.ingest_metadata.clone())
@@ -377,58 +420,44 @@ impl IngestExtractedContentState {
 #[cfg(test)]
 mod tests {
 
-    use std::sync::Arc;
+    use std::{sync::Arc, time::Duration};
 
     use indexify_internal_api::{
         ContentMetadata,
-        ExtractionGraph,
         ExtractionPolicy,
         ExtractionPolicyContentSource,
-        ExtractorDescription,
         StructuredDataSchema,
         Task,
         TaskOutcome,
     };
     use serde_json::json;
-    use tokio::task::JoinHandle;
 
     use super::*;
     use crate::{
-        blob_storage::{BlobStorage, ContentReader},
-        coordinator::Coordinator,
+        blob_storage::{presign::PresignedUrls, BlobStorage},
+        caching::caches_extension::Caches,
         coordinator_client::CoordinatorClient,
         data_manager::DataManager,
+        extractor_router::ExtractorRouter,
         metadata_storage::{self, MetadataReaderTS, MetadataStorageTS},
         metrics,
         server::NamespaceEndpointState,
-        server_config::{IndexStoreKind, ServerConfig},
-        test_util::db_utils::{
-            create_metadata,
-            create_test_extraction_graph,
-            mock_extractor,
-            test_mock_content_metadata,
-            DEFAULT_TEST_NAMESPACE,
+        server_config::ServerConfig,
+        test_util::{
+            db_utils::{
+                create_metadata,
+                create_test_extraction_graph,
+                mock_extractor,
+                test_mock_content_metadata,
+                DEFAULT_TEST_NAMESPACE,
+            },
+            server_utils::{make_test_config, TestCoordinator},
         },
+        upload_sessions::UploadSessionManager,
         vector_index::VectorIndexManager,
         vectordbs,
     };
 
-    fn make_test_config() -> ServerConfig {
-        let mut config = ServerConfig::default();
-        config.coordinator_port += 100;
-        config.coordinator_addr = format!("localhost:{}", config.coordinator_port);
-        config.listen_port += 100;
-        config.index_config.index_store = IndexStoreKind::Qdrant;
-        config.index_config.qdrant_config = Some(Default::default());
-        config.blob_storage = crate::blob_storage::BlobStorageConfig {
-            s3: None,
-            disk: Some(crate::blob_storage::DiskStorageConfig {
-                path: "/tmp/indexify-test".to_string(),
-            }),
-        };
-        config
-    }
-
     fn make_test_task(
         task_id: &str,
         content_metadata: &ContentMetadata,
@@ -446,176 +475,114 @@ mod tests {
         task
     }
 
-    struct TestCoordinator {
-        coordinator: Arc<Coordinator>,
-        handle: JoinHandle<()>,
-    }
-
-    impl TestCoordinator {
-        async fn stop(self) {
-            self.handle.abort();
-            let _ = self.handle.await;
-        }
-
-        async fn new() -> TestCoordinator {
-            let config = make_test_config();
-            let _ = std::fs::remove_dir_all(config.state_store.clone().path.unwrap());
-            let registry = Arc::new(crate::metrics::init_provider());
-            let coordinator_server = crate::coordinator_service::CoordinatorServer::new(
-                Arc::new(config.clone()),
-                registry,
-            )
+    /// Starts a coordinator with an extraction graph and a task for content
+    /// of the graph.
+    async fn start_coordinator(config: Arc<ServerConfig>) -> TestCoordinator {
+        let test_coordinator = TestCoordinator::new(config).await;
+        let coordinator = &test_coordinator.coordinator;
+        coordinator
+            .shared_state
+            .register_executor("localhost:8950", "executor_id", vec![mock_extractor()])
             .await
-            .expect("failed to create coordinator server");
-
-            let coordinator = coordinator_server.get_coordinator();
-
-            let handle = tokio::spawn(async move {
-                coordinator_server.run().await.unwrap();
-            });
-            // wait until able to connect to coordinator
-            loop {
-                if let Ok(_) = CoordinatorClient::new(Arc::new(config.clone())).get().await {
-                    break;
-                }
-                tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-            }
-            let test_coordinator = TestCoordinator {
-                handle,
-                coordinator,
-            };
-            let extractor = mock_extractor();
-            test_coordinator
-                .create_extractor(extractor.clone())
-                .await
-                .unwrap();
-            let eg = create_test_extraction_graph(
-                "extraction_graph_name",
-                vec!["extraction_policy_name"],
-            );
-            test_coordinator
-                .create_extraction_graph(eg.clone())
-                .await
-                .unwrap();
-            let content_metadata = test_mock_content_metadata("1", "1", &eg.name);
-            test_coordinator
-                .create_content(content_metadata.clone().into())
-                .await
-                .unwrap();
-            let internal_content_metadata = test_coordinator
-                .get_internal_content(content_metadata.id.id)
-                .await;
-            test_coordinator
-                .create_task(make_test_task(
+            .unwrap();
+        let eg =
+            create_test_extraction_graph("extraction_graph_name", vec!["extraction_policy_name"]);
+        coordinator
+            .shared_state
+            .create_extraction_graph(eg.clone(), StructuredDataSchema::default(), Vec::new())
+            .await
+            .unwrap();
+        let content_metadata = test_mock_content_metadata("1", "1", &eg.name);
+        let content: indexify_coordinator::ContentMetadata = content_metadata.clone().into();
+        coordinator
+            .create_content_metadata(vec![content.into()])
+            .await
+            .unwrap();
+        let internal_content_metadata = coordinator
+            .shared_state
+            .get_content_metadata_batch(vec![content_metadata.id.id])
+            .await
+            .unwrap()
+            .first()
+            .unwrap()
+            .clone();
+        let state_change_id = coordinator
+            .shared_state
+            .get_state_change_watcher()
+            .borrow_and_update()
+            .id
+            .clone();
+        coordinator
+            .shared_state
+            .create_tasks(
+                vec![make_test_task(
                     "test",
                     &internal_content_metadata,
                     eg.extraction_policies[0].clone(),
-                ))
-                .await
-                .unwrap();
-            test_coordinator
-        }
-
-        pub async fn create_extractor(&self, extractor: ExtractorDescription) -> Result<()> {
-            self.coordinator
-                .shared_state
-                .register_executor("localhost:8950", "executor_id", vec![extractor])
-                .await?;
-            Ok(())
-        }
-
-        pub async fn create_extraction_graph(
-            &self,
-            extraction_graph: ExtractionGraph,
-        ) -> Result<()> {
-            self.coordinator
-                .shared_state
-                .create_extraction_graph(
-                    extraction_graph,
-                    StructuredDataSchema::default(),
-                    Vec::new(),
-                )
-                .await?;
-            Ok(())
-        }
-
-        pub async fn create_content(
-            &self,
-            content: indexify_coordinator::ContentMetadata,
-        ) -> Result<()> {
-            self.coordinator
-                .create_content_metadata(vec![content.into()])
-                .await
-                .unwrap();
-
-            Ok(())
-        }
-
-        pub async fn get_internal_content(
-            &self,
-            id: String,
-        ) -> indexify_internal_api::ContentMetadata {
-            self.coordinator
-                .shared_state
-                .get_content_metadata_batch(vec![id])
-                .await
-                .unwrap()
-                .first()
-                .unwrap()
-                .clone()
-        }
-
-        pub async fn create_task(&self, task: Task) -> Result<()> {
-            let state_change_id = self
-                .coordinator
-                .shared_state
-                .get_state_change_watcher()
-                .borrow_and_update()
-                .id
-                .clone();
-
-            self.coordinator
-                .shared_state
-                .create_tasks(vec![task], &state_change_id)
-                .await
-                .unwrap();
-            Ok(())
-        }
+                )],
+                &state_change_id,
+            )
+            .await
+            .unwrap();
+        test_coordinator
     }
 
-    async fn new_endpoint_state() -> Result<NamespaceEndpointState> {
-        let config = make_test_config();
+    async fn new_endpoint_state(config: &ServerConfig) -> Result<NamespaceEndpointState> {
         let vector_db = vectordbs::create_vectordb(config.index_config.clone()).await?;
         let coordinator_client = Arc::new(CoordinatorClient::new(Arc::new(config.clone())));
+        let blob_storage = Arc::new(BlobStorage::new_with_config(config.blob_storage.clone())?);
+        let content_reader = Arc::new(blob_storage.content_reader());
+        let extractor_router = Arc::new(ExtractorRouter::new(
+            coordinator_client.clone(),
+            Caches::new(config.cache.clone()),
+        )?);
         let vector_index_manager = Arc::new(
-            VectorIndexManager::new(coordinator_client.clone(), vector_db.clone())
-                .map_err(|e| anyhow!("unable to create vector index {}", e))?,
+            VectorIndexManager::new(
+                coordinator_client.clone(),
+                config.index_config.clone(),
+                config.vector_stores.clone(),
+                vector_db.clone(),
+                content_reader.clone(),
+                extractor_router.clone(),
+            )
+            .map_err(|e| anyhow!("unable to create vector index {}", e))?,
         );
         let metadata_index_manager: MetadataStorageTS =
             metadata_storage::from_config(&config.metadata_storage)?;
         let metadata_reader: MetadataReaderTS =
             metadata_storage::from_config_reader(&config.metadata_storage)?;
-        let blob_storage = Arc::new(BlobStorage::new_with_config(config.blob_storage.clone()));
         let data_manager = Arc::new(DataManager::new(
             vector_index_manager,
             metadata_index_manager,
             metadata_reader,
-            blob_storage,
+            blob_storage.clone(),
             coordinator_client.clone(),
         ));
         let namespace_endpoint_state = NamespaceEndpointState {
             data_manager: data_manager.clone(),
             coordinator_client: coordinator_client.clone(),
-            content_reader: Arc::new(ContentReader::new()),
+            content_reader,
+            upload_sessions: Arc::new(UploadSessionManager::new(
+                blob_storage,
+                Duration::from_secs(config.upload_session_ttl_secs),
+            )),
+            presigned_urls: Arc::new(PresignedUrls::new(
+                config.blob_storage.clone(),
+                config.presigned_urls.clone(),
+            )),
             registry: Arc::new(metrics::init_provider()),
             metrics: Arc::new(metrics::server::Metrics::new()),
+            extractor_router,
         };
         Ok(namespace_endpoint_state)
     }
 
     #[tokio::test]
     async fn test_new() {
-        let state = new_endpoint_state().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let state = new_endpoint_state(&make_test_config(dir.path()))
+            .await
+            .unwrap();
         let ingest_state = IngestExtractedContentState::new(state);
         assert!(matches!(ingest_state.content_state, ContentState::Init));
     }
@@ -631,14 +598,18 @@ mod tests {
     async fn test_begin() {
         set_tracing();
 
-        let state = new_endpoint_state().await.unwrap();
-        let coordinator = TestCoordinator::new().await;
+        let dir = tempfile::tempdir().unwrap();
+        let config = Arc::new(make_test_config(dir.path()));
+        let state = new_endpoint_state(&config).await.unwrap();
+        let coordinator = start_coordinator(config).await;
 
         let mut ingest_state = IngestExtractedContentState::new(state);
         let payload = BeginExtractedContentIngest {
             task_id: "test".to_string(),
             executor_id: "test".to_string(),
             task_outcome: TaskOutcome::Success,
+            started_at: None,
+            failure_reason: None,
         };
         ingest_state.begin(payload.clone()).await.unwrap();
         let new_payload = if let ContentState::Writing(s) = &ingest_state.content_state {
@@ -699,24 +670,96 @@ mod tests {
         }
 
         // compare file content with written content
-        let content = ingest_state.state.content_reader.bytes(&url).await.unwrap();
+        let content = ingest_state
+            .state
+            .content_reader
+            .bytes(&url, false)
+            .await
+            .unwrap();
         assert_eq!(content, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
 
         coordinator.stop().await;
     }
 
+    #[tokio::test]
+    async fn test_cancelled_task() {
+        set_tracing();
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = Arc::new(make_test_config(dir.path()));
+        let state = new_endpoint_state(&config).await.unwrap();
+        let coordinator = start_coordinator(config).await;
+
+        let mut ingest_state = IngestExtractedContentState::new(state);
+        let payload = BeginExtractedContentIngest {
+            task_id: "test".to_string(),
+            executor_id: "test".to_string(),
+            task_outcome: TaskOutcome::Success,
+            started_at: None,
+            failure_reason: None,
+        };
+        ingest_state.begin(payload).await.unwrap();
+        ingest_state.begin_multipart_content().await.unwrap();
+        let url = if let ContentState::Writing(s) = &ingest_state.content_state {
+            if let FrameState::Writing(w) = &s.frame_state {
+                w.writer.url.clone()
+            } else {
+                panic!("frame_state should be Writing");
+            }
+        } else {
+            panic!("content_state should be Writing");
+        };
+        let payload = ContentFrame {
+            bytes: vec![1, 2, 3],
+        };
+        ingest_state.write_content_frame(payload).await.unwrap();
+
+        //  Results written after the task was cancelled are rejected, and the
+        // blob of the unfinished content is deleted
+        coordinator
+            .coordinator
+            .shared_state
+            .cancel_tasks(vec!["test".to_string()])
+            .await
+            .unwrap();
+        let payload = FinishContent {
+            content_type: "test".to_string(),
+            features: Vec::new(),
+            labels: HashMap::new(),
+        };
+        assert!(ingest_state.finish_content(payload).await.is_err());
+        assert!(ingest_state
+            .state
+            .content_reader
+            .bytes(&url, false)
+            .await
+            .is_err());
+        assert!(ingest_state.begin_multipart_content().await.is_err());
+        let payload = ExtractedFeatures {
+            content_id: "1".to_string(),
+            features: Vec::new(),
+        };
+        assert!(ingest_state.write_features(payload).await.is_err());
+        assert!(ingest_state.finish().await.is_err());
+
+        coordinator.stop().await;
+    }
+
     #[tokio::test]
     async fn test_embedding_metadata() {
         set_tracing();
 
-        let state = new_endpoint_state().await.unwrap();
-        let coordinator = TestCoordinator::new().await;
+        let dir = tempfile::tempdir().unwrap();
+        let config = Arc::new(make_test_config(dir.path()));
+        let state = new_endpoint_state(&config).await.unwrap();
+        let coordinator = start_coordinator(config).await;
 
         let mut ingest_state = IngestExtractedContentState::new(state.clone());
 
         let schema = indexify_internal_api::EmbeddingSchema {
             dim: 3,
             distance: "cosine".to_string(),
+            kind: indexify_internal_api::EmbeddingKind::Dense,
         };
 
         let _ = ingest_state
@@ -730,7 +773,7 @@ mod tests {
             .state
             .data_manager
             .vector_index_manager
-            .create_index("test_index1", schema)
+            .create_index("test_index1", schema, vectordbs::IndexOptions::default())
             .await
             .unwrap();
 
@@ -738,6 +781,8 @@ mod tests {
             task_id: "test".to_string(),
             executor_id: "test".to_string(),
             task_outcome: TaskOutcome::Success,
+            started_at: None,
+            failure_reason: None,
         };
 
         ingest_state.begin(payload.clone()).await.unwrap();
@@ -816,6 +861,8 @@ mod tests {
             task_id: "test_1".to_string(),
             executor_id: "test".to_string(),
             task_outcome: TaskOutcome::Success,
+            started_at: None,
+            failure_reason: None,
         };
 
         let mut ingest_state = IngestExtractedContentState::new(state.clone());
@@ -857,14 +904,17 @@ mod tests {
     async fn test_embedding_existing_metadata() {
         set_tracing();
 
-        let state = new_endpoint_state().await.unwrap();
-        let coordinator = TestCoordinator::new().await;
+        let dir = tempfile::tempdir().unwrap();
+        let config = Arc::new(make_test_config(dir.path()));
+        let state = new_endpoint_state(&config).await.unwrap();
+        let coordinator = start_coordinator(config).await;
 
         let mut ingest_state = IngestExtractedContentState::new(state.clone());
 
         let schema = indexify_internal_api::EmbeddingSchema {
             dim: 3,
             distance: "cosine".to_string(),
+            kind: indexify_internal_api::EmbeddingKind::Dense,
         };
 
         let _ = ingest_state
@@ -878,7 +928,7 @@ mod tests {
             .state
             .data_manager
             .vector_index_manager
-            .create_index("test_index1", schema)
+            .create_index("test_index1", schema, vectordbs::IndexOptions::default())
             .await
             .unwrap();
 
@@ -886,6 +936,8 @@ mod tests {
             task_id: "test".to_string(),
             executor_id: "test".to_string(),
             task_outcome: TaskOutcome::Success,
+            started_at: None,
+            failure_reason: None,
         };
 
         ingest_state.begin(payload.clone()).await.unwrap();
@@ -942,6 +994,8 @@ mod tests {
             task_id: "test_1".to_string(),
             executor_id: "test".to_string(),
             task_outcome: TaskOutcome::Success,
+            started_at: None,
+            failure_reason: None,
         };
 
         let mut ingest_state = IngestExtractedContentState::new(state.clone());
@@ -0,0 +1,375 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use indexify_proto::indexify_coordinator::{self, ContentMetadata};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{error, info};
+
+use crate::{
+    blob_storage::{BlobStorage, BlobStorageWriter},
+    coordinator_client::CoordinatorClient,
+    utils::timestamp_secs,
+};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Number of blobs moved between two updates of their content.
+const MOVE_BATCH_SIZE: usize = 100;
+
+fn default_sweep_interval_secs() -> u64 {
+    60 * 60
+}
+
+/// Lifecycle rules of the content of a namespace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecyclePolicy {
+    pub namespace: String,
+    /// Content ingested more than this many days ago is moved to the cold
+    /// tier of the blob storage.
+    #[serde(default)]
+    pub move_to_cold_after_days: Option<u64>,
+    /// Deletes the blobs of intermediate content, extracted content which
+    /// other content was extracted from, once every task of its content tree
+    /// has finished. The content is kept and its storage url is cleared.
+    #[serde(default)]
+    pub delete_intermediate_content: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleConfig {
+    /// How often the content of namespaces is checked against their
+    /// policies.
+    #[serde(default = "default_sweep_interval_secs")]
+    pub sweep_interval_secs: u64,
+    #[serde(default)]
+    pub policies: Vec<LifecyclePolicy>,
+}
+
+impl Default for LifecycleConfig {
+    fn default() -> Self {
+        Self {
+            sweep_interval_secs: default_sweep_interval_secs(),
+            policies: vec![],
+        }
+    }
+}
+
+/// Applies the lifecycle policies of namespaces to the blobs of their
+/// content. Blobs are copied to the cold tier before their content is pointed
+/// at the copy, and are only deleted once the coordinator has accepted the
+/// update, which it does only while the content still points at the blob it
+/// is moved from.
+pub struct LifecycleManager {
+    config: LifecycleConfig,
+    blob_storage: Arc<BlobStorage>,
+    coordinator_client: Arc<CoordinatorClient>,
+}
+
+impl std::fmt::Debug for LifecycleManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LifecycleManager")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl LifecycleManager {
+    pub fn new(
+        config: LifecycleConfig,
+        blob_storage: Arc<BlobStorage>,
+        coordinator_client: Arc<CoordinatorClient>,
+    ) -> Result<Self> {
+        for policy in &config.policies {
+            if policy.move_to_cold_after_days.is_some() && !blob_storage.has_cold_tier() {
+                return Err(anyhow!(
+                    "lifecycle policy of namespace {} requires a cold tier of blob storage",
+                    policy.namespace
+                ));
+            }
+        }
+        Ok(Self {
+            config,
+            blob_storage,
+            coordinator_client,
+        })
+    }
+
+    pub async fn sweep(&self) {
+        for policy in &self.config.policies {
+            if let Err(e) = self.apply(policy).await {
+                error!(
+                    "unable to apply lifecycle policy of namespace {}: {}",
+                    policy.namespace, e
+                );
+            }
+        }
+    }
+
+    async fn apply(&self, policy: &LifecyclePolicy) -> Result<()> {
+        let content_list = self
+            .coordinator_client
+            .get()
+            .await?
+            .list_content(indexify_coordinator::ListContentRequest {
+                namespace: policy.namespace.clone(),
+                source: "".to_string(),
+                parent_id: "".to_string(),
+                labels_eq: HashMap::new(),
+            })
+            .await?
+            .into_inner()
+            .content_list
+            .into_iter()
+            // Content is listed at every one of its versions
+            .into_grouping_map_by(|c| c.id.clone())
+            .max_by_key(|_, c| c.created_at)
+            .into_values()
+            .collect_vec();
+        // Blobs shared by several pieces of content are left in place, as
+        // they can't be moved for all of them at once
+        let url_counts = content_list.iter().counts_by(|c| c.storage_url.as_str());
+        let is_shared = |c: &ContentMetadata| url_counts[c.storage_url.as_str()] > 1;
+
+        if let Some(days) = policy.move_to_cold_after_days {
+            let cutoff = timestamp_secs().saturating_sub(days * SECS_PER_DAY);
+            let due = content_list
+                .iter()
+                .filter(|c| (c.created_at as u64) < cutoff)
+                .filter(|c| {
+                    self.blob_storage.is_stored(&c.storage_url) &&
+                        !self.blob_storage.is_cold(&c.storage_url) &&
+                        !is_shared(*c)
+                })
+                .collect_vec();
+            for batch in due.chunks(MOVE_BATCH_SIZE) {
+                self.move_to_cold(batch).await?;
+            }
+        }
+
+        if policy.delete_intermediate_content {
+            for (root_content_id, intermediate) in intermediate_content(&content_list) {
+                let intermediate = intermediate
+                    .into_iter()
+                    .filter(|c| self.blob_storage.is_stored(&c.storage_url) && !is_shared(*c))
+                    .collect_vec();
+                if intermediate.is_empty() ||
+                    !self.is_content_tree_indexed(&root_content_id).await?
+                {
+                    continue;
+                }
+                self.remove_blobs(&intermediate).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn move_to_cold(&self, content_list: &[&ContentMetadata]) -> Result<()> {
+        let mut updates = Vec::new();
+        for content in content_list {
+            match self.blob_storage.copy_to_cold(&content.storage_url).await {
+                Ok(cold_url) => updates.push(indexify_coordinator::ContentStorageUpdate {
+                    content_id: content.id.clone(),
+                    from_storage_url: content.storage_url.clone(),
+                    to_storage_url: cold_url,
+                }),
+                Err(e) => error!(
+                    "unable to copy blob {} to the cold tier: {}",
+                    content.storage_url, e
+                ),
+            }
+        }
+        let moved = self.update_content_storage(updates.clone()).await?;
+
+        // Copies are made under the key of the blob, so a copy is only deleted
+        // when no content was pointed at it, by this or another server
+        let unmoved = updates
+            .iter()
+            .filter(|u| !moved.contains(&u.content_id))
+            .map(|u| u.content_id.clone())
+            .collect_vec();
+        let current_urls: HashSet<String> = if unmoved.is_empty() {
+            HashSet::new()
+        } else {
+            self.coordinator_client
+                .get()
+                .await?
+                .get_content_metadata(indexify_coordinator::GetContentMetadataRequest {
+                    content_list: unmoved,
+                })
+                .await?
+                .into_inner()
+                .content_list
+                .into_iter()
+                .map(|c| c.storage_url)
+                .collect()
+        };
+        for update in updates {
+            let stale_url = if moved.contains(&update.content_id) {
+                info!(
+                    "moved content {} to {}",
+                    update.content_id, update.to_storage_url
+                );
+                &update.from_storage_url
+            } else if !current_urls.contains(&update.to_storage_url) {
+                &update.to_storage_url
+            } else {
+                continue;
+            };
+            self.delete_blob(stale_url).await;
+        }
+        Ok(())
+    }
+
+    async fn remove_blobs(&self, content_list: &[&ContentMetadata]) -> Result<()> {
+        let updates = content_list
+            .iter()
+            .map(|c| indexify_coordinator::ContentStorageUpdate {
+                content_id: c.id.clone(),
+                from_storage_url: c.storage_url.clone(),
+                to_storage_url: "".to_string(),
+            })
+            .collect_vec();
+        let removed = self.update_content_storage(updates.clone()).await?;
+        for update in updates {
+            if removed.contains(&update.content_id) {
+                info!("removed blob of intermediate content {}", update.content_id);
+                self.delete_blob(&update.from_storage_url).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn update_content_storage(
+        &self,
+        updates: Vec<indexify_coordinator::ContentStorageUpdate>,
+    ) -> Result<HashSet<String>> {
+        if updates.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let updated = self
+            .coordinator_client
+            .get()
+            .await?
+            .update_content_storage(indexify_coordinator::UpdateContentStorageRequest { updates })
+            .await?
+            .into_inner()
+            .content_list;
+        Ok(updated.into_iter().collect())
+    }
+
+    async fn is_content_tree_indexed(&self, content_id: &str) -> Result<bool> {
+        let status = self
+            .coordinator_client
+            .get()
+            .await?
+            .get_content_tree_status(indexify_coordinator::GetContentTreeStatusRequest {
+                content_id: content_id.to_string(),
+            })
+            .await?
+            .into_inner();
+        Ok(status.indexed)
+    }
+
+    async fn delete_blob(&self, url: &str) {
+        if let Err(e) = self.blob_storage.delete(url).await {
+            error!("unable to delete blob {}: {}", url, e);
+        }
+    }
+
+    pub fn start_sweep_loop(self: &Arc<Self>, mut shutdown_rx: watch::Receiver<bool>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(manager.config.sweep_interval_secs));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        manager.sweep().await;
+                    }
+                    _ = shutdown_rx.changed() => {
+                        info!("shutting down lifecycle sweep loop");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Intermediate content, extracted content which other content was extracted
+/// from, grouped by the id of the root of its content tree.
+fn intermediate_content(
+    content_list: &[ContentMetadata],
+) -> HashMap<String, Vec<&ContentMetadata>> {
+    let parent_ids: HashSet<&str> = content_list
+        .iter()
+        .map(|c| c.parent_id.as_str())
+        .filter(|parent_id| !parent_id.is_empty())
+        .collect();
+    let mut intermediate: HashMap<String, Vec<&ContentMetadata>> = HashMap::new();
+    for content in content_list {
+        if content.parent_id.is_empty() ||
+            content.root_content_id.is_empty() ||
+            !parent_ids.contains(content.id.as_str())
+        {
+            continue;
+        }
+        intermediate
+            .entry(content.root_content_id.clone())
+            .or_default()
+            .push(content);
+    }
+    intermediate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(id: &str, parent_id: &str, root_content_id: &str) -> ContentMetadata {
+        ContentMetadata {
+            id: id.to_string(),
+            parent_id: parent_id.to_string(),
+            root_content_id: root_content_id.to_string(),
+            storage_url: format!("file://blobs/{}", id),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_intermediate_content() {
+        // root -> text -> chunks, and a second root whose text was not chunked
+        let content_list = vec![
+            content("root", "", ""),
+            content("text", "root", "root"),
+            content("chunk1", "text", "root"),
+            content("chunk2", "text", "root"),
+            content("root2", "", ""),
+            content("text2", "root2", "root2"),
+        ];
+        let intermediate = intermediate_content(&content_list);
+        assert_eq!(intermediate.len(), 1);
+        let ids = intermediate["root"]
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect_vec();
+        assert_eq!(ids, vec!["text"]);
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: LifecycleConfig = serde_yaml::from_str(
+            "policies:\n  - namespace: default\n    move_to_cold_after_days: 30\n",
+        )
+        .unwrap();
+        assert_eq!(config.sweep_interval_secs, default_sweep_interval_secs());
+        assert_eq!(config.policies[0].move_to_cold_after_days, Some(30));
+        assert!(!config.policies[0].delete_intermediate_content);
+    }
+}
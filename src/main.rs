@@ -23,22 +23,28 @@ pub mod task_allocator;
 
 mod api;
 mod api_utils;
+mod batch_ingest;
 mod blob_storage;
 mod caching;
 mod cmd;
 mod coordinator;
 mod coordinator_client;
 mod data_manager;
+mod data_plane_service;
+mod embedding_batcher;
+mod extraction_graph_planner;
 mod extractor_router;
 mod forwardable_coordinator;
 mod garbage_collector;
 mod grpc_helper;
 mod ingest_extracted_content;
+mod lifecycle;
 mod metadata_storage;
 mod scheduler;
 mod test_util;
 mod tls;
 mod tonic_streamer;
+mod upload_sessions;
 mod utils;
 mod vector_index;
 mod vectordbs;
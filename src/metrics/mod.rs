@@ -367,6 +367,37 @@ pub mod server {
     }
 }
 
+pub mod cache {
+    use opentelemetry::metrics::Counter;
+
+    #[derive(Debug)]
+    pub struct Metrics {
+        pub hits: Counter<u64>,
+        pub misses: Counter<u64>,
+    }
+
+    impl Default for Metrics {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl Metrics {
+        pub fn new() -> Metrics {
+            let meter = opentelemetry::global::meter("indexify-server");
+            let hits = meter
+                .u64_counter("indexify.server.cache_hits")
+                .with_description("Number of lookups served from the server cache")
+                .init();
+            let misses = meter
+                .u64_counter("indexify.server.cache_misses")
+                .with_description("Number of lookups not found in the server cache")
+                .init();
+            Metrics { hits, misses }
+        }
+    }
+}
+
 use opentelemetry::{
     metrics::{Counter, Histogram},
     KeyValue,
@@ -1,17 +1,17 @@
 use std::{
-    collections::{hash_map::DefaultHasher, HashMap},
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
     hash::{Hash, Hasher},
 };
 
 use anyhow::{anyhow, Ok, Result};
 use indexify_internal_api as internal_api;
 use indexify_internal_api::StateChange;
-use internal_api::OutputSchema;
 use tracing::info;
 
 use crate::{
     state::SharedState,
     task_allocator::{planner::plan::TaskAllocationPlan, TaskAllocator},
+    utils::timestamp_secs,
 };
 
 pub struct Scheduler {
@@ -76,7 +76,7 @@ impl Scheduler {
         for policy in policies {
             let extractor = self.shared_state.extractor_with_name(&policy.extractor)?;
             for (name, schema) in extractor.outputs {
-                if let OutputSchema::Embedding(_) = schema {
+                if schema.embedding_schema().is_some() {
                     let table_name = policy.output_table_mapping.get(&name).unwrap();
                     tables.push(table_name.clone());
                 }
@@ -111,11 +111,77 @@ impl Scheduler {
                 }
                 tasks
             }
+            internal_api::ChangeType::ReextractOutdatedContent => {
+                self.create_reextraction_tasks(&state_change.object_id)
+                    .await?
+            }
             _ => Vec::new(),
         };
         Ok(tasks)
     }
 
+    /// Creates tasks to extract the content an extraction policy was applied
+    /// to with older versions of its extractor again, with the version it is
+    /// registered with now. Content which already has a task for that version
+    /// is left alone.
+    async fn create_reextraction_tasks(
+        &self,
+        extraction_policy_id: &str,
+    ) -> Result<Vec<internal_api::Task>> {
+        let extraction_policy = self
+            .shared_state
+            .get_extraction_policy(extraction_policy_id)?;
+        let extractor = self
+            .shared_state
+            .extractor_with_name(&extraction_policy.extractor)?;
+        let policy_tasks = self
+            .shared_state
+            .list_tasks(
+                &extraction_policy.namespace,
+                Some(extraction_policy.id.clone()),
+            )
+            .await?;
+        let mut outdated_content = HashSet::new();
+        let mut current_content = HashSet::new();
+        for task in policy_tasks {
+            let content_id = task.content_metadata.id.id;
+            if task.extractor_version == extractor.version {
+                current_content.insert(content_id);
+            } else if task.outcome == internal_api::TaskOutcome::Success {
+                outdated_content.insert(content_id);
+            }
+        }
+        let content_ids = outdated_content
+            .difference(&current_content)
+            .cloned()
+            .collect();
+        let content_list = self
+            .shared_state
+            .get_content_metadata_batch(content_ids)
+            .await?;
+        let tables = self
+            .tables_for_policies(&[extraction_policy.clone()])
+            .await?;
+        let mut tasks = Vec::new();
+        for content in content_list {
+            if content.tombstoned {
+                continue;
+            }
+            let task = self
+                .create_task(&extraction_policy.id, &content, &tables)
+                .await?;
+            tasks.push(task);
+        }
+        info!(
+            "re-extracting {} content with extractor {} version {} for policy {}",
+            tasks.len(),
+            extractor.name,
+            extractor.version,
+            extraction_policy.name
+        );
+        Ok(tasks)
+    }
+
     pub async fn allocate_tasks(
         &self,
         tasks: Vec<internal_api::Task>,
@@ -191,8 +257,13 @@ impl Scheduler {
         extraction_policy.name.hash(&mut hasher);
         extraction_policy.namespace.hash(&mut hasher);
         content.id.hash(&mut hasher);
+        // Tasks of newer extractor versions get ids of their own, so that
+        // content can be extracted again after an extractor is upgraded.
+        if !extractor.version.is_empty() {
+            extractor.version.hash(&mut hasher);
+        }
         let id = format!("{:x}", hasher.finish());
-        let task = internal_api::Task {
+        let mut task = internal_api::Task {
             id,
             extractor: extraction_policy.extractor.clone(),
             extraction_graph_name: extraction_policy.graph_name.clone(),
@@ -203,7 +274,23 @@ impl Scheduler {
             input_params: extraction_policy.input_params.clone(),
             outcome: internal_api::TaskOutcome::Unknown,
             index_tables: index_tables.to_vec(),
+            extractor_version: extractor.version.clone(),
+            assigned_at: 0,
+            failure_reason: None,
+            history: Vec::new(),
         };
+        let created_at = timestamp_secs();
+        task.record_event(internal_api::TaskEventType::Created, created_at, None, None);
+        if let Some(max_size) = extraction_policy.limits.max_content_size_bytes {
+            if content.size_bytes > max_size {
+                task.outcome = internal_api::TaskOutcome::Failed;
+                task.failure_reason = Some(format!(
+                    "content size of {} bytes exceeds the limit of {} bytes of extraction policy {}",
+                    content.size_bytes, max_size, extraction_policy.name
+                ));
+                task.record_outcome(created_at, None);
+            }
+        }
         info!("created task: {:?}", task);
         Ok(task)
     }
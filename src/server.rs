@@ -4,7 +4,7 @@ use anyhow::{anyhow, Result};
 use axum::{
     body::Body,
     extract::{DefaultBodyLimit, Multipart, Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     routing::{delete, get, post, put},
     Extension,
@@ -15,11 +15,16 @@ use axum_otel_metrics::HttpMetricsLayerBuilder;
 use axum_server::{tls_rustls::RustlsConfig, Handle};
 use axum_tracing_opentelemetry::middleware::OtelAxumLayer;
 use axum_typed_websockets::WebSocketUpgrade;
-use hyper::{header::CONTENT_TYPE, Method};
+use bytes::Bytes;
+use hyper::{
+    header::{ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_NONE_MATCH, IF_RANGE, RANGE},
+    Method,
+};
 use indexify_internal_api as internal_api;
 use indexify_proto::indexify_coordinator::{
     self,
     GcTaskAcknowledgement,
+    GetTaskRequest,
     ListStateChangesRequest,
     ListTasksRequest,
 };
@@ -39,21 +44,30 @@ use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
     api::{self, *},
-    blob_storage::{BlobStorage, ContentReader},
+    api_utils,
+    batch_ingest::BatchIngestState,
+    blob_storage::{
+        presign::{self, PresignedUpload, PresignedUrls, SignedBlobParams},
+        BlobStorage,
+        ContentReader,
+    },
     caching::caches_extension::Caches,
     coordinator_client::CoordinatorClient,
     data_manager::DataManager,
+    data_plane_service::DataPlaneServer,
     extractor_router::ExtractorRouter,
     ingest_extracted_content::IngestExtractedContentState,
+    lifecycle::LifecycleManager,
     metadata_storage::{self, MetadataReaderTS, MetadataStorageTS},
     metrics,
     server_config::ServerConfig,
     tls::build_mtls_config,
+    upload_sessions::UploadSessionManager,
     vector_index::VectorIndexManager,
     vectordbs,
 };
 
-const DEFAULT_SEARCH_LIMIT: u64 = 5;
+pub const DEFAULT_SEARCH_LIMIT: u64 = 5;
 
 #[derive(RustEmbed)]
 #[folder = "ui/build"]
@@ -64,8 +78,11 @@ pub struct NamespaceEndpointState {
     pub data_manager: Arc<DataManager>,
     pub coordinator_client: Arc<CoordinatorClient>,
     pub content_reader: Arc<ContentReader>,
+    pub upload_sessions: Arc<UploadSessionManager>,
+    pub presigned_urls: Arc<PresignedUrls>,
     pub registry: Arc<prometheus::Registry>,
     pub metrics: Arc<metrics::server::Metrics>,
+    pub extractor_router: Arc<ExtractorRouter>,
 }
 
 #[derive(OpenApi)]
@@ -76,14 +93,29 @@ pub struct NamespaceEndpointState {
             get_namespace,
             add_texts,
             list_indexes,
+            migrate_index,
+            get_index_migration,
+            reextract_outdated_content,
+            plan_extraction_graph,
             index_search,
             list_extractors,
             list_executors,
             list_content,
             get_content_metadata,
             upload_file,
+            create_upload_session,
+            get_upload_session,
+            upload_part,
+            complete_upload_session,
+            abort_upload_session,
+            create_presigned_upload,
+            finalize_presigned_upload,
+            presigned_download,
             list_tasks,
-            extract_content
+            get_task,
+            cancel_tasks,
+            extract_content,
+            extract_content_batch
         ),
         components(
             schemas(CreateNamespace, CreateNamespaceResponse, IndexDistance,
@@ -91,8 +123,18 @@ pub struct NamespaceEndpointState {
                 DocumentFragment, ListIndexesResponse, ExtractorOutputSchema, Index, SearchRequest, ListNamespacesResponse, ListExtractorsResponse
             , ExtractorDescription, DataNamespace, ExtractionPolicy, ExtractionPolicyRequest, ExtractionPolicyResponse, Executor,
             MetadataResponse, ExtractedMetadata, ListExecutorsResponse, EmbeddingSchema, ExtractResponse, ExtractRequest,
+            ExtractBatchRequest, ExtractBatchResponse,
             Content, Feature, FeatureType, GetContentMetadataResponse, ListTasksResponse, internal_api::Task, internal_api::TaskOutcome,
             internal_api::Content, internal_api::ContentMetadata, ListContentResponse, GetNamespaceResponse, ExtractionPolicyResponse,
+            CreateUploadSessionRequest, UploadSessionResponse, UploadedPart,
+            PresignedUploadRequest, PresignedUploadResponse, FinalizeUploadRequest, PresignedDownloadResponse,
+            vectordbs::IndexOptions, vectordbs::IndexType, vectordbs::Quantization,
+            MigrateIndexRequest, IndexMigrationStatus, IndexMigrationState, internal_api::EmbeddingKind,
+            ReextractOutdatedContentResponse, PlanExtractionGraphRequest, PlanExtractionGraphResponse,
+            PlannedExtraction, internal_api::RoutingCondition, internal_api::ConditionOperator,
+            internal_api::ExtractionPolicyLimits, internal_api::ExecutorResources,
+            internal_api::ExecutorAffinity, CancelTasksRequest, CancelTasksResponse,
+            GetTaskResponse, internal_api::TaskEvent, internal_api::TaskEventType,
         )
         ),
         tags(
@@ -129,17 +171,31 @@ impl Server {
         }
         let vector_db = vectordbs::create_vectordb(self.config.index_config.clone()).await?;
         let coordinator_client = Arc::new(CoordinatorClient::new(Arc::clone(&self.config)));
+        let blob_storage = Arc::new(
+            BlobStorage::new_with_config(self.config.blob_storage.clone())
+                .map_err(|e| anyhow!("unable to create blob storage {}", e))?,
+        );
+        let content_reader = Arc::new(blob_storage.content_reader());
+        let caches = Caches::new(self.config.cache.clone());
+        let extractor_router = Arc::new(
+            ExtractorRouter::new(coordinator_client.clone(), caches.clone())
+                .map_err(|e| anyhow!("unable to create extractor router {}", e))?,
+        );
         let vector_index_manager = Arc::new(
-            VectorIndexManager::new(coordinator_client.clone(), vector_db.clone())
-                .map_err(|e| anyhow!("unable to create vector index {}", e))?,
+            VectorIndexManager::new(
+                coordinator_client.clone(),
+                self.config.index_config.clone(),
+                self.config.vector_stores.clone(),
+                vector_db.clone(),
+                content_reader.clone(),
+                extractor_router.clone(),
+            )
+            .map_err(|e| anyhow!("unable to create vector index {}", e))?,
         );
         let metadata_index_manager: MetadataStorageTS =
             metadata_storage::from_config(&self.config.metadata_storage)?;
         let metadata_reader: MetadataReaderTS =
             metadata_storage::from_config_reader(&self.config.metadata_storage)?;
-        let blob_storage = Arc::new(BlobStorage::new_with_config(
-            self.config.blob_storage.clone(),
-        ));
         let data_manager = Arc::new(DataManager::new(
             vector_index_manager,
             metadata_index_manager,
@@ -155,16 +211,53 @@ impl Server {
             data_manager.clone(),
             shutdown_rx.clone(),
         );
+        let upload_sessions = Arc::new(UploadSessionManager::new(
+            blob_storage.clone(),
+            Duration::from_secs(self.config.upload_session_ttl_secs),
+        ));
+        match upload_sessions.recover().await {
+            Ok(recovered) => info!("recovered {} upload sessions", recovered),
+            Err(e) => tracing::error!("unable to recover upload sessions: {}", e),
+        }
+        upload_sessions.start_expiry_loop(shutdown_rx.clone());
+        if !self.config.lifecycle.policies.is_empty() {
+            let lifecycle = Arc::new(LifecycleManager::new(
+                self.config.lifecycle.clone(),
+                blob_storage.clone(),
+                coordinator_client.clone(),
+            )?);
+            lifecycle.start_sweep_loop(shutdown_rx.clone());
+        }
         let namespace_endpoint_state = NamespaceEndpointState {
             data_manager: data_manager.clone(),
             coordinator_client: coordinator_client.clone(),
-            content_reader: Arc::new(ContentReader::new()),
+            content_reader,
+            upload_sessions,
+            presigned_urls: Arc::new(PresignedUrls::new(
+                self.config.blob_storage.clone(),
+                self.config.presigned_urls.clone(),
+            )),
             registry,
             metrics: Arc::new(crate::metrics::server::Metrics::new()),
+            extractor_router,
         };
-        let caches = Caches::new(self.config.cache.clone());
+        let data_plane = DataPlaneServer::new(
+            data_manager.clone(),
+            namespace_endpoint_state.metrics.clone(),
+        );
+        let grpc_addr = self.config.grpc_addr_sock()?;
+        let grpc_tls_config = self.config.tls.clone();
+        let grpc_shutdown_rx = shutdown_rx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = data_plane
+                .serve(grpc_addr, grpc_tls_config, grpc_shutdown_rx)
+                .await
+            {
+                tracing::error!("data plane grpc server failed: {}", e);
+            }
+        });
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
+            .allow_methods([Method::GET, Method::POST, Method::PUT])
             .allow_origin(Any)
             .allow_headers([CONTENT_TYPE]);
 
@@ -179,10 +272,26 @@ impl Server {
                 "/namespaces/:namespace/extraction_graphs",
                 post(create_extraction_graph).with_state(namespace_endpoint_state.clone()),
             )
+            .route(
+                "/namespaces/:namespace/extraction_graphs/:extraction_graph/dry_run",
+                post(plan_extraction_graph).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/namespaces/:namespace/extraction_graphs/:extraction_graph/extraction_policies/:extraction_policy/reextract",
+                post(reextract_outdated_content).with_state(namespace_endpoint_state.clone()),
+            )
             .route(
                 "/namespaces/:namespace/indexes",
                 get(list_indexes).with_state(namespace_endpoint_state.clone()),
             )
+            .route(
+                "/namespaces/:namespace/indexes/:index/migrate",
+                post(migrate_index).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/namespaces/:namespace/indexes/:index/migration",
+                get(get_index_migration).with_state(namespace_endpoint_state.clone()),
+            )
             .route(
                 "/namespaces/:namespace/add_texts",
                 post(add_texts).with_state(namespace_endpoint_state.clone()),
@@ -215,6 +324,48 @@ impl Server {
                 "/namespaces/:namespace/upload_file",
                 post(upload_file).with_state(namespace_endpoint_state.clone()),
             )
+            .route(
+                "/namespaces/:namespace/batch",
+                post(batch_ingest).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/namespaces/:namespace/uploads",
+                post(create_upload_session).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/namespaces/:namespace/uploads/:upload_id",
+                get(get_upload_session).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/namespaces/:namespace/uploads/:upload_id",
+                delete(abort_upload_session).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/namespaces/:namespace/uploads/:upload_id/parts/:part_number",
+                put(upload_part).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/namespaces/:namespace/uploads/:upload_id/complete",
+                post(complete_upload_session).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/namespaces/:namespace/presigned_uploads",
+                post(create_presigned_upload).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/namespaces/:namespace/presigned_uploads/finalize",
+                post(finalize_presigned_upload).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/namespaces/:namespace/content/:content_id/presigned_download",
+                get(presigned_download).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/blobs",
+                get(get_signed_blob)
+                    .put(put_signed_blob)
+                    .with_state(namespace_endpoint_state.clone()),
+            )
             .route(
                 "/namespaces/:namespace/content/:content_id",
                 put(update_content)
@@ -269,10 +420,22 @@ impl Server {
                 "/namespaces/:namespace/tasks",
                 get(list_tasks).with_state(namespace_endpoint_state.clone()),
             )
+            .route(
+                "/namespaces/:namespace/tasks/:task_id",
+                get(get_task).with_state(namespace_endpoint_state.clone()),
+            )
+            .route(
+                "/namespaces/:namespace/tasks/cancel",
+                post(cancel_tasks).with_state(namespace_endpoint_state.clone()),
+            )
             .route(
                 "/extractors/extract",
                 post(extract_content).with_state(namespace_endpoint_state.clone()),
             )
+            .route(
+                "/extractors/extract_batch",
+                post(extract_content_batch).with_state(namespace_endpoint_state.clone()),
+            )
             .route(
                 "/task_assignments",
                 get(list_task_assignments).with_state(namespace_endpoint_state.clone()),
@@ -565,6 +728,54 @@ async fn create_extraction_graph(
     Ok(Json(ExtractionGraphResponse { indexes }))
 }
 
+#[tracing::instrument]
+#[utoipa::path(
+    post,
+    path = "/namespaces/{namespace}/extraction_graphs/{extraction_graph}/dry_run",
+    request_body = PlanExtractionGraphRequest,
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Extraction policies which would be applied to the content", body = PlanExtractionGraphResponse),
+        (status = INTERNAL_SERVER_ERROR, description = "Unable to plan the extraction graph")
+    ),
+)]
+#[axum::debug_handler]
+async fn plan_extraction_graph(
+    Path((namespace, extraction_graph)): Path<(String, String)>,
+    State(state): State<NamespaceEndpointState>,
+    Json(request): Json<PlanExtractionGraphRequest>,
+) -> Result<Json<PlanExtractionGraphResponse>, IndexifyAPIError> {
+    let extractions = state
+        .data_manager
+        .plan_extraction_graph(&namespace, &extraction_graph, request)
+        .await
+        .map_err(IndexifyAPIError::internal_error)?;
+    Ok(Json(PlanExtractionGraphResponse { extractions }))
+}
+
+#[tracing::instrument]
+#[utoipa::path(
+    post,
+    path = "/namespaces/{namespace}/extraction_graphs/{extraction_graph}/extraction_policies/{extraction_policy}/reextract",
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Content extracted with older extractor versions scheduled for extraction", body = ReextractOutdatedContentResponse),
+        (status = INTERNAL_SERVER_ERROR, description = "Unable to re-extract the content of the extraction policy")
+    ),
+)]
+#[axum::debug_handler]
+async fn reextract_outdated_content(
+    Path((namespace, extraction_graph, extraction_policy)): Path<(String, String, String)>,
+    State(state): State<NamespaceEndpointState>,
+) -> Result<Json<ReextractOutdatedContentResponse>, IndexifyAPIError> {
+    state
+        .data_manager
+        .reextract_outdated_content(&namespace, &extraction_graph, &extraction_policy)
+        .await
+        .map_err(IndexifyAPIError::internal_error)?;
+    Ok(Json(ReextractOutdatedContentResponse {}))
+}
+
 #[tracing::instrument(skip(state, payload))]
 #[utoipa::path(
     post,
@@ -590,18 +801,14 @@ async fn add_texts(
     }
     for document in &payload.documents {
         if let Some(id) = &document.id {
-            if !DataManager::is_hex_string(id) {
-                return Err(IndexifyAPIError::new(
-                    StatusCode::BAD_REQUEST,
-                    &format!("Invalid ID format: {}, ID must be a hex string", id),
-                ));
-            }
-            let retrieved_content = state
+            DataManager::validate_content_id(id)
+                .map_err(|e| IndexifyAPIError::new(StatusCode::BAD_REQUEST, &e.to_string()))?;
+            if state
                 .data_manager
-                .get_content_metadata(&namespace, vec![id.clone()])
+                .content_exists(&namespace, id)
                 .await
-                .map_err(IndexifyAPIError::internal_error)?;
-            if !retrieved_content.is_empty() {
+                .map_err(IndexifyAPIError::internal_error)?
+            {
                 return Err(IndexifyAPIError::new(
                     StatusCode::BAD_REQUEST,
                     &format!("content with the provided id {} already exists", id),
@@ -612,17 +819,8 @@ async fn add_texts(
 
     let content = payload
         .documents
-        .iter()
-        .map(|d| api::ContentWithId {
-            id: d.id.clone().unwrap_or_else(DataManager::make_id),
-            content: api::Content {
-                content_type: mime::TEXT_PLAIN.to_string(),
-                bytes: d.text.as_bytes().to_vec(),
-                labels: d.labels.clone(),
-                features: vec![],
-            },
-            extraction_graph_names: payload.extraction_graph_names.clone(),
-        })
+        .into_iter()
+        .map(|d| DataManager::text_content(d, &payload.extraction_graph_names))
         .collect();
     state
         .data_manager
@@ -652,12 +850,14 @@ async fn ingest_remote_file(
             &payload.mime_type,
             payload.labels,
             &payload.extraction_graph_names,
+            payload.mode,
+            payload.sha256,
         )
         .await
         .map_err(|e| {
             IndexifyAPIError::new(
                 StatusCode::BAD_REQUEST,
-                &format!("failed to add text: {}", e),
+                &format!("failed to ingest remote file: {}", e),
             )
         })?;
     Ok(Json(IngestRemoteFileResponse { content_id }))
@@ -793,6 +993,7 @@ async fn get_content_tree_metadata(
 async fn download_content(
     Path((namespace, content_id)): Path<(String, String)>,
     State(state): State<NamespaceEndpointState>,
+    headers: HeaderMap,
 ) -> Result<Response<Body>, IndexifyAPIError> {
     let content_list = state
         .data_manager
@@ -804,17 +1005,84 @@ async fn download_content(
         .ok_or(anyhow!("content not found"))
         .map_err(|e| IndexifyAPIError::not_found(&e.to_string()))?
         .clone();
+
+    let etag =
+        (!content_metadata.hash.is_empty()).then(|| format!("\"{}\"", content_metadata.hash));
+
+    if let (Some(etag), Some(if_none_match)) = (
+        &etag,
+        headers.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()),
+    ) {
+        if api_utils::etag_matches(if_none_match, etag) {
+            return Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(ETAG, etag)
+                .body(Body::empty())
+                .map_err(|e| {
+                    IndexifyAPIError::new(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string())
+                });
+        }
+    }
+
+    // Ranges are only honoured when the size of the content is known, and when
+    // an If-Range validator is present it must match the current ETag.
+    let size = content_metadata.size;
+    let if_range_matches = match headers.get(IF_RANGE).and_then(|v| v.to_str().ok()) {
+        Some(if_range) => etag
+            .as_ref()
+            .map_or(false, |etag| api_utils::if_range_matches(if_range, etag)),
+        None => true,
+    };
+    let range = match headers.get(RANGE).and_then(|v| v.to_str().ok()) {
+        Some(range) if size > 0 && if_range_matches => {
+            match api_utils::parse_range_header(range, size) {
+                Ok(range) => range,
+                Err(_) => {
+                    return Response::builder()
+                        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                        .header(CONTENT_RANGE, format!("bytes */{}", size))
+                        .body(Body::empty())
+                        .map_err(|e| {
+                            IndexifyAPIError::new(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string())
+                        });
+                }
+            }
+        }
+        _ => None,
+    };
+
     let mut resp_builder =
         Response::builder().header("Content-Type", content_metadata.mime_type.clone());
-    if content_metadata.size > 0 {
-        resp_builder = resp_builder.header("Content-Length", content_metadata.size);
+    if let Some(etag) = &etag {
+        resp_builder = resp_builder.header(ETAG, etag);
+    }
+    if size > 0 {
+        resp_builder = resp_builder.header(ACCEPT_RANGES, "bytes");
+    }
+    match &range {
+        Some(range) => {
+            resp_builder = resp_builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", range.start, range.end - 1, size),
+                )
+                .header("Content-Length", range.end - range.start);
+        }
+        None if size > 0 => {
+            resp_builder = resp_builder.header("Content-Length", size);
+        }
+        None => {}
     }
     resp_builder
         .body(Body::from_stream(async_stream::stream! {
             let storage_url = &content_metadata.storage_url.clone();
             let content_reader = state.content_reader.clone();
-            let reader = content_reader.get(storage_url);
-            let mut content_stream = reader.get(storage_url);
+            let reader = content_reader.get(storage_url, content_metadata.encoded);
+            let mut content_stream = match range {
+                Some(range) => reader.get_range(storage_url, range),
+                None => reader.get(storage_url),
+            };
             while let Some(buf)  = content_stream.next().await {
                 yield buf;
             }
@@ -862,20 +1130,16 @@ async fn upload_file(
         .collect();
 
     let id = params.id.clone().unwrap_or_else(DataManager::make_id);
-    if !DataManager::is_hex_string(&id) {
-        return Err(IndexifyAPIError::new(
-            StatusCode::BAD_REQUEST,
-            "Invalid ID format, ID must be a hex string",
-        ));
-    }
+    DataManager::validate_content_id(&id)
+        .map_err(|e| IndexifyAPIError::new(StatusCode::BAD_REQUEST, &e.to_string()))?;
 
     //  check if the id already exists for content metadata
-    let retrieved_content = state
+    if state
         .data_manager
-        .get_content_metadata(&namespace, vec![id.clone()])
+        .content_exists(&namespace, &id)
         .await
-        .map_err(IndexifyAPIError::internal_error)?;
-    if !retrieved_content.is_empty() {
+        .map_err(IndexifyAPIError::internal_error)?
+    {
         return Err(IndexifyAPIError::new(
             StatusCode::BAD_REQUEST,
             "content with the provided id already exists",
@@ -885,18 +1149,7 @@ async fn upload_file(
     while let Some(field) = files.next_field().await.unwrap() {
         if let Some(name) = field.file_name() {
             info!("user provided file name = {:?}", name);
-            let ext = std::path::Path::new(&name)
-                .extension()
-                .unwrap_or_default()
-                .to_str()
-                .unwrap_or_default();
-            let name = nanoid::nanoid!(16);
-            let name = if !ext.is_empty() {
-                format!("{}.{}", name, ext)
-            } else {
-                name
-            };
-            let content_mime = mime_guess::from_ext(ext).first_or_octet_stream();
+            let (name, content_mime) = DataManager::upload_file_name(name);
             info!("writing to blob store, file name = {:?}", name);
 
             let stream = field.map(|res| res.map_err(|err| anyhow::anyhow!(err)));
@@ -952,6 +1205,483 @@ async fn upload_file(
     ))
 }
 
+/// Ingests a batch of texts, remote files and files. The first part of the
+/// multipart body is the `request` field with the JSON `BatchIngestRequest`,
+/// followed by the uploaded files in the fields named by their items.
+#[axum::debug_handler]
+async fn batch_ingest(
+    Path(namespace): Path<String>,
+    State(state): State<NamespaceEndpointState>,
+    mut parts: Multipart,
+) -> Result<Json<BatchIngestResponse>, IndexifyAPIError> {
+    let field = parts
+        .next_field()
+        .await
+        .map_err(|e| IndexifyAPIError::new(StatusCode::BAD_REQUEST, &e.to_string()))?
+        .ok_or_else(|| IndexifyAPIError::new(StatusCode::BAD_REQUEST, "request is missing"))?;
+    if field.name() != Some("request") {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "the first part must be the request",
+        ));
+    }
+    let bytes = field
+        .bytes()
+        .await
+        .map_err(|e| IndexifyAPIError::new(StatusCode::BAD_REQUEST, &e.to_string()))?;
+    let request: BatchIngestRequest = serde_json::from_slice(&bytes).map_err(|e| {
+        IndexifyAPIError::new(StatusCode::BAD_REQUEST, &format!("invalid request: {}", e))
+    })?;
+    if request.extraction_graph_names.is_empty() {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "extraction_graph_names must not be empty",
+        ));
+    }
+
+    let mut batch = BatchIngestState::new(state.data_manager.clone(), &namespace, request)
+        .await
+        .map_err(IndexifyAPIError::internal_error)?;
+    while let Some(field) = parts
+        .next_field()
+        .await
+        .map_err(|e| IndexifyAPIError::new(StatusCode::BAD_REQUEST, &e.to_string()))?
+    {
+        let Some(index) = field.name().and_then(|name| batch.file_item(name)) else {
+            continue;
+        };
+        let file_name = field.file_name().map(|name| name.to_string());
+        let stream = field.map(|res| res.map_err(|err| anyhow::anyhow!(err)));
+        batch.write_file(index, file_name.as_deref(), stream).await;
+    }
+    batch.write_items().await;
+    Ok(Json(batch.finish().await))
+}
+
+#[tracing::instrument]
+#[utoipa::path(
+    post,
+    path = "/namespaces/{namespace}/uploads",
+    request_body = CreateUploadSessionRequest,
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Starts a resumable upload", body = UploadSessionResponse),
+        (status = BAD_REQUEST, description = "Unable to start upload")
+    ),
+)]
+#[axum::debug_handler]
+async fn create_upload_session(
+    Path(namespace): Path<String>,
+    State(state): State<NamespaceEndpointState>,
+    Json(payload): Json<CreateUploadSessionRequest>,
+) -> Result<Json<UploadSessionResponse>, IndexifyAPIError> {
+    if payload.extraction_graph_names.is_empty() {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "extraction_graph_names must not be empty",
+        ));
+    }
+    let id = payload.id.clone().unwrap_or_else(DataManager::make_id);
+    if !DataManager::is_hex_string(&id) {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "Invalid ID format, ID must be a hex string",
+        ));
+    }
+    let retrieved_content = state
+        .data_manager
+        .get_content_metadata(&namespace, vec![id.clone()])
+        .await
+        .map_err(IndexifyAPIError::internal_error)?;
+    if !retrieved_content.is_empty() {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "content with the provided id already exists",
+        ));
+    }
+
+    let (name, guessed_mime) = DataManager::upload_file_name(&payload.file_name);
+    let content_mime = payload
+        .mime_type
+        .clone()
+        .unwrap_or_else(|| guessed_mime.to_string());
+    let session = state
+        .upload_sessions
+        .create(
+            &namespace,
+            &id,
+            &name,
+            &content_mime,
+            payload.labels,
+            payload.extraction_graph_names,
+        )
+        .await
+        .map_err(|e| {
+            IndexifyAPIError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("failed to start upload: {}", e),
+            )
+        })?;
+    Ok(Json((&session).into()))
+}
+
+#[tracing::instrument]
+#[utoipa::path(
+    get,
+    path = "/namespaces/{namespace}/uploads/{upload_id}",
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Parts received so far for a resumable upload", body = UploadSessionResponse),
+        (status = 404, description = "Upload not found")
+    ),
+)]
+#[axum::debug_handler]
+async fn get_upload_session(
+    Path((namespace, upload_id)): Path<(String, String)>,
+    State(state): State<NamespaceEndpointState>,
+) -> Result<Json<UploadSessionResponse>, IndexifyAPIError> {
+    let session = state
+        .upload_sessions
+        .get(&namespace, &upload_id)
+        .await
+        .map_err(|e| IndexifyAPIError::not_found(&e.to_string()))?;
+    Ok(Json((&session).into()))
+}
+
+#[tracing::instrument(skip(state, body))]
+#[utoipa::path(
+    put,
+    path = "/namespaces/{namespace}/uploads/{upload_id}/parts/{part_number}",
+    request_body(content_type = "application/octet-stream", content = Vec<u8>),
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Part stored", body = UploadSessionResponse),
+        (status = BAD_REQUEST, description = "Unable to store part")
+    ),
+)]
+#[axum::debug_handler]
+async fn upload_part(
+    Path((namespace, upload_id, part_number)): Path<(String, String, usize)>,
+    State(state): State<NamespaceEndpointState>,
+    body: Bytes,
+) -> Result<Json<UploadSessionResponse>, IndexifyAPIError> {
+    let size_bytes = body.len() as u64;
+    let session = state
+        .upload_sessions
+        .put_part(&namespace, &upload_id, part_number, body)
+        .await
+        .map_err(|e| {
+            IndexifyAPIError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("failed to upload part: {}", e),
+            )
+        })?;
+    state
+        .metrics
+        .node_content_bytes_uploaded
+        .add(size_bytes, &[]);
+    Ok(Json((&session).into()))
+}
+
+#[tracing::instrument]
+#[utoipa::path(
+    post,
+    path = "/namespaces/{namespace}/uploads/{upload_id}/complete",
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Upload assembled and content created"),
+        (status = BAD_REQUEST, description = "Unable to complete upload")
+    ),
+)]
+#[axum::debug_handler]
+async fn complete_upload_session(
+    Path((namespace, upload_id)): Path<(String, String)>,
+    State(state): State<NamespaceEndpointState>,
+) -> Result<Json<UploadFileResponse>, IndexifyAPIError> {
+    let content_metadata = state
+        .upload_sessions
+        .complete(&namespace, &upload_id)
+        .await
+        .map_err(|e| {
+            IndexifyAPIError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("failed to complete upload: {}", e),
+            )
+        })?;
+    let content_id = content_metadata.id.clone();
+    state
+        .data_manager
+        .create_content_metadata(content_metadata)
+        .await
+        .map_err(|e| {
+            IndexifyAPIError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("failed to create content for file: {}", e),
+            )
+        })?;
+    state.metrics.node_content_uploads.add(1, &[]);
+    Ok(Json(UploadFileResponse { content_id }))
+}
+
+#[tracing::instrument]
+#[utoipa::path(
+    delete,
+    path = "/namespaces/{namespace}/uploads/{upload_id}",
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Upload aborted and its parts removed"),
+        (status = 404, description = "Upload not found")
+    ),
+)]
+#[axum::debug_handler]
+async fn abort_upload_session(
+    Path((namespace, upload_id)): Path<(String, String)>,
+    State(state): State<NamespaceEndpointState>,
+) -> Result<Json<()>, IndexifyAPIError> {
+    state
+        .upload_sessions
+        .abort(&namespace, &upload_id)
+        .await
+        .map_err(|e| IndexifyAPIError::not_found(&e.to_string()))?;
+    Ok(Json(()))
+}
+
+#[tracing::instrument]
+#[utoipa::path(
+    post,
+    path = "/namespaces/{namespace}/presigned_uploads",
+    request_body = PresignedUploadRequest,
+    tag = "indexify",
+    responses(
+        (status = 200, description = "URL to upload a file directly to blob storage", body = PresignedUploadResponse),
+        (status = BAD_REQUEST, description = "Unable to presign upload")
+    ),
+)]
+#[axum::debug_handler]
+async fn create_presigned_upload(
+    Path(namespace): Path<String>,
+    State(state): State<NamespaceEndpointState>,
+    Json(payload): Json<PresignedUploadRequest>,
+) -> Result<Json<PresignedUploadResponse>, IndexifyAPIError> {
+    let (key, _) = DataManager::upload_file_name(&payload.file_name);
+    if !presign::is_valid_upload_key(&key) {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid file extension",
+        ));
+    }
+    let content_id = payload.id.clone().unwrap_or_else(DataManager::make_id);
+    if !DataManager::is_hex_string(&content_id) {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "Invalid ID format, ID must be a hex string",
+        ));
+    }
+    let retrieved_content = state
+        .data_manager
+        .get_content_metadata(&namespace, vec![content_id.clone()])
+        .await
+        .map_err(IndexifyAPIError::internal_error)?;
+    if !retrieved_content.is_empty() {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "content with the provided id already exists",
+        ));
+    }
+    let expires_in = presign::expiry(payload.expires_in_secs);
+    let upload_url = state
+        .presigned_urls
+        .upload_url(&key, expires_in)
+        .await
+        .map_err(|e| IndexifyAPIError::new(StatusCode::BAD_REQUEST, &e.to_string()))?;
+    let expires_at = presign::expires_at(expires_in).map_err(IndexifyAPIError::internal_error)?;
+    let upload = PresignedUpload {
+        namespace,
+        content_id,
+        token: nanoid::nanoid!(32),
+        expires_at,
+        sha256: None,
+    };
+    state
+        .data_manager
+        .register_presigned_upload(&key, &upload)
+        .await
+        .map_err(IndexifyAPIError::internal_error)?;
+    Ok(Json(PresignedUploadResponse {
+        key,
+        content_id: upload.content_id,
+        upload_token: upload.token,
+        upload_url,
+        expires_at,
+    }))
+}
+
+#[tracing::instrument]
+#[utoipa::path(
+    post,
+    path = "/namespaces/{namespace}/presigned_uploads/finalize",
+    request_body = FinalizeUploadRequest,
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Uploaded file registered as content"),
+        (status = BAD_REQUEST, description = "Unable to finalize upload")
+    ),
+)]
+#[axum::debug_handler]
+async fn finalize_presigned_upload(
+    Path(namespace): Path<String>,
+    State(state): State<NamespaceEndpointState>,
+    Json(payload): Json<FinalizeUploadRequest>,
+) -> Result<Json<UploadFileResponse>, IndexifyAPIError> {
+    if payload.extraction_graph_names.is_empty() {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "extraction_graph_names must not be empty",
+        ));
+    }
+    if !presign::is_valid_upload_key(&payload.key) {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid upload key",
+        ));
+    }
+    let content_mime = payload.mime_type.clone().unwrap_or_else(|| {
+        mime_guess::from_path(&payload.key)
+            .first_or_octet_stream()
+            .to_string()
+    });
+    let content_metadata = state
+        .data_manager
+        .finalize_upload(
+            &namespace,
+            &payload.key,
+            &payload.upload_token,
+            payload.sha256.as_deref(),
+            &content_mime,
+            payload.labels,
+            payload.extraction_graph_names,
+        )
+        .await
+        .map_err(|e| {
+            IndexifyAPIError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("failed to finalize upload: {}", e),
+            )
+        })?;
+    state.metrics.node_content_uploads.add(1, &[]);
+    state
+        .metrics
+        .node_content_bytes_uploaded
+        .add(content_metadata.size_bytes, &[]);
+    Ok(Json(UploadFileResponse {
+        content_id: content_metadata.id,
+    }))
+}
+
+#[tracing::instrument]
+#[utoipa::path(
+    get,
+    path = "/namespaces/{namespace}/content/{content_id}/presigned_download",
+    params(PresignedDownloadParams),
+    tag = "indexify",
+    responses(
+        (status = 200, description = "URL to download content directly from blob storage", body = PresignedDownloadResponse),
+        (status = 404, description = "Content not found")
+    ),
+)]
+#[axum::debug_handler]
+async fn presigned_download(
+    Path((namespace, content_id)): Path<(String, String)>,
+    State(state): State<NamespaceEndpointState>,
+    Query(params): Query<PresignedDownloadParams>,
+) -> Result<Json<PresignedDownloadResponse>, IndexifyAPIError> {
+    let content_list = state
+        .data_manager
+        .get_content_metadata(&namespace, vec![content_id.clone()])
+        .await
+        .map_err(IndexifyAPIError::internal_error)?;
+    let content_metadata = content_list
+        .first()
+        .ok_or_else(|| IndexifyAPIError::not_found(&format!("content {} not found", content_id)))?;
+    // Signed urls serve the blob as it is stored
+    if content_metadata.encoded {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "content is stored compressed or encrypted, download it through the server",
+        ));
+    }
+    let expires_in = presign::expiry(params.expires_in_secs);
+    let download_url = state
+        .presigned_urls
+        .download_url(&content_metadata.storage_url, expires_in)
+        .await
+        .map_err(|e| IndexifyAPIError::new(StatusCode::BAD_REQUEST, &e.to_string()))?;
+    let expires_at = presign::expires_at(expires_in).map_err(IndexifyAPIError::internal_error)?;
+    Ok(Json(PresignedDownloadResponse {
+        download_url,
+        expires_at,
+    }))
+}
+
+/// Serves the bytes of a blob on disk to the holder of a signed url.
+#[tracing::instrument(skip(state))]
+async fn get_signed_blob(
+    State(state): State<NamespaceEndpointState>,
+    Query(params): Query<SignedBlobParams>,
+) -> Result<Response<Body>, IndexifyAPIError> {
+    state
+        .presigned_urls
+        .verify("GET", &params)
+        .map_err(|e| IndexifyAPIError::new(StatusCode::FORBIDDEN, &e.to_string()))?;
+    if !params.key.starts_with("file://") {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "only blobs on disk are served",
+        ));
+    }
+    let content_type = mime_guess::from_path(&params.key)
+        .first_or_octet_stream()
+        .to_string();
+    Response::builder()
+        .header(CONTENT_TYPE, content_type)
+        .body(Body::from_stream(async_stream::stream! {
+            let reader = state.content_reader.get(&params.key, false);
+            let mut content_stream = reader.get(&params.key);
+            while let Some(buf) = content_stream.next().await {
+                yield buf;
+            }
+        }))
+        .map_err(|e| IndexifyAPIError::new(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+}
+
+/// Stores the body as the blob a signed upload url was issued for.
+#[tracing::instrument(skip(state, body))]
+async fn put_signed_blob(
+    State(state): State<NamespaceEndpointState>,
+    Query(params): Query<SignedBlobParams>,
+    body: Body,
+) -> Result<(), IndexifyAPIError> {
+    state
+        .presigned_urls
+        .verify("PUT", &params)
+        .map_err(|e| IndexifyAPIError::new(StatusCode::FORBIDDEN, &e.to_string()))?;
+    if !presign::is_valid_upload_key(&params.key) {
+        return Err(IndexifyAPIError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid upload key",
+        ));
+    }
+    let stream = body
+        .into_data_stream()
+        .map(|res| res.map_err(|e| anyhow!("unable to read request body: {}", e)));
+    state
+        .data_manager
+        .write_presigned_blob(&params.key, stream)
+        .await
+        .map_err(|e| IndexifyAPIError::new(StatusCode::BAD_REQUEST, &e.to_string()))?;
+    Ok(())
+}
+
 #[tracing::instrument]
 #[utoipa::path(
     put,
@@ -988,18 +1718,7 @@ async fn update_content(
             ))?
             .to_string();
         info!("user provided file name = {:?}", name);
-        let ext = std::path::Path::new(&name)
-            .extension()
-            .unwrap_or_default()
-            .to_str()
-            .unwrap_or_default();
-        let name = nanoid::nanoid!(16);
-        let name = if !ext.is_empty() {
-            format!("{}.{}", name, ext)
-        } else {
-            name
-        };
-        let content_mime = mime_guess::from_ext(ext).first_or_octet_stream();
+        let (name, content_mime) = DataManager::upload_file_name(&name);
         info!("writing to blob store, file name = {:?}", name);
 
         let stream = file.map(|res| res.map_err(|err| anyhow::anyhow!(err)));
@@ -1156,6 +1875,13 @@ async fn list_tasks(
         .list_tasks(ListTasksRequest {
             namespace: namespace.clone(),
             extraction_policy: query.extraction_policy.unwrap_or("".to_string()),
+            outcome: query
+                .outcome
+                .map(|o| indexify_coordinator::TaskOutcome::from(o) as i32),
+            executor_id: query.executor_id.unwrap_or_default(),
+            content_id: query.content_id.unwrap_or_default(),
+            created_after: query.created_after.unwrap_or_default(),
+            created_before: query.created_before.unwrap_or_default(),
         })
         .await
         .map_err(|e| IndexifyAPIError::new(StatusCode::INTERNAL_SERVER_ERROR, e.message()))?
@@ -1169,6 +1895,61 @@ async fn list_tasks(
     Ok(Json(ListTasksResponse { tasks }))
 }
 
+#[tracing::instrument]
+#[utoipa::path(
+    get,
+    path = "/namespaces/{namespace}/tasks/{task_id}",
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Gets a task with its execution history", body = GetTaskResponse),
+        (status = NOT_FOUND, description = "Task not found")
+    ),
+)]
+#[axum::debug_handler]
+async fn get_task(
+    Path((namespace, task_id)): Path<(String, String)>,
+    State(state): State<NamespaceEndpointState>,
+) -> Result<Json<GetTaskResponse>, IndexifyAPIError> {
+    let task = state
+        .coordinator_client
+        .get()
+        .await
+        .map_err(IndexifyAPIError::internal_error)?
+        .get_task(GetTaskRequest { task_id })
+        .await
+        .map_err(|e| IndexifyAPIError::new(StatusCode::NOT_FOUND, e.message()))?
+        .into_inner()
+        .task
+        .filter(|task| task.namespace == namespace)
+        .ok_or_else(|| IndexifyAPIError::new(StatusCode::NOT_FOUND, "task not found"))?;
+    Ok(Json(GetTaskResponse { task: task.into() }))
+}
+
+#[tracing::instrument]
+#[utoipa::path(
+    post,
+    path = "/namespaces/{namespace}/tasks/cancel",
+    request_body = CancelTasksRequest,
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Cancels unfinished tasks", body = CancelTasksResponse),
+        (status = INTERNAL_SERVER_ERROR, description = "Unable to cancel tasks")
+    ),
+)]
+#[axum::debug_handler]
+async fn cancel_tasks(
+    Path(namespace): Path<String>,
+    State(state): State<NamespaceEndpointState>,
+    Json(request): Json<CancelTasksRequest>,
+) -> Result<Json<CancelTasksResponse>, IndexifyAPIError> {
+    let cancelled_task_ids = state
+        .data_manager
+        .cancel_tasks(&namespace, request)
+        .await
+        .map_err(IndexifyAPIError::internal_error)?;
+    Ok(Json(CancelTasksResponse { cancelled_task_ids }))
+}
+
 #[utoipa::path(
     post,
     path = "/extractors/extract",
@@ -1184,9 +1965,8 @@ async fn extract_content(
     State(namespace_endpoint): State<NamespaceEndpointState>,
     Json(request): Json<ExtractRequest>,
 ) -> Result<Json<ExtractResponse>, IndexifyAPIError> {
-    let extractor_router = ExtractorRouter::new(namespace_endpoint.coordinator_client.clone())
-        .map_err(|e| IndexifyAPIError::new(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let response = extractor_router
+    let response = namespace_endpoint
+        .extractor_router
         .extract_content(&request.name, request.content, request.input_params)
         .await
         .map_err(|e| {
@@ -1198,6 +1978,36 @@ async fn extract_content(
     Ok(Json(response.into()))
 }
 
+#[utoipa::path(
+    post,
+    path = "/extractors/extract_batch",
+    request_body = ExtractBatchRequest,
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Extract many contents with a single call to an extractor", body = ExtractBatchResponse),
+        (status = INTERNAL_SERVER_ERROR, description = "Unable to extract content")
+    ),
+)]
+#[axum::debug_handler]
+async fn extract_content_batch(
+    State(namespace_endpoint): State<NamespaceEndpointState>,
+    Json(request): Json<ExtractBatchRequest>,
+) -> Result<Json<ExtractBatchResponse>, IndexifyAPIError> {
+    let responses = namespace_endpoint
+        .extractor_router
+        .extract_content_batch(&request.name, request.content_list, request.input_params)
+        .await
+        .map_err(|e| {
+            IndexifyAPIError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("failed to extract content: {}", e),
+            )
+        })?;
+    Ok(Json(ExtractBatchResponse {
+        outcomes: responses.into_iter().map(ExtractResponse::from).collect(),
+    }))
+}
+
 #[axum::debug_handler]
 async fn list_task_assignments(
     State(namespace_endpoint): State<NamespaceEndpointState>,
@@ -1235,6 +2045,55 @@ async fn list_indexes(
     Ok(Json(ListIndexesResponse { indexes }))
 }
 
+#[tracing::instrument]
+#[utoipa::path(
+    post,
+    path = "/namespaces/{namespace}/indexes/{index}/migrate",
+    tag = "indexify",
+    request_body = MigrateIndexRequest,
+    responses(
+        (status = 202, description = "Migration of the index started", body = IndexMigrationStatus),
+        (status = BAD_REQUEST, description = "Unable to migrate the index")
+    ),
+)]
+#[axum::debug_handler]
+async fn migrate_index(
+    Path((namespace, index)): Path<(String, String)>,
+    State(state): State<NamespaceEndpointState>,
+    Json(request): Json<MigrateIndexRequest>,
+) -> Result<(StatusCode, Json<IndexMigrationStatus>), IndexifyAPIError> {
+    let status = state
+        .data_manager
+        .migrate_index(&namespace, &index, &request.vector_store)
+        .await
+        .map_err(|e| IndexifyAPIError::new(StatusCode::BAD_REQUEST, &e.to_string()))?;
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+#[tracing::instrument]
+#[utoipa::path(
+    get,
+    path = "/namespaces/{namespace}/indexes/{index}/migration",
+    tag = "indexify",
+    responses(
+        (status = 200, description = "Status of the migration of the index", body = IndexMigrationStatus),
+        (status = 404, description = "Index was not migrated")
+    ),
+)]
+#[axum::debug_handler]
+async fn get_index_migration(
+    Path((namespace, index)): Path<(String, String)>,
+    State(state): State<NamespaceEndpointState>,
+) -> Result<Json<IndexMigrationStatus>, IndexifyAPIError> {
+    let status = state
+        .data_manager
+        .index_migration_status(&namespace, &index)
+        .await
+        .map_err(IndexifyAPIError::internal_error)?
+        .ok_or_else(|| IndexifyAPIError::not_found("index was not migrated"))?;
+    Ok(Json(status))
+}
+
 #[utoipa::path(
     post,
     path = "/namespace/{namespace}/search",
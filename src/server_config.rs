@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     fmt,
     fs,
     net::{AddrParseError, IpAddr, Ipv4Addr, SocketAddr},
@@ -12,7 +13,10 @@ use figment::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::blob_storage::{BlobStorageConfig, DiskStorageConfig};
+use crate::{
+    blob_storage::{presign::PresignedUrlConfig, BlobStorageConfig, DiskStorageConfig},
+    lifecycle::LifecycleConfig,
+};
 
 fn default_executor_port() -> u64 {
     0
@@ -23,6 +27,10 @@ fn default_server_port() -> u64 {
     8900
 }
 
+fn default_server_grpc_port() -> u64 {
+    8910
+}
+
 fn default_coordinator_port() -> u64 {
     8950
 }
@@ -35,6 +43,10 @@ fn default_raft_port() -> u64 {
     8970
 }
 
+fn default_upload_session_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, strum::Display)]
 #[strum(serialize_all = "kebab-case")]
 pub enum MetadataStoreKind {
@@ -58,13 +70,14 @@ impl Default for MetadataStoreConfig {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, strum::Display)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, strum::Display, strum::EnumString)]
 #[strum(serialize_all = "kebab-case")]
 pub enum IndexStoreKind {
     Qdrant,
     PgVector,
-    //OpenSearchKnn,
+    OpenSearchKnn,
     Lancedb,
+    Embedded,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,6 +93,20 @@ impl Default for LancedbConfig {
         }
     }
 }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct EmbeddedVectorDbConfig {
+    pub path: String,
+}
+
+impl Default for EmbeddedVectorDbConfig {
+    fn default() -> Self {
+        Self {
+            path: "/tmp/indexify/vectors".into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct QdrantConfig {
@@ -141,6 +168,7 @@ pub struct VectorIndexConfig {
     pub pg_vector_config: Option<PgVectorConfig>,
     pub open_search_basic: Option<OpenSearchBasicConfig>,
     pub lancedb_config: Option<LancedbConfig>,
+    pub embedded_config: Option<EmbeddedVectorDbConfig>,
 }
 
 impl Default for VectorIndexConfig {
@@ -151,6 +179,7 @@ impl Default for VectorIndexConfig {
             pg_vector_config: Some(PgVectorConfig::default()),
             open_search_basic: Some(OpenSearchBasicConfig::default()),
             lancedb_config: Some(LancedbConfig::default()),
+            embedded_config: Some(EmbeddedVectorDbConfig::default()),
         }
     }
 }
@@ -348,10 +377,14 @@ pub struct MemoryConfig {
     pub max_size: usize,
 }
 
+fn default_cache_ttl_secs() -> u64 {
+    3600
+}
+
 /// ServerCacheConfig is a struct that contains the configuration for the
 /// server-side cache. It is a wrapper around configuration for the different
 /// cache backends supported by the server.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerCacheConfig {
     /// backend is the cache backend to use. See ServerCacheBackend for the
     /// different options.
@@ -364,6 +397,22 @@ pub struct ServerCacheConfig {
     /// memory is the configuration for the memory cache backend. It is required
     /// if the backend is set to Memory.
     pub memory: Option<MemoryConfig>,
+
+    /// ttl_secs is how long entries are kept in the cache. Entries never
+    /// expire when it is set to 0.
+    #[serde(default = "default_cache_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl Default for ServerCacheConfig {
+    fn default() -> Self {
+        Self {
+            backend: ServerCacheBackend::default(),
+            redis: None,
+            memory: None,
+            ttl_secs: default_cache_ttl_secs(),
+        }
+    }
 }
 
 /// SledConfig is a struct that contains the configuration for the sled
@@ -412,12 +461,20 @@ pub struct ServerConfig {
     pub listen_if: NetworkAddress,
     #[serde(default = "default_server_port")]
     pub listen_port: u64,
+    /// grpc_port is the port of the gRPC data plane, which mirrors the HTTP
+    /// ingestion and search endpoints.
+    #[serde(default = "default_server_grpc_port")]
+    pub grpc_port: u64,
     #[serde(default = "default_coordinator_port")]
     pub coordinator_port: u64,
     #[serde(default = "default_coordinator_http_port")]
     pub coordinator_http_port: u64,
     pub raft_port: u64,
     pub index_config: VectorIndexConfig,
+    /// vector_stores are additional vector stores, by name, which indexes can
+    /// be created in instead of the one of index_config.
+    #[serde(default)]
+    pub vector_stores: HashMap<String, VectorIndexConfig>,
     pub metadata_storage: MetadataStoreConfig,
     #[serde(default)]
     pub coordinator_addr: String,
@@ -432,6 +489,20 @@ pub struct ServerConfig {
     pub cache: ServerCacheConfig,
     #[serde(default)]
     pub state_store: StateStoreConfig,
+    /// upload_session_ttl_secs is how long a resumable upload can go without
+    /// receiving a part before it is aborted. Sessions are recorded in the
+    /// blob store, so they survive restarts; on S3 the bucket should also
+    /// have a lifecycle rule aborting incomplete multipart uploads.
+    #[serde(default = "default_upload_session_ttl_secs")]
+    pub upload_session_ttl_secs: u64,
+    /// presigned_urls configures the signed URLs issued for blobs stored on
+    /// disk. It is required to use presigned urls with disk blob storage.
+    #[serde(default)]
+    pub presigned_urls: Option<PresignedUrlConfig>,
+    /// lifecycle configures the per namespace policies which move the blobs
+    /// of content to the cold tier of blob storage, or delete them.
+    #[serde(default)]
+    pub lifecycle: LifecycleConfig,
 }
 
 impl Default for ServerConfig {
@@ -439,10 +510,12 @@ impl Default for ServerConfig {
         Self {
             listen_if: "0.0.0.0".into(),
             listen_port: default_server_port(),
+            grpc_port: default_server_grpc_port(),
             coordinator_port: default_coordinator_port(),
             coordinator_http_port: default_coordinator_http_port(),
             raft_port: default_raft_port(),
             index_config: VectorIndexConfig::default(),
+            vector_stores: HashMap::new(),
             metadata_storage: MetadataStoreConfig::default(),
             coordinator_addr: format!("localhost:{}", default_coordinator_port()),
             blob_storage: BlobStorageConfig {
@@ -450,6 +523,10 @@ impl Default for ServerConfig {
                 disk: Some(DiskStorageConfig {
                     path: "/tmp/indexify/blob_storage".to_string(),
                 }),
+                gcs: None,
+                azure: None,
+                encoding: None,
+                cold: None,
             },
             tls: None,
             coordinator_tls: None,
@@ -458,6 +535,9 @@ impl Default for ServerConfig {
             node_id: 0,
             cache: ServerCacheConfig::default(),
             state_store: StateStoreConfig::default(),
+            upload_session_ttl_secs: default_upload_session_ttl_secs(),
+            presigned_urls: None,
+            lifecycle: LifecycleConfig::default(),
         }
     }
 }
@@ -487,6 +567,13 @@ impl ServerConfig {
         })
     }
 
+    pub fn grpc_addr_sock(&self) -> Result<SocketAddr> {
+        let addr = format!("{}:{}", self.listen_if, self.grpc_port);
+        addr.parse().map_err(|e: AddrParseError| {
+            anyhow!("Failed to parse listen address {} :{}", addr, e.to_string())
+        })
+    }
+
     pub fn coordinator_lis_addr_sock(&self) -> Result<SocketAddr> {
         let addr = format!("{}:{}", self.listen_if, self.coordinator_port);
         addr.parse().map_err(|e: AddrParseError| {
@@ -50,7 +50,10 @@ impl ForwardableRaft {
             return self.network.forward(&leader_address.addr, request).await;
         }
 
-        self.raft.client_write(request).await?;
+        let reply = self.raft.client_write(request).await?;
+        if let Some(reason) = reply.data.value {
+            return Err(anyhow::anyhow!(reason));
+        }
         let response = StateMachineUpdateResponse {
             handled_by: self.id,
         };
@@ -161,10 +161,14 @@ impl RaftGrpcServer {
         let response = StateMachineUpdateResponse {
             handled_by: self.id,
         };
-        self.raft
+        let reply = self
+            .raft
             .client_write(request)
             .await
             .map_err(|e| GrpcHelper::internal_err(e.to_string()))?;
+        if let Some(reason) = reply.data.value {
+            return Err(GrpcHelper::internal_err(reason));
+        }
         GrpcHelper::ok_response(response)
     }
 }
@@ -31,7 +31,13 @@ use openraft::{
 };
 use serde::Serialize;
 use store::{
-    requests::{RequestPayload, StateChangeProcessed, StateMachineUpdateRequest},
+    requests::{
+        ContentStorageUpdate,
+        IndexStoreUpdate,
+        RequestPayload,
+        StateChangeProcessed,
+        StateMachineUpdateRequest,
+    },
     ExecutorId,
     ExecutorIdRef,
     Response,
@@ -52,7 +58,7 @@ use self::{
     store::{StateMachineColumns, StateMachineStore},
 };
 use crate::{
-    coordinator_filters::matches_mime_type,
+    coordinator_filters::{matches_mime_type, matches_routing_conditions},
     garbage_collector::GarbageCollector,
     metrics::{
         coordinator::Metrics,
@@ -411,6 +417,9 @@ impl App {
             }) {
                 continue;
             }
+            if !matches_routing_conditions(&extraction_policy.conditions, &content_metadata) {
+                continue;
+            }
             let extractor = self.extractor_with_name(&extraction_policy.extractor)?;
             if !matches_mime_type(&extractor.input_mime_types, &content_metadata.content_type) {
                 info!(
@@ -518,6 +527,7 @@ impl App {
         let req = StateMachineUpdateRequest {
             payload: RequestPayload::RemoveExecutor {
                 executor_id: executor_id.to_string(),
+                removed_at: timestamp_secs(),
             },
             new_state_changes: vec![StateChange::new(
                 executor_id.to_string(),
@@ -612,6 +622,49 @@ impl App {
         Ok(())
     }
 
+    /// Marks the tasks which haven't finished yet cancelled. Cancelled tasks
+    /// which were assigned are listed by `cancelled_tasks_for_executor` until
+    /// `release_cancelled_tasks` is called for them.
+    pub async fn cancel_tasks(&self, task_ids: Vec<TaskId>) -> Result<()> {
+        let req = StateMachineUpdateRequest {
+            payload: RequestPayload::CancelTasks {
+                task_ids,
+                cancelled_at: timestamp_secs(),
+                update_time: SystemTime::now(),
+            },
+            new_state_changes: vec![],
+            state_changes_processed: vec![],
+        };
+        let _resp = self.forwardable_raft.client_write(req).await?;
+        Ok(())
+    }
+
+    /// Ids of the cancelled tasks the executor wasn't told about yet.
+    pub async fn cancelled_tasks_for_executor(&self, executor_id: &str) -> Result<Vec<TaskId>> {
+        let task_ids = self
+            .state_machine
+            .get_from_cf::<HashSet<TaskId>, _>(StateMachineColumns::CancelledTasks, executor_id)?
+            .unwrap_or_default();
+        Ok(task_ids.into_iter().collect())
+    }
+
+    pub async fn release_cancelled_tasks(
+        &self,
+        executor_id: &str,
+        task_ids: Vec<TaskId>,
+    ) -> Result<()> {
+        let req = StateMachineUpdateRequest {
+            payload: RequestPayload::ReleaseCancelledTasks {
+                executor_id: executor_id.to_string(),
+                task_ids,
+            },
+            new_state_changes: vec![],
+            state_changes_processed: vec![],
+        };
+        let _resp = self.forwardable_raft.client_write(req).await?;
+        Ok(())
+    }
+
     pub async fn create_gc_tasks(
         &self,
         gc_tasks: Vec<indexify_internal_api::GarbageCollectionTask>,
@@ -715,6 +768,24 @@ impl App {
         addr: &str,
         executor_id: &str,
         extractors: Vec<internal_api::ExtractorDescription>,
+    ) -> Result<String> {
+        self.register_executor_with_capabilities(
+            addr,
+            executor_id,
+            extractors,
+            internal_api::ExecutorResources::default(),
+            HashMap::new(),
+        )
+        .await
+    }
+
+    pub async fn register_executor_with_capabilities(
+        &self,
+        addr: &str,
+        executor_id: &str,
+        extractors: Vec<internal_api::ExtractorDescription>,
+        resources: internal_api::ExecutorResources,
+        labels: HashMap<String, String>,
     ) -> Result<String> {
         let state_change = StateChange::new(
             executor_id.to_string(),
@@ -727,6 +798,8 @@ impl App {
                 executor_id: executor_id.to_string(),
                 extractors,
                 ts_secs: timestamp_secs(),
+                resources,
+                labels,
             },
             new_state_changes: vec![state_change.clone()],
             state_changes_processed: vec![],
@@ -779,7 +852,10 @@ impl App {
         state_change_id: &str,
     ) -> Result<()> {
         let req = StateMachineUpdateRequest {
-            payload: RequestPayload::AssignTask { assignments },
+            payload: RequestPayload::AssignTask {
+                assignments,
+                assigned_at: timestamp_secs(),
+            },
             new_state_changes: vec![],
             state_changes_processed: vec![StateChangeProcessed {
                 state_change_id: state_change_id.to_string(),
@@ -793,6 +869,24 @@ impl App {
     pub async fn create_content_batch(
         &self,
         content_metadata: Vec<internal_api::ContentMetadata>,
+    ) -> Result<()> {
+        self.write_content_batch(content_metadata, false).await
+    }
+
+    /// Creates content like `create_content_batch`, but fails instead of
+    /// updating content whose id exists already. The state machine rejects the
+    /// content as well when content with its id is created concurrently.
+    pub async fn create_new_content_batch(
+        &self,
+        content_metadata: Vec<internal_api::ContentMetadata>,
+    ) -> Result<()> {
+        self.write_content_batch(content_metadata, true).await
+    }
+
+    async fn write_content_batch(
+        &self,
+        content_metadata: Vec<internal_api::ContentMetadata>,
+        create_only: bool,
     ) -> Result<()> {
         if content_metadata.is_empty() {
             return Ok(());
@@ -830,6 +924,12 @@ impl App {
             if let Some(existing_content) =
                 existing_content_map.get(&incoming_content.id.id.to_string())
             {
+                if create_only {
+                    return Err(anyhow!(
+                        "content with id {} already exists",
+                        incoming_content.id.id
+                    ));
+                }
                 if existing_content.hash != incoming_content.hash {
                     //  this is a root node that is being updated
                     let mut incoming_content = incoming_content.clone();
@@ -1046,6 +1146,76 @@ impl App {
         Ok(())
     }
 
+    /// Merges metadata into the metadata extracted along with content,
+    /// replacing the values of keys it has already.
+    pub async fn merge_extracted_metadata(
+        &self,
+        content_id: ContentMetadataId,
+        metadata: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        if metadata.is_empty() {
+            return Ok(());
+        }
+        let req = StateMachineUpdateRequest {
+            payload: RequestPayload::MergeExtractedMetadata {
+                content_id,
+                metadata,
+            },
+            new_state_changes: vec![],
+            state_changes_processed: vec![],
+        };
+        self.forwardable_raft
+            .client_write(req)
+            .await
+            .map_err(|e| anyhow!("unable to merge extracted metadata: {}", e.to_string()))?;
+        Ok(())
+    }
+
+    /// Moves the blobs of content to new storage urls. Content which is no
+    /// longer stored at the url an update moves it from is left as is.
+    pub async fn update_content_storage(&self, updates: Vec<ContentStorageUpdate>) -> Result<()> {
+        if updates.is_empty() {
+            return Ok(());
+        }
+        let req = StateMachineUpdateRequest {
+            payload: RequestPayload::UpdateContentStorage { updates },
+            new_state_changes: vec![],
+            state_changes_processed: vec![],
+        };
+        self.forwardable_raft
+            .client_write(req)
+            .await
+            .map_err(|e| anyhow!("unable to update content storage: {}", e.to_string()))?;
+        Ok(())
+    }
+
+    /// Whether every task created for the content tree rooted at `content_id`
+    /// has finished, and no content of the tree is still waiting for its
+    /// tasks to be created.
+    pub async fn is_content_tree_indexed(&self, content_id: &str) -> Result<bool> {
+        let content_tree = self.get_content_tree_metadata(content_id)?;
+        if content_tree.is_empty() {
+            return Err(anyhow!("content with id: {} not found", content_id));
+        }
+        let pending_content: HashSet<String> = self
+            .unprocessed_state_change_events()
+            .await?
+            .into_iter()
+            .map(|state_change| match state_change.change_type {
+                internal_api::ChangeType::TaskCompleted { content_id } => content_id.to_string(),
+                _ => state_change.object_id,
+            })
+            .collect();
+        for content in &content_tree {
+            if pending_content.contains(&content.id.to_string()) ||
+                !self.are_content_tasks_completed(&content.id).await
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     /// Get content based on id's without version. Will fetch the latest version
     /// for each one
     pub async fn get_content_metadata_batch(
@@ -1184,11 +1354,14 @@ impl App {
     }
 
     pub async fn get_index(&self, id: &str) -> Result<internal_api::Index> {
-        let index = self
-            .state_machine
-            .get_from_cf::<internal_api::Index, _>(StateMachineColumns::IndexTable, id)?
-            .ok_or_else(|| anyhow!("Index with id {} not found", id))?;
-        Ok(index)
+        self.find_index(id)?
+            .ok_or_else(|| anyhow!("Index with id {} not found", id))
+    }
+
+    /// Returns the index with the id, if there is one.
+    pub fn find_index(&self, id: &str) -> Result<Option<internal_api::Index>> {
+        self.state_machine
+            .get_from_cf::<internal_api::Index, _>(StateMachineColumns::IndexTable, id)
     }
 
     pub async fn set_indexes(&self, indexes: Vec<internal_api::Index>) -> Result<()> {
@@ -1201,6 +1374,61 @@ impl App {
         Ok(())
     }
 
+    /// Records the extractor version the indexes of an extraction policy are
+    /// upgraded to, and schedules the content extracted with older versions to
+    /// be extracted again.
+    pub async fn reextract_outdated_content(
+        &self,
+        extraction_policy_id: &str,
+        indexes: Vec<internal_api::Index>,
+    ) -> Result<()> {
+        let state_change = StateChange::new(
+            extraction_policy_id.to_string(),
+            internal_api::ChangeType::ReextractOutdatedContent,
+            timestamp_secs(),
+        );
+        let req = StateMachineUpdateRequest {
+            payload: RequestPayload::SetIndex { indexes },
+            new_state_changes: vec![state_change],
+            state_changes_processed: vec![],
+        };
+        self.forwardable_raft.client_write(req).await?;
+        Ok(())
+    }
+
+    /// Moves an index to other vector stores and returns it as stored after
+    /// the update, which is left unapplied if the index was moved since.
+    pub async fn update_index_store(
+        &self,
+        update: IndexStoreUpdate,
+    ) -> Result<internal_api::Index> {
+        let index_id = update.index_id.clone();
+        let req = StateMachineUpdateRequest {
+            payload: RequestPayload::UpdateIndexStore { update },
+            new_state_changes: vec![],
+            state_changes_processed: vec![],
+        };
+        self.forwardable_raft
+            .client_write(req)
+            .await
+            .map_err(|e| anyhow!("unable to update index store: {}", e.to_string()))?;
+        self.get_index(&index_id).await
+    }
+
+    /// Indexes which are not in the default vector store or are being
+    /// migrated to another one.
+    pub async fn list_index_stores(&self) -> Result<Vec<internal_api::Index>> {
+        let indexes = self
+            .state_machine
+            .get_all_rows_from_cf::<internal_api::Index>(StateMachineColumns::IndexTable)
+            .await?
+            .into_iter()
+            .map(|(_, index)| index)
+            .filter(|index| !index.vector_store.is_empty() || !index.migrating_to.is_empty())
+            .collect();
+        Ok(indexes)
+    }
+
     pub async fn list_state_changes(&self) -> Result<Vec<StateChange>> {
         let state_changes = self
             .state_machine
@@ -1255,6 +1483,16 @@ impl App {
             .await
     }
 
+    /// Ids of the unfinished tasks of the content.
+    pub async fn pending_tasks_for_content(
+        &self,
+        content_id: &ContentMetadataId,
+    ) -> HashSet<TaskId> {
+        self.state_machine
+            .get_pending_tasks_for_content(content_id)
+            .await
+    }
+
     pub async fn insert_executor_running_task_count(&mut self, executor_id: &str, task_count: u64) {
         self.state_machine
             .insert_executor_running_task_count(executor_id, task_count)
@@ -1365,6 +1603,7 @@ mod tests {
         ContentMetadata,
         ContentMetadataId,
         ExtractionGraph,
+        Index,
         StructuredDataSchema,
         TaskOutcome,
     };
@@ -1372,7 +1611,12 @@ mod tests {
     use crate::{
         state::{
             store::{
-                requests::{RequestPayload, StateMachineUpdateRequest},
+                requests::{
+                    ContentStorageUpdate,
+                    IndexStoreUpdate,
+                    RequestPayload,
+                    StateMachineUpdateRequest,
+                },
                 ExecutorId,
                 TaskId,
             },
@@ -1384,6 +1628,7 @@ mod tests {
             test_mock_content_metadata,
         },
         test_utils::RaftTestCluster,
+        utils::timestamp_secs,
     };
 
     /// Test to determine that a task that was created can be read back
@@ -1416,6 +1661,45 @@ mod tests {
         Ok(())
     }
 
+    /// Test that content with the id of existing content is not created again
+    #[tokio::test]
+    async fn test_create_existing_content() -> Result<(), anyhow::Error> {
+        let cluster = RaftTestCluster::new(1, None).await?;
+        cluster.initialize(Duration::from_secs(2)).await?;
+        let node = cluster.get_raft_node(0)?;
+
+        let content = ContentMetadata {
+            id: ContentMetadataId::new("content_id"),
+            ..Default::default()
+        };
+        node.create_new_content_batch(vec![content.clone()]).await?;
+        let state_changes = node.unprocessed_state_change_events().await?.len();
+
+        let res = node.create_new_content_batch(vec![content.clone()]).await;
+        assert!(res.unwrap_err().to_string().contains("already exists"));
+
+        //  the state machine rejects content created concurrently, which passed the
+        // check above
+        let request = StateMachineUpdateRequest {
+            payload: RequestPayload::CreateContent {
+                content_metadata: vec![content.clone()],
+            },
+            new_state_changes: vec![indexify_internal_api::StateChange::new(
+                content.id.to_string(),
+                indexify_internal_api::ChangeType::NewContent,
+                timestamp_secs(),
+            )],
+            state_changes_processed: vec![],
+        };
+        let res = node.forwardable_raft.client_write(request).await;
+        assert!(res.unwrap_err().to_string().contains("already exists"));
+        assert_eq!(
+            node.unprocessed_state_change_events().await?.len(),
+            state_changes
+        );
+        Ok(())
+    }
+
     /// Test to determine that assigning a task to an executor works correctly
     #[tokio::test]
     // #[tracing_test::traced_test]
@@ -1460,7 +1744,10 @@ mod tests {
                 .into_iter()
                 .collect();
         let request = StateMachineUpdateRequest {
-            payload: RequestPayload::AssignTask { assignments },
+            payload: RequestPayload::AssignTask {
+                assignments,
+                assigned_at: timestamp_secs(),
+            },
             new_state_changes: vec![],
             state_changes_processed: vec![],
         };
@@ -1524,7 +1811,10 @@ mod tests {
                 .into_iter()
                 .collect();
         let request = StateMachineUpdateRequest {
-            payload: RequestPayload::AssignTask { assignments },
+            payload: RequestPayload::AssignTask {
+                assignments,
+                assigned_at: timestamp_secs(),
+            },
             new_state_changes: vec![],
             state_changes_processed: vec![],
         };
@@ -1660,6 +1950,137 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_update_content_storage() -> Result<(), anyhow::Error> {
+        let cluster = RaftTestCluster::new(1, None).await?;
+        cluster.initialize(Duration::from_secs(2)).await?;
+        let node = cluster.get_raft_node(0)?;
+
+        let content = ContentMetadata {
+            id: ContentMetadataId::new("content_id"),
+            storage_url: "file://hot/content".to_string(),
+            ..Default::default()
+        };
+        node.create_content_batch(vec![content.clone()]).await?;
+        assert!(!node.is_content_tree_indexed(&content.id.id).await?);
+        node.mark_change_events_as_processed(node.unprocessed_state_change_events().await?)
+            .await?;
+        assert!(node.is_content_tree_indexed(&content.id.id).await?);
+        assert!(node.is_content_tree_indexed("missing").await.is_err());
+
+        let update = |from: &str, to: &str| ContentStorageUpdate {
+            content_id: content.id.clone(),
+            from_storage_url: from.to_string(),
+            to_storage_url: to.to_string(),
+        };
+        node.update_content_storage(vec![update("file://hot/content", "file://cold/content")])
+            .await?;
+        let read_content = node.get_content_metadata_with_version(&content.id).await?;
+        assert_eq!(read_content.storage_url, "file://cold/content");
+
+        //  Updates from a url the content is no longer stored at are ignored
+        node.update_content_storage(vec![update("file://hot/content", "file://other/content")])
+            .await?;
+        let read_content = node.get_content_metadata_with_version(&content.id).await?;
+        assert_eq!(read_content.storage_url, "file://cold/content");
+        assert_eq!(read_content.id, content.id);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_extracted_metadata() -> Result<(), anyhow::Error> {
+        let cluster = RaftTestCluster::new(1, None).await?;
+        cluster.initialize(Duration::from_secs(2)).await?;
+        let node = cluster.get_raft_node(0)?;
+
+        let content = ContentMetadata {
+            id: ContentMetadataId::new("content_id"),
+            extracted_metadata: HashMap::from([
+                ("language".to_string(), serde_json::json!("en")),
+                ("pages".to_string(), serde_json::json!(2)),
+            ]),
+            ..Default::default()
+        };
+        node.create_content_batch(vec![content.clone()]).await?;
+
+        node.merge_extracted_metadata(
+            content.id.clone(),
+            HashMap::from([
+                ("language".to_string(), serde_json::json!("de")),
+                ("topic".to_string(), serde_json::json!("news")),
+            ]),
+        )
+        .await?;
+        let read_content = node.get_content_metadata_with_version(&content.id).await?;
+        assert_eq!(
+            read_content.extracted_metadata,
+            HashMap::from([
+                ("language".to_string(), serde_json::json!("de")),
+                ("pages".to_string(), serde_json::json!(2)),
+                ("topic".to_string(), serde_json::json!("news")),
+            ])
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_update_index_store() -> Result<(), anyhow::Error> {
+        let cluster = RaftTestCluster::new(1, None).await?;
+        cluster.initialize(Duration::from_secs(2)).await?;
+        let node = cluster.get_raft_node(0)?;
+
+        let index = Index {
+            id: "index_id".to_string(),
+            namespace: "namespace".to_string(),
+            name: "index".to_string(),
+            ..Default::default()
+        };
+        node.set_indexes(vec![index.clone()]).await?;
+        assert!(node.list_index_stores().await?.is_empty());
+
+        let update = |from: (&str, &str), to: (&str, &str)| IndexStoreUpdate {
+            index_id: index.id.clone(),
+            from_vector_store: from.0.to_string(),
+            from_migrating_to: from.1.to_string(),
+            vector_store: to.0.to_string(),
+            migrating_to: to.1.to_string(),
+        };
+        let read_index = node
+            .update_index_store(update(("", ""), ("", "qdrant")))
+            .await?;
+        assert_eq!(read_index.migrating_to, "qdrant");
+        assert_eq!(node.list_index_stores().await?, vec![read_index]);
+
+        //  Updates from stores the index is no longer in are ignored
+        let read_index = node
+            .update_index_store(update(("", ""), ("embedded", "")))
+            .await?;
+        assert_eq!(read_index.vector_store, "");
+        assert_eq!(read_index.migrating_to, "qdrant");
+
+        let read_index = node
+            .update_index_store(update(("", "qdrant"), ("qdrant", "")))
+            .await?;
+        assert_eq!(read_index.vector_store, "qdrant");
+        assert_eq!(read_index.migrating_to, "");
+        assert!(node
+            .update_index_store(update(("", ""), ("", "")))
+            .await
+            .is_ok());
+        assert!(node
+            .update_index_store(IndexStoreUpdate {
+                index_id: "missing".to_string(),
+                ..update(("", ""), ("", ""))
+            })
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
     #[tokio::test]
     #[tracing_test::traced_test]
     async fn test_create_read_and_match_extraction_policies() -> Result<(), anyhow::Error> {
@@ -94,6 +94,7 @@ pub enum StateMachineColumns {
     Tasks,                              //  TaskId -> Task
     GarbageCollectionTasks,             //  GCTaskId -> GCTask
     TaskAssignments,                    //  ExecutorId -> HashSet<TaskId>
+    CancelledTasks,                     //  ExecutorId -> HashSet<TaskId>
     StateChanges,                       //  StateChangeId -> StateChange
     ContentTable,                       //  ContentId -> ContentMetadata
     ExtractionPolicies,                 //  ExtractionPolicyId -> ExtractionPolicy
@@ -118,6 +119,7 @@ impl StateMachineColumns {
 
 #[derive(serde::Serialize, Deserialize, Debug, Clone)]
 pub struct Response {
+    /// The reason the request was rejected, if it was
     pub value: Option<String>,
 }
 
@@ -536,6 +538,15 @@ impl StateMachineStore {
             .are_content_tasks_completed(content_id)
     }
 
+    pub async fn get_pending_tasks_for_content(
+        &self,
+        content_id: &ContentMetadataId,
+    ) -> HashSet<TaskId> {
+        self.data
+            .indexify_state
+            .get_pending_tasks_for_content(content_id)
+    }
+
     pub fn get_content_children(
         &self,
         content_id: &ContentMetadataId,
@@ -638,18 +649,18 @@ impl RaftStateMachine<TypeConfig> for Arc<StateMachineStore> {
                 let mut guard = self.data.last_applied_log_id.write().await;
                 *guard = Some(ent.log_id);
             }
-            let resp_value = None;
+            let mut resp_value = None;
             match ent.payload {
                 EntryPayload::Blank => {}
                 EntryPayload::Normal(req) => {
-                    change_events.extend(req.new_state_changes.clone());
-
-                    if let Err(e) = self
+                    match self
                         .data
                         .indexify_state
                         .apply_state_machine_updates(req.clone(), &self.db)
                     {
-                        panic!("error applying state machine update: {}", e);
+                        Ok(None) => change_events.extend(req.new_state_changes.clone()),
+                        Ok(rejected) => resp_value = rejected,
+                        Err(e) => panic!("error applying state machine update: {}", e),
                     };
 
                     //  if the payload is a GC task, send it via channel
@@ -20,6 +20,26 @@ pub struct StateChangeProcessed {
     pub processed_at: u64,
 }
 
+/// Moves the blob of a piece of content to another storage url. It is only
+/// applied while the content is still stored at `from_storage_url`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ContentStorageUpdate {
+    pub content_id: internal_api::ContentMetadataId,
+    pub from_storage_url: String,
+    pub to_storage_url: String,
+}
+
+/// Moves an index to other vector stores. It is only applied while the index
+/// is still in `from_vector_store` and migrating to `from_migrating_to`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IndexStoreUpdate {
+    pub index_id: String,
+    pub from_vector_store: String,
+    pub from_migrating_to: String,
+    pub vector_store: String,
+    pub migrating_to: String,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum RequestPayload {
     //  NOTE: This isn't strictly a state machine update. It's used to change cluster membership.
@@ -34,9 +54,15 @@ pub enum RequestPayload {
         executor_id: String,
         extractors: Vec<internal_api::ExtractorDescription>,
         ts_secs: u64,
+        #[serde(default)]
+        resources: internal_api::ExecutorResources,
+        #[serde(default)]
+        labels: HashMap<String, String>,
     },
     RemoveExecutor {
         executor_id: String,
+        #[serde(default)]
+        removed_at: u64,
     },
     CreateNamespace {
         name: String,
@@ -46,6 +72,8 @@ pub enum RequestPayload {
     },
     AssignTask {
         assignments: HashMap<TaskId, ExecutorId>,
+        #[serde(default)]
+        assigned_at: u64,
     },
     CreateOrAssignGarbageCollectionTask {
         gc_tasks: Vec<internal_api::GarbageCollectionTask>,
@@ -65,18 +93,37 @@ pub enum RequestPayload {
     UpdateContent {
         content_metadata: Vec<internal_api::ContentMetadata>,
     },
+    UpdateContentStorage {
+        updates: Vec<ContentStorageUpdate>,
+    },
+    MergeExtractedMetadata {
+        content_id: internal_api::ContentMetadataId,
+        metadata: HashMap<String, serde_json::Value>,
+    },
     TombstoneContentTree {
         content_metadata: Vec<internal_api::ContentMetadata>,
     },
     SetIndex {
         indexes: Vec<internal_api::Index>,
     },
+    UpdateIndexStore {
+        update: IndexStoreUpdate,
+    },
     UpdateTask {
         task: internal_api::Task,
         executor_id: Option<String>,
         content_metadata: Vec<internal_api::ContentMetadata>,
         update_time: SystemTime,
     },
+    CancelTasks {
+        task_ids: Vec<TaskId>,
+        cancelled_at: u64,
+        update_time: SystemTime,
+    },
+    ReleaseCancelledTasks {
+        executor_id: ExecutorId,
+        task_ids: Vec<TaskId>,
+    },
     MarkStateChangesProcessed {
         state_changes: Vec<StateChangeProcessed>,
     },
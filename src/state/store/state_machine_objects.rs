@@ -23,7 +23,13 @@ use serde::de::DeserializeOwned;
 use tracing::{error, warn};
 
 use super::{
-    requests::{RequestPayload, StateChangeProcessed, StateMachineUpdateRequest},
+    requests::{
+        ContentStorageUpdate,
+        IndexStoreUpdate,
+        RequestPayload,
+        StateChangeProcessed,
+        StateMachineUpdateRequest,
+    },
     serializer::JsonEncode,
     ExecutorId,
     ExtractionGraphId,
@@ -555,6 +561,14 @@ impl PendingTasksForContent {
         guard.get(content_id).is_none()
     }
 
+    pub fn tasks(&self, content_id: &ContentMetadataId) -> HashSet<TaskId> {
+        let guard = self.pending_tasks_for_content.read().unwrap();
+        guard
+            .get(content_id)
+            .map(|policies_map| policies_map.values().flatten().cloned().collect())
+            .unwrap_or_default()
+    }
+
     pub fn inner(
         &self,
     ) -> HashMap<ContentMetadataId, HashMap<ExtractionPolicyId, HashSet<TaskId>>> {
@@ -760,6 +774,31 @@ impl IndexifyState {
         Ok(())
     }
 
+    fn update_index_store(
+        &self,
+        db: &Arc<OptimisticTransactionDB>,
+        txn: &rocksdb::Transaction<OptimisticTransactionDB>,
+        update: &IndexStoreUpdate,
+    ) -> Result<(), StateMachineError> {
+        let value = txn
+            .get_cf(StateMachineColumns::IndexTable.cf(db), &update.index_id)
+            .map_err(|e| StateMachineError::TransactionError(e.to_string()))?;
+        let Some(value) = value else {
+            return Ok(());
+        };
+        let mut index = JsonEncoder::decode::<internal_api::Index>(&value)?;
+        //  Another update won the race, the caller finds out by reading the index
+        // back
+        if index.vector_store != update.from_vector_store ||
+            index.migrating_to != update.from_migrating_to
+        {
+            return Ok(());
+        }
+        index.vector_store = update.vector_store.clone();
+        index.migrating_to = update.migrating_to.clone();
+        self.set_index(db, txn, &index, &update.index_id)
+    }
+
     fn set_tasks(
         &self,
         db: &Arc<OptimisticTransactionDB>,
@@ -814,6 +853,78 @@ impl IndexifyState {
         Ok(())
     }
 
+    /// Records on the tasks of a removed executor that they were unassigned
+    /// from it, and returns the ones which have to be assigned again.
+    fn unassign_tasks(
+        &self,
+        db: &Arc<OptimisticTransactionDB>,
+        txn: &rocksdb::Transaction<OptimisticTransactionDB>,
+        task_ids: Vec<TaskId>,
+        executor_id: &str,
+        removed_at: u64,
+    ) -> Result<Vec<TaskId>, StateMachineError> {
+        let mut unassigned_task_ids = Vec::new();
+        for task_id in task_ids {
+            let value = txn
+                .get_cf(StateMachineColumns::Tasks.cf(db), &task_id)
+                .map_err(|e| StateMachineError::DatabaseError(e.to_string()))?;
+            let Some(value) = value else {
+                continue;
+            };
+            let mut task = JsonEncoder::decode::<internal_api::Task>(&value)?;
+            if task.terminal_state() {
+                continue;
+            }
+            task.record_event(
+                internal_api::TaskEventType::Unassigned,
+                removed_at,
+                Some(executor_id.to_string()),
+                None,
+            );
+            txn.put_cf(
+                StateMachineColumns::Tasks.cf(db),
+                &task_id,
+                JsonEncoder::encode(&task)?,
+            )
+            .map_err(|e| StateMachineError::DatabaseError(e.to_string()))?;
+            unassigned_task_ids.push(task_id);
+        }
+        Ok(unassigned_task_ids)
+    }
+
+    /// Records the assignment of the tasks to their executors on the tasks.
+    fn set_tasks_assigned(
+        &self,
+        db: &Arc<OptimisticTransactionDB>,
+        txn: &rocksdb::Transaction<OptimisticTransactionDB>,
+        assignments: &HashMap<TaskId, ExecutorId>,
+        assigned_at: u64,
+    ) -> Result<(), StateMachineError> {
+        for (task_id, executor_id) in assignments {
+            let value = txn
+                .get_cf(StateMachineColumns::Tasks.cf(db), task_id)
+                .map_err(|e| StateMachineError::DatabaseError(e.to_string()))?
+                .ok_or_else(|| {
+                    StateMachineError::DatabaseError(format!("Task {} not found", task_id))
+                })?;
+            let mut task = JsonEncoder::decode::<internal_api::Task>(&value)?;
+            task.assigned_at = assigned_at;
+            task.record_event(
+                internal_api::TaskEventType::Assigned,
+                assigned_at,
+                Some(executor_id.clone()),
+                None,
+            );
+            txn.put_cf(
+                StateMachineColumns::Tasks.cf(db),
+                task_id,
+                JsonEncoder::encode(&task)?,
+            )
+            .map_err(|e| StateMachineError::DatabaseError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
     fn set_garbage_collection_tasks(
         &self,
         db: &Arc<OptimisticTransactionDB>,
@@ -876,6 +987,129 @@ impl IndexifyState {
         }
     }
 
+    /// Marks the tasks which haven't finished yet cancelled and returns them.
+    /// Assigned ones are moved from the task assignments of their executor to
+    /// its cancelled tasks, until it is told about the cancellation.
+    fn cancel_tasks(
+        &self,
+        db: &Arc<OptimisticTransactionDB>,
+        txn: &rocksdb::Transaction<OptimisticTransactionDB>,
+        task_ids: &[TaskId],
+        cancelled_at: u64,
+        update_time: SystemTime,
+    ) -> Result<Vec<internal_api::Task>, StateMachineError> {
+        let mut cancelled_tasks = Vec::new();
+        for task_id in task_ids {
+            let value = txn
+                .get_cf(StateMachineColumns::Tasks.cf(db), task_id)
+                .map_err(|e| StateMachineError::DatabaseError(e.to_string()))?;
+            let Some(value) = value else {
+                continue;
+            };
+            let mut task = JsonEncoder::decode::<internal_api::Task>(&value)?;
+            if task.terminal_state() {
+                continue;
+            }
+            let executor_id = task
+                .history
+                .iter()
+                .rev()
+                .find(|event| {
+                    event.event_type == internal_api::TaskEventType::Assigned ||
+                        event.event_type == internal_api::TaskEventType::Unassigned
+                })
+                .filter(|event| event.event_type == internal_api::TaskEventType::Assigned)
+                .and_then(|event| event.executor_id.clone());
+            if let Some(executor_id) = executor_id {
+                let mut assigned_tasks =
+                    self.get_task_assignments_for_executor(db, txn, &executor_id)?;
+                if assigned_tasks.remove(task_id) {
+                    let mut cancelled_task_ids =
+                        self.get_cancelled_tasks_for_executor(db, txn, &executor_id)?;
+                    cancelled_task_ids.insert(task_id.clone());
+                    self.set_cancelled_tasks_for_executor(
+                        db,
+                        txn,
+                        &executor_id,
+                        &cancelled_task_ids,
+                    )?;
+                    let task_assignment = HashMap::from([(executor_id, assigned_tasks)]);
+                    self.set_task_assignments(db, txn, &task_assignment)?;
+                }
+            }
+            task.outcome = internal_api::TaskOutcome::Cancelled;
+            task.record_outcome(cancelled_at, None);
+            cancelled_tasks.push(task);
+        }
+        self.update_tasks(db, txn, cancelled_tasks.iter().collect(), update_time)?;
+        Ok(cancelled_tasks)
+    }
+
+    /// The reason an update of the task is rejected, if it already finished.
+    fn finished_task(
+        &self,
+        db: &Arc<OptimisticTransactionDB>,
+        txn: &rocksdb::Transaction<OptimisticTransactionDB>,
+        task_id: &TaskId,
+    ) -> Result<Option<String>, StateMachineError> {
+        let value = txn
+            .get_cf(StateMachineColumns::Tasks.cf(db), task_id)
+            .map_err(|e| StateMachineError::DatabaseError(e.to_string()))?;
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        let task = JsonEncoder::decode::<internal_api::Task>(&value)?;
+        Ok(match task.outcome {
+            internal_api::TaskOutcome::Unknown => None,
+            internal_api::TaskOutcome::Cancelled => Some(format!("task {} was cancelled", task_id)),
+            outcome => Some(format!(
+                "task {} already finished with outcome {:?}",
+                task_id, outcome
+            )),
+        })
+    }
+
+    fn get_cancelled_tasks_for_executor(
+        &self,
+        db: &Arc<OptimisticTransactionDB>,
+        txn: &rocksdb::Transaction<OptimisticTransactionDB>,
+        executor_id: &str,
+    ) -> Result<HashSet<TaskId>, StateMachineError> {
+        let value = txn
+            .get_cf(StateMachineColumns::CancelledTasks.cf(db), executor_id)
+            .map_err(|e| {
+                StateMachineError::DatabaseError(format!("Error reading cancelled tasks: {}", e))
+            })?;
+        match value {
+            Some(value) => Ok(JsonEncoder::decode(&value)?),
+            None => Ok(HashSet::new()),
+        }
+    }
+
+    /// Set the cancelled tasks the executor wasn't told about yet, removing
+    /// its entry when there are none left
+    fn set_cancelled_tasks_for_executor(
+        &self,
+        db: &Arc<OptimisticTransactionDB>,
+        txn: &rocksdb::Transaction<OptimisticTransactionDB>,
+        executor_id: &str,
+        task_ids: &HashSet<TaskId>,
+    ) -> Result<(), StateMachineError> {
+        let cancelled_tasks_cf = StateMachineColumns::CancelledTasks.cf(db);
+        let result = if task_ids.is_empty() {
+            txn.delete_cf(cancelled_tasks_cf, executor_id)
+        } else {
+            txn.put_cf(
+                cancelled_tasks_cf,
+                executor_id,
+                JsonEncoder::encode(task_ids)?,
+            )
+        };
+        result.map_err(|e| {
+            StateMachineError::DatabaseError(format!("Error writing cancelled tasks: {}", e))
+        })
+    }
+
     /// Set the list of tasks that have been assigned to some executor
     fn set_task_assignments(
         &self,
@@ -955,6 +1189,91 @@ impl IndexifyState {
         Ok(())
     }
 
+    /// Returns the id of the first of the given content which exists already
+    /// and is not tombstoned.
+    fn existing_content(
+        &self,
+        db: &Arc<OptimisticTransactionDB>,
+        txn: &rocksdb::Transaction<OptimisticTransactionDB>,
+        contents_vec: &Vec<internal_api::ContentMetadata>,
+    ) -> Result<Option<internal_api::ContentMetadataId>, StateMachineError> {
+        for content in contents_vec {
+            let content_key = format!("{}::v{}", content.id.id, content.id.version);
+            let value = txn
+                .get_cf(StateMachineColumns::ContentTable.cf(db), &content_key)
+                .map_err(|e| StateMachineError::TransactionError(e.to_string()))?;
+            let Some(value) = value else {
+                continue;
+            };
+            let existing = JsonEncoder::decode::<internal_api::ContentMetadata>(&value)?;
+            if !existing.tombstoned {
+                return Ok(Some(content.id.clone()));
+            }
+        }
+        Ok(None)
+    }
+
+    fn merge_extracted_metadata(
+        &self,
+        db: &Arc<OptimisticTransactionDB>,
+        txn: &rocksdb::Transaction<OptimisticTransactionDB>,
+        content_id: &internal_api::ContentMetadataId,
+        metadata: &HashMap<String, serde_json::Value>,
+    ) -> Result<(), StateMachineError> {
+        let content_key = format!("{}::v{}", content_id.id, content_id.version);
+        let value = txn
+            .get_cf(StateMachineColumns::ContentTable.cf(db), &content_key)
+            .map_err(|e| StateMachineError::TransactionError(e.to_string()))?;
+        //  Content which was deleted since the metadata was extracted is left as is
+        let Some(value) = value else {
+            return Ok(());
+        };
+        let mut content = JsonEncoder::decode::<internal_api::ContentMetadata>(&value)?;
+        content.extracted_metadata.extend(metadata.clone());
+        let serialized_content = JsonEncoder::encode(&content)?;
+        txn.put_cf(
+            StateMachineColumns::ContentTable.cf(db),
+            content_key,
+            &serialized_content,
+        )
+        .map_err(|e| StateMachineError::DatabaseError(format!("error writing content: {}", e)))?;
+        Ok(())
+    }
+
+    fn update_content_storage(
+        &self,
+        db: &Arc<OptimisticTransactionDB>,
+        txn: &rocksdb::Transaction<OptimisticTransactionDB>,
+        updates: &Vec<ContentStorageUpdate>,
+    ) -> Result<(), StateMachineError> {
+        for update in updates {
+            let content_key = format!("{}::v{}", update.content_id.id, update.content_id.version);
+            let value = txn
+                .get_cf(StateMachineColumns::ContentTable.cf(db), &content_key)
+                .map_err(|e| StateMachineError::TransactionError(e.to_string()))?;
+            //  Content which was deleted or moved elsewhere since the update was
+            // requested is left as is
+            let Some(value) = value else {
+                continue;
+            };
+            let mut content = JsonEncoder::decode::<internal_api::ContentMetadata>(&value)?;
+            if content.storage_url != update.from_storage_url {
+                continue;
+            }
+            content.storage_url = update.to_storage_url.clone();
+            let serialized_content = JsonEncoder::encode(&content)?;
+            txn.put_cf(
+                StateMachineColumns::ContentTable.cf(db),
+                content_key,
+                &serialized_content,
+            )
+            .map_err(|e| {
+                StateMachineError::DatabaseError(format!("error writing content: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+
     fn tombstone_content_tree(
         &self,
         db: &Arc<OptimisticTransactionDB>,
@@ -999,6 +1318,7 @@ impl IndexifyState {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn set_executor(
         &self,
         db: &Arc<OptimisticTransactionDB>,
@@ -1007,12 +1327,16 @@ impl IndexifyState {
         executor_id: &str,
         extractors: &Vec<ExtractorDescription>,
         ts_secs: &u64,
+        resources: &internal_api::ExecutorResources,
+        labels: &HashMap<String, String>,
     ) -> Result<(), StateMachineError> {
         let serialized_executor = JsonEncoder::encode(&internal_api::ExecutorMetadata {
             id: executor_id.into(),
             last_seen: *ts_secs,
             addr: addr.clone(),
             extractors: extractors.clone(),
+            resources: *resources,
+            labels: labels.clone(),
         })?;
         txn.put_cf(
             StateMachineColumns::Executors.cf(db),
@@ -1217,12 +1541,14 @@ impl IndexifyState {
         );
     }
 
-    /// This method will make all state machine forward index writes to RocksDB
+    /// This method will make all state machine forward index writes to RocksDB.
+    /// It returns the reason a request was rejected without changing the
+    /// state, if it was.
     pub fn apply_state_machine_updates(
         &self,
         request: StateMachineUpdateRequest,
         db: &Arc<OptimisticTransactionDB>,
-    ) -> Result<(), StateMachineError> {
+    ) -> Result<Option<String>, StateMachineError> {
         let txn = db.transaction();
 
         self.set_new_state_changes(db, &txn, &request.new_state_changes)?;
@@ -1250,7 +1576,11 @@ impl IndexifyState {
                     self.delete_content(db, &txn, vec![gc_task.content_id.clone()])?;
                 }
             }
-            RequestPayload::AssignTask { assignments } => {
+            RequestPayload::AssignTask {
+                assignments,
+                assigned_at,
+            } => {
+                self.set_tasks_assigned(db, &txn, assignments, *assigned_at)?;
                 let assignments: HashMap<&String, HashSet<TaskId>> =
                     assignments
                         .iter()
@@ -1277,6 +1607,11 @@ impl IndexifyState {
                 content_metadata,
                 update_time,
             } => {
+                //  Outcomes of tasks which finished while their executor was reporting, for
+                // example because they were cancelled, are rejected
+                if let Some(reason) = self.finished_task(db, &txn, &task.id)? {
+                    return Ok(Some(reason));
+                }
                 self.update_tasks(db, &txn, vec![task], *update_time)?;
                 self.set_content(db, &txn, content_metadata)?;
 
@@ -1298,19 +1633,70 @@ impl IndexifyState {
                     }
                 }
             }
+            RequestPayload::CancelTasks {
+                task_ids,
+                cancelled_at,
+                update_time,
+            } => {
+                //  NOTE: Forward and reverse indexes are updated together, only the tasks which
+                // were unfinished are cancelled. The load of executors running cancelled tasks
+                // is only decremented once they are told about the cancellation
+                let cancelled_tasks =
+                    self.cancel_tasks(db, &txn, task_ids, *cancelled_at, *update_time)?;
+                for task in cancelled_tasks {
+                    self.unassigned_tasks.remove(&task.id);
+                    self.unfinished_tasks_by_extractor
+                        .remove(&task.extractor, &task.id);
+                    self.pending_tasks_for_content.remove(
+                        &task.content_metadata.id,
+                        &task.extraction_policy_id,
+                        &task.id,
+                    );
+                }
+            }
+            RequestPayload::ReleaseCancelledTasks {
+                executor_id,
+                task_ids,
+            } => {
+                //  NOTE: Forward and reverse indexes are updated together, so that the load of
+                // the executor is only decremented for tasks which weren't released before
+                let mut cancelled_task_ids =
+                    self.get_cancelled_tasks_for_executor(db, &txn, executor_id)?;
+                for task_id in task_ids {
+                    if cancelled_task_ids.remove(task_id) {
+                        self.executor_running_task_count
+                            .decrement_running_task_count(executor_id);
+                    }
+                }
+                self.set_cancelled_tasks_for_executor(db, &txn, executor_id, &cancelled_task_ids)?;
+            }
             RequestPayload::RegisterExecutor {
                 addr,
                 executor_id,
                 extractors,
                 ts_secs,
+                resources,
+                labels,
             } => {
                 //  Insert the executor
-                self.set_executor(db, &txn, addr.into(), executor_id, extractors, ts_secs)?;
+                self.set_executor(
+                    db,
+                    &txn,
+                    addr.into(),
+                    executor_id,
+                    extractors,
+                    ts_secs,
+                    resources,
+                    labels,
+                )?;
 
                 //  Insert the associated extractors
                 self.set_extractors(db, &txn, extractors)?;
             }
-            RequestPayload::RemoveExecutor { executor_id } => {
+            RequestPayload::RemoveExecutor {
+                executor_id,
+                removed_at,
+            } => {
                 //  NOTE: Special case where forward and reverse indexes are updated together
 
                 //  Get a handle on the executor before deleting it from the DB
@@ -1318,6 +1704,8 @@ impl IndexifyState {
 
                 // Remove all tasks assigned to this executor and get a handle on the task ids
                 let task_ids = self.delete_task_assignments_for_executor(db, &txn, executor_id)?;
+                let task_ids = self.unassign_tasks(db, &txn, task_ids, executor_id, *removed_at)?;
+                self.set_cancelled_tasks_for_executor(db, &txn, executor_id, &HashSet::new())?;
 
                 txn.commit()
                     .map_err(|e| StateMachineError::TransactionError(e.to_string()))?;
@@ -1338,14 +1726,31 @@ impl IndexifyState {
                 // Remove from the executor load table
                 self.executor_running_task_count.remove(executor_id);
 
-                return Ok(());
+                return Ok(None);
             }
             RequestPayload::CreateContent { content_metadata } => {
+                //  Content created concurrently with the same id is rejected rather than
+                // overwritten, dropping the transaction
+                if let Some(id) = self.existing_content(db, &txn, content_metadata)? {
+                    return Ok(Some(format!("content with id {} already exists", id)));
+                }
                 self.set_content(db, &txn, content_metadata)?;
             }
             RequestPayload::UpdateContent { content_metadata } => {
                 self.set_content(db, &txn, content_metadata)?;
             }
+            RequestPayload::UpdateContentStorage { updates } => {
+                self.update_content_storage(db, &txn, updates)?;
+            }
+            RequestPayload::MergeExtractedMetadata {
+                content_id,
+                metadata,
+            } => {
+                self.merge_extracted_metadata(db, &txn, content_id, metadata)?;
+            }
+            RequestPayload::UpdateIndexStore { update } => {
+                self.update_index_store(db, &txn, update)?;
+            }
             RequestPayload::TombstoneContentTree { content_metadata } => {
                 self.tombstone_content_tree(db, &txn, content_metadata)?;
             }
@@ -1384,7 +1789,7 @@ impl IndexifyState {
         txn.commit()
             .map_err(|e| StateMachineError::TransactionError(e.to_string()))?;
 
-        Ok(())
+        Ok(None)
     }
 
     /// This method handles all reverse index writes. All reverse indexes are
@@ -1402,6 +1807,8 @@ impl IndexifyState {
                 executor_id,
                 extractors,
                 ts_secs,
+                resources,
+                labels,
             } => {
                 // Inserts the executor list of extractors to the executor -> extractor mapping
                 // table
@@ -1415,6 +1822,8 @@ impl IndexifyState {
                     last_seen: ts_secs,
                     addr: addr.clone(),
                     extractors: extractors.clone(),
+                    resources,
+                    labels,
                 };
                 // initialize executor load at 0
                 self.executor_running_task_count.insert(&executor_id, 0);
@@ -1422,6 +1831,11 @@ impl IndexifyState {
             }
             RequestPayload::CreateTasks { tasks } => {
                 for task in tasks {
+                    // Tasks which failed when they were created, for example because their
+                    // content exceeds the limits of their policy, are never assigned
+                    if task.terminal_state() {
+                        continue;
+                    }
                     self.unassigned_tasks.insert(&task.id);
                     self.unfinished_tasks_by_extractor
                         .insert(&task.extractor, &task.id);
@@ -1433,7 +1847,7 @@ impl IndexifyState {
                 }
                 Ok(())
             }
-            RequestPayload::AssignTask { assignments } => {
+            RequestPayload::AssignTask { assignments, .. } => {
                 for (task_id, executor_id) in assignments {
                     self.unassigned_tasks.remove(&task_id);
 
@@ -2173,6 +2587,10 @@ impl IndexifyState {
             .are_content_tasks_completed(content_id)
     }
 
+    pub fn get_pending_tasks_for_content(&self, content_id: &ContentMetadataId) -> HashSet<TaskId> {
+        self.pending_tasks_for_content.tasks(content_id)
+    }
+
     pub fn executor_count(&self) -> usize {
         self.executor_running_task_count.executor_count()
     }
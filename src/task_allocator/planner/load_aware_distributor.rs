@@ -3,7 +3,7 @@ use std::{
     collections::{BinaryHeap, HashMap, HashSet},
 };
 
-use indexify_internal_api::ExecutorMetadata;
+use indexify_internal_api::{ExecutorAffinity, ExecutorMetadata, ExecutorResources};
 use tracing::error;
 
 use super::{plan::TaskAllocationPlan, AllocationPlanner, AllocationPlannerResult};
@@ -201,6 +201,107 @@ impl LoadAwareDistributor {
         }
         executors_load_min_heap
     }
+
+    /// Placement constraints of each of the tasks, from the limits and the
+    /// executor affinity of their extraction policies. Tasks which can be
+    /// placed on any executor are left out.
+    async fn placement_by_task(
+        &self,
+        task_ids: &HashSet<TaskId>,
+    ) -> HashMap<TaskId, TaskPlacement> {
+        let mut placement_by_policy: HashMap<String, TaskPlacement> = HashMap::new();
+        let mut result = HashMap::new();
+        for task_id in task_ids {
+            let task = match self.shared_state.task_with_id(task_id).await {
+                Ok(task) => task,
+                Err(e) => {
+                    error!("unable to get task {}: {}", task_id, e);
+                    continue;
+                }
+            };
+            let placement = placement_by_policy
+                .entry(task.extraction_policy_id.clone())
+                .or_insert_with(|| {
+                    self.shared_state
+                        .get_extraction_policy(&task.extraction_policy_id)
+                        .map(|policy| TaskPlacement {
+                            resources: policy.limits.resources,
+                            affinity: policy.affinity,
+                        })
+                        .unwrap_or_default()
+                });
+            if *placement != TaskPlacement::default() {
+                result.insert(task_id.clone(), placement.clone());
+            }
+        }
+        result
+    }
+
+    async fn executors_by_id(&self) -> HashMap<ExecutorId, ExecutorMetadata> {
+        match self.shared_state.get_executors().await {
+            Ok(executors) => executors
+                .into_iter()
+                .map(|executor| (executor.id.clone(), executor))
+                .collect(),
+            Err(e) => {
+                error!("unable to get executors: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+}
+
+/// Resources and labels an executor needs to be assigned a task.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct TaskPlacement {
+    resources: ExecutorResources,
+    affinity: ExecutorAffinity,
+}
+
+impl TaskPlacement {
+    fn allows(&self, executor: &ExecutorMetadata) -> bool {
+        executor.resources.satisfies(&self.resources) && self.affinity.allows(&executor.labels)
+    }
+}
+
+/// Pops the executor a task is assigned to from the heap.
+///
+/// Without placement constraints this is the least loaded executor. Otherwise
+/// it is the executor with the most preferred labels among the executors
+/// with the required resources and labels, the least loaded one on ties.
+/// Executors which aren't selected stay in the heap.
+fn select_executor(
+    heap: &mut MinHeap<ExecutorLoad>,
+    placement: Option<&TaskPlacement>,
+    executors: &HashMap<ExecutorId, ExecutorMetadata>,
+) -> Option<Reverse<ExecutorLoad>> {
+    let placement = match placement {
+        Some(placement) => placement,
+        None => return heap.pop(),
+    };
+    let mut popped = Vec::new();
+    let mut selected: Option<(usize, usize)> = None;
+    // Executors are popped least loaded first, so only a higher score replaces
+    // the selected executor.
+    while let Some(executor_load) = heap.pop() {
+        if let Some(executor) = executors.get(&executor_load.0.executor_id) {
+            if placement.allows(executor) {
+                let score = placement.affinity.score(&executor.labels);
+                if selected.map_or(true, |(_, best)| score > best) {
+                    selected = Some((popped.len(), score));
+                }
+            }
+        }
+        popped.push(executor_load);
+        if let Some((_, best)) = selected {
+            if best == placement.affinity.preferred_labels.len() {
+                break;
+            }
+        }
+    }
+    let selected = selected.map(|(index, _)| popped.swap_remove(index));
+    heap.extend(popped);
+    selected
 }
 
 #[async_trait::async_trait]
@@ -244,6 +345,15 @@ impl AllocationPlanner for LoadAwareDistributor {
             MinHeap<ExecutorLoad>,
         > = self.initialize_executor_load_min_heaps_by_extractor().await;
 
+        // Tasks of policies with resource limits or an executor affinity can only be
+        // assigned to executors advertising those resources and labels.
+        let placements = self.placement_by_task(&task_ids).await;
+        let executors = if placements.is_empty() {
+            HashMap::new()
+        } else {
+            self.executors_by_id().await
+        };
+
         // Prepare the allocation plan structure to record task assignments.
         let mut plan = TaskAllocationPlan(HashMap::new());
 
@@ -262,8 +372,9 @@ impl AllocationPlanner for LoadAwareDistributor {
             };
             // Iterate over each task ID assigned to the current extractor.
             for task_id in task_ids.iter() {
-                // Attempt to pop the executor with the least load from the heap.
-                match heap.pop() {
+                let placement = placements.get(task_id);
+                let selected = select_executor(heap, placement, &executors);
+                match selected {
                     Some(executor_load) => {
                         // If an executor is found, assign the task to it and increment its load.
                         // Then, push the updated load back into the heap to maintain the min-heap
@@ -277,7 +388,13 @@ impl AllocationPlanner for LoadAwareDistributor {
                     None => {
                         // If no executor is available for this task, log an error.
                         // This case might require attention to ensure tasks are not left unhandled.
-                        error!("No matching executor found for task: {}", task_id);
+                        match placement {
+                            Some(placement) => error!(
+                                "No executor with resources {:?} and labels {:?} found for task: {}",
+                                placement.resources, placement.affinity.required_labels, task_id
+                            ),
+                            None => error!("No matching executor found for task: {}", task_id),
+                        }
                     }
                 }
             }
@@ -320,6 +437,10 @@ mod tests {
             input_params: json!(null),
             outcome: internal_api::TaskOutcome::Unknown,
             index_tables: vec![],
+            extractor_version: "".to_string(),
+            assigned_at: 0,
+            failure_reason: None,
+            history: vec![],
         }
     }
 
@@ -386,6 +507,142 @@ mod tests {
         );
     }
 
+    fn labels(labels: &[(&str, &str)]) -> HashMap<String, String> {
+        labels
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn executors_with_labels(
+        executors: &[(&str, u32, &[(&str, &str)])],
+    ) -> HashMap<ExecutorId, ExecutorMetadata> {
+        executors
+            .iter()
+            .map(|(id, gpus, executor_labels)| {
+                let executor = ExecutorMetadata {
+                    id: id.to_string(),
+                    last_seen: 0,
+                    addr: "".to_string(),
+                    extractors: vec![],
+                    resources: ExecutorResources {
+                        gpus: *gpus,
+                        ..Default::default()
+                    },
+                    labels: labels(executor_labels),
+                };
+                (executor.id.clone(), executor)
+            })
+            .collect()
+    }
+
+    fn executor_loads(loads: &[(&str, usize)]) -> MinHeap<ExecutorLoad> {
+        loads
+            .iter()
+            .map(|(executor_id, running_task_count)| {
+                Reverse(ExecutorLoad {
+                    executor_id: executor_id.to_string(),
+                    running_task_count: *running_task_count,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select_executor_affinity() {
+        let executors = executors_with_labels(&[
+            ("cpu", 0, &[("zone", "us-east")]),
+            ("gpu_west", 1, &[("gpu", "a100"), ("zone", "us-west")]),
+            ("gpu_east", 1, &[("gpu", "a100"), ("zone", "us-east")]),
+        ]);
+        let loads = [("cpu", 0), ("gpu_west", 1), ("gpu_east", 2)];
+
+        //  Without placement constraints the least loaded executor is selected
+        let mut heap = executor_loads(&loads);
+        let selected = select_executor(&mut heap, None, &executors).unwrap();
+        assert_eq!(selected.0.executor_id, "cpu");
+        assert_eq!(heap.len(), 2);
+
+        //  Required labels are a hard constraint
+        let mut heap = executor_loads(&loads);
+        let placement = TaskPlacement {
+            affinity: ExecutorAffinity {
+                required_labels: labels(&[("gpu", "a100")]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let selected = select_executor(&mut heap, Some(&placement), &executors).unwrap();
+        assert_eq!(selected.0.executor_id, "gpu_west");
+        assert_eq!(heap.len(), 2);
+
+        //  Preferred labels win over load
+        let mut heap = executor_loads(&loads);
+        let placement = TaskPlacement {
+            affinity: ExecutorAffinity {
+                required_labels: labels(&[("gpu", "a100")]),
+                preferred_labels: labels(&[("zone", "us-east")]),
+            },
+            ..Default::default()
+        };
+        let selected = select_executor(&mut heap, Some(&placement), &executors).unwrap();
+        assert_eq!(selected.0.executor_id, "gpu_east");
+        assert_eq!(heap.len(), 2);
+
+        //  Executors without the preferred labels are still selected when no
+        //  executor has them
+        let mut heap = executor_loads(&loads);
+        let placement = TaskPlacement {
+            affinity: ExecutorAffinity {
+                preferred_labels: labels(&[("zone", "eu-central")]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let selected = select_executor(&mut heap, Some(&placement), &executors).unwrap();
+        assert_eq!(selected.0.executor_id, "cpu");
+
+        //  No executor has the required labels, they all stay in the heap
+        let mut heap = executor_loads(&loads);
+        let placement = TaskPlacement {
+            affinity: ExecutorAffinity {
+                required_labels: labels(&[("gpu", "h100")]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(select_executor(&mut heap, Some(&placement), &executors).is_none());
+        assert_eq!(heap.len(), 3);
+    }
+
+    #[test]
+    fn test_select_executor_resources_and_labels() {
+        let executors = executors_with_labels(&[
+            ("small", 0, &[("tier", "batch")]),
+            ("large", 2, &[]),
+            ("large_batch", 1, &[("tier", "batch")]),
+        ]);
+
+        //  Resources and required labels must both be satisfied
+        let mut heap = executor_loads(&[("small", 0), ("large", 1), ("large_batch", 5)]);
+        let placement = TaskPlacement {
+            resources: ExecutorResources {
+                gpus: 1,
+                ..Default::default()
+            },
+            affinity: ExecutorAffinity {
+                required_labels: labels(&[("tier", "batch")]),
+                ..Default::default()
+            },
+        };
+        let selected = select_executor(&mut heap, Some(&placement), &executors).unwrap();
+        assert_eq!(selected.0.executor_id, "large_batch");
+
+        //  Executors that aren't registered can't satisfy placement constraints
+        let mut heap = executor_loads(&[("unknown", 0)]);
+        assert!(select_executor(&mut heap, Some(&placement), &executors).is_none());
+    }
+
     #[tokio::test]
     #[tracing_test::traced_test]
     async fn test_plan_allocations_empty() -> Result<(), anyhow::Error> {
@@ -69,6 +69,7 @@ pub mod db_utils {
             internal_api::OutputSchema::Embedding(internal_api::EmbeddingSchema {
                 dim: 384,
                 distance: "cosine".to_string(),
+                kind: internal_api::EmbeddingKind::Dense,
             }),
         );
         internal_api::ExtractorDescription {
@@ -77,6 +78,8 @@ pub mod db_utils {
             input_params: json!({}),
             outputs,
             input_mime_types: vec!["*/*".to_string()],
+            version: "".to_string(),
+            output_mime_types: vec![],
         }
     }
 
@@ -84,3 +87,91 @@ pub mod db_utils {
         vec![mock_extractor()]
     }
 }
+
+#[cfg(test)]
+pub mod server_utils {
+    use std::{net::TcpListener, path::Path, sync::Arc, time::Duration};
+
+    use tokio::task::JoinHandle;
+
+    use crate::{
+        blob_storage::{BlobStorageConfig, DiskStorageConfig},
+        coordinator::Coordinator,
+        coordinator_client::CoordinatorClient,
+        coordinator_service::CoordinatorServer,
+        server_config::{EmbeddedVectorDbConfig, IndexStoreKind, ServerConfig, StateStoreConfig},
+    };
+
+    // Ports picked by the OS, bound together so that they are distinct
+    fn free_ports<const N: usize>() -> [u64; N] {
+        let listeners: Vec<TcpListener> = (0..N)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+        std::array::from_fn(|i| listeners[i].local_addr().unwrap().port() as u64)
+    }
+
+    /// Config of a server and coordinator listening on free local ports, so
+    /// that tests can run in parallel. State, vectors and blobs are kept in
+    /// `dir`.
+    pub fn make_test_config(dir: &Path) -> ServerConfig {
+        let [listen_port, coordinator_port, coordinator_http_port, raft_port] = free_ports();
+        let mut config = ServerConfig::default();
+        config.listen_port = listen_port;
+        config.coordinator_port = coordinator_port;
+        config.coordinator_http_port = coordinator_http_port;
+        config.raft_port = raft_port;
+        config.coordinator_addr = format!("localhost:{}", coordinator_port);
+        config.seed_node = format!("localhost:{}", raft_port);
+        config.state_store = StateStoreConfig {
+            path: Some(dir.join("state").display().to_string()),
+        };
+        config.index_config.index_store = IndexStoreKind::Embedded;
+        config.index_config.embedded_config = Some(EmbeddedVectorDbConfig {
+            path: dir.join("vectors").display().to_string(),
+        });
+        config.blob_storage = BlobStorageConfig {
+            s3: None,
+            disk: Some(DiskStorageConfig {
+                path: dir.join("blobs").display().to_string(),
+            }),
+            gcs: None,
+            azure: None,
+            encoding: None,
+            cold: None,
+        };
+        config
+    }
+
+    /// Coordinator running in the background until stopped.
+    pub struct TestCoordinator {
+        pub coordinator: Arc<Coordinator>,
+        handle: JoinHandle<()>,
+    }
+
+    impl TestCoordinator {
+        /// Starts a coordinator and waits until clients can connect to it.
+        pub async fn new(config: Arc<ServerConfig>) -> TestCoordinator {
+            let coordinator_server =
+                CoordinatorServer::new(config.clone(), Arc::new(crate::metrics::init_provider()))
+                    .await
+                    .expect("failed to create coordinator server");
+            let coordinator = coordinator_server.get_coordinator();
+            let handle = tokio::spawn(async move {
+                coordinator_server.run().await.unwrap();
+            });
+            let coordinator_client = CoordinatorClient::new(config);
+            while coordinator_client.get().await.is_err() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            TestCoordinator {
+                coordinator,
+                handle,
+            }
+        }
+
+        pub async fn stop(self) {
+            self.handle.abort();
+            let _ = self.handle.await;
+        }
+    }
+}
@@ -0,0 +1,940 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use futures::{StreamExt, TryStreamExt};
+use indexify_proto::indexify_coordinator;
+use nanoid::nanoid;
+use object_store::{path::Path, ObjectStore};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::{watch, Mutex, RwLock};
+use tracing::{error, info};
+
+use crate::{
+    api,
+    blob_storage::{BlobStorage, BlobStorageWriter},
+};
+
+/// How often abandoned upload sessions are looked for.
+const EXPIRY_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Prefix, in the blob store, of the records of upload sessions.
+const SESSION_PREFIX: &str = "upload_sessions";
+
+/// Prefix, in the blob store, of the records of the parts of upload
+/// sessions. Every part has a record of its own, so that servers receiving
+/// parts of the same session don't overwrite each other's.
+const PART_PREFIX: &str = "upload_session_parts";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StagedPart {
+    part_id: String,
+    size_bytes: u64,
+    sha256: String,
+}
+
+/// Sha256 of the parts received in order so far, along with the sha256 of
+/// each of those parts. It is only kept in memory, so uploads whose parts
+/// arrived out of order or through another server, were replaced after being
+/// hashed or outlived a restart are hashed by reading the assembled blob.
+#[derive(Clone)]
+struct PrefixHasher {
+    part_hashes: Vec<String>,
+    hasher: Sha256,
+}
+
+impl fmt::Debug for PrefixHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrefixHasher")
+            .field("hashed_parts", &self.part_hashes.len())
+            .finish()
+    }
+}
+
+/// A resumable upload of a single file, made of numbered parts which are
+/// staged in the blob store until the upload is completed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadSession {
+    pub id: String,
+    pub namespace: String,
+    pub content_id: String,
+    pub file_name: String,
+    pub mime_type: String,
+    pub labels: HashMap<String, String>,
+    pub extraction_graph_names: Vec<String>,
+    pub expires_at: u64,
+    multipart_id: String,
+    // Loaded from the records of the parts
+    #[serde(skip)]
+    parts: BTreeMap<usize, StagedPart>,
+    #[serde(skip)]
+    hasher: Option<PrefixHasher>,
+    // Set once the session is being completed or removed, after which no
+    // more parts are accepted
+    #[serde(skip)]
+    closed: bool,
+}
+
+impl UploadSession {
+    /// Received parts in order, along with the offset each part starts at
+    /// assuming every part before it has been received.
+    pub fn parts(&self) -> Vec<api::UploadedPart> {
+        let mut offset = 0;
+        self.parts
+            .iter()
+            .map(|(part_number, part)| {
+                let uploaded = api::UploadedPart {
+                    part_number: *part_number,
+                    offset,
+                    size_bytes: part.size_bytes,
+                };
+                offset += part.size_bytes;
+                uploaded
+            })
+            .collect()
+    }
+
+    pub fn received_bytes(&self) -> u64 {
+        self.parts.values().map(|part| part.size_bytes).sum()
+    }
+
+    fn hash_part(&mut self, part_number: usize, data: &[u8], sha256: &str) {
+        self.hasher = self.hasher.take().and_then(|mut prefix| {
+            if part_number == prefix.part_hashes.len() {
+                prefix.hasher.update(data);
+                prefix.part_hashes.push(sha256.to_string());
+                Some(prefix)
+            } else if part_number < prefix.part_hashes.len() &&
+                prefix.part_hashes[part_number] != sha256
+            {
+                // A part which was already hashed was replaced
+                None
+            } else {
+                Some(prefix)
+            }
+        });
+    }
+
+    // The sha256 of the content, like for every other way of ingesting it,
+    // when every part was hashed in order as it arrived
+    fn content_hash(&self) -> Option<String> {
+        self.hasher
+            .as_ref()
+            .filter(|prefix| {
+                prefix
+                    .part_hashes
+                    .iter()
+                    .eq(self.parts.values().map(|part| &part.sha256))
+            })
+            .map(|prefix| format!("{:x}", prefix.hasher.clone().finalize()))
+    }
+
+    // Parts but the last one of an upload have to be at least
+    // `min_part_size` bytes
+    fn check_part_size(
+        &self,
+        part_number: usize,
+        size_bytes: u64,
+        min_part_size: u64,
+    ) -> Result<()> {
+        if size_bytes < min_part_size && self.parts.range(part_number + 1..).next().is_some() {
+            return Err(anyhow!(
+                "part {} is smaller than the minimum part size of {} bytes, which only the last part can be",
+                part_number,
+                min_part_size
+            ));
+        }
+        if let Some((smaller, _)) = self
+            .parts
+            .range(..part_number)
+            .find(|(_, part)| part.size_bytes < min_part_size)
+        {
+            return Err(anyhow!(
+                "part {} can't follow part {}, which is smaller than the minimum part size of {} bytes",
+                part_number,
+                smaller,
+                min_part_size
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl From<&UploadSession> for api::UploadSessionResponse {
+    fn from(session: &UploadSession) -> Self {
+        Self {
+            upload_id: session.id.clone(),
+            content_id: session.content_id.clone(),
+            expires_at: session.expires_at,
+            received_bytes: session.received_bytes(),
+            parts: session.parts(),
+        }
+    }
+}
+
+/// Tracks resumable uploads. Parts are durably staged in the blob store, as
+/// S3 multipart uploads or as part files, and every session and each of its
+/// parts is recorded in the blob store as well, so that uploads survive
+/// restarts and can be resumed through any server sharing the blob store.
+/// The records are read again on every access to a session. Sessions which
+/// see no activity for the configured ttl are aborted.
+pub struct UploadSessionManager {
+    blob_storage: Arc<BlobStorage>,
+    sessions: RwLock<HashMap<String, Arc<Mutex<UploadSession>>>>,
+    ttl: Duration,
+}
+
+impl std::fmt::Debug for UploadSessionManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UploadSessionManager")
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn check_upload_id(upload_id: &str) -> Result<()> {
+    let valid = upload_id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if upload_id.is_empty() || !valid {
+        return Err(anyhow!("upload session {} not found", upload_id));
+    }
+    Ok(())
+}
+
+fn record_path(upload_id: &str) -> Result<Path> {
+    check_upload_id(upload_id)?;
+    Ok(Path::from(format!("{}/{}.json", SESSION_PREFIX, upload_id)))
+}
+
+fn parts_path(upload_id: &str) -> Result<Path> {
+    check_upload_id(upload_id)?;
+    Ok(Path::from(format!("{}/{}", PART_PREFIX, upload_id)))
+}
+
+fn part_path(upload_id: &str, part_number: usize) -> Result<Path> {
+    Ok(parts_path(upload_id)?.child(format!("{}.json", part_number)))
+}
+
+impl UploadSessionManager {
+    pub fn new(blob_storage: Arc<BlobStorage>, ttl: Duration) -> Self {
+        Self {
+            blob_storage,
+            sessions: RwLock::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    pub async fn create(
+        &self,
+        namespace: &str,
+        content_id: &str,
+        file_name: &str,
+        mime_type: &str,
+        labels: HashMap<String, String>,
+        extraction_graph_names: Vec<String>,
+    ) -> Result<UploadSession> {
+        let multipart_id = self.blob_storage.create_multipart(file_name).await?;
+        let session = UploadSession {
+            id: nanoid!(16),
+            namespace: namespace.to_string(),
+            content_id: content_id.to_string(),
+            file_name: file_name.to_string(),
+            mime_type: mime_type.to_string(),
+            labels,
+            extraction_graph_names,
+            expires_at: now_secs() + self.ttl.as_secs(),
+            multipart_id,
+            parts: BTreeMap::new(),
+            hasher: Some(PrefixHasher {
+                part_hashes: Vec::new(),
+                hasher: Sha256::new(),
+            }),
+            closed: false,
+        };
+        if let Err(e) = self.save(&session).await {
+            self.discard(&session).await;
+            return Err(anyhow!("unable to record upload session: {}", e));
+        }
+        info!(
+            "created upload session {} for content {}",
+            session.id, session.content_id
+        );
+        self.sessions
+            .write()
+            .await
+            .insert(session.id.clone(), Arc::new(Mutex::new(session.clone())));
+        Ok(session)
+    }
+
+    pub async fn get(&self, namespace: &str, upload_id: &str) -> Result<UploadSession> {
+        let session = self.session(namespace, upload_id).await?;
+        let mut session = session.lock().await;
+        self.refresh(&mut session).await?;
+        Ok(session.clone())
+    }
+
+    /// Stores a part of the upload, replacing any part previously received
+    /// with the same number.
+    pub async fn put_part(
+        &self,
+        namespace: &str,
+        upload_id: &str,
+        part_number: usize,
+        data: Bytes,
+    ) -> Result<UploadSession> {
+        let session = self.session(namespace, upload_id).await?;
+        let size_bytes = data.len() as u64;
+        let (file_name, multipart_id) = {
+            let mut session = session.lock().await;
+            if session.closed {
+                return Err(anyhow!("upload session {} not found", upload_id));
+            }
+            self.refresh(&mut session).await?;
+            session.check_part_size(part_number, size_bytes, self.blob_storage.min_part_size())?;
+            (session.file_name.clone(), session.multipart_id.clone())
+        };
+        let part_id = self
+            .blob_storage
+            .put_part(&file_name, &multipart_id, part_number, data.clone())
+            .await?;
+
+        let mut session = session.lock().await;
+        if session.closed {
+            return Err(anyhow!("upload session {} not found", upload_id));
+        }
+        self.refresh(&mut session).await?;
+        let part = StagedPart {
+            part_id,
+            size_bytes,
+            sha256: format!("{:x}", Sha256::digest(&data)),
+        };
+        self.save_part(&session.id, part_number, &part).await?;
+        session.hash_part(part_number, &data, &part.sha256);
+        session.parts.insert(part_number, part);
+        session.expires_at = now_secs() + self.ttl.as_secs();
+        self.save(&session).await?;
+        Ok(session.clone())
+    }
+
+    /// Assembles the received parts and returns the metadata of the uploaded
+    /// content. Parts must be numbered contiguously starting at 0.
+    pub async fn complete(
+        &self,
+        namespace: &str,
+        upload_id: &str,
+    ) -> Result<indexify_coordinator::ContentMetadata> {
+        let session = self.session(namespace, upload_id).await?;
+        let mut session = session.lock().await;
+        if session.closed {
+            return Err(anyhow!("upload session {} not found", upload_id));
+        }
+        self.refresh(&mut session).await?;
+        if session.parts.is_empty() {
+            return Err(anyhow!("upload session {} has no parts", upload_id));
+        }
+        if let Some(missing) = (0..session.parts.len()).find(|i| !session.parts.contains_key(i)) {
+            return Err(anyhow!(
+                "upload session {} is missing part {}",
+                upload_id,
+                missing
+            ));
+        }
+
+        // No more parts can be added while the session is being completed
+        session.closed = true;
+        let part_ids = session
+            .parts
+            .values()
+            .map(|part| part.part_id.clone())
+            .collect();
+        let res = match self
+            .blob_storage
+            .complete_multipart(&session.file_name, &session.multipart_id, part_ids)
+            .await
+        {
+            Ok(res) => res,
+            Err(e) => {
+                session.closed = false;
+                return Err(anyhow!("unable to complete upload: {}", e));
+            }
+        };
+        self.remove(&session).await;
+        let encoded = self.blob_storage.is_encoding();
+        let hash = match session.content_hash() {
+            Some(hash) => hash,
+            None => match self.hash_blob(&res.url, encoded).await {
+                Ok(hash) => hash,
+                Err(e) => {
+                    if let Err(e) = self.blob_storage.delete(&res.url).await {
+                        error!("unable to delete blob {}: {}", res.url, e);
+                    }
+                    return Err(anyhow!("unable to hash uploaded content: {}", e));
+                }
+            },
+        };
+
+        Ok(indexify_coordinator::ContentMetadata {
+            id: session.content_id.clone(),
+            file_name: session.file_name.clone(),
+            storage_url: res.url,
+            parent_id: "".to_string(),
+            root_content_id: "".to_string(),
+            created_at: now_secs() as i64,
+            mime: session.mime_type.clone(),
+            namespace: session.namespace.clone(),
+            labels: session.labels.clone(),
+            source: "".to_string(),
+            size_bytes: session.received_bytes(),
+            hash,
+            extraction_policy_ids: HashMap::new(),
+            extraction_graph_names: session.extraction_graph_names.clone(),
+            extracted_metadata: String::new(),
+            encoded,
+        })
+    }
+
+    pub async fn abort(&self, namespace: &str, upload_id: &str) -> Result<()> {
+        let session = self.session(namespace, upload_id).await?;
+        let mut session = session.lock().await;
+        if session.closed {
+            return Ok(());
+        }
+        self.refresh(&mut session).await?;
+        session.closed = true;
+        self.blob_storage
+            .abort_multipart(&session.file_name, &session.multipart_id)
+            .await?;
+        self.remove(&session).await;
+        Ok(())
+    }
+
+    /// Aborts every session which has expired and returns how many were
+    /// removed.
+    pub async fn remove_expired(&self) -> usize {
+        let now = now_secs();
+        let sessions: Vec<Arc<Mutex<UploadSession>>> =
+            self.sessions.read().await.values().cloned().collect();
+        let mut removed = 0;
+        for session in sessions {
+            let mut session = session.lock().await;
+            if session.closed || session.expires_at > now {
+                continue;
+            }
+            info!("removing expired upload session {}", session.id);
+            session.closed = true;
+            self.discard(&session).await;
+            removed += 1;
+        }
+        removed
+    }
+
+    /// Loads the sessions recorded in the blob store, aborting the ones which
+    /// expired, and discards the parts staged for uploads which no session
+    /// refers to, such as the ones of a server which stopped before recording
+    /// their session. Returns how many sessions were loaded.
+    ///
+    /// S3 doesn't list the multipart uploads of a bucket through this client,
+    /// a lifecycle rule aborting incomplete multipart uploads should be set on
+    /// the bucket for the ones which were never recorded.
+    pub async fn recover(&self) -> Result<usize> {
+        let store = self.blob_storage.object_store()?;
+        let records: Vec<Path> = store
+            .list(Some(&Path::from(SESSION_PREFIX)))
+            .map_ok(|meta| meta.location)
+            .try_collect()
+            .await?;
+        let now = now_secs();
+        let mut multipart_ids = HashSet::new();
+        let mut recovered = 0;
+        for location in records {
+            let session = match Self::read_session(store.as_ref(), &location).await {
+                Ok(Some(session)) => session,
+                Ok(None) => continue,
+                Err(e) => {
+                    error!("unable to read upload session {}: {}", location, e);
+                    continue;
+                }
+            };
+            if session.expires_at <= now {
+                info!("removing expired upload session {}", session.id);
+                self.discard(&session).await;
+                continue;
+            }
+            multipart_ids.insert(session.multipart_id.clone());
+            self.sessions
+                .write()
+                .await
+                .insert(session.id.clone(), Arc::new(Mutex::new(session)));
+            recovered += 1;
+        }
+        for upload_id in self.blob_storage.staged_uploads().await? {
+            if multipart_ids.contains(&upload_id) {
+                continue;
+            }
+            info!("removing parts of orphaned upload {}", upload_id);
+            // Staged parts are found by upload id alone
+            if let Err(e) = self.blob_storage.abort_multipart("", &upload_id).await {
+                error!("unable to remove orphaned upload {}: {}", upload_id, e);
+            }
+        }
+        Ok(recovered)
+    }
+
+    pub fn start_expiry_loop(self: &Arc<Self>, mut shutdown_rx: watch::Receiver<bool>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EXPIRY_SWEEP_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        manager.remove_expired().await;
+                    }
+                    _ = shutdown_rx.changed() => {
+                        info!("shutting down upload session expiry loop");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    // Sessions created through another server are loaded from their record,
+    // callers reload the parts and expiry of the session with `refresh` once
+    // they hold its lock
+    async fn session(&self, namespace: &str, upload_id: &str) -> Result<Arc<Mutex<UploadSession>>> {
+        let not_found = || anyhow!("upload session {} not found", upload_id);
+        let cached = self.sessions.read().await.get(upload_id).cloned();
+        let session = match cached {
+            Some(session) => session,
+            None => {
+                let store = self.blob_storage.object_store()?;
+                let session = Self::read_session(store.as_ref(), &record_path(upload_id)?)
+                    .await?
+                    .ok_or_else(not_found)?;
+                self.sessions
+                    .write()
+                    .await
+                    .entry(upload_id.to_string())
+                    .or_insert_with(|| Arc::new(Mutex::new(session)))
+                    .clone()
+            }
+        };
+        if session.lock().await.namespace != namespace {
+            return Err(not_found());
+        }
+        Ok(session)
+    }
+
+    // Reloads the parts and expiry of the session, which other servers
+    // sharing the blob store may have changed. Sessions completed or aborted
+    // through another server are forgotten.
+    async fn refresh(&self, session: &mut UploadSession) -> Result<()> {
+        let store = self.blob_storage.object_store()?;
+        let record: Option<UploadSession> =
+            Self::read_record(store.as_ref(), &record_path(&session.id)?).await?;
+        let Some(record) = record else {
+            session.closed = true;
+            self.sessions.write().await.remove(&session.id);
+            return Err(anyhow!("upload session {} not found", session.id));
+        };
+        session.expires_at = session.expires_at.max(record.expires_at);
+        session.parts = Self::read_parts(store.as_ref(), &session.id).await?;
+        Ok(())
+    }
+
+    async fn read_session(
+        store: &dyn ObjectStore,
+        location: &Path,
+    ) -> Result<Option<UploadSession>> {
+        let Some(mut session) = Self::read_record::<UploadSession>(store, location).await? else {
+            return Ok(None);
+        };
+        session.parts = Self::read_parts(store, &session.id).await?;
+        Ok(Some(session))
+    }
+
+    async fn read_parts(
+        store: &dyn ObjectStore,
+        upload_id: &str,
+    ) -> Result<BTreeMap<usize, StagedPart>> {
+        let records: Vec<Path> = store
+            .list(Some(&parts_path(upload_id)?))
+            .map_ok(|meta| meta.location)
+            .try_collect()
+            .await?;
+        let mut parts = BTreeMap::new();
+        for location in records {
+            let part_number = location
+                .filename()
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|part_number| part_number.parse::<usize>().ok());
+            let Some(part_number) = part_number else {
+                continue;
+            };
+            if let Some(part) = Self::read_record(store, &location).await? {
+                parts.insert(part_number, part);
+            }
+        }
+        Ok(parts)
+    }
+
+    async fn read_record<T: DeserializeOwned>(
+        store: &dyn ObjectStore,
+        location: &Path,
+    ) -> Result<Option<T>> {
+        let bytes = match store.get(location).await {
+            Ok(result) => result.bytes().await?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(serde_json::from_slice(&bytes)?))
+    }
+
+    async fn save_part(
+        &self,
+        upload_id: &str,
+        part_number: usize,
+        part: &StagedPart,
+    ) -> Result<()> {
+        let record = serde_json::to_vec(part)?;
+        self.blob_storage
+            .object_store()?
+            .put(&part_path(upload_id, part_number)?, Bytes::from(record))
+            .await?;
+        Ok(())
+    }
+
+    async fn save(&self, session: &UploadSession) -> Result<()> {
+        let record = serde_json::to_vec(session)?;
+        self.blob_storage
+            .object_store()?
+            .put(&record_path(&session.id)?, Bytes::from(record))
+            .await?;
+        Ok(())
+    }
+
+    // Forgets a session which was completed or aborted
+    async fn remove(&self, session: &UploadSession) {
+        self.sessions.write().await.remove(&session.id);
+        if let Err(e) = self.delete_record(&session.id).await {
+            error!(
+                "unable to remove record of upload session {}: {}",
+                session.id, e
+            );
+        }
+    }
+
+    async fn delete_record(&self, upload_id: &str) -> Result<()> {
+        let store = self.blob_storage.object_store()?;
+        let record = record_path(upload_id)?;
+        let part_records: Vec<Path> = store
+            .list(Some(&parts_path(upload_id)?))
+            .map_ok(|meta| meta.location)
+            .try_collect()
+            .await?;
+        for location in part_records.iter().chain([&record]) {
+            match store.delete(location).await {
+                Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    // Aborts the upload of a session and forgets it
+    async fn discard(&self, session: &UploadSession) {
+        if let Err(e) = self
+            .blob_storage
+            .abort_multipart(&session.file_name, &session.multipart_id)
+            .await
+        {
+            error!("unable to abort upload session {}: {}", session.id, e);
+        }
+        self.remove(session).await;
+    }
+
+    async fn hash_blob(&self, url: &str, encoded: bool) -> Result<String> {
+        let reader = self.blob_storage.content_reader().get(url, encoded);
+        let mut stream = reader.get(url);
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = stream.next().await {
+            hasher.update(&chunk?);
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::blob_storage::{BlobStorageConfig, ContentReader, DiskStorageConfig};
+
+    fn test_manager(path: &str, ttl: Duration) -> UploadSessionManager {
+        let blob_storage = Arc::new(
+            BlobStorage::new_with_config(BlobStorageConfig {
+                s3: None,
+                disk: Some(DiskStorageConfig {
+                    path: path.to_string(),
+                }),
+                gcs: None,
+                azure: None,
+                encoding: None,
+                cold: None,
+            })
+            .unwrap(),
+        );
+        UploadSessionManager::new(blob_storage, ttl)
+    }
+
+    #[tokio::test]
+    async fn test_resumable_upload() -> Result<()> {
+        let dir = tempdir()?;
+        let manager = test_manager(dir.path().to_str().unwrap(), Duration::from_secs(60));
+
+        let session = manager
+            .create(
+                "test",
+                "abc123",
+                "upload.txt",
+                "text/plain",
+                HashMap::new(),
+                vec!["graph".to_string()],
+            )
+            .await?;
+
+        manager
+            .put_part("test", &session.id, 1, Bytes::from_static(b"world"))
+            .await?;
+        assert!(manager.complete("test", &session.id).await.is_err());
+        assert!(manager.get("other", &session.id).await.is_err());
+
+        let session = manager
+            .put_part("test", &session.id, 0, Bytes::from_static(b"hello "))
+            .await?;
+        let parts = session.parts();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1].offset, 6);
+        assert_eq!(session.received_bytes(), 11);
+
+        let content_metadata = manager.complete("test", &session.id).await?;
+        assert_eq!(content_metadata.id, "abc123");
+        assert_eq!(content_metadata.size_bytes, 11);
+        // parts which arrived out of order are hashed from the assembled blob
+        assert_eq!(
+            content_metadata.hash,
+            format!("{:x}", Sha256::digest(b"hello world"))
+        );
+        let bytes = ContentReader::default()
+            .bytes(&content_metadata.storage_url, content_metadata.encoded)
+            .await?;
+        assert_eq!(bytes, "hello world");
+        assert!(manager.get("test", &session.id).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_in_order_hash() -> Result<()> {
+        let dir = tempdir()?;
+        let manager = test_manager(dir.path().to_str().unwrap(), Duration::from_secs(60));
+        let session = manager
+            .create(
+                "test",
+                "abc123",
+                "upload.txt",
+                "text/plain",
+                HashMap::new(),
+                vec![],
+            )
+            .await?;
+        for (part_number, data) in ["hello", " ", "world"].iter().enumerate() {
+            manager
+                .put_part(
+                    "test",
+                    &session.id,
+                    part_number,
+                    Bytes::from_static(data.as_bytes()),
+                )
+                .await?;
+        }
+        let session = manager.get("test", &session.id).await?;
+        assert_eq!(
+            session.content_hash(),
+            Some(format!("{:x}", Sha256::digest(b"hello world")))
+        );
+        let content_metadata = manager.complete("test", &session.id).await?;
+        assert_eq!(
+            content_metadata.hash,
+            format!("{:x}", Sha256::digest(b"hello world"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().to_str().unwrap();
+        let manager = test_manager(path, Duration::from_secs(60));
+        let session = manager
+            .create(
+                "test",
+                "abc123",
+                "upload.txt",
+                "text/plain",
+                HashMap::new(),
+                vec![],
+            )
+            .await?;
+        manager
+            .put_part("test", &session.id, 0, Bytes::from_static(b"hello "))
+            .await?;
+        // parts staged for an upload without a session are orphaned
+        let orphan = manager.blob_storage.create_multipart("orphan.txt").await?;
+        manager
+            .blob_storage
+            .put_part("orphan.txt", &orphan, 0, Bytes::from_static(b"orphan"))
+            .await?;
+
+        // the session is resumed after a restart, or through another server
+        let restarted = test_manager(path, Duration::from_secs(60));
+        assert_eq!(restarted.recover().await?, 1);
+        assert!(!dir.path().join("uploads").join(&orphan).exists());
+        let session = restarted
+            .put_part("test", &session.id, 1, Bytes::from_static(b"world"))
+            .await?;
+        assert_eq!(session.received_bytes(), 11);
+        let other = test_manager(path, Duration::from_secs(60));
+        assert_eq!(other.get("test", &session.id).await?.parts().len(), 2);
+        let content_metadata = restarted.complete("test", &session.id).await?;
+        assert_eq!(
+            content_metadata.hash,
+            format!("{:x}", Sha256::digest(b"hello world"))
+        );
+        assert!(restarted.get("test", &session.id).await.is_err());
+
+        // expired sessions are aborted on recovery
+        let expiring = test_manager(path, Duration::from_secs(0));
+        expiring
+            .create(
+                "test",
+                "def456",
+                "expired.txt",
+                "text/plain",
+                HashMap::new(),
+                vec![],
+            )
+            .await?;
+        let restarted = test_manager(path, Duration::from_secs(60));
+        assert_eq!(restarted.recover().await?, 0);
+        assert!(restarted.blob_storage.staged_uploads().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shared_session() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().to_str().unwrap();
+        let server_a = test_manager(path, Duration::from_secs(60));
+        let server_b = test_manager(path, Duration::from_secs(60));
+        let session = server_a
+            .create(
+                "test",
+                "abc123",
+                "upload.txt",
+                "text/plain",
+                HashMap::new(),
+                vec![],
+            )
+            .await?;
+
+        // parts received by either server are seen by both, and a part
+        // replaced through the other server isn't hashed from memory
+        server_a
+            .put_part("test", &session.id, 0, Bytes::from_static(b"HELLO "))
+            .await?;
+        server_b
+            .put_part("test", &session.id, 0, Bytes::from_static(b"hello "))
+            .await?;
+        server_b
+            .put_part("test", &session.id, 1, Bytes::from_static(b"world"))
+            .await?;
+        let session = server_a
+            .put_part("test", &session.id, 2, Bytes::from_static(b"!"))
+            .await?;
+        assert_eq!(session.parts().len(), 3);
+        assert_eq!(server_b.get("test", &session.id).await?.parts().len(), 3);
+        let content_metadata = server_a.complete("test", &session.id).await?;
+        assert_eq!(
+            content_metadata.hash,
+            format!("{:x}", Sha256::digest(b"hello world!"))
+        );
+        assert!(server_b.get("test", &session.id).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_part_size() -> Result<()> {
+        let dir = tempdir()?;
+        let manager = test_manager(dir.path().to_str().unwrap(), Duration::from_secs(60));
+        let session = manager
+            .create(
+                "test",
+                "abc123",
+                "upload.txt",
+                "text/plain",
+                HashMap::new(),
+                vec![],
+            )
+            .await?;
+        manager
+            .put_part("test", &session.id, 1, Bytes::from_static(b"world"))
+            .await?;
+        let session = manager.get("test", &session.id).await?;
+        // only the last part can be smaller than the minimum
+        assert!(session.check_part_size(0, 6, 5).is_ok());
+        assert!(session.check_part_size(0, 4, 5).is_err());
+        assert!(session.check_part_size(2, 6, 6).is_err());
+        assert!(session.check_part_size(2, 6, 5).is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remove_expired() -> Result<()> {
+        let dir = tempdir()?;
+        let manager = test_manager(dir.path().to_str().unwrap(), Duration::from_secs(0));
+
+        let session = manager
+            .create(
+                "test",
+                "abc123",
+                "upload.txt",
+                "text/plain",
+                HashMap::new(),
+                vec![],
+            )
+            .await?;
+        assert_eq!(manager.remove_expired().await, 1);
+        assert!(manager
+            .put_part("test", &session.id, 0, Bytes::from_static(b"hello"))
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
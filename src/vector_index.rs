@@ -1,25 +1,68 @@
-use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
 
 use anyhow::{anyhow, Result};
 use bytes::Bytes;
 use futures::future::join_all;
 use indexify_internal_api as internal_api;
-use indexify_proto::indexify_coordinator::Index;
-use internal_api::ExtractedEmbeddings;
-use tracing::info;
+use indexify_proto::indexify_coordinator::{self, Index};
+use internal_api::{EmbeddingVector, ExtractedEmbeddings};
+use tracing::{info, warn};
 
 use crate::{
     api,
     blob_storage::ContentReader,
     coordinator_client::CoordinatorClient,
+    embedding_batcher::EmbeddingBatcher,
     extractor_router::ExtractorRouter,
     metrics::{vector_storage::Metrics, Timer},
-    vectordbs::{CreateIndexParams, Filter, IndexDistance, SearchResult, VectorChunk, VectorDBTS},
+    server_config::{IndexStoreKind, VectorIndexConfig},
+    vectordbs::{
+        self,
+        CreateIndexParams,
+        Filter,
+        IndexDistance,
+        IndexOptions,
+        SearchResult,
+        VectorChunk,
+        VectorDBTS,
+    },
 };
 
+/// How long the vector stores of indexes read from the coordinator are used
+/// before being read again. Migrations wait for every server to have read
+/// them before copying an index.
+const INDEX_ROUTES_TTL: Duration = Duration::from_secs(5);
+
+/// Number of vectors copied at once when migrating an index.
+const MIGRATION_PAGE_SIZE: u64 = 256;
+
+/// Number of times the vectors of a migrated index are counted before giving
+/// up, writes to both stores can be in flight while they are counted.
+const MIGRATION_VERIFY_ATTEMPTS: u32 = 5;
+
+/// Vector store and migration target of the indexes which aren't in the
+/// default vector store, by table name.
+struct IndexRoutes {
+    read_at: Option<Instant>,
+    routes: HashMap<String, (String, String)>,
+}
+
 pub struct VectorIndexManager {
     vector_db: VectorDBTS,
-    extractor_router: ExtractorRouter,
+    index_config: VectorIndexConfig,
+    vector_stores: HashMap<String, VectorIndexConfig>,
+    stores: RwLock<HashMap<String, VectorDBTS>>,
+    routes: RwLock<IndexRoutes>,
+    // Migrations started on this server, by table name
+    migrations: RwLock<HashMap<String, api::IndexMigrationStatus>>,
+    coordinator_client: Arc<CoordinatorClient>,
+    embedding_batcher: Arc<EmbeddingBatcher>,
     content_reader: Arc<ContentReader>,
     metrics: Metrics,
 }
@@ -41,35 +84,186 @@ pub struct ScoredText {
 }
 
 impl VectorIndexManager {
-    pub fn new(coordinator_client: Arc<CoordinatorClient>, vector_db: VectorDBTS) -> Result<Self> {
-        let extractor_router = ExtractorRouter::new(coordinator_client.clone())?;
-        let content_reader = Arc::new(ContentReader::new());
+    pub fn new(
+        coordinator_client: Arc<CoordinatorClient>,
+        index_config: VectorIndexConfig,
+        vector_stores: HashMap<String, VectorIndexConfig>,
+        vector_db: VectorDBTS,
+        content_reader: Arc<ContentReader>,
+        extractor_router: Arc<ExtractorRouter>,
+    ) -> Result<Self> {
         Ok(Self {
             vector_db,
-            extractor_router,
+            index_config,
+            vector_stores,
+            stores: RwLock::new(HashMap::new()),
+            routes: RwLock::new(IndexRoutes {
+                read_at: None,
+                routes: HashMap::new(),
+            }),
+            migrations: RwLock::new(HashMap::new()),
+            coordinator_client,
+            embedding_batcher: Arc::new(EmbeddingBatcher::new(extractor_router)),
             content_reader,
             metrics: Metrics::new(),
         })
     }
 
+    /// Name under which indexes refer to a vector store, the default store of
+    /// the server is referred to with an empty name.
+    fn store_name(&self, name: &str) -> String {
+        if !self.vector_stores.contains_key(name) &&
+            name == self.index_config.index_store.to_string()
+        {
+            String::new()
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Name of a vector store as configured, for the default store as well.
+    fn display_name(&self, name: &str) -> String {
+        if name.is_empty() {
+            self.index_config.index_store.to_string()
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Configuration of a vector store. Stores are either configured by name,
+    /// or are of a kind configured in the default index config.
+    fn store_config(&self, name: &str) -> Result<VectorIndexConfig> {
+        if let Some(config) = self.vector_stores.get(name) {
+            return Ok(config.clone());
+        }
+        let kind = IndexStoreKind::from_str(name)
+            .map_err(|_| anyhow!("unknown vector store: {}", name))?;
+        Ok(VectorIndexConfig {
+            index_store: kind,
+            ..self.index_config.clone()
+        })
+    }
+
+    /// Vector store with the specified name, created from the configuration
+    /// of the server the first time it is used.
+    async fn store(&self, name: &str) -> Result<VectorDBTS> {
+        let name = self.store_name(name);
+        if name.is_empty() {
+            return Ok(self.vector_db.clone());
+        }
+        if let Some(store) = self.stores.read().unwrap().get(&name) {
+            return Ok(store.clone());
+        }
+        let store = vectordbs::create_vectordb(self.store_config(&name)?).await?;
+        Ok(self
+            .stores
+            .write()
+            .unwrap()
+            .entry(name)
+            .or_insert(store)
+            .clone())
+    }
+
+    /// Vector store and migration target of an index, read again from the
+    /// coordinator once they are older than `INDEX_ROUTES_TTL`. Routes which
+    /// can't be read again fail, as the index could have been migrated.
+    async fn route(&self, table_name: &str) -> Result<(String, String)> {
+        let stale = {
+            let routes = self.routes.read().unwrap();
+            routes
+                .read_at
+                .map_or(true, |read_at| read_at.elapsed() >= INDEX_ROUTES_TTL)
+        };
+        if stale {
+            let routes = self
+                .read_routes()
+                .await
+                .map_err(|e| anyhow!("unable to read vector stores of indexes: {}", e))?;
+            *self.routes.write().unwrap() = IndexRoutes {
+                read_at: Some(Instant::now()),
+                routes,
+            }
+        }
+        Ok(self
+            .routes
+            .read()
+            .unwrap()
+            .routes
+            .get(table_name)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn read_routes(&self) -> Result<HashMap<String, (String, String)>> {
+        let indexes = self
+            .coordinator_client
+            .get()
+            .await?
+            .list_index_stores(indexify_coordinator::ListIndexStoresRequest {})
+            .await?
+            .into_inner()
+            .indexes;
+        Ok(indexes
+            .into_iter()
+            .map(|index| (index.table_name, (index.vector_store, index.migrating_to)))
+            .collect())
+    }
+
+    /// Store reads of an index go to.
+    async fn read_store(&self, table_name: &str) -> Result<VectorDBTS> {
+        let (vector_store, _) = self.route(table_name).await?;
+        self.store(&vector_store).await
+    }
+
+    /// Stores writes of an index go to, which includes the store the index is
+    /// being migrated to.
+    async fn write_stores(&self, table_name: &str) -> Result<Vec<VectorDBTS>> {
+        let (vector_store, migrating_to) = self.route(table_name).await?;
+        let mut stores = vec![self.store(&vector_store).await?];
+        if !migrating_to.is_empty() {
+            stores.push(self.store(&migrating_to).await?);
+        }
+        Ok(stores)
+    }
+
+    /// Returns an error if the vector store of the options doesn't exist or
+    /// can't create an index with them.
+    pub async fn validate_index_options(&self, options: &IndexOptions) -> Result<()> {
+        let vector_store = options.vector_store.clone().unwrap_or_default();
+        self.store(&vector_store)
+            .await?
+            .validate_index_options(options)
+    }
+
+    /// Creates an index in the vector store of the options, and returns the
+    /// name of the store indexes refer to it with.
     pub async fn create_index(
         &self,
         index_name: &str,
         schema: internal_api::EmbeddingSchema,
+        options: IndexOptions,
     ) -> Result<String> {
+        let vector_store = self.store_name(&options.vector_store.clone().unwrap_or_default());
         let create_index_params = CreateIndexParams {
             vectordb_index_name: index_name.to_string(),
             vector_dim: schema.dim as u64,
             distance: IndexDistance::from_str(schema.distance.as_str())?,
-            unique_params: None,
+            kind: schema.kind,
+            options,
         };
         info!("Creating index: {:?}", create_index_params);
-        self.vector_db.create_index(create_index_params).await?;
-        Ok(index_name.to_string())
+        self.store(&vector_store)
+            .await?
+            .create_index(create_index_params)
+            .await?;
+        Ok(vector_store)
     }
 
     pub async fn drop_index(&self, index_name: &str) -> Result<()> {
-        self.vector_db.drop_index(index_name).await
+        for store in self.write_stores(index_name).await? {
+            store.drop_index(index_name).await?;
+        }
+        Ok(())
     }
 
     pub async fn add_embedding(
@@ -89,17 +283,21 @@ impl VectorIndexManager {
             );
             vector_chunks.push(vector_chunk);
         });
-        self.vector_db
-            .add_embedding(vector_index_name, vector_chunks)
-            .await?;
+        for store in self.write_stores(vector_index_name).await? {
+            store
+                .add_embedding(vector_index_name, vector_chunks.clone())
+                .await?;
+        }
         Ok(())
     }
 
     pub async fn remove_embedding(&self, vector_index_name: &str, content_id: &str) -> Result<()> {
         let _timer = Timer::start(&self.metrics.vector_delete);
-        self.vector_db
-            .remove_embedding(vector_index_name, content_id)
-            .await?;
+        for store in self.write_stores(vector_index_name).await? {
+            store
+                .remove_embedding(vector_index_name, content_id)
+                .await?;
+        }
         Ok(())
     }
 
@@ -108,7 +306,10 @@ impl VectorIndexManager {
         index: &str,
         content_ids: Vec<String>,
     ) -> Result<Vec<VectorChunk>> {
-        self.vector_db.get_points(index, content_ids).await
+        self.read_store(index)
+            .await?
+            .get_points(index, content_ids)
+            .await
     }
 
     pub async fn update_metadata(
@@ -118,9 +319,272 @@ impl VectorIndexManager {
         metadata: HashMap<String, serde_json::Value>,
     ) -> Result<()> {
         let _timer = Timer::start(&self.metrics.vector_metadata_update);
-        self.vector_db
-            .update_metadata(index, content_id, metadata)
-            .await
+        for store in self.write_stores(index).await? {
+            store
+                .update_metadata(index, content_id.clone(), metadata.clone())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Starts migrating an index to another vector store in the background,
+    /// once the index can be created in the store with its options. The
+    /// progress is reported by `migration_status`.
+    pub async fn start_migration(
+        self: &Arc<Self>,
+        index: Index,
+        to: &str,
+    ) -> Result<api::IndexMigrationStatus> {
+        let to = self.store_name(to);
+        self.check_migration(&index, &to).await?;
+        let status = api::IndexMigrationStatus {
+            vector_store: self.display_name(&to),
+            state: api::IndexMigrationState::Running,
+            num_vectors: None,
+            error: None,
+        };
+        {
+            let mut migrations = self.migrations.write().unwrap();
+            let running = migrations
+                .get(&index.table_name)
+                .map_or(false, |s| s.state == api::IndexMigrationState::Running);
+            if running {
+                return Err(anyhow!("index {} is already being migrated", index.name));
+            }
+            migrations.insert(index.table_name.clone(), status.clone());
+        }
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let result = manager.migrate_index(index.clone(), &to).await;
+            let mut migrations = manager.migrations.write().unwrap();
+            let Some(status) = migrations.get_mut(&index.table_name) else {
+                return;
+            };
+            match result {
+                Ok((_, num_vectors)) => {
+                    status.state = api::IndexMigrationState::Completed;
+                    status.num_vectors = Some(num_vectors);
+                }
+                Err(e) => {
+                    warn!("unable to migrate index {}: {}", index.name, e);
+                    status.state = api::IndexMigrationState::Failed;
+                    status.error = Some(e.to_string());
+                }
+            }
+        });
+        Ok(status)
+    }
+
+    /// Status of the last migration of an index started on this server, or of
+    /// the migration in progress according to the index. A migration which
+    /// was interrupted is resumed by migrating the index to the same store.
+    pub fn migration_status(&self, index: &Index) -> Option<api::IndexMigrationStatus> {
+        if let Some(status) = self.migrations.read().unwrap().get(&index.table_name) {
+            return Some(status.clone());
+        }
+        (!index.migrating_to.is_empty()).then(|| api::IndexMigrationStatus {
+            vector_store: self.display_name(&index.migrating_to),
+            state: api::IndexMigrationState::Running,
+            num_vectors: None,
+            error: None,
+        })
+    }
+
+    /// Options of an index to create it with in another vector store.
+    fn index_options(index: &Index) -> Result<IndexOptions> {
+        if index.index_options.is_empty() {
+            return Ok(IndexOptions::default());
+        }
+        let options: IndexOptions = serde_json::from_str(&index.index_options)
+            .map_err(|e| anyhow!("invalid options of index {}: {}", index.name, e))?;
+        Ok(IndexOptions {
+            vector_store: None,
+            ..options
+        })
+    }
+
+    // Fails if the index can't be migrated to the `to` store
+    async fn check_migration(&self, index: &Index, to: &str) -> Result<()> {
+        if to == index.vector_store {
+            return Err(anyhow!(
+                "index {} is already in vector store {}",
+                index.name,
+                self.display_name(to)
+            ));
+        }
+        if !index.migrating_to.is_empty() && index.migrating_to != to {
+            return Err(anyhow!(
+                "index {} is being migrated to vector store {}",
+                index.name,
+                self.display_name(&index.migrating_to)
+            ));
+        }
+        self.store(to)
+            .await?
+            .validate_index_options(&Self::index_options(index)?)
+            .map_err(|e| {
+                anyhow!(
+                    "index {} can't be migrated to vector store {}: {}",
+                    index.name,
+                    self.display_name(to),
+                    e
+                )
+            })
+    }
+
+    /// Copies the vectors of an index to another vector store and switches the
+    /// index to it once both stores hold the same number of vectors. Writes go
+    /// to both stores while the vectors are copied. The index is created in
+    /// the target store with the options it was created with, and is left in
+    /// its vector store when the migration fails. The source is kept
+    /// afterwards. Vectors which were already in the target store are
+    /// replaced. Returns the name of the new store and the number of vectors
+    /// copied.
+    pub async fn migrate_index(&self, index: Index, to: &str) -> Result<(String, u64)> {
+        let to = self.store_name(to);
+        self.check_migration(&index, &to).await?;
+        let source = self.store(&index.vector_store).await?;
+        let target = self.store(&to).await?;
+        let schema: internal_api::EmbeddingSchema = serde_json::from_str(&index.schema)?;
+
+        //  Vectors left in the store by an interrupted migration, or from when the
+        // index was last in it, are copied again. The index is created before
+        // writes go to it.
+        target.drop_index(&index.table_name).await?;
+        target
+            .create_index(CreateIndexParams {
+                vectordb_index_name: index.table_name.clone(),
+                vector_dim: schema.dim as u64,
+                distance: IndexDistance::from_str(schema.distance.as_str())?,
+                kind: schema.kind,
+                options: Self::index_options(&index)?,
+            })
+            .await?;
+        if index.migrating_to.is_empty() {
+            self.update_index_store(
+                &index,
+                (&index.vector_store, ""),
+                (&index.vector_store, &to),
+            )
+            .await?;
+            //  Every server has to be writing to both stores before vectors are copied
+            tokio::time::sleep(INDEX_ROUTES_TTL * 2).await;
+        }
+        info!(
+            "migrating index {} to vector store {}",
+            index.name,
+            self.display_name(&to)
+        );
+
+        let copied = self
+            .copy_index(&index.table_name, source, target.clone())
+            .await;
+        let num_vectors = match copied {
+            Ok(num_vectors) => num_vectors,
+            Err(e) => {
+                //  Writes stop going to the target before it is dropped
+                if let Err(rollback_err) = self
+                    .update_index_store(
+                        &index,
+                        (&index.vector_store, &to),
+                        (&index.vector_store, ""),
+                    )
+                    .await
+                {
+                    return Err(anyhow!(
+                        "{}, and unable to roll back the migration: {}",
+                        e,
+                        rollback_err
+                    ));
+                }
+                if let Err(e) = target.drop_index(&index.table_name).await {
+                    warn!(
+                        "unable to drop index {} after failed migration: {}",
+                        index.name, e
+                    );
+                }
+                return Err(e);
+            }
+        };
+
+        self.update_index_store(&index, (&index.vector_store, &to), (&to, ""))
+            .await?;
+        Ok((self.display_name(&to), num_vectors))
+    }
+
+    async fn copy_index(
+        &self,
+        table_name: &str,
+        source: VectorDBTS,
+        target: VectorDBTS,
+    ) -> Result<u64> {
+        let mut cursor = None;
+        loop {
+            let (chunks, next) = source
+                .scan_points(table_name, cursor, MIGRATION_PAGE_SIZE)
+                .await?;
+            if !chunks.is_empty() {
+                target.add_embedding(table_name, chunks).await?;
+            }
+            cursor = next;
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        let mut attempts = 0;
+        loop {
+            let expected = source.num_vectors(table_name).await?;
+            let copied = target.num_vectors(table_name).await?;
+            if expected == copied {
+                return Ok(copied);
+            }
+            attempts += 1;
+            if attempts == MIGRATION_VERIFY_ATTEMPTS {
+                return Err(anyhow!(
+                    "index {} has {} vectors after being copied, expected {}",
+                    table_name,
+                    copied,
+                    expected
+                ));
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    /// Moves an index from the `from` vector store and migration target to the
+    /// `to` ones, failing if it was moved by someone else in the meantime.
+    async fn update_index_store(
+        &self,
+        index: &Index,
+        from: (&str, &str),
+        to: (&str, &str),
+    ) -> Result<()> {
+        let updated = self
+            .coordinator_client
+            .get()
+            .await?
+            .update_index_store(indexify_coordinator::UpdateIndexStoreRequest {
+                namespace: index.namespace.clone(),
+                name: index.name.clone(),
+                from_vector_store: from.0.to_string(),
+                from_migrating_to: from.1.to_string(),
+                vector_store: to.0.to_string(),
+                migrating_to: to.1.to_string(),
+            })
+            .await?
+            .into_inner()
+            .index
+            .ok_or_else(|| anyhow!("index {} not found", index.name))?;
+        if updated.vector_store != to.0 || updated.migrating_to != to.1 {
+            return Err(anyhow!(
+                "vector store of index {} was changed concurrently",
+                index.name
+            ));
+        }
+        //  Routes of this server are up to date right away
+        self.routes.write().unwrap().read_at = None;
+        Ok(())
     }
 
     pub async fn search(
@@ -148,7 +612,13 @@ impl VectorIndexManager {
         let embedding = self.generate_embedding(&index.extractor, content).await?;
 
         let search_result = self
-            .search_vector_db(index.table_name, embedding.values, k as u64, filters)
+            .search_vector_db(
+                &index.vector_store,
+                index.table_name,
+                embedding.values,
+                k as u64,
+                filters,
+            )
             .await?;
 
         let mut content_byte_map = HashMap::new();
@@ -196,8 +666,8 @@ impl VectorIndexManager {
     ) -> Result<internal_api::Embedding> {
         let _timer = Timer::start(&self.metrics.vector_search_extract_embeddings);
         let feature = self
-            .extractor_router
-            .extract_content(extractor, content, None)
+            .embedding_batcher
+            .extract(extractor, content)
             .await
             .map_err(|e| anyhow!("unable to extract embedding: {}", e.to_string()))?
             .features
@@ -212,13 +682,18 @@ impl VectorIndexManager {
 
     async fn search_vector_db(
         &self,
+        vector_store: &str,
         index: String,
-        embedding: Vec<f32>,
+        embedding: EmbeddingVector,
         k: u64,
         filters: Vec<Filter>,
     ) -> Result<Vec<SearchResult>> {
         let _timer = Timer::start(&self.metrics.vector_search_db);
-        let search_result = self.vector_db.search(index, embedding, k, filters).await?;
+        let search_result = self
+            .store(vector_store)
+            .await?
+            .search(index, embedding, k, filters)
+            .await?;
         Ok(search_result)
     }
 
@@ -231,9 +706,10 @@ impl VectorIndexManager {
         let mut content_ids = Vec::new();
 
         for search_result in search_results {
-            let content = self
-                .content_reader
-                .bytes(&search_result.content_metadata.storage_url);
+            let content = self.content_reader.bytes(
+                &search_result.content_metadata.storage_url,
+                search_result.content_metadata.encoded,
+            );
             content_bytes_list.push(content);
             content_ids.push(search_result.content_metadata.id.id.clone());
         }
@@ -252,3 +728,108 @@ impl VectorIndexManager {
         Ok(content_byte_map)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blob_storage::BlobStorage,
+        caching::caches_extension::Caches,
+        server_config::{EmbeddedVectorDbConfig, ServerCacheConfig, ServerConfig},
+    };
+
+    fn embedded_config(dir: &tempfile::TempDir) -> VectorIndexConfig {
+        VectorIndexConfig {
+            index_store: IndexStoreKind::Embedded,
+            embedded_config: Some(EmbeddedVectorDbConfig {
+                path: dir.path().to_str().unwrap().to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_named_vector_stores() -> Result<()> {
+        let (default_dir, small_dir) = (tempfile::tempdir()?, tempfile::tempdir()?);
+        let config = ServerConfig::default();
+        let index_config = embedded_config(&default_dir);
+        let vector_db = vectordbs::create_vectordb(index_config.clone()).await?;
+        let blob_storage = BlobStorage::new_with_config(config.blob_storage.clone())?;
+        let coordinator_client = Arc::new(CoordinatorClient::new(Arc::new(config)));
+        let extractor_router = ExtractorRouter::new(
+            coordinator_client.clone(),
+            Caches::new(ServerCacheConfig::default()),
+        )?;
+        let manager = VectorIndexManager::new(
+            coordinator_client,
+            index_config,
+            HashMap::from([("small".to_string(), embedded_config(&small_dir))]),
+            vector_db.clone(),
+            Arc::new(blob_storage.content_reader()),
+            Arc::new(extractor_router),
+        )?;
+        let schema = internal_api::EmbeddingSchema {
+            dim: 2,
+            distance: "cosine".to_string(),
+            kind: internal_api::EmbeddingKind::Dense,
+        };
+        let options = |vector_store: &str| IndexOptions {
+            vector_store: Some(vector_store.to_string()),
+            ..Default::default()
+        };
+
+        let vector_store = manager
+            .create_index("small_index", schema.clone(), options("small"))
+            .await?;
+        assert_eq!(vector_store, "small");
+        let small_db = manager.store("small").await?;
+        assert_eq!(small_db.num_vectors("small_index").await?, 0);
+        assert!(vector_db.num_vectors("small_index").await.is_err());
+
+        //  The default store is referred to without a name
+        let vector_store = manager
+            .create_index("default_index", schema.clone(), options("embedded"))
+            .await?;
+        assert_eq!(vector_store, "");
+        assert_eq!(vector_db.num_vectors("default_index").await?, 0);
+        assert!(small_db.num_vectors("default_index").await.is_err());
+
+        assert!(manager
+            .validate_index_options(&options("missing"))
+            .await
+            .is_err());
+        assert!(manager
+            .create_index("missing_index", schema, options("missing"))
+            .await
+            .is_err());
+
+        //  Indexes are migrated with the options they were created with
+        let hnsw = IndexOptions {
+            vector_store: Some("qdrant".to_string()),
+            index_type: Some(vectordbs::IndexType::Hnsw {
+                m: None,
+                ef_construction: None,
+            }),
+            quantization: None,
+        };
+        let index = Index {
+            name: "hnsw_index".to_string(),
+            table_name: "hnsw_index".to_string(),
+            vector_store: "qdrant".to_string(),
+            index_options: serde_json::to_string(&hnsw)?,
+            ..Default::default()
+        };
+        let migrated_options = VectorIndexManager::index_options(&index)?;
+        assert_eq!(migrated_options.vector_store, None);
+        assert_eq!(migrated_options.index_type, hnsw.index_type);
+        assert!(manager.check_migration(&index, "small").await.is_err());
+        let flat = Index {
+            index_options: String::new(),
+            ..index
+        };
+        assert!(manager.check_migration(&flat, "small").await.is_ok());
+        assert!(manager.check_migration(&flat, "qdrant").await.is_err());
+        assert!(manager.migration_status(&flat).is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,897 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    ops::Bound,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use indexify_internal_api::{EmbeddingKind, EmbeddingVector, SparseVector};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{
+    CreateIndexParams,
+    Filter,
+    FilterOperator,
+    IndexDistance,
+    IndexOptions,
+    IndexType,
+    SearchResult,
+    VectorChunk,
+    VectorDb,
+};
+use crate::server_config::EmbeddedVectorDbConfig;
+
+/// Extension of the files holding the log of an index.
+const LOG_EXTENSION: &str = "log";
+
+/// Logs are compacted once they hold this many more entries than vectors.
+const COMPACTION_SLACK: usize = 1000;
+
+/// Entry of the log of an index. Indexes are persisted as the log of their
+/// changes, which is replayed when the store is opened.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum LogEntry {
+    Create {
+        name: String,
+        vector_dim: u64,
+        distance: IndexDistance,
+        #[serde(default)]
+        kind: EmbeddingKind,
+    },
+    Upsert {
+        chunk: VectorChunk,
+    },
+    Remove {
+        content_id: String,
+    },
+    UpdateMetadata {
+        content_id: String,
+        metadata: HashMap<String, serde_json::Value>,
+    },
+}
+
+struct EmbeddedIndex {
+    name: String,
+    vector_dim: u64,
+    distance: IndexDistance,
+    kind: EmbeddingKind,
+    // Ordered by content id, which scans page through
+    chunks: BTreeMap<String, VectorChunk>,
+    path: PathBuf,
+    log: BufWriter<File>,
+    log_entries: usize,
+}
+
+impl EmbeddedIndex {
+    fn create(
+        path: PathBuf,
+        name: &str,
+        vector_dim: u64,
+        distance: IndexDistance,
+        kind: EmbeddingKind,
+    ) -> Result<Self> {
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("unable to create index file {}", path.display()))?;
+        let mut index = Self {
+            name: name.to_string(),
+            vector_dim,
+            distance,
+            kind,
+            chunks: BTreeMap::new(),
+            path,
+            log: BufWriter::new(log),
+            log_entries: 0,
+        };
+        let header = index.create_entry();
+        index.append(&[header])?;
+        Ok(index)
+    }
+
+    fn open(path: PathBuf) -> Result<Self> {
+        let file = File::open(&path)?;
+        let mut lines = BufReader::new(file).lines();
+        let header = lines
+            .next()
+            .ok_or_else(|| anyhow!("index file {} is empty", path.display()))??;
+        let LogEntry::Create {
+            name,
+            vector_dim,
+            distance,
+            kind,
+        } = serde_json::from_str::<LogEntry>(&header)?
+        else {
+            return Err(anyhow!("index file {} has no header", path.display()));
+        };
+        let mut chunks = BTreeMap::new();
+        let mut log_entries = 1;
+        for line in lines {
+            let line = line?;
+            // A write cut short by a crash leaves a partial last line
+            let Ok(entry) = serde_json::from_str::<LogEntry>(&line) else {
+                tracing::warn!("skipping corrupted entry of index file {}", path.display());
+                continue;
+            };
+            Self::apply(&mut chunks, entry);
+            log_entries += 1;
+        }
+        let log = OpenOptions::new().append(true).open(&path)?;
+        Ok(Self {
+            name,
+            vector_dim,
+            distance,
+            kind,
+            chunks,
+            path,
+            log: BufWriter::new(log),
+            log_entries,
+        })
+    }
+
+    fn create_entry(&self) -> LogEntry {
+        LogEntry::Create {
+            name: self.name.clone(),
+            vector_dim: self.vector_dim,
+            distance: self.distance.clone(),
+            kind: self.kind,
+        }
+    }
+
+    fn apply(chunks: &mut BTreeMap<String, VectorChunk>, entry: LogEntry) {
+        match entry {
+            LogEntry::Create { .. } => {}
+            LogEntry::Upsert { chunk } => {
+                chunks.insert(chunk.content_id.clone(), chunk);
+            }
+            LogEntry::Remove { content_id } => {
+                chunks.remove(&content_id);
+            }
+            LogEntry::UpdateMetadata {
+                content_id,
+                metadata,
+            } => {
+                if let Some(chunk) = chunks.get_mut(&content_id) {
+                    chunk.metadata.extend(metadata);
+                }
+            }
+        }
+    }
+
+    /// Persists the entries before applying them, so that the index in memory
+    /// never has changes which are not on disk.
+    fn write(&mut self, entries: Vec<LogEntry>) -> Result<()> {
+        self.append(&entries)?;
+        for entry in entries {
+            Self::apply(&mut self.chunks, entry);
+        }
+        if self.log_entries > 2 * self.chunks.len() + COMPACTION_SLACK {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, entries: &[LogEntry]) -> Result<()> {
+        for entry in entries {
+            serde_json::to_writer(&mut self.log, entry)?;
+            self.log.write_all(b"\n")?;
+        }
+        self.log.flush()?;
+        self.log_entries += entries.len();
+        Ok(())
+    }
+
+    // Rewrites the log with a single entry per vector
+    fn compact(&mut self) -> Result<()> {
+        let tmp_path = self.path.with_extension("compact");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer(&mut writer, &self.create_entry())?;
+        writer.write_all(b"\n")?;
+        for chunk in self.chunks.values() {
+            serde_json::to_writer(
+                &mut writer,
+                &LogEntry::Upsert {
+                    chunk: chunk.clone(),
+                },
+            )?;
+            writer.write_all(b"\n")?;
+        }
+        writer.into_inner()?.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        self.log = BufWriter::new(OpenOptions::new().append(true).open(&self.path)?);
+        self.log_entries = self.chunks.len() + 1;
+        Ok(())
+    }
+
+    /// Returns the embedding as stored in the index, or an error if it isn't
+    /// of the kind and dimensions of the index. Dense vectors written to
+    /// multi-vector indexes, such as the pooled vectors of other stores, are
+    /// stored as a single vector.
+    fn check_embedding(&self, embedding: EmbeddingVector) -> Result<EmbeddingVector> {
+        let dim = self.vector_dim as usize;
+        let embedding = match (self.kind, embedding) {
+            (EmbeddingKind::MultiVector, EmbeddingVector::Dense(values)) => {
+                EmbeddingVector::MultiVector(vec![values])
+            }
+            (_, embedding) => embedding,
+        };
+        if embedding.kind() != self.kind {
+            return Err(anyhow!(
+                "index {} has {:?} embeddings, got a {:?} embedding",
+                self.name,
+                self.kind,
+                embedding.kind()
+            ));
+        }
+        let valid = match &embedding {
+            EmbeddingVector::Dense(values) => values.len() == dim,
+            EmbeddingVector::Sparse(sparse) => {
+                sparse.indices.len() == sparse.values.len() &&
+                    sparse.indices.iter().all(|i| (*i as usize) < dim)
+            }
+            EmbeddingVector::MultiVector(vectors) => {
+                !vectors.is_empty() && vectors.iter().all(|v| v.len() == dim)
+            }
+        };
+        if !valid {
+            return Err(anyhow!(
+                "embedding doesn't have the {} dimensions of index {}",
+                dim,
+                self.name
+            ));
+        }
+        Ok(embedding)
+    }
+
+    fn search(&self, query: &EmbeddingVector, k: usize, filters: &[Filter]) -> Vec<SearchResult> {
+        let mut scored = self
+            .chunks
+            .values()
+            .filter(|chunk| filters.iter().all(|f| matches_filter(&chunk.metadata, f)))
+            .map(|chunk| {
+                let score = score_embedding(&self.distance, query, &chunk.embedding);
+                (score, chunk)
+            })
+            .collect::<Vec<_>>();
+        // Euclidean scores are distances, the others are similarities
+        scored.sort_by(|(a, _), (b, _)| match self.distance {
+            IndexDistance::Euclidean => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            _ => b.partial_cmp(a).unwrap_or(Ordering::Equal),
+        });
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(score, chunk)| SearchResult {
+                content_id: chunk.content_id.clone(),
+                confidence_score: score,
+                metadata: chunk.metadata.clone(),
+                root_content_metadata: chunk.root_content_metadata.clone(),
+                content_metadata: chunk.content_metadata.clone(),
+            })
+            .collect()
+    }
+}
+
+fn score(distance: &IndexDistance, a: &[f32], b: &[f32]) -> f32 {
+    let dot = || a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    match distance {
+        IndexDistance::Dot => dot(),
+        IndexDistance::Cosine => {
+            let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            let norms = norm(a) * norm(b);
+            if norms == 0. {
+                0.
+            } else {
+                dot() / norms
+            }
+        }
+        IndexDistance::Euclidean => a
+            .iter()
+            .zip(b)
+            .map(|(x, y)| (x - y) * (x - y))
+            .sum::<f32>()
+            .sqrt(),
+    }
+}
+
+/// Scores sparse vectors without expanding them to all of their dimensions.
+fn score_sparse(distance: &IndexDistance, a: &SparseVector, b: &SparseVector) -> f32 {
+    let b_values: HashMap<u32, f32> = b.indices.iter().copied().zip(b.values.clone()).collect();
+    let dot = a
+        .indices
+        .iter()
+        .zip(&a.values)
+        .map(|(i, x)| x * b_values.get(i).unwrap_or(&0.))
+        .sum::<f32>();
+    let squared_norm = |v: &SparseVector| v.values.iter().map(|x| x * x).sum::<f32>();
+    match distance {
+        IndexDistance::Dot => dot,
+        IndexDistance::Cosine => {
+            let norms = (squared_norm(a) * squared_norm(b)).sqrt();
+            if norms == 0. {
+                0.
+            } else {
+                dot / norms
+            }
+        }
+        IndexDistance::Euclidean => (squared_norm(a) + squared_norm(b) - 2. * dot)
+            .max(0.)
+            .sqrt(),
+    }
+}
+
+/// Scores embeddings of the kind of the index. Multi-vectors are scored with
+/// late interaction, the sum over the query vectors of their best score
+/// against any vector of the embedding.
+fn score_embedding(distance: &IndexDistance, query: &EmbeddingVector, b: &EmbeddingVector) -> f32 {
+    match (query, b) {
+        (EmbeddingVector::Dense(a), EmbeddingVector::Dense(b)) => score(distance, a, b),
+        (EmbeddingVector::Sparse(a), EmbeddingVector::Sparse(b)) => score_sparse(distance, a, b),
+        (EmbeddingVector::MultiVector(a), EmbeddingVector::MultiVector(b)) => a
+            .iter()
+            .map(|q| {
+                let scores = b.iter().map(|v| score(distance, q, v));
+                match distance {
+                    IndexDistance::Euclidean => scores.fold(f32::INFINITY, f32::min),
+                    _ => scores.fold(f32::NEG_INFINITY, f32::max),
+                }
+            })
+            .sum(),
+        //  Kinds are checked when embeddings are written and searched
+        _ => 0.,
+    }
+}
+
+fn matches_filter(metadata: &HashMap<String, serde_json::Value>, filter: &Filter) -> bool {
+    let is_eq = match metadata.get(&filter.key) {
+        Some(serde_json::Value::String(value)) => *value == filter.value,
+        Some(value) => value.to_string() == filter.value,
+        None => false,
+    };
+    match filter.operator {
+        FilterOperator::Eq => is_eq,
+        FilterOperator::Neq => !is_eq,
+    }
+}
+
+type EmbeddedIndexTS = Arc<RwLock<EmbeddedIndex>>;
+
+/// Vector store running in the server process, for single node deployments
+/// and tests. Vectors are searched exhaustively and every index is persisted
+/// to a file of its own in the directory of the store. Files are written on
+/// the blocking thread pool so that writes don't stall the runtime.
+pub struct EmbeddedDb {
+    path: PathBuf,
+    indexes: RwLock<HashMap<String, EmbeddedIndexTS>>,
+}
+
+impl std::fmt::Debug for EmbeddedDb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmbeddedDb")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl EmbeddedDb {
+    pub fn new(config: &EmbeddedVectorDbConfig) -> Result<Self> {
+        let path = PathBuf::from(&config.path);
+        fs::create_dir_all(&path)
+            .with_context(|| format!("unable to create directory {}", path.display()))?;
+        let mut indexes = HashMap::new();
+        for entry in fs::read_dir(&path)? {
+            let file_path = entry?.path();
+            if file_path.extension().and_then(|e| e.to_str()) != Some(LOG_EXTENSION) {
+                continue;
+            }
+            let index = EmbeddedIndex::open(file_path.clone())
+                .with_context(|| format!("unable to open index {}", file_path.display()))?;
+            indexes.insert(index.name.clone(), Arc::new(RwLock::new(index)));
+        }
+        Ok(Self {
+            path,
+            indexes: RwLock::new(indexes),
+        })
+    }
+
+    // Index names are hashed so that any name, however long, is a valid file
+    // name. The name itself is kept in the header of the log.
+    fn index_path(&self, index: &str) -> PathBuf {
+        let file_name = format!("{:x}", Sha256::digest(index.as_bytes()));
+        self.path.join(format!("{}.{}", file_name, LOG_EXTENSION))
+    }
+
+    fn index(&self, index: &str) -> Result<EmbeddedIndexTS> {
+        self.indexes
+            .read()
+            .unwrap()
+            .get(index)
+            .cloned()
+            .ok_or_else(|| anyhow!("index not found: {}", index))
+    }
+
+    async fn write(&self, index: &str, entries: Vec<LogEntry>) -> Result<()> {
+        let index = self.index(index)?;
+        tokio::task::spawn_blocking(move || index.write().unwrap().write(entries)).await?
+    }
+
+    fn read<T>(&self, index: &str, f: impl FnOnce(&EmbeddedIndex) -> T) -> Result<T> {
+        let index = self.index(index)?;
+        let index = index.read().unwrap();
+        Ok(f(&index))
+    }
+}
+
+#[async_trait]
+impl VectorDb for EmbeddedDb {
+    fn name(&self) -> String {
+        "embedded".into()
+    }
+
+    fn validate_index_options(&self, options: &IndexOptions) -> Result<()> {
+        if !matches!(options.index_type, None | Some(IndexType::Flat)) {
+            return Err(anyhow!(
+                "the embedded vector store only supports flat indexes"
+            ));
+        }
+        if options.quantization.is_some() {
+            return Err(anyhow!(
+                "the embedded vector store doesn't support quantization of vectors"
+            ));
+        }
+        Ok(())
+    }
+
+    async fn create_index(&self, index: CreateIndexParams) -> Result<()> {
+        self.validate_index_options(&index.options)?;
+        if self
+            .indexes
+            .read()
+            .unwrap()
+            .contains_key(&index.vectordb_index_name)
+        {
+            return Ok(());
+        }
+        let path = self.index_path(&index.vectordb_index_name);
+        let name = index.vectordb_index_name.clone();
+        let embedded_index = tokio::task::spawn_blocking(move || {
+            EmbeddedIndex::create(path, &name, index.vector_dim, index.distance, index.kind)
+        })
+        .await??;
+        // An index created concurrently is kept, the header this one appended
+        // to the same file is skipped when the log is replayed
+        self.indexes
+            .write()
+            .unwrap()
+            .entry(index.vectordb_index_name)
+            .or_insert_with(|| Arc::new(RwLock::new(embedded_index)));
+        Ok(())
+    }
+
+    async fn add_embedding(&self, index: &str, chunks: Vec<VectorChunk>) -> Result<()> {
+        let entries = self.read(index, |index| {
+            chunks
+                .into_iter()
+                .map(|chunk| {
+                    let embedding = index.check_embedding(chunk.embedding).map_err(|e| {
+                        anyhow!("invalid embedding of content {}: {}", chunk.content_id, e)
+                    })?;
+                    Ok(LogEntry::Upsert {
+                        chunk: VectorChunk { embedding, ..chunk },
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+        })??;
+        self.write(index, entries).await
+    }
+
+    async fn remove_embedding(&self, index: &str, content_id: &str) -> Result<()> {
+        self.write(
+            index,
+            vec![LogEntry::Remove {
+                content_id: content_id.to_string(),
+            }],
+        )
+        .await
+    }
+
+    async fn get_points(&self, index: &str, content_ids: Vec<String>) -> Result<Vec<VectorChunk>> {
+        self.read(index, |index| {
+            content_ids
+                .iter()
+                .filter_map(|id| index.chunks.get(id).cloned())
+                .collect()
+        })
+    }
+
+    async fn scan_points(
+        &self,
+        index: &str,
+        cursor: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<VectorChunk>, Option<String>)> {
+        self.read(index, |index| {
+            let start = cursor.map_or(Bound::Unbounded, Bound::Excluded);
+            let chunks = index
+                .chunks
+                .range((start, Bound::Unbounded))
+                .take(limit as usize)
+                .map(|(_, chunk)| chunk.clone())
+                .collect::<Vec<_>>();
+            let next = (chunks.len() as u64 == limit)
+                .then(|| chunks.last().map(|c| c.content_id.clone()))
+                .flatten();
+            (chunks, next)
+        })
+    }
+
+    async fn update_metadata(
+        &self,
+        index: &str,
+        content_id: String,
+        metadata: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        self.write(
+            index,
+            vec![LogEntry::UpdateMetadata {
+                content_id,
+                metadata,
+            }],
+        )
+        .await
+    }
+
+    async fn search(
+        &self,
+        index: String,
+        query_embedding: EmbeddingVector,
+        k: u64,
+        filters: Vec<Filter>,
+    ) -> Result<Vec<SearchResult>> {
+        self.read(&index, |index| {
+            let query = index.check_embedding(query_embedding)?;
+            Ok(index.search(&query, k as usize, &filters))
+        })?
+    }
+
+    async fn drop_index(&self, index: &str) -> Result<()> {
+        let Some(index) = self.indexes.write().unwrap().remove(index) else {
+            return Ok(());
+        };
+        tokio::task::spawn_blocking(move || {
+            let path = index.read().unwrap().path.clone();
+            fs::remove_file(path)
+        })
+        .await??;
+        Ok(())
+    }
+
+    async fn num_vectors(&self, index: &str) -> Result<u64> {
+        self.read(index, |index| index.chunks.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        test_util::db_utils::test_mock_content_metadata,
+        vectordbs::{
+            tests::{
+                basic_search,
+                crud_operations,
+                insertion_idempotent,
+                scan_points,
+                search_filters,
+                store_metadata,
+            },
+            VectorDBTS,
+        },
+    };
+
+    async fn create_test_db(dir: &tempfile::TempDir, index_name: &str) -> VectorDBTS {
+        let db = EmbeddedDb::new(&EmbeddedVectorDbConfig {
+            path: dir.path().to_str().unwrap().to_string(),
+        })
+        .unwrap();
+        db.create_index(CreateIndexParams {
+            vectordb_index_name: index_name.into(),
+            vector_dim: 2,
+            distance: IndexDistance::Cosine,
+            kind: EmbeddingKind::Dense,
+            options: IndexOptions::default(),
+        })
+        .await
+        .unwrap();
+        Arc::new(db)
+    }
+
+    #[tokio::test]
+    async fn test_search_basic() {
+        let dir = tempfile::tempdir().unwrap();
+        basic_search(create_test_db(&dir, "hello-index").await, "hello-index").await;
+    }
+
+    #[tokio::test]
+    async fn test_store_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        store_metadata(
+            create_test_db(&dir, "metadata-index").await,
+            "metadata-index",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_insertion_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let index_name = "idempotent-index";
+        insertion_idempotent(create_test_db(&dir, index_name).await, index_name).await;
+    }
+
+    #[tokio::test]
+    async fn test_deletion() {
+        let dir = tempfile::tempdir().unwrap();
+        crud_operations(create_test_db(&dir, "delete-index").await, "delete-index").await;
+    }
+
+    #[tokio::test]
+    async fn test_search_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        search_filters(create_test_db(&dir, "hello-index").await, "hello-index").await;
+    }
+
+    #[tokio::test]
+    async fn test_scan_points() {
+        let dir = tempfile::tempdir().unwrap();
+        scan_points(create_test_db(&dir, "scan-index").await, "scan-index").await;
+    }
+
+    #[tokio::test]
+    async fn test_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let index_name = "default.minilm/embedding";
+        let db = create_test_db(&dir, index_name).await;
+        let chunk = |id: &str, embedding: Vec<f32>| VectorChunk {
+            content_id: id.to_string(),
+            embedding: embedding.into(),
+            metadata: HashMap::new(),
+            root_content_metadata: None,
+            content_metadata: test_mock_content_metadata(id, "1", "graph1"),
+        };
+        db.add_embedding(
+            index_name,
+            vec![chunk("1", vec![1., 0.]), chunk("2", vec![0., 1.])],
+        )
+        .await
+        .unwrap();
+        db.remove_embedding(index_name, "1").await.unwrap();
+        db.update_metadata(
+            index_name,
+            "2".to_string(),
+            HashMap::from([("key".to_string(), serde_json::json!("value"))]),
+        )
+        .await
+        .unwrap();
+        assert!(db
+            .add_embedding(index_name, vec![chunk("3", vec![1., 0., 0.])])
+            .await
+            .is_err());
+        drop(db);
+
+        let db = EmbeddedDb::new(&EmbeddedVectorDbConfig {
+            path: dir.path().to_str().unwrap().to_string(),
+        })
+        .unwrap();
+        assert_eq!(db.num_vectors(index_name).await.unwrap(), 1);
+        let results = db
+            .search(index_name.to_string(), vec![0., 2.].into(), 10, vec![])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].content_id, "2");
+        assert_eq!(results[0].metadata["key"], "value");
+
+        db.drop_index(index_name).await.unwrap();
+        assert!(fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let index_name = "compacted-index";
+        let db = create_test_db(&dir, index_name).await;
+        for i in 0..COMPACTION_SLACK + 10 {
+            db.add_embedding(
+                index_name,
+                vec![VectorChunk {
+                    content_id: "0".to_string(),
+                    embedding: vec![i as f32, 1.].into(),
+                    metadata: HashMap::new(),
+                    root_content_metadata: None,
+                    content_metadata: test_mock_content_metadata("0", "1", "graph1"),
+                }],
+            )
+            .await
+            .unwrap();
+        }
+        let path = dir
+            .path()
+            .read_dir()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let lines = fs::read_to_string(path).unwrap().lines().count();
+        assert!(lines < COMPACTION_SLACK);
+
+        let db = EmbeddedDb::new(&EmbeddedVectorDbConfig {
+            path: dir.path().to_str().unwrap().to_string(),
+        })
+        .unwrap();
+        let points = db
+            .get_points(index_name, vec!["0".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            points[0].embedding,
+            vec![(COMPACTION_SLACK + 9) as f32, 1.].into()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sparse_and_multi_vector_embeddings() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = EmbeddedDb::new(&EmbeddedVectorDbConfig {
+            path: dir.path().to_str().unwrap().to_string(),
+        })
+        .unwrap();
+        let chunk = |id: &str, embedding: EmbeddingVector| VectorChunk {
+            content_id: id.to_string(),
+            embedding,
+            metadata: HashMap::new(),
+            root_content_metadata: None,
+            content_metadata: test_mock_content_metadata(id, "1", "graph1"),
+        };
+        let sparse = |indices: Vec<u32>, values: Vec<f32>| {
+            EmbeddingVector::Sparse(SparseVector { indices, values })
+        };
+        for (name, kind) in [
+            ("sparse-index", EmbeddingKind::Sparse),
+            ("multi-vector-index", EmbeddingKind::MultiVector),
+        ] {
+            db.create_index(CreateIndexParams {
+                vectordb_index_name: name.into(),
+                vector_dim: 1000,
+                distance: IndexDistance::Dot,
+                kind,
+                options: IndexOptions::default(),
+            })
+            .await
+            .unwrap();
+        }
+
+        db.add_embedding(
+            "sparse-index",
+            vec![
+                chunk("1", sparse(vec![3, 999], vec![1., 2.])),
+                chunk("2", sparse(vec![3, 7], vec![2., 1.])),
+            ],
+        )
+        .await
+        .unwrap();
+        assert!(db
+            .add_embedding(
+                "sparse-index",
+                vec![chunk("3", sparse(vec![1000], vec![1.]))]
+            )
+            .await
+            .is_err());
+        assert!(db
+            .add_embedding("sparse-index", vec![chunk("3", vec![1.; 1000].into())])
+            .await
+            .is_err());
+        let results = db
+            .search(
+                "sparse-index".into(),
+                sparse(vec![999], vec![1.]),
+                2,
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].content_id, "1");
+        assert_eq!(results[0].confidence_score, 2.);
+        assert_eq!(results[1].confidence_score, 0.);
+
+        let mut a = vec![0.; 1000];
+        a[0] = 1.;
+        let mut b = vec![0.; 1000];
+        b[1] = 1.;
+        db.add_embedding(
+            "multi-vector-index",
+            vec![
+                chunk(
+                    "1",
+                    EmbeddingVector::MultiVector(vec![a.clone(), b.clone()]),
+                ),
+                chunk("2", a.clone().into()),
+            ],
+        )
+        .await
+        .unwrap();
+        let points = db
+            .get_points("multi-vector-index", vec!["2".into()])
+            .await
+            .unwrap();
+        assert_eq!(
+            points[0].embedding,
+            EmbeddingVector::MultiVector(vec![a.clone()])
+        );
+        //  Every query vector adds its best match
+        let results = db
+            .search(
+                "multi-vector-index".into(),
+                EmbeddingVector::MultiVector(vec![a, b]),
+                2,
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].content_id, "1");
+        assert_eq!(results[0].confidence_score, 2.);
+        assert_eq!(results[1].confidence_score, 1.);
+    }
+
+    #[tokio::test]
+    async fn test_long_index_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let index_name = "a".repeat(300);
+        let db = create_test_db(&dir, &index_name).await;
+        assert_eq!(db.num_vectors(&index_name).await.unwrap(), 0);
+        drop(db);
+
+        let db = EmbeddedDb::new(&EmbeddedVectorDbConfig {
+            path: dir.path().to_str().unwrap().to_string(),
+        })
+        .unwrap();
+        assert_eq!(db.num_vectors(&index_name).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = EmbeddedDb::new(&EmbeddedVectorDbConfig {
+            path: dir.path().to_str().unwrap().to_string(),
+        })
+        .unwrap();
+        assert!(db
+            .validate_index_options(&IndexOptions {
+                vector_store: None,
+                index_type: Some(IndexType::Flat),
+                quantization: None,
+            })
+            .is_ok());
+        assert!(db
+            .validate_index_options(&IndexOptions {
+                vector_store: None,
+                index_type: Some(IndexType::Hnsw {
+                    m: None,
+                    ef_construction: None,
+                }),
+                quantization: None,
+            })
+            .is_err());
+    }
+}
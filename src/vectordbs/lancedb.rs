@@ -18,19 +18,95 @@ use arrow_array::{
 use arrow_schema::{DataType, Field, Schema, SchemaRef};
 use async_trait::async_trait;
 use futures::{StreamExt, TryStreamExt};
+use indexify_internal_api::{EmbeddingKind, EmbeddingVector};
 use itertools::izip;
 use lance::dataset::{BatchUDF, WriteParams};
 use lancedb::{
-    query::{ExecutableQuery, QueryBase},
+    index::{vector::IvfPqIndexBuilder, Index},
+    query::{ExecutableQuery, QueryBase, Select},
     table::{NewColumnTransform, WriteOptions},
     Connection,
     Table,
 };
 use tracing;
 
-use super::{CreateIndexParams, Filter, FilterOperator, SearchResult, VectorChunk, VectorDb};
+use super::{
+    dense_vector,
+    CreateIndexParams,
+    Filter,
+    FilterOperator,
+    IndexOptions,
+    IndexType,
+    Quantization,
+    SearchResult,
+    VectorChunk,
+    VectorDb,
+};
 use crate::server_config::LancedbConfig;
 
+/// Key of the schema metadata of tables under which the options of their
+/// index are stored.
+const INDEX_OPTIONS_KEY: &str = "indexify_index_options";
+
+/// Product quantization trains 256 centroids per sub vector, so the vector
+/// index of a table is only built once it has at least this many rows.
+const MIN_ROWS_FOR_IVF_PQ: usize = 256;
+
+/// Builder of the vector index of tables with the specified options. Tables
+/// without a vector index are searched exhaustively.
+fn ivf_pq_index(options: &IndexOptions) -> Result<Option<IvfPqIndexBuilder>> {
+    match (&options.index_type, &options.quantization) {
+        (None | Some(IndexType::Flat), None) => Ok(None),
+        (
+            Some(IndexType::Ivf { num_partitions }),
+            Some(Quantization::Product { num_sub_vectors }),
+        ) => {
+            // FIXME search is hardcoded to cosine, see search
+            let mut builder =
+                IvfPqIndexBuilder::default().distance_type(lancedb::DistanceType::Cosine);
+            if let Some(num_partitions) = num_partitions {
+                builder = builder.num_partitions(*num_partitions);
+            }
+            if let Some(num_sub_vectors) = num_sub_vectors {
+                builder = builder.num_sub_vectors(*num_sub_vectors);
+            }
+            Ok(Some(builder))
+        }
+        (Some(IndexType::Hnsw { .. }), _) => Err(anyhow!("lancedb doesn't support hnsw indexes")),
+        (_, Some(Quantization::Scalar)) => {
+            Err(anyhow!("lancedb doesn't support scalar quantization"))
+        }
+        _ => Err(anyhow!(
+            "lancedb only supports ivf indexes with product quantization"
+        )),
+    }
+}
+
+// Builds the vector index of the table once it has enough rows to train it
+async fn build_vector_index(tbl: &Table) -> Result<()> {
+    let schema = tbl.schema().await?;
+    let Some(options) = schema.metadata().get(INDEX_OPTIONS_KEY) else {
+        return Ok(());
+    };
+    let options: IndexOptions = serde_json::from_str(options)?;
+    let Some(builder) = ivf_pq_index(&options)? else {
+        return Ok(());
+    };
+    let min_rows = match options.index_type {
+        Some(IndexType::Ivf {
+            num_partitions: Some(num_partitions),
+        }) => MIN_ROWS_FOR_IVF_PQ.max(num_partitions as usize),
+        _ => MIN_ROWS_FOR_IVF_PQ,
+    };
+    if !tbl.list_indices().await?.is_empty() || tbl.count_rows(None).await? < min_rows {
+        return Ok(());
+    }
+    tbl.create_index(&["vector"], Index::IvfPq(builder))
+        .execute()
+        .await
+        .map_err(|e| anyhow!("unable to create vector index: {}", e))
+}
+
 fn from_filter_to_str(filters: Vec<Filter>) -> String {
     filters
         .into_iter()
@@ -70,8 +146,20 @@ async fn vector_chunk_from_batch(
                 .map(|x| x.unwrap().to_string())
                 .collect();
         } else if field_name == "vector" {
-            // Looks like we are not using the embedding
-            // so we are just doing empty Vec to make the structs happy
+            let vectors = batch
+                .column_by_name(field_name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<FixedSizeListArray>()
+                .ok_or(anyhow!("vector column is not a list of vectors"))?;
+            for i in 0..vectors.len() {
+                let vector = vectors.value(i);
+                let vector = vector
+                    .as_any()
+                    .downcast_ref::<PrimitiveArray<Float32Type>>()
+                    .ok_or(anyhow!("vector is not a list of floats"))?;
+                embeddings.push(vector.values().to_vec());
+            }
         } else if field_name == "content_metadata" {
             for row in as_string_array(batch.column_by_name(field_name).unwrap()) {
                 let row = row.ok_or(anyhow!("content_metadata is null"))?;
@@ -87,18 +175,17 @@ async fn vector_chunk_from_batch(
             if metadatas.is_empty() {
                 metadatas = vec![HashMap::new(); column.len()];
             }
-            // FIXME SHould be a better way to enumerate with index
-            let mut i = 0;
-            for row in as_string_array(batch.column_by_name(field_name).unwrap()) {
-                let row = row.ok_or(anyhow!("metadata is null"))?;
-                let value: serde_json::Value = serde_json::json!(row);
+            //  Values are stored as json, rows without the key are null
+            for (i, row) in as_string_array(column).iter().enumerate() {
+                let Some(row) = row else {
+                    continue;
+                };
+                let value = serde_json::from_str(row).unwrap_or_else(|_| serde_json::json!(row));
                 metadatas[i].insert(field_name.to_string(), value);
-                i += 1;
             }
         }
     }
-    // Looks like we are not using the embedding
-    // so we are just doing empty Vec to make the structs happy
+    // Batches of queries which don't select the vector column
     if embeddings.is_empty() {
         embeddings = vec![Vec::new(); ids.len()];
     }
@@ -120,7 +207,7 @@ async fn vector_chunk_from_batch(
         let content_metadata = serde_json::from_str(&content_metadata)?;
         results.push(VectorChunk {
             content_id: id,
-            embedding,
+            embedding: embedding.into(),
             metadata,
             content_metadata,
             root_content_metadata,
@@ -192,21 +279,38 @@ impl VectorDb for LanceDb {
         "lancedb".into()
     }
 
+    fn validate_index_options(&self, options: &IndexOptions) -> Result<()> {
+        ivf_pq_index(options)?;
+        Ok(())
+    }
+
     #[tracing::instrument]
     async fn create_index(&self, index: CreateIndexParams) -> Result<()> {
-        let schema = Arc::new(Schema::new(vec![
-            Field::new("id", DataType::Utf8, false),
-            Field::new(
-                "vector",
-                DataType::FixedSizeList(
-                    Arc::new(Field::new("item", DataType::Float32, true)),
-                    index.vector_dim as i32,
+        self.validate_index_options(&index.options)?;
+        //  Multi-vector embeddings are stored as the mean of their vectors
+        if index.kind == EmbeddingKind::Sparse {
+            return Err(anyhow!("lancedb doesn't support sparse embeddings"));
+        }
+        let metadata = HashMap::from([(
+            INDEX_OPTIONS_KEY.to_string(),
+            serde_json::to_string(&index.options)?,
+        )]);
+        let schema = Arc::new(Schema::new_with_metadata(
+            vec![
+                Field::new("id", DataType::Utf8, false),
+                Field::new(
+                    "vector",
+                    DataType::FixedSizeList(
+                        Arc::new(Field::new("item", DataType::Float32, true)),
+                        index.vector_dim as i32,
+                    ),
+                    true,
                 ),
-                true,
-            ),
-            Field::new("root_content_metadata", DataType::Utf8, true),
-            Field::new("content_metadata", DataType::Utf8, false),
-        ]));
+                Field::new("root_content_metadata", DataType::Utf8, true),
+                Field::new("content_metadata", DataType::Utf8, false),
+            ],
+            metadata,
+        ));
         let batches = RecordBatchIterator::new(vec![], schema.clone());
         let _ = self
             .conn
@@ -232,11 +336,15 @@ impl VectorDb for LanceDb {
             .await
             .map_err(|e| anyhow!("unable to open table: {}", e))?;
         let ids = StringArray::from_iter_values(chunks.iter().map(|c| c.content_id.clone()));
-        let vector_dim = chunks[0].embedding.len() as i32;
+        let embeddings = chunks
+            .iter()
+            .map(|c| dense_vector(c.embedding.clone(), "lancedb"))
+            .collect::<Result<Vec<_>>>()?;
+        let vector_dim = embeddings[0].len() as i32;
         let vectors = FixedSizeListArray::from_iter_primitive::<Float32Type, _, _>(
-            chunks
+            embeddings
                 .iter()
-                .map(|c| Some(c.embedding.iter().map(|e| Some(*e)))),
+                .map(|embedding| Some(embedding.iter().map(|e| Some(*e)))),
             vector_dim,
         );
         let metadata = chunks
@@ -297,7 +405,8 @@ impl VectorDb for LanceDb {
         merge_insert
             .execute(Box::new(batches))
             .await
-            .map_err(|e| anyhow!("unable to add to table {}", e))
+            .map_err(|e| anyhow!("unable to add to table {}", e))?;
+        build_vector_index(&tbl).await
     }
 
     async fn get_points(&self, index: &str, ids: Vec<String>) -> Result<Vec<VectorChunk>> {
@@ -330,6 +439,55 @@ impl VectorDb for LanceDb {
         Ok(results)
     }
 
+    async fn scan_points(
+        &self,
+        index: &str,
+        cursor: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<VectorChunk>, Option<String>)> {
+        let tbl = self
+            .conn
+            .open_table(index)
+            .execute()
+            .await
+            .map_err(|e| anyhow!("unable to open table: {}", e))?;
+
+        //  Lance doesn't sort the rows of queries, so the ids of the page are
+        // read first
+        let mut query = tbl.query().select(Select::Columns(vec!["id".to_string()]));
+        if let Some(cursor) = cursor {
+            query = query.only_if(format!("id > '{}'", cursor));
+        }
+        let batches = query
+            .execute()
+            .await
+            .map_err(|e| anyhow!("unable to select records: {}", e))?
+            .try_collect::<Vec<_>>()
+            .await?;
+        let mut ids = batches
+            .iter()
+            .flat_map(|batch| {
+                as_string_array(batch.column_by_name("id").unwrap())
+                    .iter()
+                    .flatten()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.truncate(limit as usize);
+        let next = (ids.len() as u64 == limit)
+            .then(|| ids.last().cloned())
+            .flatten();
+        if ids.is_empty() {
+            return Ok((vec![], None));
+        }
+
+        let mut chunks = self.get_points(index, ids).await?;
+        chunks.sort_by(|a, b| a.content_id.cmp(&b.content_id));
+        Ok((chunks, next))
+    }
+
     async fn update_metadata(
         &self,
         index: &str,
@@ -382,10 +540,11 @@ impl VectorDb for LanceDb {
     async fn search(
         &self,
         index: String,
-        query_embedding: Vec<f32>,
+        query_embedding: EmbeddingVector,
         k: u64,
         filters: Vec<Filter>,
     ) -> Result<Vec<SearchResult>> {
+        let query_embedding = dense_vector(query_embedding, "lancedb")?;
         // FIXME remove the hardcoding to cosine
         // We need to pass the distance metric from
         // data manager to the vector db
@@ -434,10 +593,11 @@ impl VectorDb for LanceDb {
 
     #[tracing::instrument]
     async fn drop_index(&self, index: &str) -> Result<()> {
-        self.conn
-            .drop_table(&index)
-            .await
-            .map_err(|e| anyhow!("unable to drop index: {}", e))
+        // Dropping tables which don't exist succeeds, as with other vector dbs
+        match self.conn.drop_table(&index).await {
+            Ok(()) | Err(lancedb::Error::TableNotFound { .. }) => Ok(()),
+            Err(e) => Err(anyhow!("unable to drop index: {}", e)),
+        }
     }
 
     #[tracing::instrument]
@@ -458,16 +618,20 @@ mod tests {
     use std::sync::Arc;
 
     use super::*;
-    use crate::vectordbs::{
-        tests::{
-            basic_search,
-            crud_operations,
-            insertion_idempotent,
-            search_filters,
-            store_metadata,
+    use crate::{
+        test_util::db_utils::test_mock_content_metadata,
+        vectordbs::{
+            tests::{
+                basic_search,
+                crud_operations,
+                insertion_idempotent,
+                scan_points,
+                search_filters,
+                store_metadata,
+            },
+            IndexDistance,
+            VectorDBTS,
         },
-        IndexDistance,
-        VectorDBTS,
     };
 
     #[tokio::test]
@@ -486,7 +650,8 @@ mod tests {
                 vectordb_index_name: "hello-index".into(),
                 vector_dim: 2,
                 distance: crate::vectordbs::IndexDistance::Cosine,
-                unique_params: None,
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
             })
             .await
             .unwrap();
@@ -512,7 +677,8 @@ mod tests {
                 vectordb_index_name: index_name.into(),
                 vector_dim: 2,
                 distance: crate::vectordbs::IndexDistance::Cosine,
-                unique_params: None,
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
             })
             .await
             .unwrap();
@@ -536,7 +702,8 @@ mod tests {
                 vectordb_index_name: index_name.into(),
                 vector_dim: 2,
                 distance: IndexDistance::Cosine,
-                unique_params: None,
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
             })
             .await
             .unwrap();
@@ -560,7 +727,8 @@ mod tests {
                 vectordb_index_name: index_name.into(),
                 vector_dim: 2,
                 distance: IndexDistance::Cosine,
-                unique_params: None,
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
             })
             .await
             .unwrap();
@@ -584,10 +752,115 @@ mod tests {
                 vectordb_index_name: "hello-index".into(),
                 vector_dim: 2,
                 distance: crate::vectordbs::IndexDistance::Cosine,
-                unique_params: None,
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
             })
             .await
             .unwrap();
         search_filters(lance, "hello-index").await;
     }
+
+    #[tokio::test]
+    async fn test_scan_points() {
+        let dir = tempfile::tempdir().unwrap();
+        let lance: VectorDBTS = Arc::new(
+            LanceDb::new(&LancedbConfig {
+                path: dir.path().to_str().unwrap().to_string(),
+            })
+            .await
+            .unwrap(),
+        );
+        lance
+            .create_index(CreateIndexParams {
+                vectordb_index_name: "scan-index".into(),
+                vector_dim: 2,
+                distance: IndexDistance::Cosine,
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
+            })
+            .await
+            .unwrap();
+        scan_points(lance, "scan-index").await;
+    }
+
+    #[tokio::test]
+    async fn test_ivf_pq_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let lance = LanceDb::new(&LancedbConfig {
+            path: dir.path().to_str().unwrap().to_string(),
+        })
+        .await
+        .unwrap();
+        let options = IndexOptions {
+            vector_store: None,
+            index_type: Some(IndexType::Ivf {
+                num_partitions: Some(2),
+            }),
+            quantization: Some(Quantization::Product {
+                num_sub_vectors: Some(2),
+            }),
+        };
+        let invalid = IndexOptions {
+            vector_store: None,
+            index_type: Some(IndexType::Ivf {
+                num_partitions: None,
+            }),
+            quantization: None,
+        };
+        assert!(lance.validate_index_options(&options).is_ok());
+        assert!(lance.validate_index_options(&invalid).is_err());
+        lance
+            .create_index(CreateIndexParams {
+                vectordb_index_name: "ivf-pq-index".into(),
+                vector_dim: 4,
+                distance: IndexDistance::Cosine,
+                kind: EmbeddingKind::Dense,
+                options,
+            })
+            .await
+            .unwrap();
+
+        // the index is only built once there are enough vectors to train it
+        let content_metadata = test_mock_content_metadata("0", "1", "graph1");
+        let chunks = |ids: std::ops::Range<usize>| {
+            ids.map(|i| VectorChunk {
+                content_id: i.to_string(),
+                embedding: vec![i as f32, 1., (i % 7) as f32, (i % 3) as f32].into(),
+                metadata: HashMap::new(),
+                root_content_metadata: None,
+                content_metadata: content_metadata.clone(),
+            })
+            .collect()
+        };
+        let num_indices = || async {
+            let tbl = lance
+                .conn
+                .open_table("ivf-pq-index")
+                .execute()
+                .await
+                .unwrap();
+            tbl.list_indices().await.unwrap().len()
+        };
+        lance
+            .add_embedding("ivf-pq-index", chunks(0..100))
+            .await
+            .unwrap();
+        assert_eq!(num_indices().await, 0);
+        lance
+            .add_embedding("ivf-pq-index", chunks(100..300))
+            .await
+            .unwrap();
+        assert_eq!(num_indices().await, 1);
+
+        let results = lance
+            .search(
+                "ivf-pq-index".into(),
+                vec![3., 1., 3., 0.].into(),
+                5,
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 5);
+    }
 }
@@ -1,15 +1,17 @@
 use std::{collections::HashMap, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use indexify_internal_api::ContentMetadata;
+use indexify_internal_api::{ContentMetadata, EmbeddingKind, EmbeddingVector};
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
+use utoipa::ToSchema;
 
 use crate::server_config::{IndexStoreKind, VectorIndexConfig};
 
+pub mod embedded;
 pub mod lancedb;
-//pub mod open_search;
+pub mod open_search;
 pub mod pg_vector;
 pub mod qdrant;
 
@@ -30,14 +32,56 @@ pub enum IndexDistance {
     Euclidean,
 }
 
+/// Kind of index built over the vectors of an index. Parameters which are not
+/// set are left to the defaults of the vector database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IndexType {
+    /// Exact search over every vector, without an index.
+    Flat,
+    Hnsw {
+        m: Option<u32>,
+        ef_construction: Option<u32>,
+    },
+    Ivf {
+        num_partitions: Option<u32>,
+    },
+}
+
+/// Compression of the vectors stored in an index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Quantization {
+    /// Every dimension is stored as an 8 bit integer.
+    Scalar,
+    /// Vectors are split into sub vectors, each stored as the id of its
+    /// nearest centroid.
+    Product { num_sub_vectors: Option<u32> },
+}
+
+/// Options of the index created for an embedding output of an extraction
+/// policy. The default index of the vector database is created when no option
+/// is set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct IndexOptions {
+    /// Name of the vector store the index is created in, the default store of
+    /// the server when not set.
+    #[serde(default)]
+    pub vector_store: Option<String>,
+    #[serde(default)]
+    pub index_type: Option<IndexType>,
+    #[serde(default)]
+    pub quantization: Option<Quantization>,
+}
+
 /// A request to create a new vector index in the vector database.
 #[derive(Clone, Debug)]
 pub struct CreateIndexParams {
     pub vectordb_index_name: String,
     pub vector_dim: u64,
     pub distance: IndexDistance,
-    // TODO: Probably better if this is a HashMap<String, String> (?), or a generic (?)
-    pub unique_params: Option<Vec<String>>,
+    pub kind: EmbeddingKind,
+    pub options: IndexOptions,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -51,10 +95,10 @@ pub struct SearchResult {
 
 pub type VectorDBTS = Arc<dyn VectorDb + Sync + Send>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VectorChunk {
     pub content_id: String,
-    pub embedding: Vec<f32>,
+    pub embedding: EmbeddingVector,
     pub metadata: HashMap<String, serde_json::Value>,
     pub root_content_metadata: Option<ContentMetadata>,
     pub content_metadata: ContentMetadata,
@@ -62,7 +106,7 @@ pub struct VectorChunk {
 impl VectorChunk {
     pub fn new(
         content_id: String,
-        embedding: Vec<f32>,
+        embedding: EmbeddingVector,
         metadata: HashMap<String, serde_json::Value>,
         root_content: Option<ContentMetadata>,
         content_metadata: &ContentMetadata,
@@ -116,6 +160,10 @@ pub trait VectorDb {
     /// Creates a new vector index with the specified configuration.
     async fn create_index(&self, index: CreateIndexParams) -> Result<()>;
 
+    /// Returns an error if the vector database can't create an index with the
+    /// specified options.
+    fn validate_index_options(&self, options: &IndexOptions) -> Result<()>;
+
     /// Adds a vector embedding to the specified index, along with associated
     /// attributes.
     async fn add_embedding(&self, index: &str, chunks: Vec<VectorChunk>) -> Result<()>;
@@ -127,6 +175,16 @@ pub trait VectorDb {
     /// Retrieves the vector embeddings for the specified content IDs
     async fn get_points(&self, index: &str, content_ids: Vec<String>) -> Result<Vec<VectorChunk>>;
 
+    /// Retrieves a page of at most `limit` vector embeddings of the index,
+    /// ordered by content id. Pages start at the opaque `cursor` returned
+    /// with the previous page, which is `None` once every embedding was read.
+    async fn scan_points(
+        &self,
+        index: &str,
+        cursor: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<VectorChunk>, Option<String>)>;
+
     /// Update metadata for the specified content ID
     async fn update_metadata(
         &self,
@@ -136,11 +194,11 @@ pub trait VectorDb {
     ) -> Result<()>;
 
     /// Searches for the nearest neighbors of a query vector in the specified
-    /// index.
+    /// index. The query is of the same kind as the embeddings of the index.
     async fn search(
         &self,
         index: String,
-        query_embedding: Vec<f32>,
+        query_embedding: EmbeddingVector,
         k: u64,
         filters: Vec<Filter>,
     ) -> Result<Vec<SearchResult>>;
@@ -156,20 +214,61 @@ pub trait VectorDb {
     //  TODO: Add delete content using namespace and content id
 }
 
+/// Dense vector stored for an embedding in vector databases which don't
+/// support its kind natively. Multi-vectors are stored as the mean of their
+/// vectors and searched with the mean of the query vectors, sparse vectors
+/// can't be stored.
+pub fn dense_vector(embedding: EmbeddingVector, vector_db: &str) -> Result<Vec<f32>> {
+    match embedding {
+        EmbeddingVector::Dense(values) => Ok(values),
+        EmbeddingVector::MultiVector(vectors) => {
+            let dim = vectors
+                .first()
+                .map(|vector| vector.len())
+                .ok_or(anyhow!("multi-vector embedding has no vectors"))?;
+            if vectors.iter().any(|vector| vector.len() != dim) {
+                return Err(anyhow!(
+                    "vectors of multi-vector embedding have different dimensions"
+                ));
+            }
+            let mut mean = vec![0.; dim];
+            for vector in &vectors {
+                for (sum, value) in mean.iter_mut().zip(vector) {
+                    *sum += value / vectors.len() as f32;
+                }
+            }
+            Ok(mean)
+        }
+        EmbeddingVector::Sparse(_) => {
+            Err(anyhow!("{} doesn't support sparse embeddings", vector_db))
+        }
+    }
+}
+
 /// Creates a new vector database based on the specified configuration.
 pub async fn create_vectordb(config: VectorIndexConfig) -> Result<VectorDBTS> {
+    let missing_config = || anyhow!("no configuration for {} vector store", config.index_store);
     match config.index_store {
-        IndexStoreKind::Qdrant => Ok(Arc::new(QdrantDb::new(config.qdrant_config.unwrap()))),
+        IndexStoreKind::Qdrant => Ok(Arc::new(QdrantDb::new(
+            config.qdrant_config.clone().ok_or_else(missing_config)?,
+        ))),
         IndexStoreKind::PgVector => Ok(Arc::new(
-            pg_vector::PgVector::new(config.pg_vector_config.unwrap()).await?,
+            pg_vector::PgVector::new(config.pg_vector_config.clone().ok_or_else(missing_config)?)
+                .await?,
         )),
-        // TODO Bring it back
-        //IndexStoreKind::OpenSearchKnn => Ok(Arc::new(OpenSearchKnn::new(
-        //    config.open_search_basic.unwrap(),
-        //))),
+        IndexStoreKind::OpenSearchKnn => Ok(Arc::new(open_search::OpenSearchKnn::new(
+            config
+                .open_search_basic
+                .clone()
+                .ok_or_else(missing_config)?,
+        ))),
         IndexStoreKind::Lancedb => Ok(Arc::new(
-            lancedb::LanceDb::new(&config.lancedb_config.unwrap()).await?,
+            lancedb::LanceDb::new(config.lancedb_config.as_ref().ok_or_else(missing_config)?)
+                .await?,
         )),
+        IndexStoreKind::Embedded => Ok(Arc::new(embedded::EmbeddedDb::new(
+            &config.embedded_config.unwrap_or_default(),
+        )?)),
     }
 }
 
@@ -177,11 +276,13 @@ pub async fn create_vectordb(config: VectorIndexConfig) -> Result<VectorDBTS> {
 mod tests {
     use std::collections::HashMap;
 
+    use indexify_internal_api::{Embedding, EmbeddingVector, SparseVector};
     use serde_json::json;
 
-    use super::{Filter, FilterOperator, VectorDBTS};
+    use super::{create_vectordb, dense_vector, Filter, FilterOperator, VectorDBTS};
     use crate::{
         data_manager::DataManager,
+        server_config::{IndexStoreKind, VectorIndexConfig},
         test_util::db_utils::{create_metadata, test_mock_content_metadata},
         vectordbs::VectorChunk,
     };
@@ -190,7 +291,7 @@ mod tests {
         let content_id = "0";
         let chunk = VectorChunk {
             content_id: content_id.into(),
-            embedding: vec![0., 2.],
+            embedding: vec![0., 2.].into(),
             metadata: create_metadata(vec![("key1", "value1"), ("key2", "value2")]),
             root_content_metadata: Some(test_mock_content_metadata(content_id, "1", "graph1")),
             content_metadata: test_mock_content_metadata(content_id, "1", "graph1"),
@@ -218,7 +319,7 @@ mod tests {
         let metadata1 = create_metadata(vec![("key1", "value1"), ("key2", "value2")]);
         let chunk = VectorChunk {
             content_id: "0".into(),
-            embedding: vec![0., 2.],
+            embedding: vec![0., 2.].into(),
             metadata: metadata1.clone(),
             root_content_metadata: Some(test_mock_content_metadata("0", "1", "graph1")),
             content_metadata: test_mock_content_metadata("0", "1", "graph1"),
@@ -229,22 +330,60 @@ mod tests {
             .unwrap();
 
         let results = vector_db
-            .search(index_name.into(), vec![10., 8.], 1, vec![])
+            .search(index_name.into(), vec![10., 8.].into(), 1, vec![])
             .await
             .unwrap();
         assert_eq!(results.len(), 1);
     }
 
+    #[test]
+    fn test_dense_vector() {
+        let parse = |data| serde_json::from_value::<Embedding>(data).unwrap().values;
+        let dense = parse(json!({"values": [1.0, 2.0], "distance": "cosine"}));
+        assert_eq!(dense, EmbeddingVector::Dense(vec![1., 2.]));
+        let sparse = parse(json!({
+            "values": {"indices": [3, 5], "values": [0.5, 1.0]},
+            "distance": "dot"
+        }));
+        assert_eq!(
+            sparse,
+            EmbeddingVector::Sparse(SparseVector {
+                indices: vec![3, 5],
+                values: vec![0.5, 1.],
+            })
+        );
+        let multi_vector = parse(json!({
+            "values": [[1.0, 2.0], [3.0, 0.0]],
+            "distance": "cosine"
+        }));
+
+        assert_eq!(dense_vector(dense, "store").unwrap(), vec![1., 2.]);
+        assert_eq!(dense_vector(multi_vector, "store").unwrap(), vec![2., 1.]);
+        assert!(dense_vector(sparse, "store").is_err());
+        assert!(dense_vector(EmbeddingVector::MultiVector(vec![]), "store").is_err());
+    }
+
     fn make_id() -> String {
         DataManager::make_id()
     }
 
+    #[tokio::test]
+    async fn test_missing_config() {
+        let config = VectorIndexConfig {
+            index_store: IndexStoreKind::OpenSearchKnn,
+            open_search_basic: None,
+            ..Default::default()
+        };
+        let err = create_vectordb(config).await.err().unwrap();
+        assert!(err.to_string().starts_with("no configuration for"));
+    }
+
     pub async fn store_metadata(vector_db: VectorDBTS, index_name: &str) {
         let content_ids = vec![make_id(), make_id()];
         let metadata1 = create_metadata(vec![("key1", "value1"), ("key2", "value2")]);
         let chunk1 = VectorChunk {
             content_id: content_ids[0].clone(),
-            embedding: vec![0.1, 0.2],
+            embedding: vec![0.1, 0.2].into(),
             metadata: metadata1.clone(),
             root_content_metadata: Some(test_mock_content_metadata("0", "1", "graph1")),
             content_metadata: test_mock_content_metadata("0", "1", "graph1"),
@@ -256,7 +395,7 @@ mod tests {
         let metadata2 = create_metadata(vec![("key1", "value3"), ("key2", "value4")]);
         let chunk2 = VectorChunk {
             content_id: content_ids[1].clone(),
-            embedding: vec![0.3, 0.4],
+            embedding: vec![0.3, 0.4].into(),
             metadata: metadata2.clone(),
             root_content_metadata: Some(test_mock_content_metadata("0", "1", "graph1")),
             content_metadata: test_mock_content_metadata("0", "1", "graph1"),
@@ -294,6 +433,49 @@ mod tests {
         assert_eq!(result[0].metadata, new_metadata);
     }
 
+    pub async fn scan_points(vector_db: VectorDBTS, index_name: &str) {
+        let content_ids = (1..=5)
+            .map(|i| format!("{:x}", (1u64 << 60) + i))
+            .collect::<Vec<_>>();
+        let chunks = content_ids
+            .iter()
+            .map(|content_id| VectorChunk {
+                content_id: content_id.clone(),
+                embedding: vec![0.5, 1.5].into(),
+                metadata: create_metadata(vec![("key1", content_id)]),
+                root_content_metadata: None,
+                content_metadata: test_mock_content_metadata(content_id, "1", "graph1"),
+            })
+            .collect();
+        vector_db.add_embedding(index_name, chunks).await.unwrap();
+
+        let mut scanned = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = vector_db.scan_points(index_name, cursor, 2).await.unwrap();
+            assert!(page.len() <= 2);
+            scanned.extend(page);
+            cursor = next;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(
+            scanned
+                .iter()
+                .map(|c| c.content_id.clone())
+                .collect::<Vec<_>>(),
+            content_ids
+        );
+        for chunk in scanned {
+            assert_eq!(chunk.embedding, vec![0.5, 1.5].into());
+            assert_eq!(
+                chunk.metadata,
+                create_metadata(vec![("key1", &chunk.content_id)])
+            );
+        }
+    }
+
     pub async fn insertion_idempotent(vector_db: VectorDBTS, index_name: &str) {
         let metadata1 = HashMap::from([
             ("key1".to_string(), json!("value1")),
@@ -301,7 +483,7 @@ mod tests {
         ]);
         let chunk = VectorChunk {
             content_id: "0".into(),
-            embedding: vec![0., 2.],
+            embedding: vec![0., 2.].into(),
             metadata: metadata1.clone(),
             root_content_metadata: Some(test_mock_content_metadata("0", "1", "graph1")),
             content_metadata: test_mock_content_metadata("0", "1", "graph1"),
@@ -324,7 +506,7 @@ mod tests {
         let metadata1 = create_metadata(vec![("key1", "value1"), ("key2", "value2")]);
         let chunk = VectorChunk {
             content_id: content_ids[0].clone(),
-            embedding: vec![0., 2.],
+            embedding: vec![0., 2.].into(),
             metadata: metadata1,
             root_content_metadata: Some(test_mock_content_metadata(&content_ids[0], "1", "graph1")),
             content_metadata: test_mock_content_metadata(&content_ids[0], "1", "graph1"),
@@ -332,7 +514,7 @@ mod tests {
         let metadata2 = create_metadata(vec![("key1", "value3"), ("key2", "value4")]);
         let chunk1 = VectorChunk {
             content_id: content_ids[1].clone(),
-            embedding: vec![0., 3.],
+            embedding: vec![0., 3.].into(),
             metadata: metadata2,
             root_content_metadata: Some(test_mock_content_metadata(&content_ids[1], "1", "graph1")),
             content_metadata: test_mock_content_metadata(&content_ids[1], "1", "graph1"),
@@ -345,7 +527,7 @@ mod tests {
         let res = vector_db
             .search(
                 index_name.to_string(),
-                vec![0., 2.],
+                vec![0., 2.].into(),
                 2,
                 vec![Filter {
                     key: "key1".to_string(),
@@ -361,7 +543,7 @@ mod tests {
         let res = vector_db
             .search(
                 index_name.to_string(),
-                vec![0., 2.],
+                vec![0., 2.].into(),
                 2,
                 vec![Filter {
                     key: "key1".to_string(),
@@ -377,7 +559,7 @@ mod tests {
         let res = vector_db
             .search(
                 index_name.to_string(),
-                vec![0., 2.],
+                vec![0., 2.].into(),
                 2,
                 vec![
                     Filter {
@@ -400,7 +582,7 @@ mod tests {
         let res = vector_db
             .search(
                 index_name.to_string(),
-                vec![0., 2.],
+                vec![0., 2.].into(),
                 2,
                 vec![
                     Filter {
@@ -421,7 +603,7 @@ mod tests {
 
         assert_eq!(
             vector_db
-                .search(index_name.to_string(), vec![0., 2.], 2, vec![])
+                .search(index_name.to_string(), vec![0., 2.].into(), 2, vec![])
                 .await
                 .unwrap()
                 .len(),
@@ -1,27 +1,166 @@
+use std::collections::HashMap;
+
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use indexify_internal_api::{ContentMetadata, EmbeddingKind, EmbeddingVector};
 use opensearch::{
     auth::Credentials,
     cert::CertificateValidation,
-    http::transport::{SingleNodeConnectionPool, TransportBuilder},
-    indices::IndicesCreateParts,
+    http::{
+        response::Response,
+        transport::{SingleNodeConnectionPool, TransportBuilder},
+    },
+    indices::{IndicesCreateParts, IndicesDeleteParts},
+    params::Refresh,
     BulkOperation,
+    BulkParts,
+    CountParts,
+    DeleteParts,
+    MgetParts,
     OpenSearch,
+    SearchParts,
+    UpdateParts,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use url::Url;
 
-use super::{CreateIndexParams, VectorDb};
-use crate::{
-    server_config::OpenSearchBasicConfig,
-    vectordbs::{IndexDistance, SearchResult, VectorChunk},
+use super::{
+    dense_vector,
+    CreateIndexParams,
+    Filter,
+    FilterOperator,
+    IndexOptions,
+    IndexType,
+    SearchResult,
+    VectorChunk,
+    VectorDb,
 };
+use crate::{server_config::OpenSearchBasicConfig, vectordbs::IndexDistance};
+
+/// Source of the document of a vector in its index.
+#[derive(Debug, Serialize, Deserialize)]
+struct Document {
+    embedding: Vec<f32>,
+    #[serde(default)]
+    metadata: HashMap<String, Value>,
+    root_content_metadata: Option<ContentMetadata>,
+    content_metadata: ContentMetadata,
+}
+
+impl Document {
+    fn into_chunk(self, content_id: String) -> VectorChunk {
+        VectorChunk {
+            content_id,
+            embedding: self.embedding.into(),
+            metadata: self.metadata,
+            root_content_metadata: self.root_content_metadata,
+            content_metadata: self.content_metadata,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Hit {
+    #[serde(rename = "_id")]
+    id: String,
+    // Hits of sorted searches aren't scored
+    #[serde(rename = "_score")]
+    score: Option<f32>,
+    #[serde(rename = "_source")]
+    source: Document,
+}
+
+#[derive(Deserialize)]
+struct MgetDoc {
+    #[serde(rename = "_id")]
+    id: String,
+    #[serde(default)]
+    found: bool,
+    #[serde(rename = "_source")]
+    source: Option<Document>,
+}
+
+// Vectors are indexed by the lucene engine, the only one which filters
+// documents while searching rather than filtering the k nearest vectors
+fn knn_method(distance: &IndexDistance, options: &IndexOptions) -> Result<Value> {
+    if options.quantization.is_some() {
+        return Err(anyhow!(
+            "opensearch doesn't support quantization of vectors"
+        ));
+    }
+    let mut parameters = serde_json::Map::new();
+    match &options.index_type {
+        None => {}
+        Some(IndexType::Hnsw { m, ef_construction }) => {
+            if let Some(m) = m {
+                parameters.insert("m".to_string(), json!(m));
+            }
+            if let Some(ef_construction) = ef_construction {
+                parameters.insert("ef_construction".to_string(), json!(ef_construction));
+            }
+        }
+        Some(IndexType::Flat) | Some(IndexType::Ivf { .. }) => {
+            return Err(anyhow!("opensearch only supports hnsw indexes"));
+        }
+    }
+    Ok(json!({
+        "name": "hnsw",
+        "engine": "lucene",
+        "space_type": match distance {
+            IndexDistance::Cosine => "cosinesimil",
+            IndexDistance::Dot => "innerproduct",
+            IndexDistance::Euclidean => "l2",
+        },
+        "parameters": parameters,
+    }))
+}
+
+fn knn_filter(filters: Vec<Filter>) -> Option<Value> {
+    if filters.is_empty() {
+        return None;
+    }
+    let (mut must, mut must_not) = (Vec::new(), Vec::new());
+    for filter in filters {
+        let term = json!({ "term": { (format!("metadata.{}", filter.key)): filter.value } });
+        match filter.operator {
+            FilterOperator::Eq => must.push(term),
+            FilterOperator::Neq => must_not.push(term),
+        }
+    }
+    Some(json!({ "bool": { "must": must, "must_not": must_not } }))
+}
+
+/// Body of a response, or an error if the request failed.
+async fn response_body(response: Response, action: &str) -> Result<Value> {
+    let status = response.status_code();
+    let body = response
+        .json::<Value>()
+        .await
+        .map_err(|e| anyhow!("unable to parse opensearch response to {}: {}", action, e))?;
+    if !status.is_success() {
+        return Err(anyhow!(
+            "unable to {} in opensearch: {} {}",
+            action,
+            status,
+            body["error"]
+        ));
+    }
+    Ok(body)
+}
 
 pub struct OpenSearchKnn {
     config: OpenSearchBasicConfig,
 }
 
+impl std::fmt::Debug for OpenSearchKnn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenSearchKnn")
+            .field("addr", &self.config.addr)
+            .finish()
+    }
+}
+
 impl OpenSearchKnn {
     pub fn new(config: OpenSearchBasicConfig) -> OpenSearchKnn {
         Self { config }
@@ -47,294 +186,624 @@ impl VectorDb for OpenSearchKnn {
         "open search".into()
     }
 
+    fn validate_index_options(&self, options: &IndexOptions) -> Result<()> {
+        knn_method(&IndexDistance::Cosine, options)?;
+        Ok(())
+    }
+
+    #[tracing::instrument]
     async fn create_index(&self, index_params: CreateIndexParams) -> Result<()> {
+        //  Multi-vector embeddings are stored as the mean of their vectors
+        if index_params.kind == EmbeddingKind::Sparse {
+            return Err(anyhow!("opensearch doesn't support sparse embeddings"));
+        }
+        let method = knn_method(&index_params.distance, &index_params.options)?;
         let response = self
             .create_client()?
             .indices()
             .create(IndicesCreateParts::Index(&index_params.vectordb_index_name))
-            .body(json!(
-                {
-                    "settings" : {
-                        "index": { "knn": true, }
-                    },
-                    "mappings" : {
-                        "properties" : {
-                            "embeddings" : {
-                                "type" : "knn_vector",
-                                "dimension" : index_params.vector_dim as i32,
-                                "method": {
-                                    "name": "hnsw",
-                                    "space_type": match index_params.distance {
-                                        IndexDistance::Cosine => "cosinesimil",
-                                        IndexDistance::Dot => "innerproduct",
-                                        IndexDistance::Euclidean => "l2",
-                                    },
-                                    "engine": "nmslib"
-                                }
-                            }
+            .body(json!({
+                "settings": {
+                    "index": { "knn": true }
+                },
+                "mappings": {
+                    // Metadata is filtered on exact values
+                    "dynamic_templates": [{
+                        "metadata_strings": {
+                            "path_match": "metadata.*",
+                            "match_mapping_type": "string",
+                            "mapping": { "type": "keyword" }
                         }
+                    }],
+                    "properties": {
+                        "embedding": {
+                            "type": "knn_vector",
+                            "dimension": index_params.vector_dim,
+                            "method": method
+                        },
+                        "metadata": { "type": "object" },
+                        "root_content_metadata": { "type": "object", "enabled": false },
+                        "content_metadata": { "type": "object", "enabled": false }
                     }
                 }
-            ))
+            }))
             .send()
             .await
             .map_err(|e| anyhow!("unable to create opensearch index: {}", e))?;
-        match response.error_for_status_code() {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                return Err(anyhow!("unable to create opensearch index: '{}'", e));
-            }
+        match response_body(response, "create index").await {
+            Err(e) if e.to_string().contains("resource_already_exists_exception") => Ok(()),
+            result => result.map(|_| ()),
         }
     }
 
+    #[tracing::instrument]
     async fn add_embedding(&self, index_name: &str, vector_chunks: Vec<VectorChunk>) -> Result<()> {
         // TODO: implement smart batching to handle large chunks
-        let mut bulk_ops: Vec<opensearch::BulkOperation<Value>> = Vec::new();
-        for vector_chunk in vector_chunks {
-            let body = json!({
-                "embeddings": vector_chunk.embedding,
-            });
-            bulk_ops.push(BulkOperation::create(vector_chunk.content_id, body).into());
+        let mut bulk_ops: Vec<BulkOperation<Value>> = Vec::new();
+        for chunk in vector_chunks {
+            let document = serde_json::to_value(Document {
+                embedding: dense_vector(chunk.embedding, "opensearch")?,
+                metadata: chunk.metadata,
+                root_content_metadata: chunk.root_content_metadata,
+                content_metadata: chunk.content_metadata,
+            })?;
+            bulk_ops.push(BulkOperation::index(document).id(chunk.content_id).into());
         }
 
         let response = self
             .create_client()?
-            .bulk(opensearch::BulkParts::Index(index_name))
+            .bulk(BulkParts::Index(index_name))
+            .refresh(Refresh::WaitFor)
             .body(bulk_ops)
             .send()
             .await
             .map_err(|e| anyhow!("unable to add opensearch embeddings: {}", e))?;
-
-        match response.error_for_status_code() {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                return Err(anyhow!("unable to add opensearch embeddings: '{}'", e));
-            }
+        let body = response_body(response, "add embeddings").await?;
+        // Bulk requests succeed even when some of their operations fail
+        if body["errors"].as_bool().unwrap_or_default() {
+            let error = body["items"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find_map(|item| item["index"].get("error"))
+                .cloned()
+                .unwrap_or_default();
+            return Err(anyhow!("unable to add opensearch embeddings: {}", error));
         }
+        Ok(())
     }
 
+    #[tracing::instrument]
     async fn remove_embedding(&self, index_name: &str, content_id: &str) -> Result<()> {
-        let query = json!({
-            "query": {
-                "match": {
-                    "_id": content_id
-                }
-            }
-        });
-
         let response = self
             .create_client()?
-            .delete_by_query(opensearch::DeleteByQueryParts::Index(&[index_name]))
-            .body(query)
+            .delete(DeleteParts::IndexId(index_name, content_id))
+            .refresh(Refresh::WaitFor)
             .send()
-            .await?;
+            .await
+            .map_err(|e| anyhow!("unable to remove opensearch embeddings: {}", e))?;
+        if response.status_code().as_u16() == 404 {
+            return Ok(());
+        }
+        response_body(response, "remove embeddings").await?;
+        Ok(())
+    }
 
-        match response.error_for_status_code() {
-            Ok(_) => Ok(()),
-            Err(e) => return Err(anyhow!("unable to remove opensearch embeddings: '{}'", e)),
+    #[tracing::instrument]
+    async fn get_points(&self, index: &str, ids: Vec<String>) -> Result<Vec<VectorChunk>> {
+        if ids.is_empty() {
+            return Ok(vec![]);
         }
+        let response = self
+            .create_client()?
+            .mget(MgetParts::Index(index))
+            .body(json!({ "ids": ids }))
+            .send()
+            .await
+            .map_err(|e| anyhow!("unable to get opensearch embeddings: {}", e))?;
+        let mut body = response_body(response, "get embeddings").await?;
+        let docs: Vec<MgetDoc> = serde_json::from_value(body["docs"].take())?;
+        Ok(docs
+            .into_iter()
+            .filter(|doc| doc.found)
+            .filter_map(|doc| doc.source.map(|source| source.into_chunk(doc.id)))
+            .collect())
     }
 
-    async fn get_points(&self, _index: &str, _ids: Vec<String>) -> Result<Vec<VectorChunk>> {
-        // TODO: return empty vector for now
-        Ok(vec![])
+    #[tracing::instrument]
+    async fn scan_points(
+        &self,
+        index: &str,
+        cursor: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<VectorChunk>, Option<String>)> {
+        let mut body = json!({
+            "size": limit,
+            "query": { "match_all": {} },
+            "sort": [{ "_id": "asc" }],
+        });
+        if let Some(cursor) = cursor {
+            body["search_after"] = json!([cursor]);
+        }
+        let response = self
+            .create_client()?
+            .search(SearchParts::Index(&[index]))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| anyhow!("unable to scan opensearch embeddings: {}", e))?;
+        let mut body = response_body(response, "scan embeddings").await?;
+        let hits: Vec<Hit> = serde_json::from_value(body["hits"]["hits"].take())
+            .map_err(|e| anyhow!("unable to parse opensearch search response: {}", e))?;
+        let next = (hits.len() as u64 == limit)
+            .then(|| hits.last().map(|hit| hit.id.clone()))
+            .flatten();
+        let chunks = hits
+            .into_iter()
+            .map(|hit| hit.source.into_chunk(hit.id))
+            .collect();
+        Ok((chunks, next))
     }
 
-    // TODO: implementation of update_metadata
+    #[tracing::instrument]
     async fn update_metadata(
         &self,
-        _index: &str,
-        _content_id: String,
-        _metadata: serde_json::Value,
+        index: &str,
+        content_id: String,
+        metadata: HashMap<String, Value>,
     ) -> Result<()> {
+        // Partial documents are merged into the stored ones
+        let response = self
+            .create_client()?
+            .update(UpdateParts::IndexId(index, &content_id))
+            .refresh(Refresh::WaitFor)
+            .body(json!({ "doc": { "metadata": metadata } }))
+            .send()
+            .await
+            .map_err(|e| anyhow!("unable to update opensearch metadata: {}", e))?;
+        response_body(response, "update metadata").await?;
         Ok(())
     }
 
+    #[tracing::instrument]
     async fn search(
         &self,
         index_name: String,
-        query_embedding: Vec<f32>,
+        query_embedding: EmbeddingVector,
         k: u64,
-        _filters: Vec<super::Filter>,
+        filters: Vec<Filter>,
     ) -> Result<Vec<SearchResult>> {
+        let mut knn = json!({
+            "vector": dense_vector(query_embedding, "opensearch")?,
+            "k": k,
+        });
+        if let Some(filter) = knn_filter(filters) {
+            knn["filter"] = filter;
+        }
         let response = self
             .create_client()?
-            .search(opensearch::SearchParts::Index(&[&index_name]))
+            .search(SearchParts::Index(&[&index_name]))
             .body(json!({
+                "size": k,
                 "query": {
-                    "knn": {
-                        "embeddings": {
-                            "vector": query_embedding,
-                            "k": k
-                        }
-                    }
+                    "knn": { "embedding": knn }
                 }
             }))
             .send()
             .await
             .map_err(|e| anyhow!("unable to search opensearch embeddings: {}", e))?;
-
-        let response_body = response
-            .json::<Value>()
-            .await
+        let mut body = response_body(response, "search embeddings").await?;
+        let hits: Vec<Hit> = serde_json::from_value(body["hits"]["hits"].take())
             .map_err(|e| anyhow!("unable to parse opensearch search response: {}", e))?;
-
-        let returned_hits = response_body["hits"]["hits"].as_array();
-        match returned_hits {
-            None => {
-                return Err(anyhow!(
-                    "unable to parse opensearch search response".to_string(),
-                ))
-            }
-            Some(hits) => {
-                let mut documents: Vec<SearchResult> = Vec::new();
-                for hit in hits {
-                    #[derive(Deserialize)]
-                    struct OpenSearchHit {
-                        _id: String,
-                        _score: f64,
-                    }
-
-                    let hit = serde_json::from_value::<OpenSearchHit>(hit.clone());
-                    match hit {
-                        Err(e) => {
-                            return Err(anyhow!(
-                                "unable to parse opensearch search response: {}",
-                                e
-                            ));
-                        }
-                        Ok(hit) => {
-                            documents.push(SearchResult {
-                                content_id: hit._id,
-                                confidence_score: hit._score as f32,
-                            });
-                        }
-                    }
-                }
-                Ok(documents)
-            }
-        }
+        Ok(hits
+            .into_iter()
+            .map(|hit| SearchResult {
+                content_id: hit.id,
+                confidence_score: hit.score.unwrap_or_default(),
+                metadata: hit.source.metadata,
+                root_content_metadata: hit.source.root_content_metadata,
+                content_metadata: hit.source.content_metadata,
+            })
+            .collect())
     }
 
+    #[tracing::instrument]
     async fn drop_index(&self, index: &str) -> Result<()> {
         let response = self
             .create_client()?
             .indices()
-            .delete(opensearch::indices::IndicesDeleteParts::Index(&[&index]))
+            .delete(IndicesDeleteParts::Index(&[index]))
             .send()
             .await
             .map_err(|e| anyhow!("unable to delete opensearch index: {}", e))?;
-
-        match response.error_for_status_code() {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                if let Some(status) = e.status_code() {
-                    if status.as_u16() == 404 {
-                        return Ok(());
-                    }
-                }
-                return Err(anyhow!("unable to delete opensearch index: '{}'", e));
-            }
+        if response.status_code().as_u16() == 404 {
+            return Ok(());
         }
+        response_body(response, "delete index").await?;
+        Ok(())
     }
 
+    #[tracing::instrument]
     async fn num_vectors(&self, index: &str) -> Result<u64> {
         let response = self
             .create_client()?
-            .count(opensearch::CountParts::Index(&[&index]))
+            .count(CountParts::Index(&[index]))
             .send()
             .await
             .map_err(|e| anyhow!("unable to count opensearch index: {}", e))?;
-
-        let response_body = response
-            .json::<Value>()
-            .await
-            .map_err(|e| anyhow!("unable to parse opensearch count response: {}", e))?;
-
-        #[derive(Deserialize)]
-        struct OpenSearchCount {
-            count: u64,
-        }
-
-        let result = serde_json::from_value::<OpenSearchCount>(response_body)
-            .map_err(|e| anyhow!("unable to parse opensearch count response: {}", e))?;
-
-        Ok(result.count)
+        let body = response_body(response, "count embeddings").await?;
+        body["count"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("unable to parse opensearch count response: {}", body))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+    };
 
+    use axum::{
+        extract::{Path, State},
+        http::StatusCode,
+        routing::{get, post, put},
+        Json,
+        Router,
+    };
+
+    use super::*;
     use crate::{
-        server_config::OpenSearchBasicConfig,
-        vectordbs::{IndexDistance, VectorChunk, VectorDBTS},
+        test_util::db_utils::test_mock_content_metadata,
+        vectordbs::{
+            tests::{
+                basic_search,
+                crud_operations,
+                insertion_idempotent,
+                scan_points,
+                search_filters,
+                store_metadata,
+            },
+            VectorDBTS,
+        },
     };
-    const TEST_INDEX_NAME: &str = "test_index_name";
 
-    use super::{CreateIndexParams, OpenSearchKnn};
+    /// In memory stand in for the subset of the OpenSearch API used by the
+    /// backend, searching every document of an index.
+    #[derive(Clone, Default)]
+    struct FakeOpenSearch {
+        indexes: Arc<Mutex<HashMap<String, HashMap<String, Value>>>>,
+    }
 
-    fn initialize_opensearch() -> OpenSearchKnn {
-        OpenSearchKnn::new(OpenSearchBasicConfig {
-            addr: "https://localhost:9200".into(),
-            username: "admin".into(),
-            password: "admin".into(),
-        })
+    type Reply = (StatusCode, Json<Value>);
+
+    fn not_found() -> Reply {
+        (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": { "type": "index_not_found_exception" }, "status": 404 })),
+        )
     }
 
-    #[tokio::test]
-    #[tracing_test::traced_test]
-    #[ignore]
-    async fn test_search_basic() {
-        let opensearch: VectorDBTS = Arc::new(initialize_opensearch());
-        opensearch.drop_index(TEST_INDEX_NAME).await.unwrap();
+    fn matches(source: &Value, clause: &Value) -> bool {
+        let (field, value) = clause["term"].as_object().unwrap().iter().next().unwrap();
+        let key = field.strip_prefix("metadata.").unwrap();
+        match &source["metadata"][key] {
+            Value::String(s) => Some(s.as_str()) == value.as_str(),
+            other => other == value,
+        }
+    }
+
+    fn similarity(a: &[Value], b: &[Value]) -> f32 {
+        let a = a
+            .iter()
+            .map(|v| v.as_f64().unwrap() as f32)
+            .collect::<Vec<_>>();
+        let b = b
+            .iter()
+            .map(|v| v.as_f64().unwrap() as f32)
+            .collect::<Vec<_>>();
+        let dot: f32 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+        let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        dot / (norm(&a) * norm(&b)).max(f32::EPSILON)
+    }
+
+    impl FakeOpenSearch {
+        async fn create_index(State(fake): State<Self>, Path(index): Path<String>) -> Reply {
+            let mut indexes = fake.indexes.lock().unwrap();
+            if indexes.contains_key(&index) {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error": { "type": "resource_already_exists_exception" },
+                        "status": 400
+                    })),
+                );
+            }
+            indexes.insert(index.clone(), HashMap::new());
+            (
+                StatusCode::OK,
+                Json(json!({ "acknowledged": true, "index": index })),
+            )
+        }
+
+        async fn delete_index(State(fake): State<Self>, Path(index): Path<String>) -> Reply {
+            match fake.indexes.lock().unwrap().remove(&index) {
+                Some(_) => (StatusCode::OK, Json(json!({ "acknowledged": true }))),
+                None => not_found(),
+            }
+        }
+
+        async fn bulk(State(fake): State<Self>, Path(index): Path<String>, body: String) -> Reply {
+            let mut indexes = fake.indexes.lock().unwrap();
+            let Some(docs) = indexes.get_mut(&index) else {
+                return not_found();
+            };
+            let lines = body
+                .lines()
+                .filter(|l| !l.is_empty())
+                .map(|l| serde_json::from_str::<Value>(l).unwrap())
+                .collect::<Vec<_>>();
+            let mut items = Vec::new();
+            for pair in lines.chunks(2) {
+                let id = pair[0]["index"]["_id"].as_str().unwrap().to_string();
+                docs.insert(id.clone(), pair[1].clone());
+                items.push(json!({ "index": { "_id": id, "status": 201 } }));
+            }
+            (
+                StatusCode::OK,
+                Json(json!({ "errors": false, "items": items })),
+            )
+        }
+
+        async fn count(State(fake): State<Self>, Path(index): Path<String>) -> Reply {
+            match fake.indexes.lock().unwrap().get(&index) {
+                Some(docs) => (StatusCode::OK, Json(json!({ "count": docs.len() }))),
+                None => not_found(),
+            }
+        }
+
+        async fn mget(
+            State(fake): State<Self>,
+            Path(index): Path<String>,
+            Json(body): Json<Value>,
+        ) -> Reply {
+            let indexes = fake.indexes.lock().unwrap();
+            let Some(docs) = indexes.get(&index) else {
+                return not_found();
+            };
+            let docs = body["ids"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|id| match docs.get(id.as_str().unwrap()) {
+                    Some(source) => json!({ "_id": id, "found": true, "_source": source }),
+                    None => json!({ "_id": id, "found": false }),
+                })
+                .collect::<Vec<_>>();
+            (StatusCode::OK, Json(json!({ "docs": docs })))
+        }
+
+        async fn update(
+            State(fake): State<Self>,
+            Path((index, id)): Path<(String, String)>,
+            Json(body): Json<Value>,
+        ) -> Reply {
+            let mut indexes = fake.indexes.lock().unwrap();
+            let Some(source) = indexes.get_mut(&index).and_then(|docs| docs.get_mut(&id)) else {
+                return not_found();
+            };
+            for (key, value) in body["doc"].as_object().unwrap() {
+                if let (Some(Value::Object(current)), Value::Object(update)) =
+                    (source.get_mut(key), value)
+                {
+                    current.extend(update.clone());
+                    continue;
+                }
+                source[key] = value.clone();
+            }
+            (StatusCode::OK, Json(json!({ "result": "updated" })))
+        }
+
+        async fn delete_doc(
+            State(fake): State<Self>,
+            Path((index, id)): Path<(String, String)>,
+        ) -> Reply {
+            match fake
+                .indexes
+                .lock()
+                .unwrap()
+                .get_mut(&index)
+                .and_then(|docs| docs.remove(&id))
+            {
+                Some(_) => (StatusCode::OK, Json(json!({ "result": "deleted" }))),
+                None => (
+                    StatusCode::NOT_FOUND,
+                    Json(json!({ "result": "not_found" })),
+                ),
+            }
+        }
+
+        async fn search(
+            State(fake): State<Self>,
+            Path(index): Path<String>,
+            Json(body): Json<Value>,
+        ) -> Reply {
+            let indexes = fake.indexes.lock().unwrap();
+            let Some(docs) = indexes.get(&index) else {
+                return not_found();
+            };
+            if body["query"]["match_all"].is_object() {
+                //  Scans are sorted by id
+                let after = body["search_after"][0].as_str().unwrap_or_default();
+                let mut ids = docs
+                    .keys()
+                    .filter(|id| id.as_str() > after)
+                    .collect::<Vec<_>>();
+                ids.sort();
+                let hits = ids
+                    .into_iter()
+                    .take(body["size"].as_u64().unwrap() as usize)
+                    .map(|id| json!({ "_id": id, "_score": null, "_source": docs[id] }))
+                    .collect::<Vec<_>>();
+                return (StatusCode::OK, Json(json!({ "hits": { "hits": hits } })));
+            }
+            let knn = &body["query"]["knn"]["embedding"];
+            let query = knn["vector"].as_array().unwrap();
+            let filter = &knn["filter"]["bool"];
+            let clauses = |occur: &str| filter[occur].as_array().cloned().unwrap_or_default();
+            let (must, must_not) = (clauses("must"), clauses("must_not"));
+            let mut hits = docs
+                .iter()
+                .filter(|(_, source)| {
+                    must.iter().all(|c| matches(source, c)) &&
+                        !must_not.iter().any(|c| matches(source, c))
+                })
+                .map(|(id, source)| {
+                    let score = similarity(query, source["embedding"].as_array().unwrap());
+                    json!({ "_id": id, "_score": score, "_source": source })
+                })
+                .collect::<Vec<_>>();
+            hits.sort_by(|a, b| {
+                b["_score"]
+                    .as_f64()
+                    .partial_cmp(&a["_score"].as_f64())
+                    .unwrap()
+            });
+            hits.truncate(knn["k"].as_u64().unwrap() as usize);
+            (StatusCode::OK, Json(json!({ "hits": { "hits": hits } })))
+        }
+
+        async fn start() -> SocketAddr {
+            let app = Router::new()
+                .route(
+                    "/:index",
+                    put(Self::create_index).delete(Self::delete_index),
+                )
+                .route("/:index/_bulk", post(Self::bulk).put(Self::bulk))
+                .route("/:index/_count", get(Self::count).post(Self::count))
+                .route("/:index/_mget", get(Self::mget).post(Self::mget))
+                .route("/:index/_update/:id", post(Self::update))
+                .route("/:index/_doc/:id", axum::routing::delete(Self::delete_doc))
+                .route("/:index/_search", get(Self::search).post(Self::search))
+                .with_state(Self::default());
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+            addr
+        }
+    }
+
+    async fn create_test_db(index_name: &str) -> VectorDBTS {
+        let addr = FakeOpenSearch::start().await;
+        let opensearch: VectorDBTS = Arc::new(OpenSearchKnn::new(OpenSearchBasicConfig {
+            addr: format!("http://{}", addr),
+            username: "admin".into(),
+            password: "admin".into(),
+        }));
+        opensearch.drop_index(index_name).await.unwrap();
         opensearch
             .create_index(CreateIndexParams {
-                vectordb_index_name: TEST_INDEX_NAME.into(),
+                vectordb_index_name: index_name.into(),
                 vector_dim: 2,
                 distance: IndexDistance::Cosine,
-                unique_params: None,
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
             })
             .await
             .unwrap();
+        opensearch
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_search_basic() {
+        basic_search(create_test_db("hello-index").await, "hello-index").await;
+    }
+
+    #[tokio::test]
+    async fn test_store_metadata() {
+        store_metadata(create_test_db("metadata-index").await, "metadata-index").await;
+    }
+
+    #[tokio::test]
+    async fn test_insertion_idempotent() {
+        let index_name = "idempotent-index";
+        insertion_idempotent(create_test_db(index_name).await, index_name).await;
+    }
+
+    #[tokio::test]
+    async fn test_deletion() {
+        crud_operations(create_test_db("delete-index").await, "delete-index").await;
+    }
+
+    #[tokio::test]
+    async fn test_search_filters() {
+        search_filters(create_test_db("hello-index").await, "hello-index").await;
+    }
+
+    #[tokio::test]
+    async fn test_scan_points() {
+        scan_points(create_test_db("scan-index").await, "scan-index").await;
+    }
+
+    #[tokio::test]
+    async fn test_multi_vector_fallback() {
+        let index_name = "multi-vector-index";
+        let opensearch = create_test_db(index_name).await;
         let chunk = VectorChunk {
-            content_id: "0".into(),
-            embedding: vec![0., 2.],
-            metadata: serde_json::Value::Null,
+            content_id: "1".into(),
+            embedding: EmbeddingVector::MultiVector(vec![vec![1., 0.], vec![0., 2.]]),
+            metadata: HashMap::new(),
+            root_content_metadata: None,
+            content_metadata: test_mock_content_metadata("1", "1", "graph1"),
         };
         opensearch
-            .add_embedding(TEST_INDEX_NAME, vec![chunk])
+            .add_embedding(index_name, vec![chunk])
             .await
             .unwrap();
-
-        // bulk api returns a response when the document is accepted for indexing, not
-        // when it is actually indexed. So we need to wait until the document is
-        // actually indexed before searching.
-
-        const MAX_MILLIS_TO_WAIT: u64 = 2000;
-        let mut millis_spent_waiting: u64 = 0;
-        const WAIT_MILLIS_PER_ITER: u64 = 10;
-
-        loop {
-            if millis_spent_waiting >= MAX_MILLIS_TO_WAIT {
-                panic!(
-                    "timed out waiting for document to be indexed, spent '{}' millisecs waiting",
-                    millis_spent_waiting
-                );
-            }
-            let num_elements = opensearch.num_vectors(TEST_INDEX_NAME).await.unwrap();
-            if num_elements == 1 {
-                break;
-            }
-            tokio::time::sleep(tokio::time::Duration::from_millis(WAIT_MILLIS_PER_ITER)).await;
-            millis_spent_waiting += WAIT_MILLIS_PER_ITER;
-        }
+        let points = opensearch
+            .get_points(index_name, vec!["1".into()])
+            .await
+            .unwrap();
+        assert_eq!(points[0].embedding, vec![0.5, 1.].into());
 
         let results = opensearch
-            .search(TEST_INDEX_NAME.into(), vec![10., 8.], 1, vec![])
+            .search(
+                index_name.into(),
+                EmbeddingVector::MultiVector(vec![vec![1., 2.]]),
+                1,
+                vec![],
+            )
             .await
             .unwrap();
-        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].content_id, "1");
+    }
+
+    #[test]
+    fn test_knn_method() {
+        let method = knn_method(
+            &IndexDistance::Euclidean,
+            &IndexOptions {
+                vector_store: None,
+                index_type: Some(IndexType::Hnsw {
+                    m: Some(32),
+                    ef_construction: None,
+                }),
+                quantization: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(method["space_type"], "l2");
+        assert_eq!(method["parameters"], json!({ "m": 32 }));
+        assert!(knn_method(
+            &IndexDistance::Cosine,
+            &IndexOptions {
+                vector_store: None,
+                index_type: Some(IndexType::Flat),
+                quantization: None,
+            }
+        )
+        .is_err());
     }
 }
@@ -2,37 +2,55 @@ use std::collections::HashMap;
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use indexify_internal_api::ContentMetadata;
+use indexify_internal_api::{ContentMetadata, EmbeddingKind, EmbeddingVector, SparseVector};
 use qdrant_client::{
     client::{QdrantClient, QdrantClientConfig},
     qdrant::{
         point_id::PointIdOptions::Num,
         points_selector::PointsSelectorOneOf,
+        quantization_config,
         vectors::VectorsOptions,
         vectors_config::Config,
         with_payload_selector::SelectorOptions,
+        CompressionRatio,
         Condition,
         CreateCollection,
         Distance,
         Filter,
+        HnswConfigDiff,
+        NamedVectors,
         PointId,
         PointStruct,
         PointsIdsList,
         PointsSelector,
+        ProductQuantization,
+        QuantizationConfig,
+        QuantizationType,
+        RetrievedPoint,
+        ScalarQuantization,
+        ScrollPoints,
         SearchPoints,
+        SparseIndices,
+        SparseVectorConfig,
+        SparseVectorParams,
+        Vector,
         VectorParams,
+        Vectors,
         VectorsConfig,
         WithPayloadSelector,
     },
 };
 use serde::{Deserialize, Serialize};
 
-use super::{CreateIndexParams, VectorDb};
+use super::{dense_vector, CreateIndexParams, IndexOptions, IndexType, Quantization, VectorDb};
 use crate::{
     server_config::QdrantConfig,
     vectordbs::{FilterOperator, IndexDistance, SearchResult, VectorChunk},
 };
 
+/// Name of the vector of points of collections of sparse embeddings.
+const SPARSE_VECTOR_NAME: &str = "sparse";
+
 fn hex_to_u64(hex: &str) -> Result<u64, std::num::ParseIntError> {
     u64::from_str_radix(hex, 16)
 }
@@ -81,6 +99,70 @@ impl QdrantDb {
             IndexDistance::Euclidean => Distance::Euclid,
         }
     }
+
+    fn hnsw_config(index_type: &Option<IndexType>) -> Result<Option<HnswConfigDiff>> {
+        match index_type {
+            None => Ok(None),
+            // Qdrant doesn't build the graph of collections with m = 0
+            Some(IndexType::Flat) => Ok(Some(HnswConfigDiff {
+                m: Some(0),
+                ..Default::default()
+            })),
+            Some(IndexType::Hnsw { m, ef_construction }) => Ok(Some(HnswConfigDiff {
+                m: m.map(u64::from),
+                ef_construct: ef_construction.map(u64::from),
+                ..Default::default()
+            })),
+            Some(IndexType::Ivf { .. }) => Err(anyhow!("qdrant doesn't support ivf indexes")),
+        }
+    }
+
+    fn quantization_config(
+        quantization: &Option<Quantization>,
+        vector_dim: u64,
+    ) -> Result<Option<QuantizationConfig>> {
+        let quantization = match quantization {
+            None => return Ok(None),
+            Some(Quantization::Scalar) => {
+                quantization_config::Quantization::Scalar(ScalarQuantization {
+                    r#type: QuantizationType::Int8.into(),
+                    ..Default::default()
+                })
+            }
+            Some(Quantization::Product { num_sub_vectors }) => {
+                // Every sub vector of 4 byte floats is stored as a 1 byte centroid id
+                let bytes = vector_dim * 4;
+                let ratio = |n: u32| match n as u64 {
+                    0 => 0,
+                    n if bytes % n != 0 => 0,
+                    n => bytes / n,
+                };
+                let compression = match num_sub_vectors.map(|n| (n, ratio(n))) {
+                    None => CompressionRatio::X16,
+                    Some((_, 4)) => CompressionRatio::X4,
+                    Some((_, 8)) => CompressionRatio::X8,
+                    Some((_, 16)) => CompressionRatio::X16,
+                    Some((_, 32)) => CompressionRatio::X32,
+                    Some((_, 64)) => CompressionRatio::X64,
+                    Some((n, _)) => {
+                        return Err(anyhow!(
+                            "qdrant can't split {} dimensions in {} sub vectors, it only \
+                             compresses vectors 4, 8, 16, 32 or 64 times",
+                            vector_dim,
+                            n
+                        ))
+                    }
+                };
+                quantization_config::Quantization::Product(ProductQuantization {
+                    compression: compression.into(),
+                    ..Default::default()
+                })
+            }
+        };
+        Ok(Some(QuantizationConfig {
+            quantization: Some(quantization),
+        }))
+    }
 }
 
 fn content_id_from_point_id(point_id: Option<PointId>) -> Result<String> {
@@ -94,6 +176,50 @@ fn content_id_from_point_id(point_id: Option<PointId>) -> Result<String> {
     }
 }
 
+/// Vectors of the point of an embedding. Sparse embeddings are named vectors,
+/// multi-vector embeddings are stored as the mean of their vectors.
+fn point_vectors(embedding: EmbeddingVector) -> Result<Vectors> {
+    let EmbeddingVector::Sparse(sparse) = embedding else {
+        return Ok(dense_vector(embedding, "qdrant")?.into());
+    };
+    let vector = Vector {
+        data: sparse.values,
+        indices: Some(SparseIndices {
+            data: sparse.indices,
+        }),
+    };
+    Ok(Vectors {
+        vectors_options: Some(VectorsOptions::Vectors(NamedVectors {
+            vectors: HashMap::from([(SPARSE_VECTOR_NAME.to_string(), vector)]),
+        })),
+    })
+}
+
+fn chunk_from_point(point: RetrievedPoint) -> Result<VectorChunk> {
+    let (metadata, indexify_payload) = extract_metadata_from_payload(point.payload)?;
+    let vector = point.vectors.unwrap().vectors_options.unwrap(); // Unwrap the Option<VectorsOptions>
+    let embedding = match vector {
+        VectorsOptions::Vector(vector) => EmbeddingVector::Dense(vector.data),
+        VectorsOptions::Vectors(mut named) => {
+            let vector = named
+                .vectors
+                .remove(SPARSE_VECTOR_NAME)
+                .ok_or(anyhow!("Invalid vector type"))?;
+            EmbeddingVector::Sparse(SparseVector {
+                indices: vector.indices.map(|i| i.data).unwrap_or_default(),
+                values: vector.data,
+            })
+        }
+    };
+    Ok(VectorChunk {
+        content_id: content_id_from_point_id(point.id)?,
+        embedding,
+        metadata,
+        root_content_metadata: indexify_payload.root_content_metadata,
+        content_metadata: indexify_payload.content_metadata,
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct IndexifyPayload {
     pub content_metadata: ContentMetadata,
@@ -118,25 +244,50 @@ impl VectorDb for QdrantDb {
         "qdrant".into()
     }
 
+    fn validate_index_options(&self, options: &IndexOptions) -> Result<()> {
+        Self::hnsw_config(&options.index_type)?;
+        Ok(())
+    }
+
     #[tracing::instrument]
     async fn create_index(&self, index: CreateIndexParams) -> Result<()> {
-        let result = self
-            .create_client()?
-            .create_collection(&CreateCollection {
+        //  Sparse vectors are scored with the dot product
+        let collection = if index.kind == EmbeddingKind::Sparse {
+            if index.options.index_type.is_some() || index.options.quantization.is_some() {
+                return Err(anyhow!(
+                    "qdrant doesn't support index options of sparse vectors"
+                ));
+            }
+            CreateCollection {
+                collection_name: index.vectordb_index_name,
+                sparse_vectors_config: Some(SparseVectorConfig {
+                    map: HashMap::from([(
+                        SPARSE_VECTOR_NAME.to_string(),
+                        SparseVectorParams::default(),
+                    )]),
+                }),
+                ..Default::default()
+            }
+        } else {
+            CreateCollection {
                 collection_name: index.vectordb_index_name,
                 vectors_config: Some(VectorsConfig {
                     config: Some(Config::Params(VectorParams {
                         on_disk: None,
                         size: index.vector_dim,
                         distance: Self::convert_to_qdrant_distance(index.distance).into(),
-                        hnsw_config: None,
-                        quantization_config: None,
+                        hnsw_config: Self::hnsw_config(&index.options.index_type)?,
+                        quantization_config: Self::quantization_config(
+                            &index.options.quantization,
+                            index.vector_dim,
+                        )?,
                         datatype: None,
                     })),
                 }),
                 ..Default::default()
-            })
-            .await;
+            }
+        };
+        let result = self.create_client()?.create_collection(&collection).await;
         if let Err(err) = &result {
             if err.to_string().contains("already exists") {
                 return Ok(());
@@ -169,7 +320,7 @@ impl VectorDb for QdrantDb {
             })?;
             points.push(PointStruct::new(
                 hex_to_u64(&chunk_id).unwrap(),
-                chunk.embedding.clone(),
+                point_vectors(chunk.embedding)?,
                 metadata,
             ));
         }
@@ -197,23 +348,45 @@ impl VectorDb for QdrantDb {
             .map_err(|e| anyhow!("unable to read index: {}", e.to_string()))?;
         let mut documents: Vec<VectorChunk> = Vec::new();
         for point in result.result {
-            let (metadata, indexify_payload) = extract_metadata_from_payload(point.payload)?;
-            let vector = point.vectors.unwrap().vectors_options.unwrap(); // Unwrap the Option<VectorsOptions>
-            let embedding = match vector {
-                VectorsOptions::Vector(vector) => vector,
-                _ => return Err(anyhow!("Invalid vector type")),
-            };
-            documents.push(VectorChunk {
-                content_id: content_id_from_point_id(point.id)?,
-                embedding: embedding.data,
-                metadata,
-                root_content_metadata: indexify_payload.root_content_metadata,
-                content_metadata: indexify_payload.content_metadata,
-            });
+            documents.push(chunk_from_point(point)?);
         }
         Ok(documents)
     }
 
+    async fn scan_points(
+        &self,
+        index: &str,
+        cursor: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<VectorChunk>, Option<String>)> {
+        //  Qdrant pages start at the id of their first point
+        let offset = cursor
+            .map(|cursor| hex_to_u64(&cursor).map(PointId::from))
+            .transpose()?;
+        let result = self
+            .create_client()?
+            .scroll(&ScrollPoints {
+                collection_name: index.to_string(),
+                offset,
+                limit: Some(limit as u32),
+                with_payload: Some(true.into()),
+                with_vectors: Some(true.into()),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| anyhow!("unable to scan index: {}", e.to_string()))?;
+        let chunks = result
+            .result
+            .into_iter()
+            .map(chunk_from_point)
+            .collect::<Result<Vec<_>>>()?;
+        let next = match result.next_page_offset {
+            Some(point_id) => Some(content_id_from_point_id(Some(point_id))?),
+            None => None,
+        };
+        Ok((chunks, next))
+    }
+
     async fn update_metadata(
         &self,
         index: &str,
@@ -260,10 +433,20 @@ impl VectorDb for QdrantDb {
     async fn search(
         &self,
         index: String,
-        query_embedding: Vec<f32>,
+        query_embedding: EmbeddingVector,
         k: u64,
         filters: Vec<super::Filter>,
     ) -> Result<Vec<SearchResult>> {
+        let (vector, sparse_indices, vector_name) = match query_embedding {
+            EmbeddingVector::Sparse(sparse) => (
+                sparse.values,
+                Some(SparseIndices {
+                    data: sparse.indices,
+                }),
+                Some(SPARSE_VECTOR_NAME.to_string()),
+            ),
+            embedding => (dense_vector(embedding, "qdrant")?, None, None),
+        };
         let mut filter = None;
         if !filters.is_empty() {
             let mut must = Vec::new();
@@ -284,7 +467,9 @@ impl VectorDb for QdrantDb {
             .create_client()?
             .search_points(&SearchPoints {
                 collection_name: index,
-                vector: query_embedding,
+                vector,
+                sparse_indices,
+                vector_name,
                 limit: k,
                 with_payload: Some(WithPayloadSelector {
                     selector_options: Some(SelectorOptions::Enable(true)),
@@ -339,31 +524,96 @@ impl VectorDb for QdrantDb {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{collections::HashMap, sync::Arc};
+
+    use indexify_internal_api::{EmbeddingKind, EmbeddingVector, SparseVector};
 
     use super::{CreateIndexParams, QdrantDb};
     use crate::{
         server_config::QdrantConfig,
+        test_util::db_utils::test_mock_content_metadata,
         vectordbs::{
-            tests::{basic_search, insertion_idempotent, search_filters, store_metadata},
+            tests::{
+                basic_search,
+                insertion_idempotent,
+                scan_points,
+                search_filters,
+                store_metadata,
+            },
             IndexDistance,
+            IndexOptions,
+            IndexType,
+            Quantization,
+            VectorChunk,
             VectorDBTS,
         },
     };
 
+    // The tests need a running Qdrant, at QDRANT_ADDR or on localhost, and are
+    // run with `cargo test -- --ignored`
+    fn test_qdrant() -> VectorDBTS {
+        let addr =
+            std::env::var("QDRANT_ADDR").unwrap_or_else(|_| "http://localhost:6334".to_string());
+        Arc::new(QdrantDb::new(QdrantConfig { addr }))
+    }
+
     #[tokio::test]
+    #[ignore]
+    #[tracing_test::traced_test]
+    async fn test_sparse_search() {
+        let qdrant = test_qdrant();
+        let index_name = "sparse-index";
+        qdrant.drop_index(index_name).await.unwrap();
+        qdrant
+            .create_index(CreateIndexParams {
+                vectordb_index_name: index_name.into(),
+                vector_dim: 30522,
+                distance: IndexDistance::Dot,
+                kind: EmbeddingKind::Sparse,
+                options: IndexOptions::default(),
+            })
+            .await
+            .unwrap();
+        let sparse = |indices: Vec<u32>, values: Vec<f32>| {
+            EmbeddingVector::Sparse(SparseVector { indices, values })
+        };
+        let chunks = [("1", vec![3, 30000]), ("2", vec![3, 7])]
+            .into_iter()
+            .map(|(id, indices)| VectorChunk {
+                content_id: id.into(),
+                embedding: sparse(indices, vec![1., 2.]),
+                metadata: HashMap::new(),
+                root_content_metadata: None,
+                content_metadata: test_mock_content_metadata(id, "1", "graph1"),
+            })
+            .collect();
+        qdrant.add_embedding(index_name, chunks).await.unwrap();
+
+        let results = qdrant
+            .search(index_name.into(), sparse(vec![30000], vec![1.]), 1, vec![])
+            .await
+            .unwrap();
+        assert_eq!(results[0].content_id, "1");
+        let points = qdrant
+            .get_points(index_name, vec!["2".into()])
+            .await
+            .unwrap();
+        assert_eq!(points[0].embedding, sparse(vec![3, 7], vec![1., 2.]));
+    }
+
+    #[tokio::test]
+    #[ignore]
     #[tracing_test::traced_test]
     async fn test_search_basic() {
-        let qdrant: VectorDBTS = Arc::new(QdrantDb::new(QdrantConfig {
-            addr: "http://localhost:6334".into(),
-        }));
+        let qdrant = test_qdrant();
         qdrant.drop_index("hello-index").await.unwrap();
         qdrant
             .create_index(CreateIndexParams {
                 vectordb_index_name: "hello-index".into(),
                 vector_dim: 2,
                 distance: IndexDistance::Cosine,
-                unique_params: None,
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
             })
             .await
             .unwrap();
@@ -371,17 +621,17 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore]
     async fn test_store_metadata() {
-        let qdrant: VectorDBTS = Arc::new(QdrantDb::new(QdrantConfig {
-            addr: "http://localhost:6334".into(),
-        }));
+        let qdrant = test_qdrant();
         qdrant.drop_index("metadata-index").await.unwrap();
         qdrant
             .create_index(CreateIndexParams {
                 vectordb_index_name: "metadata-index".into(),
                 vector_dim: 2,
                 distance: IndexDistance::Cosine,
-                unique_params: None,
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
             })
             .await
             .unwrap();
@@ -389,20 +639,19 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore]
     #[tracing_test::traced_test]
     async fn test_insertion_idempotent() {
         let index_name = "idempotent-index";
-        let hash_on = vec!["user_id".to_string(), "url".to_string()];
-        let qdrant: VectorDBTS = Arc::new(QdrantDb::new(QdrantConfig {
-            addr: "http://localhost:6334".into(),
-        }));
+        let qdrant = test_qdrant();
         qdrant.drop_index(index_name).await.unwrap();
         qdrant
             .create_index(CreateIndexParams {
                 vectordb_index_name: index_name.into(),
                 vector_dim: 2,
                 distance: IndexDistance::Cosine,
-                unique_params: Some(hash_on.clone()),
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
             })
             .await
             .unwrap();
@@ -410,21 +659,84 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore]
     async fn test_search_filters() {
         let index_name = "metadata-index";
-        let vector_db: VectorDBTS = Arc::new(QdrantDb::new(QdrantConfig {
-            addr: "http://localhost:6334".into(),
-        }));
+        let vector_db = test_qdrant();
         vector_db.drop_index("metadata-index").await.unwrap();
         vector_db
             .create_index(CreateIndexParams {
                 vectordb_index_name: "metadata-index".into(),
                 vector_dim: 2,
                 distance: IndexDistance::Cosine,
-                unique_params: None,
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
             })
             .await
             .unwrap();
         search_filters(vector_db, index_name).await;
     }
+
+    #[tokio::test]
+    #[ignore]
+    #[tracing_test::traced_test]
+    async fn test_scan_points() {
+        let index_name = "scan-index";
+        let vector_db = test_qdrant();
+        vector_db.drop_index(index_name).await.unwrap();
+        vector_db
+            .create_index(CreateIndexParams {
+                vectordb_index_name: index_name.into(),
+                vector_dim: 2,
+                distance: IndexDistance::Cosine,
+                kind: EmbeddingKind::Dense,
+                options: IndexOptions::default(),
+            })
+            .await
+            .unwrap();
+        scan_points(vector_db, index_name).await;
+    }
+
+    #[test]
+    fn test_index_options() {
+        assert_eq!(
+            QdrantDb::hnsw_config(&Some(IndexType::Flat))
+                .unwrap()
+                .unwrap()
+                .m,
+            Some(0)
+        );
+        let hnsw = QdrantDb::hnsw_config(&Some(IndexType::Hnsw {
+            m: Some(32),
+            ef_construction: None,
+        }))
+        .unwrap()
+        .unwrap();
+        assert_eq!((hnsw.m, hnsw.ef_construct), (Some(32), None));
+        assert!(QdrantDb::hnsw_config(&Some(IndexType::Ivf {
+            num_partitions: None
+        }))
+        .is_err());
+
+        assert!(
+            QdrantDb::quantization_config(&Some(Quantization::Scalar), 384)
+                .unwrap()
+                .is_some()
+        );
+        // 384 dimensions of 4 bytes in 96 sub vectors of 1 byte
+        assert!(QdrantDb::quantization_config(
+            &Some(Quantization::Product {
+                num_sub_vectors: Some(96)
+            }),
+            384
+        )
+        .is_ok());
+        assert!(QdrantDb::quantization_config(
+            &Some(Quantization::Product {
+                num_sub_vectors: Some(100)
+            }),
+            384
+        )
+        .is_err());
+    }
 }